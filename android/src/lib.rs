@@ -21,6 +21,7 @@ pub fn android_main(app: android_activity::AndroidApp) {
             backends: Some(maplibre::render::settings::Backends::GL),
             ..WgpuSettings::default()
         },
+        None,
     );
 }
 
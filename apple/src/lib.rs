@@ -17,5 +17,6 @@ pub fn maplibre_apple_main() {
             backends: Some(maplibre::render::settings::Backends::all()),
             ..WgpuSettings::default()
         },
+        None,
     );
 }
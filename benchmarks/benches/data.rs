@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use criterion::{criterion_group, criterion_main, Criterion};
 use maplibre::{
@@ -42,11 +42,14 @@ fn bench_process_vector_tile(c: &mut Criterion) {
                     coords: MUNICH_COORDS
                         .into_world_tile(TileAddressingScheme::XYZ)
                         .unwrap(),
+                    source: None,
                     layers: HashSet::from([
                         "transportation".to_owned(),
                         "water".to_owned(),
                         "building".to_owned(),
                     ]),
+                    filters: HashMap::new(),
+                    point_layers: HashSet::new(),
                 },
                 &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(DummyContext),
             );
@@ -1,30 +1,54 @@
+use std::collections::HashSet;
+
 use maplibre::{
+    background::BackgroundPlugin,
     coords::{LatLon, WorldTileCoords},
     headless::{create_headless_renderer, map::HeadlessMap, HeadlessPlugin},
+    heatmap::HeatmapPlugin,
     plugin::Plugin,
     raster::{DefaultRasterTransferables, RasterPlugin},
     render::RenderPlugin,
-    style::Style,
+    style::{
+        loader::{StyleLoadError, StyleLoader},
+        Style,
+    },
+    terrain::TerrainPlugin,
     util::grid::google_mercator,
     vector::{DefaultVectorTransferables, VectorPlugin},
 };
 use tile_grid::{extent_wgs84_to_merc, Extent, GridIterator};
 
-pub async fn run_headless(tile_size: u32, min: LatLon, max: LatLon) {
+/// Renders the tiles within `min` and `max`. Fails if the style can not be loaded.
+pub async fn run_headless(
+    tile_size: u32,
+    min: LatLon,
+    max: LatLon,
+    style_url: Option<&str>,
+) -> Result<(), StyleLoadError> {
     let (kernel, renderer) = create_headless_renderer(tile_size, None).await;
 
-    let style = Style::default();
+    let style = match style_url {
+        Some(style_url) => {
+            StyleLoader::new(kernel.source_client().clone())
+                .load(style_url)
+                .await?
+        }
+        None => Style::default(),
+    };
 
     let requested_layers = style
         .layers
         .iter()
-        .map(|layer| layer.source_layer.as_ref().unwrap().clone())
-        .collect::<Vec<_>>();
+        .filter_map(|layer| layer.source_layer.clone())
+        .collect::<HashSet<_>>();
 
     let plugins: Vec<Box<dyn Plugin<_>>> = vec![
         Box::new(RenderPlugin::default()),
+        Box::new(BackgroundPlugin),
         Box::new(VectorPlugin::<DefaultVectorTransferables>::default()),
         Box::new(RasterPlugin::<DefaultRasterTransferables>::default()),
+        Box::new(TerrainPlugin),
+        Box::new(HeatmapPlugin),
         Box::new(HeadlessPlugin::new(true)),
     ];
 
@@ -58,4 +82,6 @@ pub async fn run_headless(tile_size: u32, min: LatLon, max: LatLon) {
 
         map.render_tile(layers);
     }
+
+    Ok(())
 }
@@ -1,9 +1,18 @@
 #![deny(unused_imports)]
 
-use std::{io::ErrorKind, path::PathBuf};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process,
+};
 
 use clap::{Parser, Subcommand};
-use maplibre::{coords::LatLon, render::settings::WgpuSettings};
+use maplibre::{
+    coords::LatLon,
+    render::settings::WgpuSettings,
+    style::validate::{validate_style_str, Severity},
+};
 use maplibre_winit::{run_headed_map, WinitMapWindowConfig};
 
 #[cfg(feature = "headless")]
@@ -34,7 +43,13 @@ fn parse_lat_long(env: &str) -> Result<LatLon, std::io::Error> {
 
 #[derive(Subcommand)]
 enum Commands {
-    Headed {},
+    Headed {
+        /// Url or path of the style which is shown instead of the default style
+        #[clap(long)]
+        style: Option<String>,
+    },
+    /// Checks a style against the style specification and reports every problem
+    ValidateStyle { file: PathBuf },
     #[cfg(feature = "headless")]
     Headless {
         #[clap(default_value_t = 400)]
@@ -49,9 +64,40 @@ enum Commands {
             default_value_t = LatLon::new(48.255861, 11.7917815798)
         )]
         max: LatLon,
+        /// Url or path of the style which is rendered instead of the default style
+        #[clap(long)]
+        style: Option<String>,
     },
 }
 
+fn validate_style(file: &Path) {
+    let style = match fs::read_to_string(file) {
+        Ok(style) => style,
+        Err(e) => {
+            eprintln!("reading {} failed: {e}", file.display());
+            process::exit(2);
+        }
+    };
+
+    let diagnostics = validate_style_str(&style);
+    for diagnostic in &diagnostics {
+        println!("{diagnostic}");
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+    println!(
+        "{}: {errors} errors, {} warnings",
+        file.display(),
+        diagnostics.len() - errors
+    );
+    if errors > 0 {
+        process::exit(1);
+    }
+}
+
 fn main() {
     env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
 
@@ -63,23 +109,33 @@ fn main() {
     // You can check for the existence of subcommands, and if found use their
     // matches just as you would the top level cmd
     match &cli.command {
-        Commands::Headed {} => run_headed_map(
+        Commands::Headed { style } => run_headed_map(
             Some(PathBuf::from("./maplibre-cache".to_string())),
             WinitMapWindowConfig::new("maplibre".to_string()),
             WgpuSettings {
                 backends: Some(maplibre::render::settings::Backends::all()),
                 ..WgpuSettings::default()
             },
+            style.as_deref(),
         ),
+        Commands::ValidateStyle { file } => validate_style(file),
         #[cfg(feature = "headless")]
         Commands::Headless {
             tile_size,
             min,
             max,
+            style,
         } => {
-            maplibre::platform::run_multithreaded(async {
-                headless::run_headless(*tile_size, *min, *max).await
+            let result = maplibre::platform::run_multithreaded(async {
+                headless::run_headless(*tile_size, *min, *max, style.as_deref()).await
             });
+            if let Err(error) = result {
+                match std::error::Error::source(&error) {
+                    Some(source) => eprintln!("{error}: {source}"),
+                    None => eprintln!("{error}"),
+                }
+                process::exit(1);
+            }
         }
     }
 }
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use cgmath::Vector2;
+use maplibre::{
+    camera::{animation::AnimationOptions, FitBoundsOptions},
+    context::MapContext,
+    coords::{LatLon, LatLonBounds, ScreenPoint},
+};
+use winit::event::{ElementState, MouseButton};
+
+use super::UpdateState;
+
+/// Boxes which are smaller than this in logical pixels, e.g. because of a shift-click, do not
+/// zoom.
+const MIN_BOX_SIZE: f64 = 3.0;
+
+/// Zooms to a box which is spanned by dragging with the primary mouse button while shift is held.
+/// The box is not drawn while dragging.
+#[derive(Default)]
+pub struct BoxZoomHandler {
+    window_position: Option<Vector2<f64>>,
+    start_window_position: Option<Vector2<f64>>,
+    /// A box which has been spanned, but not zoomed to yet.
+    finished: Option<(Vector2<f64>, Vector2<f64>)>,
+}
+
+impl UpdateState for BoxZoomHandler {
+    fn update_state(&mut self, map_context: &mut MapContext, _dt: Duration) {
+        let Some((a, b)) = self.finished.take() else {
+            return;
+        };
+        if (a.x - b.x).abs() < MIN_BOX_SIZE || (a.y - b.y).abs() < MIN_BOX_SIZE {
+            return;
+        }
+
+        let Some(corners) = [(a.x, a.y), (b.x, a.y), (b.x, b.y), (a.x, b.y)]
+            .into_iter()
+            .map(|(x, y)| map_context.view_state.unproject(ScreenPoint::new(x, y)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return;
+        };
+
+        let latitudes = corners.iter().map(|corner| corner.latitude);
+        let longitudes = corners.iter().map(|corner| corner.longitude);
+        let bounds = LatLonBounds::new(
+            LatLon::new(
+                latitudes.clone().fold(f64::INFINITY, f64::min),
+                longitudes.clone().fold(f64::INFINITY, f64::min),
+            ),
+            LatLon::new(
+                latitudes.fold(f64::NEG_INFINITY, f64::max),
+                longitudes.fold(f64::NEG_INFINITY, f64::max),
+            ),
+        );
+
+        map_context.fit_bounds(
+            bounds,
+            FitBoundsOptions::default(),
+            AnimationOptions::default(),
+        );
+    }
+}
+
+impl BoxZoomHandler {
+    /// Whether a box is being spanned, such that the mouse does not pan.
+    pub fn is_active(&self) -> bool {
+        self.start_window_position.is_some()
+    }
+
+    pub fn process_window_position(&mut self, window_position: &Vector2<f64>) -> bool {
+        self.window_position = Some(*window_position);
+        true
+    }
+
+    /// Starts a box if the primary button is pressed and `start` is set, e.g. because shift is
+    /// held. Returns whether the event has been used for the box.
+    pub fn process_mouse_key_press(
+        &mut self,
+        key: &MouseButton,
+        state: &ElementState,
+        start: bool,
+    ) -> bool {
+        if *key != MouseButton::Left {
+            return false;
+        }
+
+        match state {
+            ElementState::Pressed if start => {
+                self.start_window_position = self.window_position;
+                self.is_active()
+            }
+            ElementState::Pressed => false,
+            ElementState::Released => {
+                let Some(start_window_position) = self.start_window_position.take() else {
+                    return false;
+                };
+                self.finished = self
+                    .window_position
+                    .map(|window_position| (start_window_position, window_position));
+                true
+            }
+        }
+    }
+}
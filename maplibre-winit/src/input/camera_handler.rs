@@ -20,29 +20,34 @@ pub struct CameraHandler {
 }
 
 impl UpdateState for CameraHandler {
-    fn update_state(&mut self, MapContext { view_state, .. }: &mut MapContext, dt: Duration) {
+    fn update_state(&mut self, map_context: &mut MapContext, dt: Duration) {
         if !self.is_active {
             return;
         }
 
+        let rotation = map_context.interaction_handlers().rotation;
+        let view_state = &mut map_context.view_state;
+
         if let (Some(window_position), Some(start_window_position)) =
             (self.window_position, self.start_window_position)
         {
             let camera = view_state.camera_mut();
 
-            if self.is_middle {
+            if self.is_middle && rotation {
                 let delta: Rad<_> = (Deg(0.001 * self.sensitivity)
                     * start_window_position.distance(window_position))
                 .into();
 
                 let previous = *self.start_delta_roll.get_or_insert(camera.get_roll());
                 camera.set_roll(previous + delta);
-            } else {
-                let delta: Rad<_> = (Deg(0.001 * self.sensitivity)
-                    * (start_window_position.x - window_position.x))
-                    .into();
-                let previous = *self.start_delta_yaw.get_or_insert(camera.get_yaw());
-                camera.set_yaw(previous + delta);
+            } else if !self.is_middle {
+                if rotation {
+                    let delta: Rad<_> = (Deg(0.001 * self.sensitivity)
+                        * (start_window_position.x - window_position.x))
+                        .into();
+                    let previous = *self.start_delta_yaw.get_or_insert(camera.get_yaw());
+                    camera.set_yaw(previous + delta);
+                }
 
                 let delta: Rad<_> = (Deg(0.001 * self.sensitivity)
                     * (start_window_position.y - window_position.y))
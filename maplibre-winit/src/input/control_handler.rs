@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use cgmath::Vector2;
+use maplibre::{context::MapContext, control::ControlHit, coords::ScreenPoint};
+use winit::event::{ElementState, MouseButton};
+
+use super::UpdateState;
+
+/// Activates the built-in controls with the primary mouse button or a single finger. A control
+/// is activated if the pointer is released above the part which has been pressed. Presses on
+/// controls do not reach the map.
+#[derive(Default)]
+pub struct ControlHandler {
+    window_position: Option<Vector2<f64>>,
+    /// A press which has not been hit-tested against the controls yet.
+    pressed: Option<Vector2<f64>>,
+    released: bool,
+    /// The part of a control which has been pressed.
+    active: Option<ControlHit>,
+}
+
+impl ControlHandler {
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn process_window_position(&mut self, window_position: &Vector2<f64>) -> bool {
+        self.window_position = Some(*window_position);
+        true
+    }
+
+    pub fn process_touch_start(&mut self, window_position: &Vector2<f64>) -> bool {
+        self.window_position = Some(*window_position);
+        self.pressed = Some(*window_position);
+        true
+    }
+
+    pub fn process_touch_end(&mut self) -> bool {
+        self.released = true;
+        true
+    }
+
+    pub fn process_mouse_key_press(&mut self, key: &MouseButton, state: &ElementState) -> bool {
+        if *key != MouseButton::Left {
+            return false;
+        }
+
+        if *state == ElementState::Pressed {
+            self.pressed = self.window_position;
+        } else {
+            self.released = true;
+        }
+        true
+    }
+}
+
+impl UpdateState for ControlHandler {
+    fn update_state(&mut self, map_context: &mut MapContext, _dt: Duration) {
+        let control_at = |map_context: &MapContext, position: Vector2<f64>| {
+            map_context.control_at(ScreenPoint::new(position.x, position.y))
+        };
+
+        if let Some(pressed) = self.pressed.take() {
+            self.active = control_at(map_context, pressed);
+        }
+
+        if std::mem::take(&mut self.released) {
+            if let Some(active) = self.active.take() {
+                let released_at = self
+                    .window_position
+                    .and_then(|position| control_at(map_context, position));
+                if released_at == Some(active) {
+                    active.activate(map_context);
+                }
+            }
+        }
+    }
+}
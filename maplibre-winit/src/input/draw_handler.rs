@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use cgmath::Vector2;
+use maplibre::{
+    context::MapContext,
+    coords::ScreenPoint,
+    draw::{DrawInput, DrawMode},
+};
+use winit::{
+    event::{ElementState, MouseButton},
+    keyboard::{Key, NamedKey},
+};
+
+use super::UpdateState;
+
+/// Passes the input to the drawing tools of the map while a [`DrawMode`] is active. Clicks add
+/// vertices and vertices can be dragged with the primary mouse button or a single finger. Enter
+/// finishes the feature, escape discards it and backspace removes its last vertex.
+#[derive(Default)]
+pub struct DrawHandler {
+    window_position: Option<Vector2<f64>>,
+    /// Input which has not been passed to the map yet, in the order in which it occurred.
+    inputs: Vec<DrawInput>,
+    /// Whether a vertex is dragged.
+    active: bool,
+    /// The mode as of the last update.
+    mode: DrawMode,
+}
+
+impl DrawHandler {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Whether clicks add features, such that double clicks must not zoom.
+    pub fn is_drawing(&self) -> bool {
+        self.mode.kind().is_some()
+    }
+
+    pub fn process_window_position(&mut self, window_position: &Vector2<f64>) -> bool {
+        self.window_position = Some(*window_position);
+        let input = DrawInput::Move(ScreenPoint::new(window_position.x, window_position.y));
+        // Only the last position between presses and releases matters
+        match self.inputs.last_mut() {
+            Some(last @ DrawInput::Move(_)) => *last = input,
+            _ => self.inputs.push(input),
+        }
+        true
+    }
+
+    pub fn process_touch_start(&mut self, window_position: &Vector2<f64>) -> bool {
+        self.process_window_position(window_position);
+        self.press();
+        true
+    }
+
+    pub fn process_touch_end(&mut self) -> bool {
+        self.release();
+        true
+    }
+
+    /// Drops the presses which have not been passed to the map yet, e.g. because they pressed a
+    /// control.
+    pub fn process_touch_cancel(&mut self) -> bool {
+        self.inputs
+            .retain(|input| !matches!(input, DrawInput::Press(_)));
+        true
+    }
+
+    pub fn process_mouse_key_press(&mut self, key: &MouseButton, state: &ElementState) -> bool {
+        if *key != MouseButton::Left {
+            return false;
+        }
+
+        if *state == ElementState::Pressed {
+            self.press();
+        } else {
+            self.release();
+        }
+        true
+    }
+
+    pub fn process_key_press(&mut self, key: &Key, state: ElementState) -> bool {
+        if self.mode == DrawMode::Off || state != ElementState::Pressed {
+            return false;
+        }
+
+        let input = match key {
+            Key::Named(NamedKey::Enter) => DrawInput::Finish,
+            Key::Named(NamedKey::Escape) => DrawInput::Cancel,
+            Key::Named(NamedKey::Backspace) => DrawInput::Undo,
+            _ => return false,
+        };
+        self.inputs.push(input);
+        true
+    }
+
+    fn press(&mut self) {
+        if let Some(position) = self.window_position {
+            self.inputs
+                .push(DrawInput::Press(ScreenPoint::new(position.x, position.y)));
+        }
+    }
+
+    fn release(&mut self) {
+        if let Some(position) = self.window_position {
+            self.inputs
+                .push(DrawInput::Release(ScreenPoint::new(position.x, position.y)));
+        }
+    }
+}
+
+impl UpdateState for DrawHandler {
+    fn update_state(&mut self, map_context: &mut MapContext, _dt: Duration) {
+        self.mode = map_context.draw_mode();
+        if self.mode == DrawMode::Off {
+            self.inputs.clear();
+            self.active = false;
+            return;
+        }
+
+        for input in std::mem::take(&mut self.inputs) {
+            let consumed = map_context.draw_input(input);
+            match input {
+                DrawInput::Press(_) => self.active = consumed,
+                DrawInput::Release(_) => self.active = false,
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! Recognises gestures of two fingers, i.e. pinch to zoom, rotate and tilt.
+//!
+//! The recogniser only depends on the positions of the touches, such that it is independent of
+//! winit and can be tested with synthetic touch sequences.
+
+use std::collections::BTreeMap;
+
+use cgmath::{InnerSpace, Vector2};
+
+/// A pinch is recognised once the distance between the fingers changed by this many zoom levels.
+const ZOOM_THRESHOLD: f64 = 0.1;
+/// A rotation is recognised once the fingers rotated by this many degrees.
+const ROTATE_THRESHOLD: f64 = 15.0;
+/// A tilt is recognised once both fingers moved vertically by this many pixels.
+const PITCH_THRESHOLD: f64 = 10.0;
+/// A two-finger pan is recognised once the centroid moved by this many pixels.
+const PAN_THRESHOLD: f64 = 10.0;
+/// The change of the pitch in degrees per pixel which the fingers moved vertically.
+const PITCH_PER_PIXEL: f64 = 0.5;
+
+/// The change of the camera which has been recognised from the movement of the fingers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GestureDelta {
+    /// The centroid of the fingers, around which the map is zoomed and rotated.
+    pub around: Vector2<f64>,
+    /// The movement of the centroid, by which the map is panned.
+    pub pan: Vector2<f64>,
+    /// The change of the zoom in zoom levels.
+    pub zoom: f64,
+    /// The change of the bearing in degrees counter-clockwise.
+    pub bearing: f64,
+    /// The change of the pitch in degrees.
+    pub pitch: f64,
+}
+
+impl GestureDelta {
+    /// Combines two consecutive changes.
+    pub fn merge(self, next: GestureDelta) -> GestureDelta {
+        GestureDelta {
+            around: next.around,
+            pan: self.pan + next.pan,
+            zoom: self.zoom + next.zoom,
+            bearing: self.bearing + next.bearing,
+            pitch: self.pitch + next.pitch,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    /// None of the thresholds has been exceeded yet.
+    Undecided,
+    /// Both fingers move vertically, which changes the pitch.
+    Pitch,
+    /// The fingers pan, zoom and rotate the map. Zooming and rotating are enabled once their
+    /// thresholds have been exceeded.
+    Transform { zoom: bool, rotate: bool },
+}
+
+/// The two fingers which make up a gesture.
+#[derive(Clone, Copy, Debug)]
+struct Pair {
+    ids: [u64; 2],
+    /// The positions of the fingers when the gesture started.
+    start: [Vector2<f64>; 2],
+    /// The positions of the fingers when the last change has been recognised.
+    previous: [Vector2<f64>; 2],
+}
+
+/// Tracks touches by their id and recognises the gestures of the first two fingers.
+pub struct GestureRecognizer {
+    touches: BTreeMap<u64, Vector2<f64>>,
+    pair: Option<Pair>,
+    mode: Mode,
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self {
+            touches: BTreeMap::new(),
+            pair: None,
+            mode: Mode::Undecided,
+        }
+    }
+}
+
+impl GestureRecognizer {
+    pub fn touch_start(&mut self, id: u64, position: Vector2<f64>) {
+        self.touches.insert(id, position);
+        self.update_pair();
+    }
+
+    /// Updates the position of a touch. Returns the change of the camera if a gesture is
+    /// recognised.
+    pub fn touch_move(&mut self, id: u64, position: Vector2<f64>) -> Option<GestureDelta> {
+        *self.touches.get_mut(&id)? = position;
+
+        let pair = self.pair.as_mut()?;
+        if !pair.ids.contains(&id) {
+            return None;
+        }
+
+        let current = pair.ids.map(|id| self.touches[&id]);
+        let previous = std::mem::replace(&mut pair.previous, current);
+        let start = pair.start;
+
+        self.mode = decide(self.mode, start, current);
+        let delta = delta(previous, current);
+        match self.mode {
+            Mode::Undecided => None,
+            Mode::Pitch => Some(GestureDelta {
+                pan: Vector2::new(0.0, 0.0),
+                zoom: 0.0,
+                bearing: 0.0,
+                ..delta
+            }),
+            Mode::Transform { zoom, rotate } => Some(GestureDelta {
+                zoom: if zoom { delta.zoom } else { 0.0 },
+                bearing: if rotate { delta.bearing } else { 0.0 },
+                pitch: 0.0,
+                ..delta
+            }),
+        }
+    }
+
+    /// Removes a touch which ended or has been cancelled.
+    pub fn touch_end(&mut self, id: u64) {
+        self.touches.remove(&id);
+        self.update_pair();
+    }
+
+    /// Whether two or more fingers touch the screen.
+    pub fn is_active(&self) -> bool {
+        self.touches.len() >= 2
+    }
+
+    /// Returns the position of the touch, if exactly one finger touches the screen.
+    pub fn single_touch(&self) -> Option<Vector2<f64>> {
+        if self.touches.len() == 1 {
+            self.touches.values().next().copied()
+        } else {
+            None
+        }
+    }
+
+    /// Starts a new gesture if the first two fingers changed.
+    fn update_pair(&mut self) {
+        let mut touches = self.touches.iter();
+        let (Some((a, a_position)), Some((b, b_position))) = (touches.next(), touches.next())
+        else {
+            self.pair = None;
+            self.mode = Mode::Undecided;
+            return;
+        };
+
+        let ids = [*a, *b];
+        if self.pair.is_some_and(|pair| pair.ids == ids) {
+            return;
+        }
+
+        let positions = [*a_position, *b_position];
+        self.pair = Some(Pair {
+            ids,
+            start: positions,
+            previous: positions,
+        });
+        self.mode = Mode::Undecided;
+    }
+}
+
+/// Decides which gesture is performed, based on the movement of the fingers since the start of
+/// the gesture. A pitch is exclusive, while zooming and rotating can be combined.
+fn decide(mode: Mode, start: [Vector2<f64>; 2], current: [Vector2<f64>; 2]) -> Mode {
+    let total = delta(start, current);
+    let zoom = total.zoom.abs() > ZOOM_THRESHOLD;
+    let rotate = total.bearing.abs() > ROTATE_THRESHOLD;
+
+    match mode {
+        Mode::Pitch => Mode::Pitch,
+        Mode::Transform {
+            zoom: zooming,
+            rotate: rotating,
+        } => Mode::Transform {
+            zoom: zooming || zoom,
+            rotate: rotating || rotate,
+        },
+        Mode::Undecided => {
+            let [a, b] = [current[0] - start[0], current[1] - start[1]];
+            let between = start[1] - start[0];
+            // Fingers side by side which both move vertically in the same direction
+            let tilting = between.y.abs() <= between.x.abs()
+                && a.y.abs() > a.x.abs()
+                && b.y.abs() > b.x.abs()
+                && a.y.signum() == b.y.signum();
+
+            if tilting && a.y.abs() > PITCH_THRESHOLD && b.y.abs() > PITCH_THRESHOLD {
+                Mode::Pitch
+            } else if zoom || rotate || (!tilting && total.pan.magnitude() > PAN_THRESHOLD) {
+                Mode::Transform { zoom, rotate }
+            } else {
+                Mode::Undecided
+            }
+        }
+    }
+}
+
+/// Returns the change of the camera, if the fingers moved from `previous` to `current`.
+fn delta(previous: [Vector2<f64>; 2], current: [Vector2<f64>; 2]) -> GestureDelta {
+    let centroid = |[a, b]: [Vector2<f64>; 2]| (a + b) / 2.0;
+    let previous_between = previous[1] - previous[0];
+    let current_between = current[1] - current[0];
+
+    let distances = (previous_between.magnitude(), current_between.magnitude());
+    let zoom = if distances.0 > 0.0 && distances.1 > 0.0 {
+        (distances.1 / distances.0).log2()
+    } else {
+        0.0
+    };
+
+    // The y axis of the window points down, such that a positive angle is a clockwise rotation of
+    // the fingers. The map follows the fingers, which decreases the bearing.
+    let angle = previous_between
+        .perp_dot(current_between)
+        .atan2(previous_between.dot(current_between))
+        .to_degrees();
+
+    let vertical = ((current[0].y - previous[0].y) + (current[1].y - previous[1].y)) / 2.0;
+
+    GestureDelta {
+        around: centroid(current),
+        pan: centroid(current) - centroid(previous),
+        zoom,
+        bearing: -angle,
+        pitch: -vertical * PITCH_PER_PIXEL,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Moves two fingers from their start positions to their end positions in `steps` and returns
+    /// the sum of the recognised changes.
+    fn gesture(start: [(f64, f64); 2], end: [(f64, f64); 2], steps: u32) -> Option<GestureDelta> {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.touch_start(7, start[0].into());
+        recognizer.touch_start(3, start[1].into());
+        assert!(recognizer.is_active());
+
+        let mut total: Option<GestureDelta> = None;
+        for step in 1..=steps {
+            let t = f64::from(step) / f64::from(steps);
+            for (id, (start, end)) in [(7, (start[0], end[0])), (3, (start[1], end[1]))] {
+                let position = Vector2::new(
+                    start.0 + (end.0 - start.0) * t,
+                    start.1 + (end.1 - start.1) * t,
+                );
+                if let Some(delta) = recognizer.touch_move(id, position) {
+                    total = Some(total.map_or(delta, |total| total.merge(delta)));
+                }
+            }
+        }
+
+        recognizer.touch_end(7);
+        assert!(!recognizer.is_active());
+        assert_eq!(recognizer.single_touch(), Some(end[1].into()));
+        total
+    }
+
+    #[test]
+    fn test_pinch() {
+        let delta = gesture(
+            [(100.0, 100.0), (200.0, 100.0)],
+            [(50.0, 100.0), (250.0, 100.0)],
+            20,
+        )
+        .unwrap();
+
+        // The first steps are below the threshold
+        assert!(delta.zoom > 0.8 && delta.zoom < 1.0);
+        assert_eq!(delta.bearing, 0.0);
+        assert_eq!(delta.pitch, 0.0);
+        assert_eq!(delta.around, Vector2::new(150.0, 100.0));
+    }
+
+    #[test]
+    fn test_rotate() {
+        // A clockwise rotation of 90 degrees around (100, 100)
+        let delta = gesture(
+            [(50.0, 100.0), (150.0, 100.0)],
+            [(100.0, 50.0), (100.0, 150.0)],
+            30,
+        )
+        .unwrap();
+
+        assert!(delta.bearing < -70.0 && delta.bearing > -90.0);
+        assert_eq!(delta.pitch, 0.0);
+    }
+
+    #[test]
+    fn test_pitch() {
+        let delta = gesture(
+            [(100.0, 300.0), (200.0, 300.0)],
+            [(100.0, 200.0), (200.0, 200.0)],
+            20,
+        )
+        .unwrap();
+
+        assert!(delta.pitch > 40.0 && delta.pitch <= 50.0);
+        assert_eq!(delta.zoom, 0.0);
+        assert_eq!(delta.bearing, 0.0);
+        assert_eq!(delta.pan, Vector2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_small_movement() {
+        assert!(gesture(
+            [(100.0, 100.0), (200.0, 100.0)],
+            [(102.0, 101.0), (199.0, 100.0)],
+            5
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_single_finger() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.touch_start(1, Vector2::new(0.0, 0.0));
+        assert!(!recognizer.is_active());
+        assert_eq!(recognizer.touch_move(1, Vector2::new(100.0, 0.0)), None);
+        assert_eq!(recognizer.single_touch(), Some(Vector2::new(100.0, 0.0)));
+    }
+}
@@ -0,0 +1,247 @@
+//! Continues the motion of the map after user input ended, e.g. when the pointer is released
+//! while panning.
+//!
+//! The velocity is sampled from the input shortly before the release and decays with a constant
+//! deceleration afterwards, like in MapLibre GL JS.
+
+use std::{
+    collections::VecDeque,
+    ops::{Add, Mul},
+    time::Duration,
+};
+
+use cgmath::{InnerSpace, Vector2};
+use instant::Instant;
+
+/// Only the input of this period before the release determines the velocity.
+const SAMPLE_PERIOD: Duration = Duration::from_millis(160);
+
+/// How the motion continues after user input ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InertiaOptions {
+    /// The factor by which the velocity of the input is multiplied.
+    pub linearity: f64,
+    /// The deceleration in units per second squared.
+    pub deceleration: f64,
+    /// The maximal speed in units per second.
+    pub max_speed: f64,
+}
+
+impl InertiaOptions {
+    /// Panning, in logical pixels.
+    pub const PAN: InertiaOptions = InertiaOptions {
+        linearity: 0.3,
+        deceleration: 2500.0,
+        max_speed: 1400.0,
+    };
+    /// Zooming, in zoom levels.
+    pub const ZOOM: InertiaOptions = InertiaOptions {
+        linearity: 0.15,
+        deceleration: 20.0,
+        max_speed: 2.5,
+    };
+    /// Rotating, in degrees.
+    pub const BEARING: InertiaOptions = InertiaOptions {
+        linearity: 0.25,
+        deceleration: 1000.0,
+        max_speed: 360.0,
+    };
+}
+
+/// A quantity which can keep moving, e.g. the position or the zoom.
+pub trait Motion: Copy + Add<Output = Self> + Mul<f64, Output = Self> {
+    fn zero() -> Self;
+
+    fn magnitude(self) -> f64;
+}
+
+impl Motion for f64 {
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn magnitude(self) -> f64 {
+        self.abs()
+    }
+}
+
+impl Motion for Vector2<f64> {
+    fn zero() -> Self {
+        Vector2::new(0.0, 0.0)
+    }
+
+    fn magnitude(self) -> f64 {
+        InnerSpace::magnitude(self)
+    }
+}
+
+/// Records the changes caused by user input and continues them after the input ended.
+pub struct Inertia<T> {
+    /// `None` if the motion stops with the input.
+    options: Option<InertiaOptions>,
+    samples: VecDeque<(Instant, T)>,
+    /// The velocity in units per second, if the motion continues.
+    velocity: Option<T>,
+}
+
+impl<T: Motion> Inertia<T> {
+    pub fn new(options: Option<InertiaOptions>) -> Self {
+        Self {
+            options,
+            samples: VecDeque::new(),
+            velocity: None,
+        }
+    }
+
+    pub fn set_options(&mut self, options: Option<InertiaOptions>) {
+        self.options = options;
+        if options.is_none() {
+            self.stop();
+        }
+    }
+
+    /// Records a change which has been caused by user input. This stops the current motion.
+    pub fn record(&mut self, time: Instant, delta: T) {
+        self.velocity = None;
+        self.samples.push_back((time, delta));
+        self.drain(time);
+    }
+
+    /// Stops the motion and drops the recorded changes.
+    pub fn stop(&mut self) {
+        self.samples.clear();
+        self.velocity = None;
+    }
+
+    /// Starts the motion with the velocity of the recent changes.
+    pub fn release(&mut self, time: Instant) {
+        self.drain(time);
+        let samples = std::mem::take(&mut self.samples);
+        let (Some(options), Some((first, _)), Some((last, _))) =
+            (self.options, samples.front(), samples.back())
+        else {
+            return;
+        };
+
+        let duration = last.duration_since(*first).as_secs_f64();
+        if duration <= 0.0 {
+            return;
+        }
+
+        let amount = samples
+            .iter()
+            .fold(T::zero(), |amount, (_, delta)| amount + *delta);
+        let velocity = amount * (options.linearity / duration);
+        let speed = velocity.magnitude();
+        if speed > 0.0 {
+            self.velocity = Some(velocity * (speed.min(options.max_speed) / speed));
+        }
+    }
+
+    /// Advances the motion by `dt`. Returns the change, or `None` if there is no motion.
+    pub fn step(&mut self, dt: Duration) -> Option<T> {
+        let (velocity, options) = (self.velocity?, self.options?);
+
+        let speed = velocity.magnitude();
+        let deceleration = options.deceleration * options.linearity;
+        // The motion stops within this step if the remaining time is shorter than it
+        let time = dt.as_secs_f64().min(speed / deceleration);
+        let next_speed = speed - deceleration * time;
+        let next_velocity = velocity * (next_speed / speed);
+
+        self.velocity = (next_speed > 0.0).then_some(next_velocity);
+        Some((velocity + next_velocity) * (time / 2.0))
+    }
+
+    /// Returns the time of the last recorded change, unless the motion has been released.
+    pub fn last_change(&self) -> Option<Instant> {
+        self.samples.back().map(|(time, _)| *time)
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.velocity.is_some()
+    }
+
+    /// Drops the changes which are too old to determine the velocity.
+    fn drain(&mut self, time: Instant) {
+        while self
+            .samples
+            .front()
+            .is_some_and(|(sample_time, _)| time.duration_since(*sample_time) > SAMPLE_PERIOD)
+        {
+            self.samples.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    /// Runs the motion until it stops and returns the total change.
+    fn run<T: Motion>(inertia: &mut Inertia<T>) -> T {
+        let mut total = T::zero();
+        while let Some(delta) = inertia.step(FRAME) {
+            total = total + delta;
+        }
+        total
+    }
+
+    #[test]
+    fn test_release() {
+        let start = Instant::now();
+        let mut inertia = Inertia::new(Some(InertiaOptions::PAN));
+        for frame in 0..5 {
+            inertia.record(start + FRAME * frame, Vector2::new(10.0, 0.0));
+        }
+        inertia.release(start + FRAME * 5);
+        assert!(inertia.is_moving());
+
+        // 50 pixels within 64 milliseconds, with a linearity of 0.3
+        let speed = 50.0 * 0.3 / 0.064;
+        let distance = speed * speed / (2.0 * 2500.0 * 0.3);
+        let total = run(&mut inertia);
+        assert!((total.x - distance).abs() < 1e-6);
+        assert_eq!(total.y, 0.0);
+        assert!(!inertia.is_moving());
+    }
+
+    #[test]
+    fn test_max_speed() {
+        let start = Instant::now();
+        let mut inertia = Inertia::new(Some(InertiaOptions::ZOOM));
+        inertia.record(start, -5.0);
+        inertia.record(start + FRAME, -5.0);
+        inertia.release(start + FRAME);
+
+        let distance = 2.5 * 2.5 / (2.0 * 20.0 * 0.15);
+        assert!((run(&mut inertia) + distance).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pause_before_release() {
+        let start = Instant::now();
+        let mut inertia = Inertia::new(Some(InertiaOptions::BEARING));
+        inertia.record(start, 5.0);
+        inertia.record(start + FRAME, 5.0);
+        inertia.release(start + FRAME + SAMPLE_PERIOD * 2);
+        assert!(!inertia.is_moving());
+
+        // A single change has no velocity
+        inertia.record(start, 5.0);
+        inertia.release(start);
+        assert_eq!(inertia.step(FRAME), None);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let start = Instant::now();
+        let mut inertia = Inertia::new(None);
+        inertia.record(start, 5.0);
+        inertia.record(start + FRAME, 5.0);
+        inertia.release(start + FRAME);
+        assert!(!inertia.is_moving());
+    }
+}
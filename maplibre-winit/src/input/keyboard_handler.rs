@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use cgmath::{Deg, Vector2};
+use maplibre::{
+    camera::{animation::AnimationOptions, CameraOptions},
+    context::MapContext,
+};
+use winit::{
+    event::ElementState,
+    keyboard::{Key, NamedKey},
+};
+
+use super::UpdateState;
+
+/// The distance in logical pixels by which the arrow keys pan the map.
+const PAN_STEP: f64 = 100.0;
+/// The zoom levels by which `+` and `-` zoom the map.
+const ZOOM_STEP: f64 = 1.0;
+/// The degrees by which the arrow keys rotate the map while shift is held.
+const BEARING_STEP: f64 = 15.0;
+/// The degrees by which the arrow keys pitch the map while shift is held.
+const PITCH_STEP: f64 = 10.0;
+const STEP_DURATION: Duration = Duration::from_millis(300);
+
+enum Step {
+    Pan(Vector2<f64>),
+    Zoom(f64),
+    Bearing(f64),
+    Pitch(f64),
+}
+
+/// Pans, zooms, rotates and pitches the map in animated steps with the arrow keys, `+` and `-`.
+#[derive(Default)]
+pub struct KeyboardHandler {
+    steps: Vec<Step>,
+}
+
+impl UpdateState for KeyboardHandler {
+    fn update_state(&mut self, map_context: &mut MapContext, _dt: Duration) {
+        let rotation = map_context.interaction_handlers().rotation;
+        let animation = || AnimationOptions {
+            duration: STEP_DURATION,
+            ..AnimationOptions::default()
+        };
+
+        for step in std::mem::take(&mut self.steps) {
+            match step {
+                Step::Pan(offset) => map_context.pan_by(offset, animation()),
+                Step::Zoom(delta) => map_context.zoom_by(delta, None, animation()),
+                Step::Bearing(delta) if rotation => {
+                    let bearing = map_context.view_state.bearing() + Deg(delta);
+                    map_context.ease_to(
+                        &CameraOptions {
+                            bearing: Some(bearing),
+                            ..CameraOptions::default()
+                        },
+                        animation(),
+                    );
+                }
+                Step::Bearing(_) => {}
+                Step::Pitch(delta) => {
+                    let pitch = map_context.view_state.pitch() + Deg(delta);
+                    map_context.ease_to(
+                        &CameraOptions {
+                            pitch: Some(pitch),
+                            ..CameraOptions::default()
+                        },
+                        animation(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl KeyboardHandler {
+    pub fn process_key_press(&mut self, key: &Key, state: ElementState, shift: bool) -> bool {
+        let step = match (key.as_ref(), shift) {
+            (Key::Named(NamedKey::ArrowUp), false) => Step::Pan(Vector2::new(0.0, -PAN_STEP)),
+            (Key::Named(NamedKey::ArrowDown), false) => Step::Pan(Vector2::new(0.0, PAN_STEP)),
+            (Key::Named(NamedKey::ArrowLeft), false) => Step::Pan(Vector2::new(-PAN_STEP, 0.0)),
+            (Key::Named(NamedKey::ArrowRight), false) => Step::Pan(Vector2::new(PAN_STEP, 0.0)),
+            (Key::Named(NamedKey::ArrowUp), true) => Step::Pitch(PITCH_STEP),
+            (Key::Named(NamedKey::ArrowDown), true) => Step::Pitch(-PITCH_STEP),
+            // The map turns like the view of a person who turns left or right
+            (Key::Named(NamedKey::ArrowLeft), true) => Step::Bearing(-BEARING_STEP),
+            (Key::Named(NamedKey::ArrowRight), true) => Step::Bearing(BEARING_STEP),
+            (Key::Character("+" | "="), _) => Step::Zoom(ZOOM_STEP),
+            (Key::Character("-" | "_"), _) => Step::Zoom(-ZOOM_STEP),
+            _ => return false,
+        };
+
+        if state == ElementState::Pressed {
+            self.steps.push(step);
+        }
+        true
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use cgmath::Vector2;
+use maplibre::{
+    annotation::MarkerId,
+    context::MapContext,
+    coords::ScreenPoint,
+    events::{MapEvent, MarkerEvent},
+};
+use winit::event::{ElementState, MouseButton};
+
+use super::UpdateState;
+
+/// Moves draggable markers with the primary mouse button or a single finger. While a marker is
+/// dragged, the map is not panned.
+#[derive(Default)]
+pub struct MarkerDragHandler {
+    window_position: Option<Vector2<f64>>,
+    /// A press which has not been hit-tested against the markers yet.
+    pressed: Option<Vector2<f64>>,
+    released: bool,
+    /// The dragged marker and the offset from the pointer to the location of the marker.
+    dragging: Option<(MarkerId, Vector2<f64>)>,
+}
+
+impl MarkerDragHandler {
+    pub fn is_dragging(&self) -> bool {
+        self.dragging.is_some()
+    }
+
+    pub fn process_window_position(&mut self, window_position: &Vector2<f64>) -> bool {
+        self.window_position = Some(*window_position);
+        true
+    }
+
+    pub fn process_touch_start(&mut self, window_position: &Vector2<f64>) -> bool {
+        self.window_position = Some(*window_position);
+        self.pressed = Some(*window_position);
+        true
+    }
+
+    pub fn process_touch_end(&mut self) -> bool {
+        self.released = true;
+        true
+    }
+
+    /// Drops a press which has not started a drag yet, e.g. because it pressed a control.
+    pub fn process_touch_cancel(&mut self) -> bool {
+        self.pressed = None;
+        true
+    }
+
+    pub fn process_mouse_key_press(&mut self, key: &MouseButton, state: &ElementState) -> bool {
+        if *key != MouseButton::Left {
+            return false;
+        }
+
+        if *state == ElementState::Pressed {
+            self.pressed = self.window_position;
+        } else {
+            self.released = true;
+        }
+        true
+    }
+}
+
+impl UpdateState for MarkerDragHandler {
+    fn update_state(&mut self, map_context: &mut MapContext, _dt: Duration) {
+        if let Some(pressed) = self.pressed.take() {
+            let point = ScreenPoint::new(pressed.x, pressed.y);
+            let marker = map_context.marker_at(point).and_then(|id| {
+                let marker = map_context.marker(id).filter(|marker| marker.draggable)?;
+                let location = map_context.view_state.project(marker.lat_lon)?;
+                Some((
+                    id,
+                    marker.lat_lon,
+                    Vector2::new(location.x, location.y) - pressed,
+                ))
+            });
+
+            if let Some((id, lat_lon, offset)) = marker {
+                self.dragging = Some((id, offset));
+                map_context.emit(MapEvent::MarkerDragStart(MarkerEvent { id, lat_lon }));
+            }
+        }
+
+        if let (Some((id, offset)), Some(window_position)) = (self.dragging, self.window_position) {
+            let target = window_position + offset;
+            let lat_lon = map_context
+                .view_state
+                .unproject(ScreenPoint::new(target.x, target.y));
+            let current = map_context.marker(id).map(|marker| marker.lat_lon);
+
+            if let Some(lat_lon) = lat_lon.filter(|lat_lon| Some(*lat_lon) != current) {
+                if map_context.set_marker_position(id, lat_lon) {
+                    map_context.emit(MapEvent::MarkerDrag(MarkerEvent { id, lat_lon }));
+                } else {
+                    // The marker has been removed while it was dragged
+                    self.dragging = None;
+                }
+            }
+        }
+
+        if std::mem::take(&mut self.released) {
+            if let Some((id, _)) = self.dragging.take() {
+                if let Some(marker) = map_context.marker(id) {
+                    let lat_lon = marker.lat_lon;
+                    map_context.emit(MapEvent::MarkerDragEnd(MarkerEvent { id, lat_lon }));
+                }
+            }
+        }
+    }
+}
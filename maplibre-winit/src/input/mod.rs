@@ -3,17 +3,30 @@
 use std::time::Duration;
 
 use cgmath::Vector2;
-use maplibre::context::MapContext;
-use winit::event::{DeviceEvent, KeyEvent, TouchPhase, WindowEvent};
+use maplibre::{
+    camera::animation::AnimationOptions, context::MapContext, coords::ScreenPoint,
+    interaction::InteractionHandlers,
+};
+use winit::event::{DeviceEvent, ElementState, KeyEvent, TouchPhase, WindowEvent};
 
+pub use crate::input::inertia::InertiaOptions;
 use crate::input::{
-    camera_handler::CameraHandler, debug_handler::DebugHandler, pan_handler::PanHandler,
-    pinch_handler::PinchHandler, query_handler::QueryHandler, shift_handler::ShiftHandler,
-    zoom_handler::ZoomHandler,
+    box_zoom_handler::BoxZoomHandler, camera_handler::CameraHandler,
+    control_handler::ControlHandler, debug_handler::DebugHandler, draw_handler::DrawHandler,
+    keyboard_handler::KeyboardHandler, marker_drag_handler::MarkerDragHandler,
+    pan_handler::PanHandler, pinch_handler::PinchHandler, query_handler::QueryHandler,
+    shift_handler::ShiftHandler, zoom_handler::ZoomHandler,
 };
 
+mod box_zoom_handler;
 mod camera_handler;
+mod control_handler;
 mod debug_handler;
+mod draw_handler;
+mod gesture;
+mod inertia;
+mod keyboard_handler;
+mod marker_drag_handler;
 mod pan_handler;
 mod pinch_handler;
 mod query_handler;
@@ -27,7 +40,19 @@ pub struct InputController {
     camera_handler: CameraHandler,
     shift_handler: ShiftHandler,
     query_handler: QueryHandler,
+    keyboard_handler: KeyboardHandler,
+    box_zoom_handler: BoxZoomHandler,
+    marker_drag_handler: MarkerDragHandler,
+    control_handler: ControlHandler,
+    draw_handler: DrawHandler,
     debug_handler: DebugHandler,
+    /// The interactions which are enabled, as of the last update.
+    handlers: InteractionHandlers,
+    /// Whether a shift key is held.
+    shift: bool,
+    /// Set if the user started to move the map since the last update. This interrupts camera
+    /// animations.
+    user_interacted: bool,
 }
 
 impl InputController {
@@ -49,10 +74,39 @@ impl InputController {
             camera_handler: CameraHandler::new(sensitivity),
             shift_handler: ShiftHandler::new(speed, sensitivity),
             query_handler: QueryHandler::new(),
+            keyboard_handler: KeyboardHandler::default(),
+            box_zoom_handler: BoxZoomHandler::default(),
+            marker_drag_handler: MarkerDragHandler::default(),
+            control_handler: ControlHandler::default(),
+            draw_handler: DrawHandler::default(),
             debug_handler: DebugHandler::default(),
+            handlers: InteractionHandlers::default(),
+            shift: false,
+            user_interacted: false,
         }
     }
 
+    /// Sets how panning, zooming and rotating continue after the input ended, e.g. after the
+    /// pointer has been released. `None` stops the motion together with the input.
+    pub fn set_inertia(
+        &mut self,
+        pan: Option<InertiaOptions>,
+        zoom: Option<InertiaOptions>,
+        bearing: Option<InertiaOptions>,
+    ) {
+        self.pan_handler.set_inertia(pan);
+        self.zoom_handler.set_inertia(zoom);
+        self.pinch_handler.set_inertia(zoom, bearing);
+    }
+
+    /// Whether the input keeps changing the camera without further events, e.g. because of
+    /// inertia. The map has to be rendered until this is unset.
+    pub fn is_animating(&self) -> bool {
+        self.pan_handler.is_moving()
+            || self.zoom_handler.is_moving()
+            || self.pinch_handler.is_moving()
+    }
+
     pub fn device_input(&mut self, _event: &DeviceEvent) -> bool {
         false
     }
@@ -73,6 +127,14 @@ impl InputController {
                 self.zoom_handler.process_window_position(&position, false);
                 self.camera_handler
                     .process_window_position(&position, false);
+                self.box_zoom_handler.process_window_position(&position);
+                self.marker_drag_handler.process_window_position(&position);
+                self.control_handler.process_window_position(&position);
+                self.draw_handler.process_window_position(&position);
+                true
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.shift = modifiers.state().shift_key();
                 true
             }
             WindowEvent::KeyboardInput {
@@ -81,43 +143,113 @@ impl InputController {
                 },
                 ..
             } => {
-                self.shift_handler.process_key_press(logical_key, *state)
-                    || self.debug_handler.process_key_press(logical_key, *state)
-                    || self.zoom_handler.process_key_press(logical_key, *state)
+                let processed = self.draw_handler.process_key_press(logical_key, *state)
+                    || (self.handlers.keyboard
+                        && (self.keyboard_handler.process_key_press(
+                            logical_key,
+                            *state,
+                            self.shift,
+                        ) || self.shift_handler.process_key_press(logical_key, *state)
+                            || self.zoom_handler.process_key_press(logical_key, *state)))
+                    || (cfg!(debug_assertions)
+                        && self.debug_handler.process_key_press(logical_key, *state));
+                self.user_interacted |= processed && *state == ElementState::Pressed;
+                processed
             }
-            WindowEvent::Touch(touch) => match touch.phase {
-                TouchPhase::Started => {
-                    let position: (f64, f64) = touch.location.to_owned().into();
-                    self.pan_handler
-                        .process_touch_start(&Vector2::from(position));
-                    self.query_handler.process_touch_start();
-                    true
-                }
-                TouchPhase::Ended => {
-                    self.pan_handler.process_touch_end();
-                    self.query_handler.process_touch_end();
-                    true
-                }
-                TouchPhase::Moved => {
-                    let position: (f64, f64) = touch.location.to_owned().into();
-                    let position = Vector2::from(position) / scale_factor;
-                    self.pan_handler.process_window_position(&position, true);
-                    self.query_handler.process_window_position(&position, true);
-                    self.zoom_handler.process_window_position(&position, true);
-                    self.camera_handler.process_window_position(&position, true);
-                    true
+            WindowEvent::Touch(touch) => {
+                let position: (f64, f64) = touch.location.to_owned().into();
+                let position = Vector2::from(position) / scale_factor;
+                match touch.phase {
+                    TouchPhase::Started => {
+                        self.user_interacted = true;
+                        self.pinch_handler.process_touch_start(touch.id, &position);
+                        if self.pinch_handler.is_active() {
+                            // Further fingers start a gesture instead of panning or clicking
+                            self.pan_handler.process_touch_cancel();
+                            self.query_handler.process_touch_cancel();
+                        } else {
+                            if self.handlers.drag_pan {
+                                self.pan_handler.process_touch_start(&position);
+                            }
+                            self.query_handler.process_touch_start(&position);
+                            self.marker_drag_handler.process_touch_start(&position);
+                            self.control_handler.process_touch_start(&position);
+                            self.draw_handler.process_touch_start(&position);
+                        }
+                        true
+                    }
+                    TouchPhase::Ended | TouchPhase::Cancelled => {
+                        let was_active = self.pinch_handler.is_active();
+                        self.pinch_handler.process_touch_end(touch.id);
+                        self.marker_drag_handler.process_touch_end();
+                        self.control_handler.process_touch_end();
+                        self.draw_handler.process_touch_end();
+                        if touch.phase == TouchPhase::Ended {
+                            self.pan_handler.process_touch_end();
+                            self.query_handler.process_touch_end();
+                        } else {
+                            self.pan_handler.process_touch_cancel();
+                            self.query_handler.process_touch_cancel();
+                        }
+
+                        // Continue to pan with the finger which remains after a gesture
+                        if let Some(remaining) = self
+                            .pinch_handler
+                            .single_touch()
+                            .filter(|_| was_active && self.handlers.drag_pan)
+                        {
+                            self.pan_handler.process_touch_start(&remaining);
+                        }
+                        true
+                    }
+                    TouchPhase::Moved => {
+                        self.pinch_handler.process_touch_move(touch.id, &position);
+                        if self.pinch_handler.is_active() {
+                            return true;
+                        }
+
+                        self.pan_handler.process_window_position(&position, true);
+                        self.query_handler.process_window_position(&position, true);
+                        self.zoom_handler.process_window_position(&position, true);
+                        self.camera_handler.process_window_position(&position, true);
+                        self.marker_drag_handler.process_window_position(&position);
+                        self.control_handler.process_window_position(&position);
+                        self.draw_handler.process_window_position(&position);
+                        true
+                    }
                 }
-                TouchPhase::Cancelled => false,
-            },
+            }
             WindowEvent::MouseWheel { delta, .. } => {
+                if !self.handlers.scroll_zoom {
+                    return false;
+                }
+                self.user_interacted = true;
                 self.shift_handler.process_scroll(delta);
                 self.zoom_handler.process_scroll(delta);
                 true
             }
             WindowEvent::MouseInput { button, state, .. } => {
-                self.pan_handler.process_mouse_key_press(button, state);
+                let pressed = *state == ElementState::Pressed;
+                self.user_interacted |= pressed;
+
+                // Releases are always processed, such that interactions which have been disabled
+                // in the meantime end
+                let boxing = self.box_zoom_handler.process_mouse_key_press(
+                    button,
+                    state,
+                    self.shift && self.handlers.box_zoom,
+                );
+                if !boxing && (self.handlers.drag_pan || !pressed) {
+                    self.pan_handler.process_mouse_key_press(button, state);
+                }
                 self.query_handler.process_mouse_key_press(button, state);
-                self.camera_handler.process_mouse_key_press(button, state);
+                self.marker_drag_handler
+                    .process_mouse_key_press(button, state);
+                self.control_handler.process_mouse_key_press(button, state);
+                self.draw_handler.process_mouse_key_press(button, state);
+                if self.handlers.drag_rotate || !pressed {
+                    self.camera_handler.process_mouse_key_press(button, state);
+                }
                 true
             }
             _ => false,
@@ -131,12 +263,55 @@ pub trait UpdateState {
 
 impl UpdateState for InputController {
     fn update_state(&mut self, map_context: &mut MapContext, dt: Duration) {
+        self.handlers = map_context.interaction_handlers();
+
+        if self.user_interacted {
+            map_context.stop_camera_animation();
+            self.user_interacted = false;
+        }
+
+        // Controls are drawn above the map, so pressing them does not drag markers, pan or click
+        self.control_handler.update_state(map_context, dt);
+        if self.control_handler.is_active() {
+            self.draw_handler.process_touch_cancel();
+            self.marker_drag_handler.process_touch_cancel();
+            self.pan_handler.process_touch_cancel();
+            self.query_handler.process_touch_cancel();
+        }
+
+        // Dragging a vertex of a drawn feature does not drag markers or pan either
+        self.draw_handler.update_state(map_context, dt);
+        if self.draw_handler.is_active() {
+            self.marker_drag_handler.process_touch_cancel();
+            self.pan_handler.process_touch_cancel();
+            self.query_handler.process_touch_cancel();
+        }
+
+        // Dragging a marker takes precedence over panning the map
+        self.marker_drag_handler.update_state(map_context, dt);
+        if self.marker_drag_handler.is_dragging() {
+            self.pan_handler.process_touch_cancel();
+        }
+
         self.pan_handler.update_state(map_context, dt);
         self.pinch_handler.update_state(map_context, dt);
         self.zoom_handler.update_state(map_context, dt);
         self.camera_handler.update_state(map_context, dt);
         self.shift_handler.update_state(map_context, dt);
         self.query_handler.update_state(map_context, dt);
+        self.keyboard_handler.update_state(map_context, dt);
+        self.box_zoom_handler.update_state(map_context, dt);
         self.debug_handler.update_state(map_context, dt);
+
+        for double_click in self.query_handler.take_double_clicks() {
+            // Double clicks finish the features which are drawn
+            if self.handlers.double_click_zoom && !self.draw_handler.is_drawing() {
+                map_context.zoom_by(
+                    if self.shift { -1.0 } else { 1.0 },
+                    Some(ScreenPoint::new(double_click.x, double_click.y)),
+                    AnimationOptions::default(),
+                );
+            }
+        }
     }
 }
@@ -1,22 +1,53 @@
 use std::time::Duration;
 
 use cgmath::{EuclideanSpace, Point2, Vector2, Zero};
+use instant::Instant;
 use maplibre::context::MapContext;
 use winit::event::{ElementState, MouseButton};
 
-use super::UpdateState;
+use super::{
+    inertia::{Inertia, InertiaOptions},
+    UpdateState,
+};
 
-#[derive(Default)]
 pub struct PanHandler {
     window_position: Option<Vector2<f64>>,
     start_window_position: Option<Vector2<f64>>,
     start_camera_position: Option<Vector2<f64>>,
     is_panning: bool,
+    inertia: Inertia<Vector2<f64>>,
+}
+
+impl Default for PanHandler {
+    fn default() -> Self {
+        Self {
+            window_position: None,
+            start_window_position: None,
+            start_camera_position: None,
+            is_panning: false,
+            inertia: Inertia::new(Some(InertiaOptions::PAN)),
+        }
+    }
 }
 
 impl UpdateState for PanHandler {
-    fn update_state(&mut self, MapContext { view_state, .. }: &mut MapContext, _dt: Duration) {
+    fn update_state(&mut self, MapContext { view_state, .. }: &mut MapContext, dt: Duration) {
         if !self.is_panning {
+            if let Some(delta) = self.inertia.step(dt) {
+                // Move the ground at the center of the window along with the motion
+                let center = Vector2::new(view_state.width(), view_state.height()) / 2.0;
+                let inverted_view_proj = view_state.view_projection().invert();
+                if let (Some(from), Some(to)) = (
+                    view_state.window_to_world_at_ground(&center, &inverted_view_proj, false),
+                    view_state.window_to_world_at_ground(
+                        &(center + delta),
+                        &inverted_view_proj,
+                        false,
+                    ),
+                ) {
+                    view_state.camera_mut().move_relative(from - to);
+                }
+            }
             return;
         }
 
@@ -53,17 +84,32 @@ impl UpdateState for PanHandler {
 }
 
 impl PanHandler {
+    pub fn set_inertia(&mut self, options: Option<InertiaOptions>) {
+        self.inertia.set_options(options);
+    }
+
+    /// Whether the map keeps moving after panning.
+    pub fn is_moving(&self) -> bool {
+        self.inertia.is_moving()
+    }
+
     pub fn process_touch_start(&mut self, window_position: &Vector2<f64>) -> bool {
+        self.inertia.stop();
         self.is_panning = true;
         self.start_window_position = Some(*window_position);
         true
     }
 
     pub fn process_touch_end(&mut self) -> bool {
-        self.start_camera_position = None;
-        self.start_window_position = None;
-        self.window_position = None;
-        self.is_panning = false;
+        self.end_panning();
+        self.inertia.release(Instant::now());
+        true
+    }
+
+    /// Stops panning without continuing the motion, e.g. because a gesture started.
+    pub fn process_touch_cancel(&mut self) -> bool {
+        self.end_panning();
+        self.inertia.stop();
         true
     }
 
@@ -72,6 +118,14 @@ impl PanHandler {
             self.start_window_position = Some(*window_position);
             self.window_position = Some(*window_position);
         } else {
+            if let Some(previous) = self
+                .window_position
+                .or(self.start_window_position)
+                .filter(|_| self.is_panning)
+            {
+                self.inertia
+                    .record(Instant::now(), *window_position - previous);
+            }
             self.window_position = Some(*window_position);
         }
 
@@ -85,14 +139,20 @@ impl PanHandler {
 
         if *state == ElementState::Pressed {
             // currently panning or starting to pan
+            self.inertia.stop();
             self.is_panning = true;
         } else {
             // finished panning
-            self.start_camera_position = None;
-            self.start_window_position = None;
-            self.window_position = None;
-            self.is_panning = false;
+            self.end_panning();
+            self.inertia.release(Instant::now());
         }
         true
     }
+
+    fn end_panning(&mut self) {
+        self.start_camera_position = None;
+        self.start_window_position = None;
+        self.window_position = None;
+        self.is_panning = false;
+    }
 }
@@ -1,14 +1,168 @@
 use std::time::Duration;
 
-use maplibre::context::MapContext;
+use cgmath::{Deg, Rad, Vector2};
+use instant::Instant;
+use maplibre::{context::MapContext, coords::Zoom};
 
-use super::UpdateState;
+use super::{
+    gesture::{GestureDelta, GestureRecognizer},
+    inertia::{Inertia, InertiaOptions},
+    UpdateState,
+};
 
-#[derive(Default)]
-pub struct PinchHandler {}
+/// Zooms, rotates and tilts the map with two fingers.
+pub struct PinchHandler {
+    recognizer: GestureRecognizer,
+    /// The change of the camera which has been recognised since the last update.
+    delta: Option<GestureDelta>,
+    /// The last centroid of the fingers, around which the motion continues after the gesture.
+    around: Option<Vector2<f64>>,
+    zoom_inertia: Inertia<f64>,
+    bearing_inertia: Inertia<f64>,
+}
+
+impl Default for PinchHandler {
+    fn default() -> Self {
+        Self {
+            recognizer: GestureRecognizer::default(),
+            delta: None,
+            around: None,
+            zoom_inertia: Inertia::new(Some(InertiaOptions::ZOOM)),
+            bearing_inertia: Inertia::new(Some(InertiaOptions::BEARING)),
+        }
+    }
+}
 
 impl UpdateState for PinchHandler {
-    fn update_state(&mut self, _map_context: &mut MapContext, _dt: Duration) {
-        // TODO
+    fn update_state(&mut self, map_context: &mut MapContext, dt: Duration) {
+        let Some(delta) = self.delta.take().or_else(|| self.continue_motion(dt)) else {
+            return;
+        };
+
+        let handlers = map_context.interaction_handlers();
+        let delta = GestureDelta {
+            pan: if handlers.touch_zoom_rotate {
+                delta.pan
+            } else {
+                Vector2::new(0.0, 0.0)
+            },
+            zoom: if handlers.touch_zoom_rotate {
+                delta.zoom
+            } else {
+                0.0
+            },
+            bearing: if handlers.touch_zoom_rotate && handlers.rotation {
+                delta.bearing
+            } else {
+                0.0
+            },
+            pitch: if handlers.touch_pitch {
+                delta.pitch
+            } else {
+                0.0
+            },
+            ..delta
+        };
+        let view_state = &mut map_context.view_state;
+
+        let inverted_view_proj = view_state.view_projection().invert();
+        let previous_around = view_state.window_to_world_at_ground(
+            &(delta.around - delta.pan),
+            &inverted_view_proj,
+            false,
+        );
+
+        let current_zoom = view_state.zoom();
+        // The zoom might be clamped by the constraints of the view
+        view_state.update_zoom(current_zoom + Zoom::new(delta.zoom));
+        let scale = current_zoom.scale_delta(&view_state.zoom());
+
+        let camera = view_state.camera_mut();
+        camera.roll(Deg(-delta.bearing));
+        camera.set_pitch(camera.get_pitch() + Rad::from(Deg(delta.pitch)));
+
+        // Keep the ground which was below the fingers below their centroid
+        let inverted_view_proj = view_state.view_projection().invert();
+        if let (Some(previous_around), Some(around)) = (
+            previous_around,
+            view_state.window_to_world_at_ground(&delta.around, &inverted_view_proj, false),
+        ) {
+            view_state
+                .camera_mut()
+                .move_relative(previous_around * scale - around);
+        }
+    }
+}
+
+impl PinchHandler {
+    pub fn set_inertia(&mut self, zoom: Option<InertiaOptions>, bearing: Option<InertiaOptions>) {
+        self.zoom_inertia.set_options(zoom);
+        self.bearing_inertia.set_options(bearing);
+    }
+
+    pub fn process_touch_start(&mut self, id: u64, window_position: &Vector2<f64>) -> bool {
+        self.recognizer.touch_start(id, *window_position);
+        self.zoom_inertia.stop();
+        self.bearing_inertia.stop();
+        true
+    }
+
+    pub fn process_touch_end(&mut self, id: u64) -> bool {
+        let was_active = self.recognizer.is_active();
+        self.recognizer.touch_end(id);
+
+        if was_active && !self.recognizer.is_active() {
+            let now = Instant::now();
+            self.zoom_inertia.release(now);
+            self.bearing_inertia.release(now);
+        }
+        true
+    }
+
+    pub fn process_touch_move(&mut self, id: u64, window_position: &Vector2<f64>) -> bool {
+        if let Some(delta) = self.recognizer.touch_move(id, *window_position) {
+            let now = Instant::now();
+            self.zoom_inertia.record(now, delta.zoom);
+            self.bearing_inertia.record(now, delta.bearing);
+            self.around = Some(delta.around);
+
+            self.delta = Some(match self.delta {
+                Some(previous) => previous.merge(delta),
+                None => delta,
+            });
+        }
+        true
+    }
+
+    /// Whether the zoom or bearing keep changing after a gesture.
+    pub fn is_moving(&self) -> bool {
+        self.zoom_inertia.is_moving() || self.bearing_inertia.is_moving()
+    }
+
+    /// Whether two or more fingers touch the screen, such that they do not pan or click.
+    pub fn is_active(&self) -> bool {
+        self.recognizer.is_active()
+    }
+
+    /// Returns the position of the touch, if exactly one finger touches the screen.
+    pub fn single_touch(&self) -> Option<Vector2<f64>> {
+        self.recognizer.single_touch()
+    }
+
+    /// Continues zooming and rotating around the last centroid of the fingers after the gesture.
+    fn continue_motion(&mut self, dt: Duration) -> Option<GestureDelta> {
+        let zoom = self.zoom_inertia.step(dt);
+        let bearing = self.bearing_inertia.step(dt);
+        if zoom.is_none() && bearing.is_none() {
+            return None;
+        }
+
+        Some(GestureDelta {
+            around: self.around?,
+            pan: Vector2::new(0.0, 0.0),
+            zoom: zoom.unwrap_or_default(),
+            bearing: bearing.unwrap_or_default(),
+            pitch: 0.0,
+        })
     }
 }
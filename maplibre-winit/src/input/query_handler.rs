@@ -1,36 +1,54 @@
 use std::time::Duration;
 
-use cgmath::Vector2;
-use maplibre::{
-    context::MapContext, coords::WorldCoords, io::geometry_index::IndexedGeometry,
-    render::tile_view_pattern::DEFAULT_TILE_SIZE,
-};
+use cgmath::{InnerSpace, Vector2};
+use instant::Instant;
+use maplibre::{context::MapContext, coords::ScreenPoint, events::MapEvent};
 use winit::event::{ElementState, MouseButton};
 
 use crate::input::UpdateState;
 
+/// Pointers which moved further than this between press and release do not click.
+const CLICK_TOLERANCE: f64 = 3.0;
+/// Two clicks within this interval are a double click.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Emits [`MapEvent::Click`], [`MapEvent::DblClick`] and [`MapEvent::ContextMenu`] events.
 pub struct QueryHandler {
     window_position: Option<Vector2<f64>>,
-    clicking: bool,
-    on_click: Option<Box<dyn Fn(f32, f32)>>,
+    /// The position at which the pointer was pressed, and whether it is the secondary button.
+    pressed: Option<(Vector2<f64>, bool)>,
+    /// Clicks which have not been emitted yet.
+    clicks: Vec<(Vector2<f64>, bool)>,
+    last_click: Option<(Instant, Vector2<f64>)>,
+    /// Double clicks which have not been taken yet.
+    double_clicks: Vec<Vector2<f64>>,
 }
 
 impl QueryHandler {
     pub fn new() -> Self {
         Self {
             window_position: None,
-            clicking: false,
-            on_click: None,
+            pressed: None,
+            clicks: Vec::new(),
+            last_click: None,
+            double_clicks: Vec::new(),
         }
     }
 
-    pub fn process_touch_start(&mut self) -> bool {
-        self.clicking = true;
+    pub fn process_touch_start(&mut self, window_position: &Vector2<f64>) -> bool {
+        self.window_position = Some(*window_position);
+        self.press(false);
         true
     }
 
     pub fn process_touch_end(&mut self) -> bool {
-        self.clicking = false;
+        self.release(false);
+        true
+    }
+
+    /// Drops the press of a touch, e.g. because it became part of a gesture.
+    pub fn process_touch_cancel(&mut self) -> bool {
+        self.pressed = None;
         true
     }
 
@@ -44,79 +62,82 @@ impl QueryHandler {
     }
 
     pub fn process_mouse_key_press(&mut self, key: &MouseButton, state: &ElementState) -> bool {
-        if *key != MouseButton::Left {
-            return false;
-        }
+        let secondary = match key {
+            MouseButton::Left => false,
+            MouseButton::Right => true,
+            _ => return false,
+        };
 
         if *state == ElementState::Pressed {
-            self.clicking = true;
+            self.press(secondary);
         } else {
-            self.clicking = false;
+            self.release(secondary);
         }
         true
     }
 
-    pub fn set_on_click<F: 'static + Fn(f32, f32)>(&mut self, f: F) {
-        self.on_click = Some(Box::new(f));
+    /// Returns the positions of the double clicks since the last call.
+    pub fn take_double_clicks(&mut self) -> Vec<Vector2<f64>> {
+        std::mem::take(&mut self.double_clicks)
+    }
+
+    fn press(&mut self, secondary: bool) {
+        self.pressed = self
+            .window_position
+            .map(|window_position| (window_position, secondary));
+    }
+
+    fn release(&mut self, secondary: bool) {
+        let (Some((pressed_position, pressed_secondary)), Some(window_position)) =
+            (self.pressed.take(), self.window_position)
+        else {
+            return;
+        };
+
+        if pressed_secondary == secondary
+            && (window_position - pressed_position).magnitude() <= CLICK_TOLERANCE
+        {
+            self.clicks.push((window_position, secondary));
+        }
     }
 }
 
 impl UpdateState for QueryHandler {
-    fn update_state(
-        &mut self,
-        MapContext {
-            view_state, world, ..
-        }: &mut MapContext,
-        _dt: Duration,
-    ) {
-        if self.clicking {
-            if let Some(window_position) = self.window_position {
-                let view_proj = view_state.view_projection();
-                let inverted_view_proj = view_proj.invert();
-
-                let z = view_state.zoom().zoom_level(DEFAULT_TILE_SIZE); // FIXME: can be wrong, if tiles of different z are visible
-                let zoom = view_state.zoom();
-
-                if let Some(coordinates) = view_state.window_to_world_at_ground(
-                    &window_position,
-                    &inverted_view_proj,
-                    false,
-                ) {
-                    if let Some(geometries) = world
-                        .tiles
-                        .geometry_index
-                        .query_point(
-                            &WorldCoords {
-                                x: coordinates.x,
-                                y: coordinates.y,
-                            },
-                            z,
-                            zoom,
-                        )
-                        .map(|geometries| {
-                            geometries
-                                .iter()
-                                .cloned()
-                                .cloned()
-                                .collect::<Vec<IndexedGeometry<f64>>>()
-                        })
-                    {
-                        log::info!(
-                            "Clicked on geometry: {:?}",
-                            geometries
-                                .iter()
-                                .map(|geometry| &geometry.properties)
-                                .collect::<Vec<_>>()
-                        );
-                        if let Some(ref cb) = self.on_click {
-                            cb(window_position.x as f32, window_position.y as f32);
-                        }
-                    } else {
-                        log::info!("No geometry found.",);
-                    }
-                }
+    fn update_state(&mut self, map_context: &mut MapContext, _dt: Duration) {
+        for (window_position, secondary) in std::mem::take(&mut self.clicks) {
+            let event =
+                map_context.pointer_event(ScreenPoint::new(window_position.x, window_position.y));
+
+            if secondary {
+                map_context.emit(MapEvent::ContextMenu(event));
+                continue;
+            }
+
+            if !event.features.is_empty() {
+                log::info!(
+                    "Clicked on geometry: {:?}",
+                    event
+                        .features
+                        .iter()
+                        .map(|geometry| &geometry.properties)
+                        .collect::<Vec<_>>()
+                );
+            }
+
+            let now = Instant::now();
+            let double_click = self.last_click.is_some_and(|(time, position)| {
+                now.duration_since(time) <= DOUBLE_CLICK_INTERVAL
+                    && (window_position - position).magnitude() <= CLICK_TOLERANCE
+            });
+
+            map_context.emit(MapEvent::Click(event.clone()));
+            if double_click {
+                map_context.emit(MapEvent::DblClick(event));
+                self.double_clicks.push(window_position);
+                self.last_click = None;
+            } else {
+                self.last_click = Some((now, window_position));
             }
-            self.clicking = false;
         }
     }
 }
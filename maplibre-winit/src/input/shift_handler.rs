@@ -2,7 +2,7 @@ use std::time::Duration;
 
 use cgmath::{Vector2, Zero};
 use maplibre::context::MapContext;
-use winit::keyboard::{Key, NamedKey};
+use winit::keyboard::Key;
 
 use super::UpdateState;
 
@@ -49,19 +49,19 @@ impl ShiftHandler {
             0.0
         };
         match key.as_ref() {
-            Key::Character("w") | Key::Named(NamedKey::ArrowUp) => {
+            Key::Character("w") => {
                 self.camera_translate.y -= amount;
                 true
             }
-            Key::Character("s") | Key::Named(NamedKey::ArrowDown) => {
+            Key::Character("s") => {
                 self.camera_translate.y += amount;
                 true
             }
-            Key::Character("a") | Key::Named(NamedKey::ArrowLeft) => {
+            Key::Character("a") => {
                 self.camera_translate.x -= amount;
                 true
             }
-            Key::Character("d") | Key::Named(NamedKey::ArrowRight) => {
+            Key::Character("d") => {
                 self.camera_translate.x += amount;
                 true
             }
@@ -1,25 +1,48 @@
 use std::time::Duration;
 
 use cgmath::Vector2;
+use instant::Instant;
 use maplibre::{context::MapContext, coords::Zoom};
 use winit::keyboard::Key;
 
-use super::UpdateState;
+use super::{
+    inertia::{Inertia, InertiaOptions},
+    UpdateState,
+};
+
+/// Scrolling is considered finished if there was no scroll event within this period. Afterwards,
+/// the zoom continues to change with the velocity of the scrolling.
+const SCROLL_RELEASE: Duration = Duration::from_millis(50);
 
 pub struct ZoomHandler {
     window_position: Option<Vector2<f64>>,
     zoom_delta: Option<Zoom>,
     sensitivity: f64,
+    inertia: Inertia<f64>,
 }
 
 impl UpdateState for ZoomHandler {
-    fn update_state(&mut self, MapContext { view_state, .. }: &mut MapContext, _dt: Duration) {
-        if let Some(zoom_delta) = self.zoom_delta {
+    fn update_state(&mut self, MapContext { view_state, .. }: &mut MapContext, dt: Duration) {
+        let now = Instant::now();
+        if self
+            .inertia
+            .last_change()
+            .is_some_and(|time| now.duration_since(time) > SCROLL_RELEASE)
+        {
+            self.inertia.release(now);
+        }
+
+        let zoom_delta = self
+            .zoom_delta
+            .or_else(|| self.inertia.step(dt).map(Zoom::new));
+
+        if let Some(zoom_delta) = zoom_delta {
             if let Some(window_position) = self.window_position {
                 let current_zoom = view_state.zoom();
-                let next_zoom = current_zoom + zoom_delta;
 
-                view_state.update_zoom(next_zoom);
+                // The zoom might be clamped by the constraints of the view
+                view_state.update_zoom(current_zoom + zoom_delta);
+                let next_zoom = view_state.zoom();
                 self.zoom_delta = None;
 
                 let view_proj = view_state.view_projection();
@@ -48,9 +71,19 @@ impl ZoomHandler {
             window_position: None,
             zoom_delta: None,
             sensitivity,
+            inertia: Inertia::new(Some(InertiaOptions::ZOOM)),
         }
     }
 
+    pub fn set_inertia(&mut self, options: Option<InertiaOptions>) {
+        self.inertia.set_options(options);
+    }
+
+    /// Whether the zoom keeps changing after scrolling, or scrolling has not been released yet.
+    pub fn is_moving(&self) -> bool {
+        self.inertia.is_moving() || self.inertia.last_change().is_some()
+    }
+
     pub fn process_window_position(
         &mut self,
         window_position: &Vector2<f64>,
@@ -65,17 +98,16 @@ impl ZoomHandler {
     }
 
     pub fn process_scroll(&mut self, delta: &winit::event::MouseScrollDelta) {
-        self.update_zoom(
-            match delta {
-                winit::event::MouseScrollDelta::LineDelta(_horizontal, vertical) => {
-                    *vertical as f64
-                }
-                winit::event::MouseScrollDelta::PixelDelta(winit::dpi::PhysicalPosition {
-                    y: scroll,
-                    ..
-                }) => *scroll / 100.0,
-            } * self.sensitivity,
-        );
+        let delta = match delta {
+            winit::event::MouseScrollDelta::LineDelta(_horizontal, vertical) => *vertical as f64,
+            winit::event::MouseScrollDelta::PixelDelta(winit::dpi::PhysicalPosition {
+                y: scroll,
+                ..
+            }) => *scroll / 100.0,
+        } * self.sensitivity;
+
+        self.inertia.record(Instant::now(), delta);
+        self.update_zoom(delta);
     }
 
     pub fn process_key_press(&mut self, key: &Key, state: winit::event::ElementState) -> bool {
@@ -86,11 +118,11 @@ impl ZoomHandler {
         };
 
         match key.as_ref() {
-            Key::Character("i") | Key::Character("+") => {
+            Key::Character("i") => {
                 self.update_zoom(amount);
                 true
             }
-            Key::Character("k") | Key::Character("-") => {
+            Key::Character("k") => {
                 self.update_zoom(-amount);
                 true
             }
@@ -1,6 +1,6 @@
 #![deny(unused_imports)]
 
-use std::{fmt::Debug, marker::PhantomData};
+use std::{fmt::Debug, marker::PhantomData, sync::Arc};
 
 use instant::Instant;
 use maplibre::{
@@ -40,7 +40,7 @@ pub use noweb::*;
 pub use web::*;
 
 pub struct WinitMapWindow<ET: 'static> {
-    window: RawWinitWindow,
+    window: Arc<RawWinitWindow>,
     event_loop: Option<WinitEventLoop<ET>>,
 }
 
@@ -61,6 +61,16 @@ impl<ET> HeadedMapWindow for WinitMapWindow<ET> {
         self.window.request_redraw()
     }
 
+    fn redraw_waker(&self) -> Box<dyn Fn()> {
+        // The waker must not keep the window alive after the map has been dropped
+        let window = Arc::downgrade(&self.window);
+        Box::new(move || {
+            if let Some(window) = window.upgrade() {
+                window.request_redraw();
+            }
+        })
+    }
+
     fn scale_factor(&self) -> f64 {
         self.window.scale_factor()
     }
@@ -113,16 +123,9 @@ impl<ET: 'static + PartialEq + Debug> EventLoop<ET> for WinitEventLoop<ET> {
                         ref event,
                         window_id,
                     } if window_id == map.window().id().into() => {
-                        let map_ptr: *mut Map<E> = &mut map;
-
-                        input_controller.query_handler_mut().set_on_click(move |x, y| {
-                            unsafe {
-                                (*map_ptr).on_user_click(x, y);
-                            }
-                        });
                         match event {
                             WindowEvent::RedrawRequested => {
-                                if !map.is_initialized() {
+                                if !map.is_initialized() || map.is_suspended() {
                                     return;
                                 }
 
@@ -146,12 +149,17 @@ impl<ET: 'static + PartialEq + Debug> EventLoop<ET> for WinitEventLoop<ET> {
                                     current_frame += 1;
                                 }
 
-                                map.window().request_redraw();
+                                // Render on demand, e.g. while the camera moves or tiles load
+                                if map.needs_repaint() || max_frames.is_some() || input_controller.is_animating() {
+                                    map.window().request_redraw();
+                                }
                             }
                             _ => {}
                         }
 
-                        if !input_controller.window_input(event, scale_factor) {
+                        if input_controller.window_input(event, scale_factor) {
+                            map.window().request_redraw();
+                        } else {
                             match event {
                                 WindowEvent::CloseRequested
                                 | WindowEvent::KeyboardInput {
@@ -176,6 +184,7 @@ impl<ET: 'static + PartialEq + Debug> EventLoop<ET> for WinitEventLoop<ET> {
                                         log::info!("New scaling factor: {}", new_scale_factor);
                                         scale_factor = *new_scale_factor;
                                         map_context.resize(map_context.renderer.resources.surface.size(), scale_factor);
+                                        map.window().request_redraw();
                                     }
                                 }
                                 _ => {}
@@ -184,11 +193,15 @@ impl<ET: 'static + PartialEq + Debug> EventLoop<ET> for WinitEventLoop<ET> {
                     }
 
                     Event::Suspended => {
-                        log::info!("Suspending and dropping render state.");
-                        map.reset() // TODO: Instead of resetting the whole map (incl. the renderer) only reset the renderer
+                        log::info!("Suspending and dropping the surface and GPU resources.");
+                        map.suspend();
                     }
                     Event::Resumed => {
-                        // FIXME unimplemented!()
+                        if map.is_suspended() {
+                            log::info!("Resuming and recreating the surface.");
+                            map.resume().expect("Failed to resume map!");
+                            map.window().request_redraw();
+                        }
                     }
                     _ => {}
                 }
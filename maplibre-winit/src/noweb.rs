@@ -3,12 +3,15 @@
 //! * Platform Events like suspend/resume
 //! * Render a new frame
 
-use std::{marker::PhantomData, path::PathBuf};
+use std::{marker::PhantomData, path::PathBuf, sync::Arc};
 
 use maplibre::{
     environment::OffscreenKernelConfig,
     event_loop::EventLoop,
-    io::apc::SchedulerAsyncProcedureCall,
+    io::{
+        apc::SchedulerAsyncProcedureCall,
+        source_client::{HttpSourceClient, SourceClient},
+    },
     kernel::{Kernel, KernelBuilder},
     map::Map,
     platform::{
@@ -16,7 +19,7 @@ use maplibre::{
         ReqwestOffscreenKernelEnvironment,
     },
     render::{builder::RendererBuilder, settings::WgpuSettings, RenderPlugin},
-    style::Style,
+    style::{loader::StyleLoader, Style},
     window::{MapWindow, MapWindowConfig, PhysicalSize, WindowCreateError},
 };
 use winit::window::WindowAttributes;
@@ -91,7 +94,7 @@ impl<ET: 'static + Clone> MapWindowConfig for WinitMapWindowConfig<ET> {
             .map_err(|_| WindowCreateError::Window)?;
 
         Ok(Self::MapWindow {
-            window,
+            window: Arc::new(window),
             event_loop: Some(WinitEventLoop {
                 event_loop: raw_event_loop,
             }),
@@ -99,10 +102,13 @@ impl<ET: 'static + Clone> MapWindowConfig for WinitMapWindowConfig<ET> {
     }
 }
 
+/// Runs a map in a window. The style is loaded from `style_url`, which can be a url or a path.
+/// If no url is given, then the default style is used.
 pub fn run_headed_map<P>(
     cache_path: Option<P>,
     window_config: WinitMapWindowConfig<()>,
     wgpu_settings: WgpuSettings,
+    style_url: Option<&str>,
 ) where
     P: Into<PathBuf>,
 {
@@ -113,6 +119,16 @@ pub fn run_headed_map<P>(
         let cache_path = cache_path.map(|path| path.into());
         let client = ReqwestHttpClient::new(cache_path.clone());
 
+        let style = match style_url {
+            Some(style_url) => {
+                StyleLoader::new(SourceClient::new(HttpSourceClient::new(client.clone())))
+                    .load(style_url)
+                    .await
+                    .expect("failed to load style")
+            }
+            None => Style::default(),
+        };
+
         let kernel: Kernel<Environment<_, _, _>> = KernelBuilder::new()
             .with_map_window_config(window_config)
             .with_http_client(client.clone())
@@ -128,17 +144,24 @@ pub fn run_headed_map<P>(
         let renderer_builder = RendererBuilder::new().with_wgpu_settings(wgpu_settings);
 
         let mut map = Map::new(
-            Style::default(),
+            style,
             kernel,
             renderer_builder,
             vec![
                 Box::new(RenderPlugin::default()),
+                Box::new(maplibre::background::BackgroundPlugin),
                 Box::new(maplibre::vector::VectorPlugin::<
                     maplibre::vector::DefaultVectorTransferables,
                 >::default()),
-                // Box::new(maplibre::raster::RasterPlugin::<
-                //     maplibre::raster::DefaultRasterTransferables,
-                // >::default()),
+                Box::new(maplibre::raster::RasterPlugin::<
+                    maplibre::raster::DefaultRasterTransferables,
+                >::default()),
+                Box::new(maplibre::terrain::TerrainPlugin),
+                Box::new(maplibre::heatmap::HeatmapPlugin),
+                Box::new(maplibre::render::overlay::OverlayPlugin),
+                Box::new(maplibre::annotation::AnnotationPlugin),
+                Box::new(maplibre::control::ControlPlugin),
+                Box::new(maplibre::draw::DrawPlugin),
                 #[cfg(debug_assertions)]
                 Box::new(maplibre::debug::DebugPlugin::default()),
             ],
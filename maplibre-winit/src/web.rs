@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use std::{marker::PhantomData, sync::Arc};
 
 use maplibre::window::{MapWindow, MapWindowConfig, PhysicalSize, WindowCreateError};
 use winit::{platform::web::WindowAttributesExtWebSys, window::WindowAttributes};
@@ -36,7 +36,7 @@ impl<ET: 'static + Clone> MapWindowConfig for WinitMapWindowConfig<ET> {
             .map_err(|_| WindowCreateError::Window)?;
 
         Ok(Self::MapWindow {
-            window,
+            window: Arc::new(window),
             event_loop: Some(WinitEventLoop {
                 event_loop: raw_event_loop,
             }),
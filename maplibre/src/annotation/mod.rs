@@ -0,0 +1,344 @@
+//! Markers and popups which are anchored to locations on the map.
+//!
+//! The annotations are projected to the screen every frame, such that they follow the camera,
+//! and are drawn in the overlay. Markers can be hit-tested, e.g. in order to drag them.
+
+use std::{collections::BTreeMap, rc::Rc};
+
+use glam::{Vec2, Vec4};
+
+use crate::{
+    annotation::queue_system::queue_system,
+    coords::{LatLon, ScreenPoint},
+    environment::Environment,
+    kernel::Kernel,
+    plugin::Plugin,
+    render::{graph::RenderGraph, overlay::OverlayTextureId, RenderStageLabel},
+    schedule::Schedule,
+    tcs::world::World,
+};
+
+mod queue_system;
+
+/// Markers are drawn above the shapes of the overlay.
+pub const MARKER_Z: u32 = 1000;
+/// Popups are drawn above the markers.
+pub const POPUP_Z: u32 = 2000;
+
+/// Size of the default marker image in pixels.
+const DEFAULT_MARKER_SIZE: u32 = 24;
+
+/// The part of an annotation which is placed at its location.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Anchor {
+    #[default]
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Returns the top-left corner of a box of `size`, whose anchor is placed at `point`.
+    pub fn top_left(&self, point: Vec2, size: Vec2) -> Vec2 {
+        let relative = match self {
+            Anchor::Center => Vec2::new(0.5, 0.5),
+            Anchor::Top => Vec2::new(0.5, 0.0),
+            Anchor::Bottom => Vec2::new(0.5, 1.0),
+            Anchor::Left => Vec2::new(0.0, 0.5),
+            Anchor::Right => Vec2::new(1.0, 0.5),
+            Anchor::TopLeft => Vec2::new(0.0, 0.0),
+            Anchor::TopRight => Vec2::new(1.0, 0.0),
+            Anchor::BottomLeft => Vec2::new(0.0, 1.0),
+            Anchor::BottomRight => Vec2::new(1.0, 1.0),
+        };
+        point - relative * size
+    }
+}
+
+/// An RGBA image with 8 bits per channel, which is shown by a marker.
+#[derive(Clone, Debug)]
+pub struct MarkerImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl MarkerImage {
+    pub fn from_rgba(width: u32, height: u32, data: Vec<u8>) -> Self {
+        assert_eq!(
+            data.len(),
+            (width * height * 4) as usize,
+            "marker image must be RGBA"
+        );
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+impl Default for MarkerImage {
+    /// A blue dot with a white border.
+    fn default() -> Self {
+        let size = DEFAULT_MARKER_SIZE;
+        let center = size as f32 / 2.0;
+
+        let data = (0..size * size)
+            .flat_map(|i| {
+                let position = Vec2::new((i % size) as f32 + 0.5, (i / size) as f32 + 0.5);
+                let distance = position.distance(Vec2::splat(center));
+                if distance > center {
+                    [0, 0, 0, 0]
+                } else if distance > center - 3.0 {
+                    [255, 255, 255, 255]
+                } else {
+                    [56, 135, 190, 255]
+                }
+            })
+            .collect();
+
+        Self::from_rgba(size, size, data)
+    }
+}
+
+/// An image at a location on the map.
+#[derive(Clone, Debug)]
+pub struct Marker {
+    pub lat_lon: LatLon,
+    pub image: MarkerImage,
+    /// Size in logical pixels. Defaults to the size of the image.
+    pub size: Option<Vec2>,
+    pub anchor: Anchor,
+    /// Offset of the image from its anchor in logical pixels
+    pub offset: Vec2,
+    /// Whether the marker can be moved by dragging it.
+    pub draggable: bool,
+}
+
+impl Marker {
+    /// Creates a marker with the default image, which is centered on `lat_lon`.
+    pub fn new(lat_lon: LatLon) -> Self {
+        Self {
+            lat_lon,
+            image: MarkerImage::default(),
+            size: None,
+            anchor: Anchor::Center,
+            offset: Vec2::ZERO,
+            draggable: false,
+        }
+    }
+
+    fn size(&self) -> Vec2 {
+        self.size
+            .unwrap_or(Vec2::new(self.image.width as f32, self.image.height as f32))
+    }
+}
+
+/// A box with text at a location on the map.
+#[derive(Clone, Debug)]
+pub struct Popup {
+    pub lat_lon: LatLon,
+    /// Lines are separated by `\n`.
+    pub text: String,
+    pub anchor: Anchor,
+    /// Offset of the box from its anchor in logical pixels
+    pub offset: Vec2,
+    /// Height of a line in logical pixels
+    pub text_size: f32,
+    pub text_color: Vec4,
+    pub background_color: Vec4,
+    /// Space between the text and the border of the box in logical pixels
+    pub padding: f32,
+}
+
+impl Popup {
+    /// Creates a popup which is shown above `lat_lon`.
+    pub fn new(lat_lon: LatLon, text: impl Into<String>) -> Self {
+        Self {
+            lat_lon,
+            text: text.into(),
+            anchor: Anchor::Bottom,
+            offset: Vec2::ZERO,
+            text_size: 14.0,
+            text_color: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            background_color: Vec4::ONE,
+            padding: 6.0,
+        }
+    }
+}
+
+/// Identifies a [`Marker`] of the [`Annotations`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MarkerId(u32);
+
+/// Identifies a [`Popup`] of the [`Annotations`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PopupId(u32);
+
+struct PlacedMarker {
+    marker: Marker,
+    /// The texture of the image, once it has been added to the overlay textures
+    texture: Option<OverlayTextureId>,
+    /// The box on the screen in which the marker has been drawn during the last frame
+    bounds: Option<(Vec2, Vec2)>,
+}
+
+/// The markers and popups of the map. Annotations which have been added later are drawn on top.
+#[derive(Default)]
+pub struct Annotations {
+    markers: BTreeMap<MarkerId, PlacedMarker>,
+    popups: BTreeMap<PopupId, Popup>,
+    next_id: u32,
+    /// Textures of removed markers, which have not been removed from the overlay yet
+    released_textures: Vec<OverlayTextureId>,
+}
+
+impl Annotations {
+    pub fn add_marker(&mut self, marker: Marker) -> MarkerId {
+        let id = MarkerId(self.next_id());
+        self.markers.insert(
+            id,
+            PlacedMarker {
+                marker,
+                texture: None,
+                bounds: None,
+            },
+        );
+        id
+    }
+
+    pub fn remove_marker(&mut self, id: MarkerId) -> Option<Marker> {
+        let placed = self.markers.remove(&id)?;
+        self.released_textures.extend(placed.texture);
+        Some(placed.marker)
+    }
+
+    pub fn marker(&self, id: MarkerId) -> Option<&Marker> {
+        self.markers.get(&id).map(|placed| &placed.marker)
+    }
+
+    /// Moves a marker. Returns `false` if the marker does not exist.
+    pub fn set_marker_position(&mut self, id: MarkerId, lat_lon: LatLon) -> bool {
+        let Some(placed) = self.markers.get_mut(&id) else {
+            return false;
+        };
+        placed.marker.lat_lon = lat_lon;
+        true
+    }
+
+    pub fn markers(&self) -> impl Iterator<Item = (MarkerId, &Marker)> {
+        self.markers
+            .iter()
+            .map(|(id, placed)| (*id, &placed.marker))
+    }
+
+    /// Returns the top-most marker which has been drawn at `point` during the last frame.
+    pub fn marker_at(&self, point: ScreenPoint) -> Option<MarkerId> {
+        let point = Vec2::new(point.x as f32, point.y as f32);
+        self.markers
+            .iter()
+            .rev()
+            .find(|(_, placed)| {
+                placed
+                    .bounds
+                    .is_some_and(|(min, max)| point.cmpge(min).all() && point.cmple(max).all())
+            })
+            .map(|(id, _)| *id)
+    }
+
+    pub fn add_popup(&mut self, popup: Popup) -> PopupId {
+        let id = PopupId(self.next_id());
+        self.popups.insert(id, popup);
+        id
+    }
+
+    pub fn remove_popup(&mut self, id: PopupId) -> Option<Popup> {
+        self.popups.remove(&id)
+    }
+
+    pub fn popup(&self, id: PopupId) -> Option<&Popup> {
+        self.popups.get(&id)
+    }
+
+    pub fn popups(&self) -> impl Iterator<Item = (PopupId, &Popup)> {
+        self.popups.iter().map(|(id, popup)| (*id, popup))
+    }
+
+    fn next_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+}
+
+/// Draws the [`Annotations`]. Requires the [`OverlayPlugin`](crate::render::overlay::OverlayPlugin).
+#[derive(Default)]
+pub struct AnnotationPlugin;
+
+impl<E: Environment> Plugin<E> for AnnotationPlugin {
+    fn build(
+        &self,
+        schedule: &mut Schedule,
+        _kernel: Rc<Kernel<E>>,
+        world: &mut World,
+        _graph: &mut RenderGraph,
+    ) {
+        world.resources.init::<Annotations>();
+
+        schedule.add_system_to_stage(RenderStageLabel::Queue, queue_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchor() {
+        let size = Vec2::new(20.0, 10.0);
+        let point = Vec2::new(100.0, 100.0);
+
+        assert_eq!(Anchor::Center.top_left(point, size), Vec2::new(90.0, 95.0));
+        assert_eq!(Anchor::Bottom.top_left(point, size), Vec2::new(90.0, 90.0));
+        assert_eq!(Anchor::TopLeft.top_left(point, size), point);
+        assert_eq!(
+            Anchor::BottomRight.top_left(point, size),
+            Vec2::new(80.0, 90.0)
+        );
+    }
+
+    #[test]
+    fn test_marker_at() {
+        let mut annotations = Annotations::default();
+        let lat_lon = LatLon::new(0.0, 0.0);
+        let below = annotations.add_marker(Marker::new(lat_lon));
+        let above = annotations.add_marker(Marker::new(lat_lon));
+
+        // Markers which have not been drawn yet are not hit
+        assert_eq!(annotations.marker_at(ScreenPoint::new(10.0, 10.0)), None);
+
+        for (id, bounds) in [
+            (below, (Vec2::new(0.0, 0.0), Vec2::new(20.0, 20.0))),
+            (above, (Vec2::new(10.0, 10.0), Vec2::new(30.0, 30.0))),
+        ] {
+            annotations.markers.get_mut(&id).unwrap().bounds = Some(bounds);
+        }
+
+        assert_eq!(
+            annotations.marker_at(ScreenPoint::new(5.0, 5.0)),
+            Some(below)
+        );
+        assert_eq!(
+            annotations.marker_at(ScreenPoint::new(15.0, 15.0)),
+            Some(above)
+        );
+        assert_eq!(annotations.marker_at(ScreenPoint::new(35.0, 5.0)), None);
+    }
+}
@@ -0,0 +1,101 @@
+//! Projects the annotations for the current camera and queues them into the overlay.
+
+use glam::Vec2;
+
+use crate::{
+    annotation::{Annotations, MARKER_Z, POPUP_Z},
+    context::MapContext,
+    render::{
+        overlay::{
+            OverlayFont, OverlayImage, OverlayItem, OverlayText, OverlayTexture, OverlayTextures,
+            Shape,
+        },
+        render_phase::RenderPhase,
+    },
+};
+
+pub fn queue_system(
+    MapContext {
+        world, view_state, ..
+    }: &mut MapContext,
+) {
+    let Some((annotations, textures, font, overlay_phase)) = world.resources.query_mut::<(
+        &mut Annotations,
+        &mut OverlayTextures,
+        &OverlayFont,
+        &mut RenderPhase<OverlayItem>,
+    )>() else {
+        return;
+    };
+
+    for texture in annotations.released_textures.drain(..) {
+        textures.remove(texture);
+    }
+
+    let screen_size = Vec2::new(view_state.width() as f32, view_state.height() as f32);
+    let is_visible =
+        |min: Vec2, max: Vec2| max.cmpge(Vec2::ZERO).all() && min.cmple(screen_size).all();
+    let project = |lat_lon| {
+        view_state
+            .project(lat_lon)
+            .map(|point| Vec2::new(point.x as f32, point.y as f32))
+    };
+
+    for placed in annotations.markers.values_mut() {
+        let marker = &placed.marker;
+        let texture_id = *placed.texture.get_or_insert_with(|| {
+            textures.add(OverlayTexture {
+                width: marker.image.width,
+                height: marker.image.height,
+                data: marker.image.data.clone(),
+            })
+        });
+
+        let size = marker.size();
+        placed.bounds = project(marker.lat_lon)
+            .map(|point| marker.anchor.top_left(point + marker.offset, size))
+            .map(|min| (min, min + size))
+            .filter(|(min, max)| is_visible(*min, *max));
+
+        if let Some((pos, _)) = placed.bounds {
+            overlay_phase.add(OverlayItem::new(
+                OverlayImage {
+                    pos,
+                    size,
+                    texture_id,
+                },
+                MARKER_Z,
+            ));
+        }
+    }
+
+    for popup in annotations.popups.values() {
+        let Some(point) = project(popup.lat_lon) else {
+            continue;
+        };
+
+        let size = font.measure(&popup.text, popup.text_size) + Vec2::splat(popup.padding * 2.0);
+        let min = popup.anchor.top_left(point + popup.offset, size);
+        if !is_visible(min, min + size) {
+            continue;
+        }
+
+        overlay_phase.add(OverlayItem::new(
+            Shape::Rect {
+                pos: min,
+                size,
+                color: popup.background_color,
+            },
+            POPUP_Z,
+        ));
+        overlay_phase.add(OverlayItem::new(
+            OverlayText {
+                text: popup.text.clone(),
+                position: min + Vec2::splat(popup.padding),
+                color: popup.text_color,
+                size: popup.text_size,
+            },
+            POPUP_Z,
+        ));
+    }
+}
@@ -0,0 +1,100 @@
+//! Renders background layers, which fill the whole viewport with a color or a repeated pattern.
+
+use std::{collections::HashMap, rc::Rc};
+
+use image::RgbaImage;
+
+use crate::{
+    background::{
+        queue_system::queue_system, resource::BackgroundResources, resource_system::resource_system,
+    },
+    environment::Environment,
+    kernel::Kernel,
+    plugin::Plugin,
+    render::{
+        eventually::{Eventually, GpuResources},
+        graph::RenderGraph,
+        RenderStageLabel,
+    },
+    schedule::Schedule,
+    tcs::world::World,
+};
+
+mod queue_system;
+mod render_commands;
+mod resource;
+mod resource_system;
+
+/// Images which can be referenced by the `background-pattern` of a layer.
+#[derive(Default)]
+pub struct PatternImages {
+    images: HashMap<String, (RgbaImage, u64)>,
+    /// Incremented each time an image is added
+    revision: u64,
+}
+
+impl PatternImages {
+    /// Adds an image or replaces the image with the same `name`.
+    pub fn add_image(&mut self, name: &str, image: RgbaImage) {
+        self.revision += 1;
+        self.images.insert(name.to_string(), (image, self.revision));
+    }
+
+    pub fn remove_image(&mut self, name: &str) -> Option<RgbaImage> {
+        self.images.remove(name).map(|(image, _)| image)
+    }
+
+    /// Returns an image together with its revision.
+    pub fn get(&self, name: &str) -> Option<(&RgbaImage, u64)> {
+        self.images
+            .get(name)
+            .map(|(image, revision)| (image, *revision))
+    }
+}
+
+/// Renders the background layers of the style.
+#[derive(Default)]
+pub struct BackgroundPlugin;
+
+impl<E: Environment> Plugin<E> for BackgroundPlugin {
+    fn build(
+        &self,
+        schedule: &mut Schedule,
+        _kernel: Rc<Kernel<E>>,
+        world: &mut World,
+        _graph: &mut RenderGraph,
+    ) {
+        world
+            .resources
+            .insert(Eventually::<BackgroundResources>::Uninitialized);
+        world.resources.init::<PatternImages>();
+        world
+            .resources
+            .get_or_init_mut::<GpuResources>()
+            .add::<BackgroundResources>();
+
+        schedule.add_system_to_stage(RenderStageLabel::Prepare, resource_system);
+        schedule.add_system_to_stage(RenderStageLabel::Queue, queue_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_revisions() {
+        let mut images = PatternImages::default();
+        images.add_image("dots", RgbaImage::new(8, 8));
+        images.add_image("stripes", RgbaImage::new(4, 4));
+
+        let (_, dots) = images.get("dots").unwrap();
+        images.add_image("dots", RgbaImage::new(16, 16));
+        let (image, replaced) = images.get("dots").unwrap();
+
+        assert_eq!(image.dimensions(), (16, 16));
+        assert_ne!(dots, replaced);
+        assert!(images.remove_image("stripes").is_some());
+        assert!(images.get("stripes").is_none());
+    }
+}
@@ -0,0 +1,125 @@
+//! Uploads the background layers and queues them for rendering.
+use cgmath::{Matrix4, SquareMatrix, Vector3};
+use cint::{Alpha, EncodedSrgb};
+
+use crate::{
+    background::{render_commands::DrawBackgrounds, resource::BackgroundResources, PatternImages},
+    context::MapContext,
+    coords::WorldTileCoords,
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        render_phase::{DrawState, LayerItem, RenderPhase},
+        shaders::{ShaderBackground, Vec4f32},
+        tile_view_pattern::TileShape,
+        Renderer,
+    },
+    style::layer::{BackgroundPaint, LayerPaint},
+    tcs::tiles::Tile,
+};
+
+pub fn queue_system(
+    MapContext {
+        world,
+        style,
+        view_state,
+        renderer: Renderer { device, queue, .. },
+        ..
+    }: &mut MapContext,
+) {
+    let Some((Initialized(background_resources), pattern_images, layer_item_phase)) =
+        world.resources.query_mut::<(
+            &mut Eventually<BackgroundResources>,
+            &PatternImages,
+            &mut RenderPhase<LayerItem>,
+        )>()
+    else {
+        return;
+    };
+
+    let zoom = view_state.zoom();
+    let background_layers = style
+        .layers
+        .iter()
+        .filter(|layer| layer.is_visible_at(zoom.into()))
+        .filter_map(|layer| match &layer.paint {
+            Some(LayerPaint::Background(paint)) => Some((layer, paint)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    background_resources.retain(|id| background_layers.iter().any(|(layer, _)| layer.id == id));
+
+    let view_proj = view_state.view_projection();
+    let camera = view_state.camera().position();
+
+    for (layer, paint) in background_layers {
+        let opacity = paint.background_opacity.unwrap_or(1.0).clamp(0.0, 1.0);
+
+        let pattern = paint
+            .background_pattern
+            .as_ref()
+            .and_then(|name| Some((name.as_str(), pattern_images.get(name)?)))
+            .map(|(name, (image, revision))| (name, image, revision));
+
+        let pattern_size = pattern.map_or([0.0, 0.0], |(_, image, _)| {
+            let (width, height) = image.dimensions();
+            [width as f32, height as f32]
+        });
+
+        // The pattern repeats, so the origin is moved close to the camera in order to keep the
+        // coordinates in the shader small
+        let origin = if pattern_size[0] > 0.0 {
+            let (width, height) = (pattern_size[0] as f64, pattern_size[1] as f64);
+            Vector3::new(
+                (camera.x / width).floor() * width,
+                (camera.y / height).floor() * height,
+                0.0,
+            )
+        } else {
+            Vector3::new(0.0, 0.0, 0.0)
+        };
+
+        let inverse_view_proj = (view_proj.0 * Matrix4::from_translation(origin))
+            .invert()
+            .unwrap_or_else(Matrix4::identity)
+            .cast::<f32>()
+            .expect("Unable to cast view projection to f32");
+
+        background_resources.write_layer(
+            device,
+            queue,
+            &layer.id,
+            ShaderBackground {
+                inverse_view_proj: inverse_view_proj.into(),
+                color: background_color(paint, opacity),
+                pattern_size,
+                opacity,
+                z_index: layer.index as f32,
+            },
+            pattern,
+        );
+
+        let coords = WorldTileCoords::default();
+        layer_item_phase.add(LayerItem {
+            draw_function: Box::new(DrawState::<LayerItem, DrawBackgrounds>::new()),
+            index: layer.index,
+            style_layer: layer.id.clone(),
+            tile: Tile { coords },
+            source_shape: TileShape::new(coords, zoom),
+        });
+    }
+}
+
+/// Returns the premultiplied color of the layer, including its opacity.
+fn background_color(paint: &BackgroundPaint, opacity: f32) -> Vec4f32 {
+    let [r, g, b, a]: Vec4f32 =
+        paint
+            .background_color
+            .as_ref()
+            .map_or([0.0, 0.0, 0.0, 1.0], |color| {
+                let color: Alpha<EncodedSrgb<f32>> = color.clone().into();
+                color.into()
+            });
+    let a = a * opacity;
+    [r * a, g * a, b * a, a]
+}
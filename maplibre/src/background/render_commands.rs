@@ -0,0 +1,54 @@
+use crate::{
+    background::resource::BackgroundResources,
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        render_phase::{LayerItem, PhaseItem, RenderCommand, RenderCommandResult},
+        resource::TrackedRenderPass,
+    },
+    tcs::world::World,
+};
+
+pub struct SetBackgroundPipeline;
+impl<P: PhaseItem> RenderCommand<P> for SetBackgroundPipeline {
+    fn render<'w>(
+        world: &'w World,
+        _item: &P,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(Initialized(background_resources)) =
+            world.resources.get::<Eventually<BackgroundResources>>()
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_render_pipeline(background_resources.pipeline());
+        RenderCommandResult::Success
+    }
+}
+
+pub struct DrawBackground;
+impl RenderCommand<LayerItem> for DrawBackground {
+    fn render<'w>(
+        world: &'w World,
+        item: &LayerItem,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(Initialized(background_resources)) =
+            world.resources.get::<Eventually<BackgroundResources>>()
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        let Some(bind_group) = background_resources.get_bind_group(&item.style_layer) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(0, bind_group, &[]);
+        // Fullscreen triangle
+        pass.draw(0..3, 0..1);
+
+        RenderCommandResult::Success
+    }
+}
+
+pub type DrawBackgrounds = (SetBackgroundPipeline, DrawBackground);
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use image::RgbaImage;
+
+use crate::render::{resource::Texture, settings::Msaa, shaders::ShaderBackground};
+
+/// Resources of a single background layer.
+struct BackgroundLayer {
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// Name and revision of the pattern which is bound
+    pattern: Option<(String, u64)>,
+    pattern_texture: Option<Texture>,
+}
+
+/// Holds the resources necessary for rendering background layers:
+/// * pipeline
+/// * sampler which repeats the patterns
+/// * uniform and pattern texture per layer
+pub struct BackgroundResources {
+    pipeline: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+    /// Bound if a layer has no pattern
+    empty_pattern: Texture,
+    layers: HashMap<String, BackgroundLayer>,
+}
+
+impl BackgroundResources {
+    pub fn new(device: &wgpu::Device, pipeline: wgpu::RenderPipeline) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let empty_pattern = Self::create_pattern_texture(device, 1, 1);
+
+        Self {
+            pipeline,
+            sampler,
+            empty_pattern,
+            layers: Default::default(),
+        }
+    }
+
+    pub fn pipeline(&self) -> &wgpu::RenderPipeline {
+        &self.pipeline
+    }
+
+    fn create_pattern_texture(device: &wgpu::Device, width: u32, height: u32) -> Texture {
+        Texture::new(
+            Some("background pattern texture"),
+            device,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            width,
+            height,
+            Msaa { samples: 1 },
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        )
+    }
+
+    fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        uniform: &wgpu::Buffer,
+        pattern: &Texture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&pattern.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label: None,
+        })
+    }
+
+    /// Creates or updates the resources of a layer. The pattern is only uploaded if its name or
+    /// revision changed.
+    pub fn write_layer(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        style_layer: &str,
+        uniform: ShaderBackground,
+        pattern: Option<(&str, &RgbaImage, u64)>,
+    ) {
+        if !self.layers.contains_key(style_layer) {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("background uniform buffer"),
+                size: std::mem::size_of::<ShaderBackground>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = self.create_bind_group(device, &buffer, &self.empty_pattern);
+            self.layers.insert(
+                style_layer.to_string(),
+                BackgroundLayer {
+                    uniform: buffer,
+                    bind_group,
+                    pattern: None,
+                    pattern_texture: None,
+                },
+            );
+        }
+
+        let layer = &self.layers[style_layer];
+        queue.write_buffer(&layer.uniform, 0, bytemuck::bytes_of(&uniform));
+
+        let bound = layer
+            .pattern
+            .as_ref()
+            .map(|(name, revision)| (name.as_str(), *revision));
+        if bound == pattern.map(|(name, _, revision)| (name, revision)) {
+            return;
+        }
+
+        let pattern_texture = pattern.map(|(_, image, _)| {
+            let (width, height) = image.dimensions();
+            let texture = Self::create_pattern_texture(device, width, height);
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                image,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: Some(height),
+                },
+                texture.size,
+            );
+            texture
+        });
+
+        let bind_group = self.create_bind_group(
+            device,
+            &layer.uniform,
+            pattern_texture.as_ref().unwrap_or(&self.empty_pattern),
+        );
+
+        let layer = self
+            .layers
+            .get_mut(style_layer)
+            .expect("layer was inserted");
+        layer.bind_group = bind_group;
+        layer.pattern = pattern.map(|(name, _, revision)| (name.to_string(), revision));
+        layer.pattern_texture = pattern_texture;
+    }
+
+    pub fn get_bind_group(&self, style_layer: &str) -> Option<&wgpu::BindGroup> {
+        self.layers.get(style_layer).map(|layer| &layer.bind_group)
+    }
+
+    /// Removes the resources of layers for which `f` returns false.
+    pub fn retain<F: FnMut(&str) -> bool>(&mut self, mut f: F) {
+        self.layers.retain(|style_layer, _| f(style_layer));
+    }
+}
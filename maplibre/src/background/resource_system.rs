@@ -0,0 +1,91 @@
+//! Prepares GPU-owned resources by initializing them if they are uninitialized or out-of-date.
+use crate::{
+    background::resource::BackgroundResources,
+    context::MapContext,
+    render::{
+        eventually::Eventually,
+        resource::{RenderPipeline, TilePipeline},
+        shaders,
+        shaders::Shader,
+        RenderResources, Renderer,
+    },
+};
+
+pub fn resource_system(
+    MapContext {
+        world,
+        renderer:
+            Renderer {
+                device,
+                resources: RenderResources { surface, .. },
+                settings,
+                ..
+            },
+        ..
+    }: &mut MapContext,
+) {
+    let Some(background_resources) = world
+        .resources
+        .query_mut::<&mut Eventually<BackgroundResources>>()
+    else {
+        return;
+    };
+
+    background_resources.initialize(|| {
+        let shader = shaders::BackgroundShader {
+            format: surface.surface_format(),
+        };
+
+        let mut descriptor = TilePipeline::new(
+            "background_pipeline".into(),
+            *settings,
+            shader.describe_vertex(),
+            shader.describe_fragment(),
+            true,
+            false,
+            false,
+            false,
+            surface.is_multisampling_supported(settings.msaa),
+            false,
+        )
+        .describe_render_pipeline();
+
+        // The background covers the whole viewport and is not masked by tiles
+        if let Some(depth_stencil) = &mut descriptor.depth_stencil {
+            depth_stencil.stencil = wgpu::StencilState::default();
+        }
+
+        descriptor.layout = Some(vec![vec![
+            // background uniform
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            // pattern
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ]]);
+
+        BackgroundResources::new(device, descriptor.initialize(device))
+    });
+}
@@ -0,0 +1,559 @@
+//! Animated transitions of the camera.
+//!
+//! Animations are advanced once per frame by [`camera_animation_system`]. The time is read from
+//! a [`TimeSource`], which can be replaced by a [`ManualTimeSource`] to run animations
+//! deterministically, e.g. in tests or when rendering headless.
+
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use cgmath::InnerSpace;
+use instant::Instant;
+
+use crate::{
+    camera::{easing::Easing, CameraOptions, CameraState},
+    context::MapContext,
+    render::{camera::EdgeInsets, view_state::ViewState},
+};
+
+/// Default duration of [`CameraAnimator::ease_to`], which is the same as in maplibre-gl-js.
+const DEFAULT_EASE_DURATION: Duration = Duration::from_millis(500);
+
+/// How an animation ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationOutcome {
+    /// The camera reached the target of the animation.
+    Completed,
+    /// The animation was stopped before it completed, e.g. because of user input or because
+    /// another animation started.
+    Interrupted,
+}
+
+/// Called once an animation ended.
+pub type AnimationCallback = Box<dyn FnOnce(AnimationOutcome)>;
+
+/// Source of the time which drives animations.
+pub trait TimeSource {
+    /// Returns the time which elapsed since an arbitrary, but fixed point in time.
+    fn now(&self) -> Duration;
+}
+
+/// Reads the time from the system clock.
+pub struct InstantTimeSource(Instant);
+
+impl Default for InstantTimeSource {
+    fn default() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl TimeSource for InstantTimeSource {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// A time source which only advances when told to. Clones share the same time.
+#[derive(Clone, Default)]
+pub struct ManualTimeSource(Rc<Cell<Duration>>);
+
+impl ManualTimeSource {
+    pub fn advance(&self, duration: Duration) {
+        self.0.set(self.0.get() + duration);
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> Duration {
+        self.0.get()
+    }
+}
+
+pub struct AnimationOptions {
+    pub duration: Duration,
+    pub easing: Easing,
+    pub on_complete: Option<AnimationCallback>,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            duration: DEFAULT_EASE_DURATION,
+            easing: Easing::default(),
+            on_complete: None,
+        }
+    }
+}
+
+/// Options of [`CameraAnimator::fly_to`]. The defaults are the same as in maplibre-gl-js.
+pub struct FlyToOptions {
+    /// The zooming curve of the flight path. Large values zoom out further.
+    pub curve: f64,
+    /// The average speed of the animation relative to `curve`. A speed of `1.2` means that the
+    /// map appears to move 1.2 times `curve` screenfuls every second.
+    pub speed: f64,
+    /// The average speed of the animation in screenfuls per second, independent of `curve`.
+    /// Overrides `speed` if set.
+    pub screen_speed: Option<f64>,
+    /// Overrides the duration which is derived from the speed.
+    pub duration: Option<Duration>,
+    /// The camera jumps to the target if the animation would take longer.
+    pub max_duration: Option<Duration>,
+    pub easing: Easing,
+    pub on_complete: Option<AnimationCallback>,
+}
+
+impl Default for FlyToOptions {
+    fn default() -> Self {
+        Self {
+            curve: 1.42,
+            speed: 1.2,
+            screen_speed: None,
+            duration: None,
+            max_duration: None,
+            easing: Easing::default(),
+            on_complete: None,
+        }
+    }
+}
+
+/// The flight path of [`CameraAnimator::fly_to`] according to "Smooth and efficient zooming and
+/// panning" by Jarke J. van Wijk and Wim A.A. Nuij. Adopted from
+/// [Camera::flyTo](https://github.com/maplibre/maplibre-gl-js/blob/v3.6.2/src/ui/camera.ts#L1175)
+#[derive(Clone, Copy)]
+struct FlightPath {
+    /// The zooming curve
+    rho: f64,
+    /// Width of the viewport in pixels at the start
+    w0: f64,
+    /// Distance between start and target in pixels at the start zoom
+    u1: f64,
+    r0: f64,
+    /// Length of the path
+    s: f64,
+    /// Set if the center does not move, then the path only zooms in (`1`) or out (`-1`)
+    zoom_direction: Option<f64>,
+}
+
+impl FlightPath {
+    fn new(from: &CameraState, to: &CameraState, viewport: f64, rho: f64) -> Self {
+        let w0 = viewport;
+        let w1 = w0 / 2.0_f64.powf(to.zoom - from.zoom);
+        let u1 = (to.center - from.center).magnitude() * 2.0_f64.powf(from.zoom);
+        let rho2 = rho * rho;
+
+        let r = |i: bool| {
+            let (w, sign) = if i { (w1, -1.0) } else { (w0, 1.0) };
+            let b = (w1 * w1 - w0 * w0 + sign * rho2 * rho2 * u1 * u1) / (2.0 * w * rho2 * u1);
+            ((b * b + 1.0).sqrt() - b).ln()
+        };
+
+        let r0 = r(false);
+        let s = (r(true) - r0) / rho;
+
+        if u1.abs() < 0.000001 || !s.is_finite() {
+            // The center does not move, so only zoom
+            let zoom_direction = if w1 < w0 { -1.0 } else { 1.0 };
+            Self {
+                rho,
+                w0,
+                u1,
+                r0,
+                s: (w1 / w0).ln().abs() / rho,
+                zoom_direction: Some(zoom_direction),
+            }
+        } else {
+            Self {
+                rho,
+                w0,
+                u1,
+                r0,
+                s,
+                zoom_direction: None,
+            }
+        }
+    }
+
+    /// Returns the width of the viewport relative to its width at the start.
+    fn w(&self, s: f64) -> f64 {
+        match self.zoom_direction {
+            Some(direction) => (direction * self.rho * s).exp(),
+            None => self.r0.cosh() / (self.r0 + self.rho * s).cosh(),
+        }
+    }
+
+    /// Returns the travelled fraction of the distance between start and target.
+    fn u(&self, s: f64) -> f64 {
+        match self.zoom_direction {
+            Some(_) => 0.0,
+            None => {
+                self.w0
+                    * ((self.r0.cosh() * (self.r0 + self.rho * s).tanh() - self.r0.sinh())
+                        / (self.rho * self.rho))
+                    / self.u1
+            }
+        }
+    }
+}
+
+enum AnimationPath {
+    Ease,
+    Flight(FlightPath),
+}
+
+struct Animation {
+    from: CameraState,
+    to: CameraState,
+    path: AnimationPath,
+    duration: Duration,
+    easing: Easing,
+    /// Set when the animation is advanced the first time.
+    start: Option<Duration>,
+    on_complete: Option<AnimationCallback>,
+}
+
+impl Animation {
+    /// Returns the camera at `t`, between `0` and `1`.
+    fn camera_at(&self, t: f64) -> CameraState {
+        if t >= 1.0 {
+            return self.to;
+        }
+
+        let k = self.easing.apply(t);
+        let (from, to) = (&self.from, &self.to);
+
+        let (center, zoom) = match &self.path {
+            AnimationPath::Ease => (
+                from.center + (to.center - from.center) * k,
+                lerp(from.zoom, to.zoom, k),
+            ),
+            AnimationPath::Flight(path) => {
+                let s = k * path.s;
+                (
+                    from.center + (to.center - from.center) * path.u(s),
+                    from.zoom + (1.0 / path.w(s)).log2(),
+                )
+            }
+        };
+
+        // Rotate along the shorter direction
+        let mut bearing_delta = (to.bearing - from.bearing) % 360.0;
+        if bearing_delta > 180.0 {
+            bearing_delta -= 360.0;
+        } else if bearing_delta < -180.0 {
+            bearing_delta += 360.0;
+        }
+
+        CameraState {
+            center,
+            zoom,
+            bearing: from.bearing + bearing_delta * k,
+            pitch: lerp(from.pitch, to.pitch, k),
+            padding: EdgeInsets {
+                top: lerp(from.padding.top, to.padding.top, k),
+                bottom: lerp(from.padding.bottom, to.padding.bottom, k),
+                left: lerp(from.padding.left, to.padding.left, k),
+                right: lerp(from.padding.right, to.padding.right, k),
+            },
+        }
+    }
+
+    fn finish(mut self, outcome: AnimationOutcome) {
+        if let Some(on_complete) = self.on_complete.take() {
+            on_complete(outcome);
+        }
+    }
+}
+
+fn lerp(from: f64, to: f64, k: f64) -> f64 {
+    from + (to - from) * k
+}
+
+/// Moves the camera and runs the current camera animation. There is at most one animation at a
+/// time, starting an animation interrupts the current one.
+pub struct CameraAnimator {
+    time_source: Box<dyn TimeSource>,
+    animation: Option<Animation>,
+}
+
+impl Default for CameraAnimator {
+    fn default() -> Self {
+        Self {
+            time_source: Box::<InstantTimeSource>::default(),
+            animation: None,
+        }
+    }
+}
+
+impl CameraAnimator {
+    pub fn set_time_source(&mut self, time_source: impl TimeSource + 'static) {
+        self.time_source = Box::new(time_source);
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// Stops the current animation, the camera stays where it is.
+    pub fn stop(&mut self) {
+        if let Some(animation) = self.animation.take() {
+            animation.finish(AnimationOutcome::Interrupted);
+        }
+    }
+
+    /// Changes the camera immediately.
+    pub fn jump_to(&mut self, view_state: &mut ViewState, camera: &CameraOptions) {
+        self.stop();
+        CameraState::read(view_state)
+            .with_options(camera)
+            .write(view_state);
+    }
+
+    /// Interpolates the properties of the camera from the current camera to `camera`.
+    pub fn ease_to(
+        &mut self,
+        view_state: &ViewState,
+        camera: &CameraOptions,
+        options: AnimationOptions,
+    ) {
+        self.stop();
+        let from = CameraState::read(view_state);
+        self.animation = Some(Animation {
+            from,
+            to: from.with_options(camera),
+            path: AnimationPath::Ease,
+            duration: options.duration,
+            easing: options.easing,
+            start: None,
+            on_complete: options.on_complete,
+        });
+    }
+
+    /// Moves the camera to `camera` along a path which zooms out and in again, such that the
+    /// movement appears like a flight. This is suited for moves over long distances.
+    pub fn fly_to(
+        &mut self,
+        view_state: &ViewState,
+        camera: &CameraOptions,
+        options: FlyToOptions,
+    ) {
+        self.stop();
+        let from = CameraState::read(view_state);
+        let to = from.with_options(camera);
+
+        let viewport = view_state.width().max(view_state.height());
+        let path = FlightPath::new(&from, &to, viewport, options.curve);
+
+        let duration = options.duration.unwrap_or_else(|| {
+            let speed = options
+                .screen_speed
+                .map(|screen_speed| screen_speed / options.curve)
+                .unwrap_or(options.speed);
+            Duration::from_secs_f64((path.s / speed).max(0.0))
+        });
+        let duration = match options.max_duration {
+            Some(max_duration) if duration > max_duration => Duration::ZERO,
+            _ => duration,
+        };
+
+        self.animation = Some(Animation {
+            from,
+            to,
+            path: AnimationPath::Flight(path),
+            duration,
+            easing: options.easing,
+            start: None,
+            on_complete: options.on_complete,
+        });
+    }
+
+    /// Advances the current animation to the current time of the time source.
+    pub fn update(&mut self, view_state: &mut ViewState) {
+        let Some(animation) = &mut self.animation else {
+            return;
+        };
+
+        let now = self.time_source.now();
+        let start = *animation.start.get_or_insert(now);
+        let t = if animation.duration.is_zero() {
+            1.0
+        } else {
+            (now - start).as_secs_f64() / animation.duration.as_secs_f64()
+        };
+
+        animation.camera_at(t).write(view_state);
+
+        if t >= 1.0 {
+            if let Some(animation) = self.animation.take() {
+                animation.finish(AnimationOutcome::Completed);
+            }
+        }
+    }
+}
+
+/// Advances the camera animation.
+pub fn camera_animation_system(
+    MapContext {
+        world, view_state, ..
+    }: &mut MapContext,
+) {
+    if let Some(animator) = world.resources.get_mut::<CameraAnimator>() {
+        animator.update(view_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Deg;
+
+    use super::*;
+    use crate::{
+        coords::{LatLon, WorldCoords, Zoom},
+        window::PhysicalSize,
+    };
+
+    fn view_state() -> ViewState {
+        ViewState::new(
+            PhysicalSize::new(800, 600).unwrap(),
+            WorldCoords::from_lat_lon(LatLon::new(0.0, 0.0), Zoom::new(2.0)),
+            Zoom::new(2.0),
+            Deg(0.0),
+            Deg(60.0),
+        )
+    }
+
+    fn animator() -> (CameraAnimator, ManualTimeSource) {
+        let time = ManualTimeSource::default();
+        let mut animator = CameraAnimator::default();
+        animator.set_time_source(time.clone());
+        (animator, time)
+    }
+
+    fn outcome_recorder() -> (
+        Rc<Cell<Option<AnimationOutcome>>>,
+        Option<AnimationCallback>,
+    ) {
+        let outcome = Rc::new(Cell::new(None));
+        let recorder = outcome.clone();
+        (
+            outcome,
+            Some(Box::new(move |result| recorder.set(Some(result)))),
+        )
+    }
+
+    #[test]
+    fn test_ease_to() {
+        let mut view_state = view_state();
+        let (mut animator, time) = animator();
+        let (outcome, on_complete) = outcome_recorder();
+
+        animator.ease_to(
+            &view_state,
+            &CameraOptions {
+                zoom: Some(Zoom::new(4.0)),
+                bearing: Some(Deg(-170.0)),
+                ..Default::default()
+            },
+            AnimationOptions {
+                duration: Duration::from_secs(1),
+                easing: Easing::Linear,
+                on_complete,
+            },
+        );
+
+        animator.update(&mut view_state);
+        time.advance(Duration::from_millis(500));
+        animator.update(&mut view_state);
+
+        let state = CameraState::read(&view_state);
+        assert!((state.zoom - 3.0).abs() < 1e-9);
+        assert!((state.bearing + 85.0).abs() < 1e-9);
+        // The center stays the same while zooming
+        assert!((state.center.x - 256.0).abs() < 1e-9);
+        assert_eq!(outcome.get(), None);
+
+        time.advance(Duration::from_millis(600));
+        animator.update(&mut view_state);
+
+        let state = CameraState::read(&view_state);
+        assert!((state.zoom - 4.0).abs() < 1e-9);
+        assert!((state.bearing + 170.0).abs() < 1e-9);
+        assert!(!animator.is_animating());
+        assert_eq!(outcome.get(), Some(AnimationOutcome::Completed));
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let mut view_state = view_state();
+        let (mut animator, time) = animator();
+        let (outcome, on_complete) = outcome_recorder();
+
+        animator.ease_to(
+            &view_state,
+            &CameraOptions {
+                zoom: Some(Zoom::new(4.0)),
+                ..Default::default()
+            },
+            AnimationOptions {
+                on_complete,
+                ..Default::default()
+            },
+        );
+        animator.update(&mut view_state);
+        time.advance(Duration::from_millis(100));
+        animator.update(&mut view_state);
+        animator.stop();
+
+        assert_eq!(outcome.get(), Some(AnimationOutcome::Interrupted));
+        let zoom: f64 = view_state.zoom().into();
+        assert!(zoom > 2.0 && zoom < 4.0);
+    }
+
+    #[test]
+    fn test_fly_to() {
+        let mut view_state = view_state();
+        let (mut animator, time) = animator();
+        let (outcome, on_complete) = outcome_recorder();
+
+        animator.jump_to(
+            &mut view_state,
+            &CameraOptions {
+                zoom: Some(Zoom::new(6.0)),
+                ..Default::default()
+            },
+        );
+
+        let target = LatLon::new(48.137154, 11.576124);
+        animator.fly_to(
+            &view_state,
+            &CameraOptions {
+                center: Some(target),
+                zoom: Some(Zoom::new(12.0)),
+                ..Default::default()
+            },
+            FlyToOptions {
+                duration: Some(Duration::from_secs(2)),
+                easing: Easing::Linear,
+                on_complete,
+                ..Default::default()
+            },
+        );
+
+        let mut min_zoom = f64::MAX;
+        for _ in 0..20 {
+            animator.update(&mut view_state);
+            min_zoom = min_zoom.min(view_state.zoom().into());
+            time.advance(Duration::from_millis(100));
+        }
+        // The camera zooms out first
+        assert!(min_zoom < 6.0, "{min_zoom}");
+
+        time.advance(Duration::from_millis(100));
+        animator.update(&mut view_state);
+
+        let expected = WorldCoords::from_lat_lon(target, Zoom::new(12.0));
+        let position = view_state.camera().position();
+        assert!((position.x - expected.x).abs() < 1e-6);
+        assert!((position.y - expected.y).abs() < 1e-6);
+        assert_eq!(outcome.get(), Some(AnimationOutcome::Completed));
+    }
+}
@@ -0,0 +1,145 @@
+//! Limits of the camera, which are enforced by the
+//! [`ViewState`](crate::render::view_state::ViewState).
+
+use cgmath::{Deg, Point2, Rad};
+
+use crate::{
+    context::MapContext,
+    coords::{LatLonBounds, Zoom},
+    render::camera::{MAX_PITCH, MIN_PITCH},
+};
+
+/// Limits of the center, zoom and pitch of the camera.
+///
+/// The constraints are enforced by the [`ViewState`](crate::render::view_state::ViewState), such that animations and all input
+/// handlers respect them.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraConstraints {
+    /// The camera is kept such that the view does not leave these bounds. If the view is larger
+    /// than the bounds, then it is centered on them.
+    pub max_bounds: Option<LatLonBounds>,
+    pub min_zoom: Zoom,
+    pub max_zoom: Zoom,
+    /// The pitch is additionally limited to [`MIN_PITCH`] and [`MAX_PITCH`].
+    pub min_pitch: Deg<f64>,
+    pub max_pitch: Deg<f64>,
+    /// Whether copies of the world are rendered next to each other, such that the map repeats
+    /// horizontally. If enabled, the longitude of the center wraps around. Otherwise, the center
+    /// is kept within the world.
+    pub render_world_copies: bool,
+}
+
+impl Default for CameraConstraints {
+    fn default() -> Self {
+        Self {
+            max_bounds: None,
+            min_zoom: Zoom::new(0.0),
+            max_zoom: Zoom::new(22.0),
+            min_pitch: MIN_PITCH,
+            max_pitch: MAX_PITCH,
+            render_world_copies: true,
+        }
+    }
+}
+
+impl CameraConstraints {
+    pub fn constrain_zoom(&self, zoom: Zoom) -> Zoom {
+        let zoom = f64::from(zoom)
+            .min(f64::from(self.max_zoom))
+            .max(f64::from(self.min_zoom));
+        Zoom::new(zoom)
+    }
+
+    pub fn constrain_pitch(&self, pitch: Rad<f64>) -> Rad<f64> {
+        let min: Rad<f64> = self.min_pitch.into();
+        let max: Rad<f64> = self.max_pitch.into();
+        Rad(pitch.0.min(max.0).max(min.0))
+    }
+
+    /// Constrains the `center` of the camera in world coordinates at `zoom`. The view has a size
+    /// of `width` and `height` in world coordinates.
+    pub fn constrain_center(
+        &self,
+        center: Point2<f64>,
+        zoom: Zoom,
+        width: f64,
+        height: f64,
+    ) -> Point2<f64> {
+        let world_size = zoom.world_size();
+
+        if let Some(max_bounds) = &self.max_bounds {
+            let (north_west, south_east) = max_bounds.to_world(zoom);
+            return Point2::new(
+                constrain_axis(center.x, north_west.x, south_east.x, width),
+                constrain_axis(center.y, north_west.y, south_east.y, height),
+            );
+        }
+
+        let x = if self.render_world_copies {
+            center.x.rem_euclid(world_size)
+        } else {
+            center.x.clamp(0.0, world_size)
+        };
+
+        Point2::new(x, center.y.clamp(0.0, world_size))
+    }
+}
+
+/// Keeps a view of size `extent` centered at `center` within `min` and `max`.
+fn constrain_axis(center: f64, min: f64, max: f64, extent: f64) -> f64 {
+    if max - min <= extent {
+        (min + max) / 2.0
+    } else {
+        center.clamp(min + extent / 2.0, max - extent / 2.0)
+    }
+}
+
+/// Enforces the [`CameraConstraints`] after all input handlers and animations changed the
+/// camera.
+pub fn camera_constraints_system(MapContext { view_state, .. }: &mut MapContext) {
+    view_state.apply_constraints();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coords::{LatLon, WorldCoords};
+
+    #[test]
+    fn test_constrain_center() {
+        let zoom = Zoom::new(2.0);
+        let world_size = zoom.world_size();
+
+        let constraints = CameraConstraints::default();
+        let center =
+            constraints.constrain_center(Point2::new(world_size + 10.0, -5.0), zoom, 800.0, 600.0);
+        assert_eq!(center, Point2::new(10.0, 0.0));
+
+        let constraints = CameraConstraints {
+            render_world_copies: false,
+            ..CameraConstraints::default()
+        };
+        let center = constraints.constrain_center(Point2::new(-10.0, 100.0), zoom, 800.0, 600.0);
+        assert_eq!(center, Point2::new(0.0, 100.0));
+
+        let bounds = LatLonBounds::new(LatLon::new(-10.0, -10.0), LatLon::new(10.0, 10.0));
+        let constraints = CameraConstraints {
+            max_bounds: Some(bounds),
+            ..CameraConstraints::default()
+        };
+        let zoom = Zoom::new(8.0);
+        let (north_west, _) = bounds.to_world(zoom);
+        let center = constraints.constrain_center(Point2::new(0.0, 0.0), zoom, 800.0, 600.0);
+        assert_eq!(
+            center,
+            Point2::new(north_west.x + 400.0, north_west.y + 300.0)
+        );
+
+        // The bounds are smaller than the view
+        let zoom = Zoom::new(1.0);
+        let center = constraints.constrain_center(Point2::new(0.0, 0.0), zoom, 800.0, 600.0);
+        let expected = WorldCoords::from_lat_lon(LatLon::new(0.0, 0.0), zoom);
+        assert!((center.x - expected.x).abs() < 1e-9);
+        assert!((center.y - expected.y).abs() < 1e-9);
+    }
+}
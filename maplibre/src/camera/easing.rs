@@ -0,0 +1,123 @@
+//! Easing curves which map the progress of an animation to the progress of the animated values.
+
+/// Amount of newton iterations which are used to invert a cubic bézier curve.
+const NEWTON_ITERATIONS: usize = 8;
+/// Amount of bisections which are used if the newton iterations do not converge.
+const BISECTION_ITERATIONS: usize = 32;
+const EPSILON: f64 = 1e-7;
+
+/// An easing curve. The progress `t` of an animation, between `0` and `1`, is mapped to the
+/// progress of the animated values.
+#[derive(Debug, Clone, Copy)]
+pub enum Easing {
+    Linear,
+    /// CSS `ease-in`
+    EaseIn,
+    /// CSS `ease-out`
+    EaseOut,
+    /// CSS `ease-in-out`
+    EaseInOut,
+    /// A CSS `cubic-bezier(x1, y1, x2, y2)` curve.
+    CubicBezier(f64, f64, f64, f64),
+    Custom(fn(f64) -> f64),
+}
+
+impl Default for Easing {
+    /// The default easing of camera animations, which is the same as in maplibre-gl-js.
+    fn default() -> Self {
+        Easing::CubicBezier(0.25, 0.1, 0.25, 1.0)
+    }
+}
+
+impl Easing {
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => cubic_bezier(0.42, 0.0, 1.0, 1.0, t),
+            Easing::EaseOut => cubic_bezier(0.0, 0.0, 0.58, 1.0, t),
+            Easing::EaseInOut => cubic_bezier(0.42, 0.0, 0.58, 1.0, t),
+            Easing::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+            Easing::Custom(easing) => easing(t),
+        }
+    }
+}
+
+/// Evaluates a cubic bézier curve from `(0, 0)` to `(1, 1)` with the control points `(x1, y1)`
+/// and `(x2, y2)` at `x`. Adopted from the `UnitBezier` of WebKit.
+fn cubic_bezier(x1: f64, y1: f64, x2: f64, y2: f64, x: f64) -> f64 {
+    // Coefficients of the polynomials in the power basis
+    let cx = 3.0 * x1;
+    let bx = 3.0 * (x2 - x1) - cx;
+    let ax = 1.0 - cx - bx;
+    let cy = 3.0 * y1;
+    let by = 3.0 * (y2 - y1) - cy;
+    let ay = 1.0 - cy - by;
+
+    let sample_x = |t: f64| ((ax * t + bx) * t + cx) * t;
+    let sample_y = |t: f64| ((ay * t + by) * t + cy) * t;
+    let sample_dx = |t: f64| (3.0 * ax * t + 2.0 * bx) * t + cx;
+
+    // Find the parameter of the curve at x
+    let mut t = x;
+    for _ in 0..NEWTON_ITERATIONS {
+        let error = sample_x(t) - x;
+        if error.abs() < EPSILON {
+            return sample_y(t);
+        }
+        let dx = sample_dx(t);
+        if dx.abs() < EPSILON {
+            break;
+        }
+        t -= error / dx;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    t = x;
+    for _ in 0..BISECTION_ITERATIONS {
+        let value = sample_x(t);
+        if (value - x).abs() < EPSILON {
+            break;
+        }
+        if value < x {
+            low = t;
+        } else {
+            high = t;
+        }
+        t = (low + high) / 2.0;
+    }
+
+    sample_y(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_easing() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::default(),
+        ] {
+            assert!(easing.apply(0.0).abs() < 1e-6, "{easing:?}");
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{easing:?}");
+
+            let samples = (0..=100)
+                .map(|i| easing.apply(i as f64 / 100.0))
+                .collect::<Vec<_>>();
+            assert!(
+                samples.windows(2).all(|pair| pair[0] <= pair[1] + 1e-9),
+                "{easing:?} is not monotonic"
+            );
+        }
+
+        assert!((Easing::EaseInOut.apply(0.5) - 0.5).abs() < 1e-6);
+        assert!(Easing::EaseIn.apply(0.25) < 0.25);
+        assert!(Easing::EaseOut.apply(0.25) > 0.25);
+        assert_eq!(Easing::Linear.apply(2.0), 1.0);
+    }
+}
@@ -0,0 +1,101 @@
+//! Describes the camera of the map in terms of center, zoom, bearing, pitch and padding, and
+//! animates transitions between cameras.
+//!
+//! The bearing is the rotation of the map in degrees counter-clockwise from north. It is the
+//! negated roll of the [`Camera`](crate::render::camera::Camera).
+
+use cgmath::{Deg, Point2};
+
+use crate::{
+    coords::{LatLon, WorldCoords, Zoom},
+    render::{camera::EdgeInsets, view_state::ViewState},
+};
+
+pub mod animation;
+pub mod constraints;
+pub mod easing;
+
+/// Properties of the camera which are changed by [`jump_to`](animation::CameraAnimator::jump_to)
+/// or animations. Properties which are `None` are kept.
+#[derive(Clone, Copy, Default)]
+pub struct CameraOptions {
+    pub center: Option<LatLon>,
+    pub zoom: Option<Zoom>,
+    pub bearing: Option<Deg<f64>>,
+    pub pitch: Option<Deg<f64>>,
+    pub padding: Option<EdgeInsets>,
+}
+
+/// Options of [`MapContext::fit_bounds`](crate::context::MapContext::fit_bounds) and
+/// [`MapContext::fit_geometry`](crate::context::MapContext::fit_geometry).
+#[derive(Clone, Copy, Default)]
+pub struct FitBoundsOptions {
+    /// Padding around the bounds, in addition to the padding of the view.
+    pub padding: EdgeInsets,
+    /// Defaults to the current bearing.
+    pub bearing: Option<Deg<f64>>,
+    /// Defaults to the current pitch.
+    pub pitch: Option<Deg<f64>>,
+    /// Limits the zoom, e.g. such that a single point is not shown at the maximum zoom.
+    pub max_zoom: Option<Zoom>,
+}
+
+/// A snapshot of the camera. The center is in world coordinates at zoom `0`, such that it does
+/// not depend on the zoom.
+#[derive(Clone, Copy)]
+pub(crate) struct CameraState {
+    pub center: Point2<f64>,
+    pub zoom: f64,
+    pub bearing: f64,
+    pub pitch: f64,
+    pub padding: EdgeInsets,
+}
+
+impl CameraState {
+    pub fn read(view_state: &ViewState) -> Self {
+        let zoom: f64 = view_state.zoom().into();
+        let camera = view_state.camera();
+        let position = camera.position();
+        let scale = 2.0_f64.powf(zoom);
+
+        Self {
+            center: Point2::new(position.x / scale, position.y / scale),
+            zoom,
+            bearing: view_state.bearing().0,
+            pitch: view_state.pitch().0,
+            padding: *view_state.edge_insets(),
+        }
+    }
+
+    pub fn write(&self, view_state: &mut ViewState) {
+        // The zoom might be clamped by the constraints of the view
+        view_state.update_zoom(Zoom::new(self.zoom));
+        let scale = 2.0_f64.powf(view_state.zoom().into());
+
+        let camera = view_state.camera_mut();
+        camera.move_to(Point2::new(self.center.x * scale, self.center.y * scale));
+        camera.set_roll(Deg(-self.bearing));
+        camera.set_pitch(Deg(self.pitch));
+        view_state.set_edge_insets(self.padding);
+    }
+
+    /// Returns the state after applying `options` to this state.
+    pub fn with_options(&self, options: &CameraOptions) -> Self {
+        Self {
+            center: options
+                .center
+                .map(|center| {
+                    let world = WorldCoords::from_lat_lon(center, Zoom::default());
+                    Point2::new(world.x, world.y)
+                })
+                .unwrap_or(self.center),
+            zoom: options.zoom.map(f64::from).unwrap_or(self.zoom),
+            bearing: options
+                .bearing
+                .map(|bearing| bearing.0)
+                .unwrap_or(self.bearing),
+            pitch: options.pitch.map(|pitch| pitch.0).unwrap_or(self.pitch),
+            padding: options.padding.unwrap_or(self.padding),
+        }
+    }
+}
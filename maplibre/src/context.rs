@@ -1,6 +1,37 @@
+use std::collections::HashMap;
+
+use cgmath::{Deg, EuclideanSpace, Vector2};
+use geo_types::Geometry;
+use image::RgbaImage;
+use serde_json::Value as JsonValue;
+
 use crate::{
-    render::{view_state::ViewState, Renderer},
-    style::Style,
+    annotation::{Annotations, Marker, MarkerId, Popup, PopupId},
+    camera::{
+        animation::{AnimationOptions, CameraAnimator, FlyToOptions},
+        CameraOptions, CameraState, FitBoundsOptions,
+    },
+    control::{Control, ControlHit, Controls, Corner},
+    coords::{LatLon, LatLonBounds, ScreenPoint, WorldCoords, Zoom},
+    draw::{self, DrawInput, DrawMode, Drawing, DrawnFeature, DrawnFeatureId},
+    events::{self, MapEvent, PointerEvent},
+    feature_state::{FeatureKey, FeatureStates},
+    interaction::InteractionHandlers,
+    query::{self, QueriedFeature, QueryGeometry},
+    raster::image_source::ImageSources,
+    render::{
+        overlay::{OverlayItem, OverlayTexture, OverlayTextureId, OverlayTextures},
+        render_phase::RenderPhase,
+        view_state::ViewState,
+        Renderer,
+    },
+    repaint::{self, Repaint},
+    style::{
+        layer::StyleLayer,
+        mutation::{StyleChange, StyleError},
+        source::Source,
+        Style,
+    },
     tcs::world::World,
     window::PhysicalSize,
 };
@@ -19,6 +50,567 @@ pub struct MapContext {
 impl MapContext {
     pub fn resize(&mut self, size: PhysicalSize, scale_factor: f64) {
         self.view_state.resize(size.to_logical(scale_factor));
-        self.renderer.resize_surface(size)
+        self.renderer.resize_surface(size);
+        self.request_repaint();
+    }
+
+    /// Changes the camera immediately. The current camera animation is stopped.
+    pub fn jump_to(&mut self, camera: &CameraOptions) {
+        let animator = self.world.resources.get_or_init_mut::<CameraAnimator>();
+        animator.jump_to(&mut self.view_state, camera);
+        self.request_repaint();
+    }
+
+    /// Animates the camera to `camera` by interpolating its properties.
+    pub fn ease_to(&mut self, camera: &CameraOptions, options: AnimationOptions) {
+        let animator = self.world.resources.get_or_init_mut::<CameraAnimator>();
+        animator.ease_to(&self.view_state, camera, options);
+        self.request_repaint();
+    }
+
+    /// Animates the camera to `camera` along a path which zooms out and in again.
+    pub fn fly_to(&mut self, camera: &CameraOptions, options: FlyToOptions) {
+        let animator = self.world.resources.get_or_init_mut::<CameraAnimator>();
+        animator.fly_to(&self.view_state, camera, options);
+        self.request_repaint();
+    }
+
+    /// Requests that the map is rendered again, e.g. because the view has been changed directly.
+    pub fn request_repaint(&mut self) {
+        repaint::request_repaint(&mut self.world);
+    }
+
+    /// Whether the map needs to be rendered again, because something changed or the map is not
+    /// idle yet, e.g. because tiles are loading.
+    pub fn needs_repaint(&self) -> bool {
+        self.world
+            .resources
+            .get::<Repaint>()
+            .map_or(true, Repaint::is_needed)
+    }
+
+    /// Animates the camera such that `bounds` fit into the view.
+    pub fn fit_bounds(
+        &mut self,
+        bounds: LatLonBounds,
+        options: FitBoundsOptions,
+        animation: AnimationOptions,
+    ) {
+        let (bearing, pitch) = self.fit_bearing_and_pitch(&options);
+        let camera = self.view_state.camera_for_bounds(
+            bounds.south_west,
+            bounds.north_east,
+            options.padding,
+            bearing,
+            pitch,
+        );
+        self.ease_to_fitted(camera, &options, animation);
+    }
+
+    /// Animates the camera such that the bounding box of `geometry` fits into the view, e.g. to
+    /// show a route or a search result.
+    pub fn fit_geometry(
+        &mut self,
+        geometry: &Geometry<f64>,
+        options: FitBoundsOptions,
+        animation: AnimationOptions,
+    ) {
+        let (bearing, pitch) = self.fit_bearing_and_pitch(&options);
+        let camera = self
+            .view_state
+            .camera_for_geometry(geometry, options.padding, bearing, pitch);
+        self.ease_to_fitted(camera, &options, animation);
+    }
+
+    fn fit_bearing_and_pitch(&self, options: &FitBoundsOptions) -> (Deg<f64>, Deg<f64>) {
+        let current = CameraState::read(&self.view_state);
+        (
+            options.bearing.unwrap_or(Deg(current.bearing)),
+            options.pitch.unwrap_or(Deg(current.pitch)),
+        )
+    }
+
+    fn ease_to_fitted(
+        &mut self,
+        camera: Option<CameraOptions>,
+        options: &FitBoundsOptions,
+        animation: AnimationOptions,
+    ) {
+        let Some(mut camera) = camera else {
+            log::warn!("Unable to fit the camera, the padding leaves no space");
+            return;
+        };
+
+        if let (Some(zoom), Some(max_zoom)) = (camera.zoom, options.max_zoom) {
+            camera.zoom = Some(Zoom::new(f64::from(zoom).min(f64::from(max_zoom))));
+        }
+
+        self.ease_to(&camera, animation);
+    }
+
+    /// Stops the current camera animation, e.g. because the user started to move the map.
+    pub fn stop_camera_animation(&mut self) {
+        if let Some(animator) = self.world.resources.get_mut::<CameraAnimator>() {
+            animator.stop();
+        }
+    }
+
+    pub fn is_camera_animating(&self) -> bool {
+        self.world
+            .resources
+            .get::<CameraAnimator>()
+            .is_some_and(CameraAnimator::is_animating)
+    }
+
+    /// Animates the camera such that the map moves by `offset` in logical pixels, e.g. the map
+    /// moves to the left if `offset.x` is positive.
+    pub fn pan_by(&mut self, offset: Vector2<f64>, animation: AnimationOptions) {
+        let view_state = &self.view_state;
+        let center = Vector2::new(view_state.width(), view_state.height()) / 2.0;
+        let inverted_view_proj = view_state.view_projection().invert();
+        let (Some(from), Some(to)) = (
+            view_state.window_to_world_at_ground(&center, &inverted_view_proj, false),
+            view_state.window_to_world_at_ground(&(center + offset), &inverted_view_proj, false),
+        ) else {
+            return;
+        };
+
+        let position = view_state.camera().position().to_vec() + (to - from);
+        let center = WorldCoords::at_ground(position.x, position.y).into_lat_lon(view_state.zoom());
+        self.ease_to(
+            &CameraOptions {
+                center: Some(center),
+                ..CameraOptions::default()
+            },
+            animation,
+        );
+    }
+
+    /// Animates the zoom by `delta` zoom levels. If `around` is set, then the ground at this point
+    /// on the screen stays in place, e.g. below the cursor.
+    pub fn zoom_by(
+        &mut self,
+        delta: f64,
+        around: Option<ScreenPoint>,
+        animation: AnimationOptions,
+    ) {
+        let view_state = &self.view_state;
+        let current = view_state.zoom();
+        let zoom = view_state
+            .constraints()
+            .constrain_zoom(current + Zoom::new(delta));
+
+        let inverted_view_proj = view_state.view_projection().invert();
+        let center = around
+            .and_then(|around| {
+                view_state.window_to_world_at_ground(
+                    &Vector2::new(around.x, around.y),
+                    &inverted_view_proj,
+                    false,
+                )
+            })
+            .map(|around| {
+                let position = view_state.camera().position().to_vec();
+                let center = around + (position - around) * zoom.scale_delta(&current);
+                WorldCoords::at_ground(center.x, center.y).into_lat_lon(current)
+            });
+
+        self.ease_to(
+            &CameraOptions {
+                center,
+                zoom: Some(zoom),
+                ..CameraOptions::default()
+            },
+            animation,
+        );
+    }
+
+    /// Returns the user interactions which are enabled.
+    pub fn interaction_handlers(&self) -> InteractionHandlers {
+        self.world
+            .resources
+            .get::<InteractionHandlers>()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Enables or disables user interactions. This takes effect before the next frame.
+    pub fn set_interaction_handlers(&mut self, handlers: InteractionHandlers) {
+        *self
+            .world
+            .resources
+            .get_or_init_mut::<InteractionHandlers>() = handlers;
+    }
+
+    /// Applies a mutation to the style and records the resulting change, such that plugins which
+    /// derived state from the style, e.g. uploaded tiles, can update it before the next render.
+    pub(crate) fn mutate_style(
+        &mut self,
+        mutate: impl FnOnce(&mut Style) -> Result<StyleChange, StyleError>,
+    ) -> Result<(), StyleError> {
+        let change = mutate(&mut self.style)?;
+        if let Some(pending) = self.world.resources.get_mut::<StyleChange>() {
+            pending.merge(change);
+        }
+        self.emit(MapEvent::StyleData);
+        self.request_repaint();
+        Ok(())
+    }
+
+    /// Returns the features which are rendered at a point or within a box on the screen, ordered
+    /// from the top-most layer to the bottom-most. See [`query::query_rendered_features`].
+    pub fn query_rendered_features(
+        &self,
+        geometry: impl Into<QueryGeometry>,
+        layers: Option<&[&str]>,
+        filter: Option<&JsonValue>,
+    ) -> Vec<QueriedFeature> {
+        query::query_rendered_features(self, geometry.into(), layers, filter)
+    }
+
+    /// Returns the features of the loaded tiles of a source, regardless of whether they are
+    /// visible. See [`query::query_source_features`].
+    pub fn query_source_features(
+        &self,
+        source_id: &str,
+        source_layer: Option<&str>,
+        filter: Option<&JsonValue>,
+    ) -> Vec<QueriedFeature> {
+        query::query_source_features(self, source_id, source_layer, filter)
+    }
+
+    /// Merges `state` into the state of a feature, which paint properties can use through the
+    /// `["feature-state", name]` expression. Only the metadata of the feature is updated.
+    pub fn set_feature_state(
+        &mut self,
+        source: &str,
+        source_layer: &str,
+        feature_id: u64,
+        state: HashMap<String, JsonValue>,
+    ) {
+        self.world
+            .resources
+            .get_or_init_mut::<FeatureStates>()
+            .set(FeatureKey::new(source, source_layer, feature_id), state);
+        self.request_repaint();
+    }
+
+    pub fn get_feature_state(
+        &self,
+        source: &str,
+        source_layer: &str,
+        feature_id: u64,
+    ) -> Option<&HashMap<String, JsonValue>> {
+        self.world
+            .resources
+            .get::<FeatureStates>()?
+            .get(&FeatureKey::new(source, source_layer, feature_id))
+    }
+
+    /// Removes the value `key` from the state of a feature, or the whole state if `key` is
+    /// `None`.
+    pub fn remove_feature_state(
+        &mut self,
+        source: &str,
+        source_layer: &str,
+        feature_id: u64,
+        key: Option<&str>,
+    ) {
+        if let Some(feature_states) = self.world.resources.get_mut::<FeatureStates>() {
+            feature_states.remove(&FeatureKey::new(source, source_layer, feature_id), key);
+        }
+        self.request_repaint();
+    }
+
+    /// Replaces the frame of an image or canvas source, e.g. the next frame of a video. The source
+    /// is not recreated.
+    pub fn set_image_source_frame(&mut self, source_id: &str, frame: RgbaImage) {
+        self.world
+            .resources
+            .get_or_init_mut::<ImageSources>()
+            .set_frame(source_id, frame);
+        self.request_repaint();
+    }
+
+    /// Moves an image or canvas source to new corners, which are listed clockwise starting with
+    /// the top left corner of the image.
+    pub fn set_image_source_coordinates(&mut self, source_id: &str, corners: [LatLon; 4]) {
+        self.world
+            .resources
+            .get_or_init_mut::<ImageSources>()
+            .set_corners(source_id, corners);
+        self.request_repaint();
+    }
+
+    /// Returns the overlay items of the next frame, which are drawn by the
+    /// [`OverlayPlugin`](crate::render::overlay::OverlayPlugin). The items are removed after the
+    /// frame has been rendered, so they have to be added again for every frame.
+    pub fn overlay(&mut self) -> &mut RenderPhase<OverlayItem> {
+        self.request_repaint();
+        self.world
+            .resources
+            .get_or_init_mut::<RenderPhase<OverlayItem>>()
+    }
+
+    /// Adds a texture which [`OverlayImages`](crate::render::overlay::OverlayImage) can draw.
+    pub fn add_overlay_texture(&mut self, texture: OverlayTexture) -> OverlayTextureId {
+        self.world
+            .resources
+            .get_or_init_mut::<OverlayTextures>()
+            .add(texture)
+    }
+
+    pub fn remove_overlay_texture(&mut self, id: OverlayTextureId) -> Option<OverlayTexture> {
+        self.world
+            .resources
+            .get_mut::<OverlayTextures>()?
+            .remove(id)
+    }
+
+    /// Adds a marker, which is drawn by the [`AnnotationPlugin`](crate::annotation::AnnotationPlugin).
+    pub fn add_marker(&mut self, marker: Marker) -> MarkerId {
+        let id = self
+            .world
+            .resources
+            .get_or_init_mut::<Annotations>()
+            .add_marker(marker);
+        self.request_repaint();
+        id
+    }
+
+    pub fn remove_marker(&mut self, id: MarkerId) -> Option<Marker> {
+        let marker = self
+            .world
+            .resources
+            .get_mut::<Annotations>()?
+            .remove_marker(id);
+        self.request_repaint();
+        marker
+    }
+
+    pub fn marker(&self, id: MarkerId) -> Option<&Marker> {
+        self.world.resources.get::<Annotations>()?.marker(id)
+    }
+
+    /// Moves a marker. Returns `false` if the marker does not exist.
+    pub fn set_marker_position(&mut self, id: MarkerId, lat_lon: LatLon) -> bool {
+        let Some(annotations) = self.world.resources.get_mut::<Annotations>() else {
+            return false;
+        };
+        let is_moved = annotations.set_marker_position(id, lat_lon);
+        self.request_repaint();
+        is_moved
+    }
+
+    /// Returns the top-most marker at a point on the screen.
+    pub fn marker_at(&self, point: ScreenPoint) -> Option<MarkerId> {
+        self.world.resources.get::<Annotations>()?.marker_at(point)
+    }
+
+    /// Adds a popup, which is drawn by the [`AnnotationPlugin`](crate::annotation::AnnotationPlugin).
+    pub fn add_popup(&mut self, popup: Popup) -> PopupId {
+        let id = self
+            .world
+            .resources
+            .get_or_init_mut::<Annotations>()
+            .add_popup(popup);
+        self.request_repaint();
+        id
+    }
+
+    pub fn remove_popup(&mut self, id: PopupId) -> Option<Popup> {
+        let popup = self
+            .world
+            .resources
+            .get_mut::<Annotations>()?
+            .remove_popup(id);
+        self.request_repaint();
+        popup
+    }
+
+    /// Adds a control, which is drawn by the [`ControlPlugin`](crate::control::ControlPlugin). A
+    /// control which has been added already is moved to `corner`.
+    pub fn add_control(&mut self, control: Control, corner: Corner) {
+        self.world
+            .resources
+            .get_or_init_mut::<Controls>()
+            .add(control, corner);
+        self.request_repaint();
+    }
+
+    /// Removes a control. Returns `false` if the control has not been added.
+    pub fn remove_control(&mut self, control: Control) -> bool {
+        let Some(controls) = self.world.resources.get_mut::<Controls>() else {
+            return false;
+        };
+        let is_removed = controls.remove(control);
+        self.request_repaint();
+        is_removed
+    }
+
+    /// Returns the part of a control at a point on the screen.
+    pub fn control_at(&self, point: ScreenPoint) -> Option<ControlHit> {
+        self.world.resources.get::<Controls>()?.control_at(point)
+    }
+
+    pub fn draw_mode(&self) -> DrawMode {
+        self.world
+            .resources
+            .get::<Drawing>()
+            .map_or(DrawMode::Off, Drawing::mode)
+    }
+
+    /// Changes how the input of the [`DrawPlugin`](crate::draw::DrawPlugin) is handled. The
+    /// feature which is being drawn is discarded.
+    pub fn set_draw_mode(&mut self, mode: DrawMode) {
+        self.world
+            .resources
+            .get_or_init_mut::<Drawing>()
+            .set_mode(mode);
+        self.request_repaint();
+    }
+
+    /// Passes input to the drawing tools. Returns whether the input has been consumed, in which
+    /// case it should not move the map.
+    pub fn draw_input(&mut self, input: DrawInput) -> bool {
+        draw::handle_input(self, input)
+    }
+
+    pub fn add_drawn_feature(&mut self, feature: DrawnFeature) -> DrawnFeatureId {
+        let id = self
+            .world
+            .resources
+            .get_or_init_mut::<Drawing>()
+            .add_feature(feature);
+        self.request_repaint();
+        id
+    }
+
+    pub fn remove_drawn_feature(&mut self, id: DrawnFeatureId) -> Option<DrawnFeature> {
+        let feature = self
+            .world
+            .resources
+            .get_mut::<Drawing>()?
+            .remove_feature(id);
+        self.request_repaint();
+        feature
+    }
+
+    pub fn drawn_feature(&self, id: DrawnFeatureId) -> Option<&DrawnFeature> {
+        self.world.resources.get::<Drawing>()?.feature(id)
+    }
+
+    pub fn drawn_features(&self) -> Vec<(DrawnFeatureId, DrawnFeature)> {
+        self.world
+            .resources
+            .get::<Drawing>()
+            .map(|drawing| {
+                drawing
+                    .features()
+                    .map(|(id, feature)| (id, feature.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the drawn features as a GeoJSON feature collection, see [`Drawing::to_geojson`].
+    pub fn drawn_features_geojson(&self) -> JsonValue {
+        self.world
+            .resources
+            .get::<Drawing>()
+            .map(Drawing::to_geojson)
+            .unwrap_or_else(|| Drawing::default().to_geojson())
+    }
+
+    /// Removes all drawn features and the feature which is being drawn.
+    pub fn clear_drawn_features(&mut self) {
+        if let Some(drawing) = self.world.resources.get_mut::<Drawing>() {
+            drawing.clear();
+        }
+        self.request_repaint();
+    }
+
+    /// Emits an event, which is dispatched to the listeners of the map after the next frame.
+    pub fn emit(&mut self, event: MapEvent) {
+        events::emit(&mut self.world, event);
+    }
+
+    /// Creates a [`PointerEvent`] at a point on the screen, which contains the location and the
+    /// rendered features at the point.
+    pub fn pointer_event(&self, point: ScreenPoint) -> PointerEvent {
+        PointerEvent {
+            point,
+            lat_lon: self.view_state.unproject(point),
+            features: self.query_rendered_features(point, None, None),
+        }
+    }
+
+    /// Inserts a layer below the layer `before_id`, or on top if `before_id` is `None`.
+    pub fn add_layer(
+        &mut self,
+        layer: StyleLayer,
+        before_id: Option<&str>,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.add_layer(layer, before_id))
+    }
+
+    pub fn remove_layer(&mut self, layer_id: &str) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.remove_layer(layer_id))
+    }
+
+    /// Moves a layer below the layer `before_id`, or on top if `before_id` is `None`.
+    pub fn move_layer(
+        &mut self,
+        layer_id: &str,
+        before_id: Option<&str>,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.move_layer(layer_id, before_id))
+    }
+
+    /// Sets a paint property, e.g. `fill-color`. Layers are restyled without tessellating their
+    /// tiles again.
+    pub fn set_paint_property(
+        &mut self,
+        layer_id: &str,
+        name: &str,
+        value: JsonValue,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.set_paint_property(layer_id, name, value))
+    }
+
+    pub fn set_layout_property(
+        &mut self,
+        layer_id: &str,
+        name: &str,
+        value: JsonValue,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.set_layout_property(layer_id, name, value))
+    }
+
+    /// Sets the filter of a layer. Only the features which match the filter are drawn and
+    /// queried.
+    pub fn set_filter(
+        &mut self,
+        layer_id: &str,
+        filter: Option<JsonValue>,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.set_filter(layer_id, filter))
+    }
+
+    pub fn set_layer_zoom_range(
+        &mut self,
+        layer_id: &str,
+        minzoom: Option<u8>,
+        maxzoom: Option<u8>,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.set_layer_zoom_range(layer_id, minzoom, maxzoom))
+    }
+
+    pub fn add_source(&mut self, id: &str, source: Source) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.add_source(id, source))
+    }
+
+    pub fn remove_source(&mut self, id: &str) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.remove_source(id))
     }
 }
@@ -0,0 +1,93 @@
+//! Collects the attributions of the sources which are in use.
+
+use crate::style::{source::Source, Style};
+
+/// Converts the HTML of an attribution to plain text. Tags are removed and the copyright sign,
+/// which the overlay font lacks, is written as `(c)`.
+fn to_plain_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for ch in html.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(ch),
+            _ => {}
+        }
+    }
+
+    text.replace("&copy;", "(c)")
+        .replace('©', "(c)")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the attributions of the sources which are used by the terrain or by a layer which is
+/// visible at `zoom`, separated by `|`. Duplicates are shown once.
+pub fn attribution_text(style: &Style, zoom: f64) -> String {
+    let terrain = style
+        .terrain
+        .as_ref()
+        .map(|terrain| terrain.source.as_str());
+    let sources = style
+        .layers
+        .iter()
+        .filter(|layer| layer.is_visible_at(zoom))
+        .filter_map(|layer| layer.source.as_deref())
+        .chain(terrain);
+
+    let mut attributions: Vec<String> = Vec::new();
+    for source in sources {
+        let Some(attribution) = style.sources.get(source).and_then(Source::attribution) else {
+            continue;
+        };
+        let attribution = to_plain_text(attribution);
+        if !attribution.is_empty() && !attributions.contains(&attribution) {
+            attributions.push(attribution);
+        }
+    }
+
+    attributions.join(" | ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribution_text() {
+        let style: Style = serde_json::from_str(
+            r#"{
+                "version": 8,
+                "sources": {
+                    "a": {
+                        "type": "vector",
+                        "attribution": "<a href=\"https://openmaptiles.org/\">&copy; OpenMapTiles</a>"
+                    },
+                    "b": { "type": "vector", "attribution": "© OpenMapTiles" },
+                    "c": { "type": "vector", "attribution": "Hidden" },
+                    "d": { "type": "vector", "attribution": "Zoomed out" }
+                },
+                "layers": [
+                    { "id": "a", "type": "fill", "source": "a", "source-layer": "a" },
+                    { "id": "b", "type": "fill", "source": "b", "source-layer": "b" },
+                    {
+                        "id": "c", "type": "fill", "source": "c", "source-layer": "c",
+                        "layout": { "visibility": "none" }
+                    },
+                    { "id": "d", "type": "fill", "source": "d", "source-layer": "d", "maxzoom": 5 }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(attribution_text(&style, 10.0), "(c) OpenMapTiles");
+        assert_eq!(
+            attribution_text(&style, 2.0),
+            "(c) OpenMapTiles | Zoomed out"
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! Built-in controls which are drawn in the corners of the map, e.g. a scale bar or zoom buttons.
+//!
+//! The controls are laid out and drawn in the overlay every frame. Their parts can be hit-tested,
+//! such that the input of the platform can activate them instead of moving the map.
+
+use std::rc::Rc;
+
+use cgmath::Deg;
+use glam::Vec2;
+
+use crate::{
+    camera::{animation::AnimationOptions, CameraOptions},
+    context::MapContext,
+    control::queue_system::queue_system,
+    coords::ScreenPoint,
+    environment::Environment,
+    kernel::Kernel,
+    plugin::Plugin,
+    render::{graph::RenderGraph, RenderStageLabel},
+    schedule::Schedule,
+    tcs::world::World,
+};
+
+mod attribution;
+mod queue_system;
+mod scale_bar;
+
+pub use attribution::attribution_text;
+pub use scale_bar::{scale_bar, ScaleUnit};
+
+/// Controls are drawn above the annotations.
+pub const CONTROL_Z: u32 = 3000;
+
+/// The corner of the map in which a control is placed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    /// Returns the top-left corner of a control of `size`, which is placed `offset` pixels away
+    /// from the corner of the screen. The offset grows towards the center of the screen.
+    pub fn top_left(&self, screen_size: Vec2, size: Vec2, offset: Vec2) -> Vec2 {
+        let x = match self {
+            Corner::TopLeft | Corner::BottomLeft => offset.x,
+            Corner::TopRight | Corner::BottomRight => screen_size.x - offset.x - size.x,
+        };
+        let y = match self {
+            Corner::TopLeft | Corner::TopRight => offset.y,
+            Corner::BottomLeft | Corner::BottomRight => screen_size.y - offset.y - size.y,
+        };
+        Vec2::new(x, y)
+    }
+}
+
+/// A built-in control.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    /// Shows the distance on the ground for a length on the screen at the center of the map.
+    ScaleBar(ScaleUnit),
+    /// Shows the bearing of the map. Activating it rotates the map to the north.
+    Compass,
+    /// Buttons which zoom in and out.
+    ZoomButtons,
+    /// Shows the attributions of the sources which are in use.
+    Attribution,
+}
+
+/// A part of a control which has been drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlHit {
+    ZoomIn,
+    ZoomOut,
+    Compass,
+    ScaleBar,
+    Attribution,
+}
+
+impl ControlHit {
+    /// Performs the action of the part, e.g. after it has been clicked. Parts without an action
+    /// do nothing.
+    pub fn activate(self, map_context: &mut MapContext) {
+        match self {
+            ControlHit::ZoomIn => map_context.zoom_by(1.0, None, AnimationOptions::default()),
+            ControlHit::ZoomOut => map_context.zoom_by(-1.0, None, AnimationOptions::default()),
+            ControlHit::Compass => map_context.ease_to(
+                &CameraOptions {
+                    bearing: Some(Deg(0.0)),
+                    ..CameraOptions::default()
+                },
+                AnimationOptions::default(),
+            ),
+            ControlHit::ScaleBar | ControlHit::Attribution => {}
+        }
+    }
+}
+
+/// The controls of the map. The controls of a corner are stacked in the order in which they have
+/// been added, starting at the corner.
+pub struct Controls {
+    controls: Vec<(Control, Corner)>,
+    /// Distance in logical pixels between the controls and the edges of the map, and between the
+    /// controls of a corner
+    pub margin: Vec2,
+    /// The parts which have been drawn during the last frame
+    hits: Vec<(ControlHit, Vec2, Vec2)>,
+}
+
+impl Default for Controls {
+    /// Only the attribution is shown, because the sources usually require it.
+    fn default() -> Self {
+        Self {
+            controls: vec![(Control::Attribution, Corner::BottomRight)],
+            margin: Vec2::splat(10.0),
+            hits: Vec::new(),
+        }
+    }
+}
+
+impl Controls {
+    /// Adds a control to a corner. A control which has been added already is moved.
+    pub fn add(&mut self, control: Control, corner: Corner) {
+        self.remove(control);
+        self.controls.push((control, corner));
+    }
+
+    /// Removes a control. Returns `false` if the control has not been added.
+    pub fn remove(&mut self, control: Control) -> bool {
+        let count = self.controls.len();
+        self.controls.retain(|(added, _)| *added != control);
+        self.controls.len() != count
+    }
+
+    pub fn controls(&self) -> impl Iterator<Item = (Control, Corner)> + '_ {
+        self.controls.iter().copied()
+    }
+
+    /// Returns the part of a control which has been drawn at `point` during the last frame.
+    pub fn control_at(&self, point: ScreenPoint) -> Option<ControlHit> {
+        let point = Vec2::new(point.x as f32, point.y as f32);
+        self.hits
+            .iter()
+            .find(|(_, min, max)| point.cmpge(*min).all() && point.cmple(*max).all())
+            .map(|(hit, _, _)| *hit)
+    }
+}
+
+/// Draws the [`Controls`]. Requires the [`OverlayPlugin`](crate::render::overlay::OverlayPlugin).
+#[derive(Default)]
+pub struct ControlPlugin;
+
+impl<E: Environment> Plugin<E> for ControlPlugin {
+    fn build(
+        &self,
+        schedule: &mut Schedule,
+        _kernel: Rc<Kernel<E>>,
+        world: &mut World,
+        _graph: &mut RenderGraph,
+    ) {
+        world.resources.init::<Controls>();
+
+        schedule.add_system_to_stage(RenderStageLabel::Queue, queue_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corner() {
+        let screen_size = Vec2::new(800.0, 600.0);
+        let size = Vec2::new(100.0, 20.0);
+        let offset = Vec2::new(10.0, 40.0);
+
+        assert_eq!(
+            Corner::TopLeft.top_left(screen_size, size, offset),
+            Vec2::new(10.0, 40.0)
+        );
+        assert_eq!(
+            Corner::BottomRight.top_left(screen_size, size, offset),
+            Vec2::new(690.0, 540.0)
+        );
+    }
+
+    #[test]
+    fn test_controls() {
+        let mut controls = Controls::default();
+        controls.add(Control::Compass, Corner::TopRight);
+        controls.add(Control::Compass, Corner::TopLeft);
+        assert_eq!(
+            controls.controls().collect::<Vec<_>>(),
+            vec![
+                (Control::Attribution, Corner::BottomRight),
+                (Control::Compass, Corner::TopLeft)
+            ]
+        );
+
+        assert!(controls.remove(Control::Attribution));
+        assert!(!controls.remove(Control::ZoomButtons));
+
+        controls.hits = vec![(ControlHit::ZoomIn, Vec2::ZERO, Vec2::splat(29.0))];
+        assert_eq!(
+            controls.control_at(ScreenPoint::new(10.0, 10.0)),
+            Some(ControlHit::ZoomIn)
+        );
+        assert_eq!(controls.control_at(ScreenPoint::new(30.0, 10.0)), None);
+    }
+}
@@ -0,0 +1,210 @@
+//! Lays out the controls for the current camera and queues them into the overlay.
+
+use glam::{Vec2, Vec4};
+
+use crate::{
+    context::MapContext,
+    control::{attribution_text, scale_bar, Control, ControlHit, Controls, Corner, CONTROL_Z},
+    render::{
+        overlay::{OverlayFont, OverlayItem, OverlayKind, OverlayText, Shape},
+        render_phase::RenderPhase,
+    },
+};
+
+/// Size of the compass and the zoom buttons in logical pixels.
+const BUTTON_SIZE: f32 = 29.0;
+const SCALE_BAR_MAX_WIDTH: f32 = 100.0;
+const SCALE_BAR_HEIGHT: f32 = 18.0;
+const TEXT_SIZE: f32 = 12.0;
+const TEXT_PADDING: f32 = 4.0;
+
+const BACKGROUND_COLOR: Vec4 = Vec4::new(1.0, 1.0, 1.0, 0.8);
+const FOREGROUND_COLOR: Vec4 = Vec4::new(0.2, 0.2, 0.2, 1.0);
+const NEEDLE_COLOR: Vec4 = Vec4::new(0.9, 0.2, 0.2, 1.0);
+
+/// The content of a control for the current frame.
+enum Content {
+    ScaleBar {
+        width: f32,
+        label: String,
+    },
+    /// The bearing in radians
+    Compass {
+        bearing: f32,
+    },
+    ZoomButtons,
+    Attribution {
+        text: String,
+    },
+}
+
+impl Content {
+    fn size(&self, font: &OverlayFont) -> Vec2 {
+        match self {
+            Content::ScaleBar { width, .. } => Vec2::new(*width, SCALE_BAR_HEIGHT),
+            Content::Compass { .. } => Vec2::splat(BUTTON_SIZE),
+            Content::ZoomButtons => Vec2::new(BUTTON_SIZE, BUTTON_SIZE * 2.0),
+            Content::Attribution { text } => {
+                font.measure(text, TEXT_SIZE) + Vec2::splat(TEXT_PADDING * 2.0)
+            }
+        }
+    }
+
+    /// Returns the shapes and texts of the control, and the parts which can be hit.
+    fn draw(self, min: Vec2, size: Vec2) -> (Vec<OverlayKind>, Vec<(ControlHit, Vec2, Vec2)>) {
+        let background = Shape::Rect {
+            pos: min,
+            size,
+            color: BACKGROUND_COLOR,
+        };
+        let bar = |center: Vec2, size: Vec2| Shape::Rect {
+            pos: center - size / 2.0,
+            size,
+            color: FOREGROUND_COLOR,
+        };
+        let text = |text: String| OverlayText {
+            text,
+            position: min + Vec2::splat(TEXT_PADDING),
+            color: FOREGROUND_COLOR,
+            size: TEXT_SIZE,
+        };
+
+        match self {
+            Content::ScaleBar { label, .. } => (
+                vec![
+                    background.into(),
+                    Shape::Polyline {
+                        points: vec![
+                            min,
+                            min + Vec2::new(0.0, size.y),
+                            min + size,
+                            min + Vec2::new(size.x, 0.0),
+                        ],
+                        width: 2.0,
+                        color: FOREGROUND_COLOR,
+                        closed: false,
+                    }
+                    .into(),
+                    OverlayText {
+                        position: min + Vec2::new(TEXT_PADDING, (size.y - TEXT_SIZE) / 2.0),
+                        ..text(label)
+                    }
+                    .into(),
+                ],
+                vec![(ControlHit::ScaleBar, min, min + size)],
+            ),
+            Content::Compass { bearing } => {
+                let center = min + size / 2.0;
+                // The north of the map is rotated by the bearing counterclockwise
+                let north = Vec2::from_angle(-bearing).rotate(Vec2::NEG_Y) * (BUTTON_SIZE * 0.35);
+                let needle = |tip: Vec2, color: Vec4| Shape::Polyline {
+                    points: vec![center, center + tip],
+                    width: 4.0,
+                    color,
+                    closed: false,
+                };
+                (
+                    vec![
+                        background.into(),
+                        needle(north, NEEDLE_COLOR).into(),
+                        needle(-north, FOREGROUND_COLOR).into(),
+                    ],
+                    vec![(ControlHit::Compass, min, min + size)],
+                )
+            }
+            Content::ZoomButtons => {
+                let button = Vec2::splat(BUTTON_SIZE);
+                let zoom_in = min + button / 2.0;
+                let zoom_out = zoom_in + Vec2::new(0.0, BUTTON_SIZE);
+                let horizontal = Vec2::new(BUTTON_SIZE * 0.4, 2.0);
+                (
+                    vec![
+                        background.into(),
+                        bar(zoom_in, horizontal).into(),
+                        bar(zoom_in, Vec2::new(horizontal.y, horizontal.x)).into(),
+                        bar(zoom_out, horizontal).into(),
+                        // Separates the buttons
+                        bar(
+                            min + Vec2::new(size.x / 2.0, BUTTON_SIZE),
+                            Vec2::new(size.x, 1.0),
+                        )
+                        .into(),
+                    ],
+                    vec![
+                        (ControlHit::ZoomIn, min, min + button),
+                        (
+                            ControlHit::ZoomOut,
+                            min + Vec2::new(0.0, BUTTON_SIZE),
+                            min + size,
+                        ),
+                    ],
+                )
+            }
+            Content::Attribution { text: attribution } => (
+                vec![background.into(), text(attribution).into()],
+                vec![(ControlHit::Attribution, min, min + size)],
+            ),
+        }
+    }
+}
+
+pub fn queue_system(
+    MapContext {
+        style,
+        world,
+        view_state,
+        ..
+    }: &mut MapContext,
+) {
+    let Some((controls, font, overlay_phase)) =
+        world
+            .resources
+            .query_mut::<(&mut Controls, &OverlayFont, &mut RenderPhase<OverlayItem>)>()
+    else {
+        return;
+    };
+
+    let screen_size = Vec2::new(view_state.width() as f32, view_state.height() as f32);
+    let margin = controls.margin;
+    // Distance from each corner at which the next control is placed
+    let mut offsets = [margin; 4];
+    controls.hits.clear();
+
+    for (control, corner) in controls.controls.iter().copied() {
+        let content = match control {
+            Control::ScaleBar(unit) => {
+                let meters_per_pixel = view_state.center().circumference_at_latitude()
+                    / view_state.zoom().world_size();
+                let (width, label) = scale_bar(meters_per_pixel, unit, SCALE_BAR_MAX_WIDTH);
+                Content::ScaleBar { width, label }
+            }
+            Control::Compass => Content::Compass {
+                bearing: view_state.bearing().0.to_radians() as f32,
+            },
+            Control::ZoomButtons => Content::ZoomButtons,
+            Control::Attribution => {
+                let text = attribution_text(style, view_state.zoom().into());
+                if text.is_empty() {
+                    continue;
+                }
+                Content::Attribution { text }
+            }
+        };
+
+        let size = content.size(font);
+        let offset = &mut offsets[match corner {
+            Corner::TopLeft => 0,
+            Corner::TopRight => 1,
+            Corner::BottomLeft => 2,
+            Corner::BottomRight => 3,
+        }];
+        let min = corner.top_left(screen_size, size, *offset);
+        offset.y += size.y + margin.y;
+
+        let (items, hits) = content.draw(min, size);
+        for item in items {
+            overlay_phase.add(OverlayItem::new(item, CONTROL_Z));
+        }
+        controls.hits.extend(hits);
+    }
+}
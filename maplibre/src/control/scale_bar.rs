@@ -0,0 +1,85 @@
+//! Computes the length and the label of the scale bar.
+
+const FEET_PER_METER: f64 = 3.2808;
+const FEET_PER_MILE: f64 = 5280.0;
+
+/// The unit system of a scale bar.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScaleUnit {
+    /// Meters and kilometers
+    #[default]
+    Metric,
+    /// Feet and miles
+    Imperial,
+}
+
+/// Rounds a distance down to 1, 2, 3 or 5 times a power of ten.
+fn round_distance(distance: f64) -> f64 {
+    let exponent = distance.log10().floor() as i32;
+    let power = 10.0_f64.powi(exponent.abs());
+    let normalized = if exponent >= 0 {
+        distance / power
+    } else {
+        distance * power
+    };
+
+    let step = [5.0, 3.0, 2.0]
+        .into_iter()
+        .find(|step| normalized >= *step)
+        .unwrap_or(1.0);
+
+    // Dividing by a power of ten avoids rounding errors like 0.30000000000000004
+    if exponent >= 0 {
+        step * power
+    } else {
+        step / power
+    }
+}
+
+/// Returns the width in logical pixels and the label of a scale bar, which is at most
+/// `max_width` wide and spans a round distance. The scale is the ground resolution in meters
+/// per logical pixel.
+pub fn scale_bar(meters_per_pixel: f64, unit: ScaleUnit, max_width: f32) -> (f32, String) {
+    let max_meters = meters_per_pixel * max_width as f64;
+    let (max_distance, unit) = match unit {
+        ScaleUnit::Metric if max_meters >= 1000.0 => (max_meters / 1000.0, "km"),
+        ScaleUnit::Metric => (max_meters, "m"),
+        ScaleUnit::Imperial => {
+            let feet = max_meters * FEET_PER_METER;
+            if feet >= FEET_PER_MILE {
+                (feet / FEET_PER_MILE, "mi")
+            } else {
+                (feet, "ft")
+            }
+        }
+    };
+
+    let distance = round_distance(max_distance);
+    let width = (max_width as f64 * distance / max_distance) as f32;
+    (width, format!("{distance} {unit}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_bar() {
+        assert_eq!(round_distance(7.3), 5.0);
+        assert_eq!(round_distance(2999.0), 2000.0);
+        assert_eq!(round_distance(0.35), 0.3);
+
+        let (width, label) = scale_bar(15.0, ScaleUnit::Metric, 100.0);
+        assert_eq!(label, "1 km");
+        assert!((width - 66.666).abs() < 0.01);
+
+        let (width, label) = scale_bar(0.5, ScaleUnit::Metric, 100.0);
+        assert_eq!(label, "50 m");
+        assert_eq!(width, 100.0);
+
+        let (_, label) = scale_bar(100.0, ScaleUnit::Imperial, 100.0);
+        assert_eq!(label, "5 mi");
+        let (_, label) = scale_bar(1.0, ScaleUnit::Imperial, 100.0);
+        assert_eq!(label, "300 ft");
+    }
+}
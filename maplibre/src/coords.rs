@@ -1,6 +1,7 @@
 //! Provides utilities related to coordinates.
 
 use std::{
+    collections::HashSet,
     f64::consts::PI,
     fmt,
     fmt::{Display, Formatter},
@@ -133,7 +134,7 @@ impl From<ZoomLevel> for u8 {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct LatLon {
     pub latitude: f64,
     pub longitude: f64,
@@ -157,7 +158,7 @@ impl LatLon {
     const EARTH_CIRCUMFRENCE: f64 = 2.0 * PI * Self::EARTH_RADIUS; // meters
 
     /// The circumference at a line of latitude in meters.
-    fn circumference_at_latitude(&self) -> f64 {
+    pub fn circumference_at_latitude(&self) -> f64 {
         Self::EARTH_CIRCUMFRENCE * (self.latitude * PI / 180.0).cos()
     }
 
@@ -169,7 +170,8 @@ impl LatLon {
         (180.0 - (180.0 / PI * ((PI / 4.0 + self.latitude * PI / 360.0).tan()).ln())) / 360.0
     }
 
-    fn mercator_z_from_altitude(&self, altitude: f64) -> f64 {
+    /// Converts an altitude in meters to a fraction of the circumference of the world.
+    pub fn mercator_z_from_altitude(&self, altitude: f64) -> f64 {
         altitude / self.circumference_at_latitude()
     }
 }
@@ -189,6 +191,56 @@ impl Display for LatLon {
     }
 }
 
+/// A box of geographic coordinates which is spanned by its south-west and north-east corners.
+///
+/// If the longitude of the north-east corner is smaller than the one of the south-west corner,
+/// then the box crosses the antimeridian.
+#[derive(Copy, Clone, Debug)]
+pub struct LatLonBounds {
+    pub south_west: LatLon,
+    pub north_east: LatLon,
+}
+
+impl LatLonBounds {
+    pub fn new(south_west: LatLon, north_east: LatLon) -> Self {
+        Self {
+            south_west,
+            north_east,
+        }
+    }
+
+    /// Returns the north-west and south-east corners of the box in world coordinates at `zoom`.
+    /// If the box crosses the antimeridian, then the `x` of the south-east corner exceeds the
+    /// size of the world.
+    pub fn to_world(&self, zoom: Zoom) -> (WorldCoords, WorldCoords) {
+        let mut east = self.north_east.longitude;
+        if east < self.south_west.longitude {
+            east += 360.0;
+        }
+
+        (
+            WorldCoords::from_lat_lon(
+                LatLon::new(self.north_east.latitude, self.south_west.longitude),
+                zoom,
+            ),
+            WorldCoords::from_lat_lon(LatLon::new(self.south_west.latitude, east), zoom),
+        )
+    }
+}
+
+/// A point on the screen in logical pixels. The origin is in the upper-left corner of the view.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct ScreenPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl ScreenPoint {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
 /// `Zoom` is an exponential scale that defines the zoom of the camera on the map.
 /// We can derive the `ZoomLevel` from `Zoom` by using the `[crate::coords::ZOOM_BOUNDS]`.
 #[derive(Copy, Clone, Debug)]
@@ -206,6 +258,12 @@ impl Zoom {
     }
 }
 
+impl From<Zoom> for f64 {
+    fn from(val: Zoom) -> Self {
+        val.0
+    }
+}
+
 impl Default for Zoom {
     fn default() -> Self {
         Zoom(0.0)
@@ -247,6 +305,11 @@ impl Zoom {
         2.0_f64.powf(zoom.0 - self.0)
     }
 
+    /// Width and height of the whole world in world coordinates at this zoom.
+    pub fn world_size(&self) -> f64 {
+        TILE_SIZE * 2.0_f64.powf(self.0)
+    }
+
     /// Adopted from
     /// [Transform::coveringZoomLevel](https://github.com/maplibre/maplibre-gl-js/blob/80e232a64716779bfff841dbc18fddc1f51535ad/src/geo/transform.ts#L279-L288)
     ///
@@ -428,13 +491,28 @@ impl WorldTileCoords {
         })
     }
 
+    /// Returns the tile with the same `y` and `z`, whose `x` is wrapped into the bounds of the
+    /// zoom level. Tiles outside of these bounds belong to copies of the world, which are
+    /// rendered when the world wraps horizontally.
+    pub fn wrap(&self) -> WorldTileCoords {
+        let bounds = ZOOM_BOUNDS[self.z.0 as usize] as i64;
+        WorldTileCoords {
+            x: (self.x as i64).rem_euclid(bounds) as i32,
+            y: self.y,
+            z: self.z,
+        }
+    }
+
     /// Adopted from [tilebelt](https://github.com/mapbox/tilebelt)
+    ///
+    /// The `x` coordinate is wrapped, such that all copies of a tile share the same key. Returns
+    /// `None` if `y` exceeds the bounds of the zoom level.
     pub fn build_quad_key(&self) -> Option<Quadkey> {
         let bounds = ZOOM_BOUNDS[self.z.0 as usize];
-        let x = self.x as u32;
+        let wrapped = self.wrap();
         let y = self.y as u32;
 
-        if x >= bounds || y >= bounds {
+        if y >= bounds {
             return None;
         }
 
@@ -445,7 +523,7 @@ impl WorldTileCoords {
         for z in 1..self.z.0 + 1 {
             let mut b = 0;
             let mask: i32 = 1 << (z - 1);
-            if (self.x & mask) != 0 {
+            if (wrapped.x & mask) != 0 {
                 b += 1u8;
             }
             if (self.y & mask) != 0 {
@@ -589,6 +667,17 @@ impl WorldCoords {
         WorldCoords { x, y }
     }
 
+    /// Returns the geographic coordinates of these world coordinates at `zoom`. This is the
+    /// inverse of [`WorldCoords::from_lat_lon`].
+    pub fn into_lat_lon(self, zoom: Zoom) -> LatLon {
+        let world_size = zoom.world_size();
+        let longitude = self.x / world_size * 360.0 - 180.0;
+        let merc_n = PI * (1.0 - 2.0 * self.y / world_size);
+        let latitude = merc_n.sinh().atan().to_degrees();
+
+        LatLon::new(latitude, longitude)
+    }
+
     pub fn at_ground(x: f64, y: f64) -> Self {
         Self { x, y }
     }
@@ -599,8 +688,8 @@ impl WorldCoords {
         let y = self.y * tile_scale;
 
         WorldTileCoords {
-            x: x as i32,
-            y: y as i32,
+            x: x.floor() as i32,
+            y: y.floor() as i32,
             z,
         }
     }
@@ -644,6 +733,9 @@ pub struct ViewRegion {
     padding: i32,
     /// The maximum amount of tiles this view region contains
     max_n_tiles: usize,
+    /// Whether tiles of copies of the world, which lie next to each other horizontally, are
+    /// contained
+    world_copies: bool,
 }
 
 impl ViewRegion {
@@ -665,9 +757,17 @@ impl ViewRegion {
             zoom_level: z,
             max_n_tiles,
             padding,
+            world_copies: false,
         }
     }
 
+    /// Sets whether this region contains tiles whose `x` lies outside the bounds of the zoom
+    /// level. These tiles belong to copies of the world.
+    pub fn with_world_copies(mut self, world_copies: bool) -> Self {
+        self.world_copies = world_copies;
+        self
+    }
+
     pub fn zoom_level(&self) -> ZoomLevel {
         self.zoom_level
     }
@@ -680,16 +780,54 @@ impl ViewRegion {
             && world_coords.z == self.zoom_level
     }
 
+    /// Iterates the tiles in this region. If the region contains copies of the world, then the
+    /// `x` of the tiles can exceed the bounds of the zoom level. Otherwise, only tiles within the
+    /// bounds are returned.
     pub fn iter(&self) -> impl Iterator<Item = WorldTileCoords> + '_ {
-        (self.min_tile.x - self.padding..self.max_tile.x + 1 + self.padding)
+        let bounds = i32::try_from(ZOOM_BOUNDS[self.zoom_level.0 as usize]).unwrap_or(i32::MAX);
+
+        let mut min_x = self.min_tile.x - self.padding;
+        let mut max_x = self.max_tile.x + self.padding;
+        if !self.world_copies {
+            min_x = min_x.max(0);
+            max_x = max_x.min(bounds - 1);
+        }
+        let min_y = (self.min_tile.y - self.padding).max(0);
+        let max_y = (self.max_tile.y + self.padding).min(bounds - 1);
+
+        (min_x..=max_x)
             .flat_map(move |x| {
-                (self.min_tile.y - self.padding..self.max_tile.y + 1 + self.padding).map(move |y| {
+                (min_y..=max_y).map(move |y| {
                     let tile_coord: WorldTileCoords = (x, y, self.zoom_level).into();
                     tile_coord
                 })
             })
             .take(self.max_n_tiles)
     }
+
+    /// Iterates the distinct tiles in this region, with their `x` wrapped into the bounds of the
+    /// zoom level. The data of a tile is shared by all its copies, so this is used to request and
+    /// upload data.
+    pub fn iter_wrapped(&self) -> impl Iterator<Item = WorldTileCoords> + '_ {
+        let mut visited = HashSet::new();
+        self.iter()
+            .map(|coords| coords.wrap())
+            .filter(move |coords| visited.insert(*coords))
+    }
+
+    /// Returns the distinct tiles of [`ViewRegion::iter_wrapped`] together with all their
+    /// ancestors, which are shown in place of tiles whose data is not available yet.
+    pub fn wrapped_with_ancestors(&self) -> HashSet<WorldTileCoords> {
+        let mut tiles = HashSet::new();
+        for coords in self.iter_wrapped() {
+            let mut current = Some(coords);
+            // The ancestors of a tile which has been inserted before are already contained
+            while let Some(coords) = current.filter(|coords| tiles.insert(*coords)) {
+                current = coords.get_parent();
+            }
+        }
+        tiles
+    }
 }
 
 impl Display for TileCoords {
@@ -727,7 +865,8 @@ mod tests {
 
     use crate::{
         coords::{
-            Quadkey, TileCoords, ViewRegion, WorldCoords, WorldTileCoords, Zoom, ZoomLevel, EXTENT,
+            LatLon, Quadkey, TileCoords, ViewRegion, WorldCoords, WorldTileCoords, Zoom, ZoomLevel,
+            EXTENT,
         },
         render::tile_view_pattern::DEFAULT_TILE_SIZE,
         style::source::TileAddressingScheme,
@@ -819,4 +958,43 @@ mod tests {
             println!("{tile_coords}");
         }
     }
+
+    #[test]
+    fn test_lat_lon_round_trip() {
+        let zoom = Zoom::new(3.5);
+        for lat_lon in [
+            LatLon::new(0.0, 0.0),
+            LatLon::new(50.85045, 4.34878),
+            LatLon::new(-33.9, 151.2),
+        ] {
+            let result = WorldCoords::from_lat_lon(lat_lon, zoom).into_lat_lon(zoom);
+            assert!((result.latitude - lat_lon.latitude).abs() < 1e-9);
+            assert!((result.longitude - lat_lon.longitude).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_world_copies() {
+        let z = ZoomLevel::from(1);
+        let copy = WorldTileCoords::from((-1, 1, z));
+        assert_eq!(copy.wrap(), WorldTileCoords::from((1, 1, z)));
+        assert_eq!(copy.build_quad_key(), copy.wrap().build_quad_key());
+        assert_eq!(WorldTileCoords::from((0, 2, z)).build_quad_key(), None);
+
+        let region = ViewRegion::new(
+            Aabb2::new(Point2::new(-600.0, -100.0), Point2::new(1500.0, 600.0)),
+            0,
+            32,
+            Zoom::new(1.0),
+            z,
+        );
+        assert!(region.iter().all(|coords| (0..2).contains(&coords.x)));
+        assert!(region.iter().all(|coords| (0..2).contains(&coords.y)));
+
+        let region = region.with_world_copies(true);
+        assert_eq!(region.iter().count(), 5 * 2);
+        assert_eq!(region.iter_wrapped().count(), 2 * 2);
+        // The root tile is the parent of all four tiles
+        assert_eq!(region.wrapped_with_ancestors().len(), 2 * 2 + 1);
+    }
 }
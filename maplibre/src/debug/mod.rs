@@ -9,7 +9,7 @@ use crate::{
     kernel::Kernel,
     plugin::Plugin,
     render::{
-        eventually::Eventually,
+        eventually::{Eventually, GpuResources},
         graph::RenderGraph,
         render_phase::{Draw, PhaseItem, RenderPhase},
         tile_view_pattern::TileShape,
@@ -93,6 +93,9 @@ impl<E: Environment> Plugin<E> for DebugPlugin {
 
         resources.init::<RenderPhase<TileDebugItem>>();
         resources.insert(Eventually::<DebugPipeline>::Uninitialized);
+        resources
+            .get_or_init_mut::<GpuResources>()
+            .add::<DebugPipeline>();
 
         schedule.add_system_to_stage(RenderStageLabel::Prepare, resource_system);
         schedule.add_system_to_stage(RenderStageLabel::Queue, queue_system);
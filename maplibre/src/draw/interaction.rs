@@ -0,0 +1,215 @@
+//! Handles the input of the drawing tools.
+
+use glam::Vec2;
+
+use crate::{
+    context::MapContext,
+    coords::{LatLon, ScreenPoint},
+    draw::{snap::snap, DrawKind, DrawMode, Drawing, DrawnFeature, DrawnFeatureId, Vertex},
+    events::{DrawEvent, MapEvent},
+};
+
+/// Pointers which moved further than this between press and release do not click.
+const CLICK_TOLERANCE: f32 = 3.0;
+
+/// Input of the drawing tools. Points are in logical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DrawInput {
+    /// The primary button has been pressed or a finger touched the screen.
+    Press(ScreenPoint),
+    Move(ScreenPoint),
+    Release(ScreenPoint),
+    /// Finishes the feature which is being drawn.
+    Finish,
+    /// Discards the feature which is being drawn.
+    Cancel,
+    /// Removes the last vertex of the feature which is being drawn.
+    Undo,
+}
+
+fn to_vec2(point: ScreenPoint) -> Vec2 {
+    Vec2::new(point.x as f32, point.y as f32)
+}
+
+fn project(map_context: &MapContext, lat_lon: LatLon) -> Option<Vec2> {
+    map_context.view_state.project(lat_lon).map(to_vec2)
+}
+
+/// Returns the location at `point`, and whether it snapped to a rendered or drawn feature. The
+/// feature `exclude` is not snapped to, e.g. because it is edited.
+fn locate(
+    map_context: &MapContext,
+    drawing: &Drawing,
+    point: ScreenPoint,
+    exclude: Option<DrawnFeatureId>,
+) -> Option<(LatLon, bool)> {
+    let lat_lon = map_context.view_state.unproject(point)?;
+    if !drawing.options.snap {
+        return Some((lat_lon, false));
+    }
+
+    let tolerance = drawing.options.tolerance as f64;
+    let rendered = map_context.query_rendered_features(
+        (
+            ScreenPoint::new(point.x - tolerance, point.y - tolerance),
+            ScreenPoint::new(point.x + tolerance, point.y + tolerance),
+        ),
+        None,
+        None,
+    );
+    let geometries = rendered
+        .into_iter()
+        .map(|feature| feature.geometry)
+        .chain(
+            drawing
+                .features()
+                .filter(|(id, _)| Some(*id) != exclude)
+                .map(|(_, feature)| feature.geometry()),
+        )
+        .collect::<Vec<_>>();
+
+    let snapped = snap(
+        to_vec2(point),
+        &geometries,
+        |lat_lon| project(map_context, lat_lon),
+        drawing.options.tolerance,
+    );
+    Some(snapped.map_or((lat_lon, false), |snapped| (snapped, true)))
+}
+
+/// Adds a vertex at `point`. Clicking the last vertex finishes lines and polygons, and so does
+/// clicking the first vertex of a polygon.
+fn click(map_context: &mut MapContext, kind: DrawKind, point: ScreenPoint) {
+    let Some(drawing) = map_context.world.resources.get::<Drawing>() else {
+        return;
+    };
+    let Some((lat_lon, _)) = locate(map_context, drawing, point, None) else {
+        return;
+    };
+
+    let is_hit = |vertex: Option<&LatLon>| {
+        vertex
+            .and_then(|vertex| project(map_context, *vertex))
+            .is_some_and(|vertex| vertex.distance(to_vec2(point)) <= drawing.options.tolerance)
+    };
+    let is_finished = drawing.sketch.len() >= kind.min_vertices()
+        && match kind {
+            DrawKind::Point => false,
+            DrawKind::Line => is_hit(drawing.sketch.last()),
+            DrawKind::Polygon => is_hit(drawing.sketch.last()) || is_hit(drawing.sketch.first()),
+        };
+
+    let Some(drawing) = map_context.world.resources.get_mut::<Drawing>() else {
+        return;
+    };
+    let created = if is_finished {
+        drawing.finish()
+    } else if kind == DrawKind::Point {
+        Some(drawing.add_feature(DrawnFeature {
+            kind,
+            vertices: vec![lat_lon],
+        }))
+    } else {
+        drawing.sketch.push(lat_lon);
+        None
+    };
+
+    if let Some(id) = created {
+        map_context.emit(MapEvent::DrawCreate(DrawEvent { id }));
+    }
+}
+
+/// Handles the input of the drawing tools. Returns whether the input has been consumed, e.g. a
+/// press on a vertex which must not pan the map.
+pub fn handle_input(map_context: &mut MapContext, input: DrawInput) -> bool {
+    let Some(drawing) = map_context.world.resources.get::<Drawing>() else {
+        return false;
+    };
+    if drawing.mode == DrawMode::Off {
+        return false;
+    }
+    let kind = drawing.mode.kind();
+
+    let consumed = match input {
+        DrawInput::Press(point) => {
+            let vertex = drawing.vertex_at(to_vec2(point), |lat_lon| project(map_context, lat_lon));
+            let Some(drawing) = map_context.world.resources.get_mut::<Drawing>() else {
+                return false;
+            };
+            drawing.dragging = vertex;
+            drawing.pressed = vertex.is_none().then_some(point);
+            vertex.is_some()
+        }
+        // Nothing follows the pointer while editing
+        DrawInput::Move(_) if kind.is_none() && drawing.dragging.is_none() => return false,
+        DrawInput::Move(point) => {
+            let exclude = match drawing.dragging {
+                Some(Vertex::Feature(id, _)) => Some(id),
+                _ => None,
+            };
+            let cursor = locate(map_context, drawing, point, exclude);
+            let Some(drawing) = map_context.world.resources.get_mut::<Drawing>() else {
+                return false;
+            };
+            drawing.cursor = cursor;
+
+            let dragging = drawing.dragging;
+            if let (Some(vertex), Some((lat_lon, _))) = (dragging, cursor) {
+                if let Some(dragged) = drawing.vertex_mut(vertex) {
+                    *dragged = lat_lon;
+                }
+            }
+            dragging.is_some()
+        }
+        DrawInput::Release(point) => {
+            let Some(drawing) = map_context.world.resources.get_mut::<Drawing>() else {
+                return false;
+            };
+            let pressed = drawing.pressed.take();
+
+            if let Some(vertex) = drawing.dragging.take() {
+                if let Vertex::Feature(id, _) = vertex {
+                    map_context.emit(MapEvent::DrawUpdate(DrawEvent { id }));
+                }
+                true
+            } else if let (Some(kind), Some(pressed)) = (kind, pressed) {
+                let is_click = to_vec2(pressed).distance(to_vec2(point)) <= CLICK_TOLERANCE;
+                if is_click {
+                    click(map_context, kind, point);
+                }
+                is_click
+            } else {
+                false
+            }
+        }
+        DrawInput::Finish => {
+            let Some(drawing) = map_context.world.resources.get_mut::<Drawing>() else {
+                return false;
+            };
+            if let Some(id) = drawing.finish() {
+                map_context.emit(MapEvent::DrawCreate(DrawEvent { id }));
+                true
+            } else {
+                false
+            }
+        }
+        DrawInput::Cancel => {
+            let Some(drawing) = map_context.world.resources.get_mut::<Drawing>() else {
+                return false;
+            };
+            let is_drawing = !drawing.sketch.is_empty();
+            drawing.cancel();
+            is_drawing
+        }
+        DrawInput::Undo => map_context
+            .world
+            .resources
+            .get_mut::<Drawing>()
+            .and_then(|drawing| drawing.sketch.pop())
+            .is_some(),
+    };
+
+    // The sketch follows the pointer
+    map_context.request_repaint();
+    consumed
+}
@@ -0,0 +1,80 @@
+//! Geodesic measurements of drawn geometries.
+
+use geo::{GeodesicArea, GeodesicLength};
+use geo_types::Geometry;
+
+/// The size of a drawn geometry on the ellipsoid of the earth.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Measurement {
+    /// The length of a line or the perimeter of a polygon in meters
+    pub length: f64,
+    /// The area of a polygon in square meters
+    pub area: f64,
+}
+
+impl Measurement {
+    /// Measures a geometry in longitude (x) and latitude (y). Points have no size.
+    pub fn of(geometry: &Geometry<f64>) -> Self {
+        match geometry {
+            Geometry::LineString(line) => Self {
+                length: line.geodesic_length(),
+                area: 0.0,
+            },
+            Geometry::Polygon(polygon) => {
+                let (perimeter, area) = polygon.geodesic_perimeter_area_unsigned();
+                Self {
+                    length: perimeter,
+                    area,
+                }
+            }
+            _ => Self::default(),
+        }
+    }
+}
+
+/// Formats a length in meters for labels, e.g. `12.3 m` or `4.56 km`.
+pub fn format_length(meters: f64) -> String {
+    if meters >= 1000.0 {
+        format!("{:.2} km", meters / 1000.0)
+    } else {
+        format!("{meters:.1} m")
+    }
+}
+
+/// Formats an area in square meters for labels, e.g. `12.3 m2` or `4.56 km2`. The overlay font
+/// lacks superscripts.
+pub fn format_area(square_meters: f64) -> String {
+    if square_meters >= 1_000_000.0 {
+        format!("{:.2} km2", square_meters / 1_000_000.0)
+    } else {
+        format!("{square_meters:.1} m2")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geo_types::{line_string, polygon};
+
+    use super::*;
+
+    #[test]
+    fn test_measurement() {
+        // A degree of longitude along the equator
+        let line = line_string![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0)];
+        let measurement = Measurement::of(&line.into());
+        assert!((measurement.length - 111_319.5).abs() < 1.0);
+        assert_eq!(measurement.area, 0.0);
+
+        let square = polygon![
+            (x: 0.0, y: 0.0),
+            (x: 1.0, y: 0.0),
+            (x: 1.0, y: 1.0),
+            (x: 0.0, y: 1.0),
+        ];
+        let measurement = Measurement::of(&square.into());
+        assert!((measurement.area / 1_000_000.0 - 12_308.8).abs() < 1.0);
+
+        assert_eq!(format_length(measurement.length), "443.77 km");
+        assert_eq!(format_area(2.5), "2.5 m2");
+    }
+}
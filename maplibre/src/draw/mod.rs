@@ -0,0 +1,367 @@
+//! Tools to draw and measure points, lines and polygons on the map.
+//!
+//! While a [`DrawMode`] is active, clicks add vertices to the geometry which is being drawn, the
+//! sketch, and vertices can be moved by dragging them. New and moved vertices snap to the rendered
+//! features near the pointer, which are looked up in the geometry index. The platform passes the
+//! pointer and keyboard input as [`DrawInput`]. Finished geometries are kept in the [`Drawing`],
+//! which can be exported as GeoJSON, and are measured geodesically.
+
+use std::{collections::BTreeMap, rc::Rc};
+
+use geo_types::{Coord, Geometry, LineString, Point, Polygon};
+use glam::{Vec2, Vec4};
+use serde_json::{json, Value as JsonValue};
+
+use crate::{
+    coords::{LatLon, ScreenPoint},
+    draw::queue_system::queue_system,
+    environment::Environment,
+    kernel::Kernel,
+    plugin::Plugin,
+    render::{graph::RenderGraph, RenderStageLabel},
+    schedule::Schedule,
+    tcs::world::World,
+};
+
+mod interaction;
+mod measure;
+mod queue_system;
+mod snap;
+
+pub(crate) use interaction::handle_input;
+pub use interaction::DrawInput;
+pub use measure::{format_area, format_length, Measurement};
+
+/// Drawn features are drawn below the markers.
+pub const DRAW_Z: u32 = 500;
+
+/// What the pointer does on the map.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DrawMode {
+    /// The drawn features are shown, but the input is not handled.
+    #[default]
+    Off,
+    /// Vertices of the drawn features can be dragged.
+    Edit,
+    /// Clicks add points.
+    Point,
+    /// Clicks add the vertices of a line.
+    Line,
+    /// Clicks add the vertices of a polygon.
+    Polygon,
+}
+
+impl DrawMode {
+    /// Returns the kind of the features which are drawn in this mode.
+    pub fn kind(&self) -> Option<DrawKind> {
+        match self {
+            DrawMode::Off | DrawMode::Edit => None,
+            DrawMode::Point => Some(DrawKind::Point),
+            DrawMode::Line => Some(DrawKind::Line),
+            DrawMode::Polygon => Some(DrawKind::Polygon),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawKind {
+    Point,
+    Line,
+    Polygon,
+}
+
+impl DrawKind {
+    /// The number of vertices which a feature of this kind needs at least.
+    fn min_vertices(&self) -> usize {
+        match self {
+            DrawKind::Point => 1,
+            DrawKind::Line => 2,
+            DrawKind::Polygon => 3,
+        }
+    }
+}
+
+/// A point, line or polygon which has been drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawnFeature {
+    pub kind: DrawKind,
+    /// The vertices of the feature. The first vertex of a polygon is not repeated at the end.
+    pub vertices: Vec<LatLon>,
+}
+
+impl DrawnFeature {
+    /// Returns the geometry in longitude (x) and latitude (y).
+    pub fn geometry(&self) -> Geometry<f64> {
+        let coords = || {
+            self.vertices
+                .iter()
+                .map(|vertex| Coord {
+                    x: vertex.longitude,
+                    y: vertex.latitude,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        match self.kind {
+            DrawKind::Point => Point(coords().first().copied().unwrap_or_default()).into(),
+            DrawKind::Line => LineString::new(coords()).into(),
+            // The exterior is closed by the polygon
+            DrawKind::Polygon => Polygon::new(LineString::new(coords()), vec![]).into(),
+        }
+    }
+
+    pub fn measurement(&self) -> Measurement {
+        Measurement::of(&self.geometry())
+    }
+
+    fn to_geojson(&self, id: DrawnFeatureId) -> JsonValue {
+        let position = |vertex: &LatLon| json!([vertex.longitude, vertex.latitude]);
+        let mut positions = self.vertices.iter().map(position).collect::<Vec<_>>();
+
+        let geometry = match self.kind {
+            DrawKind::Point => json!({ "type": "Point", "coordinates": positions.first() }),
+            DrawKind::Line => json!({ "type": "LineString", "coordinates": positions }),
+            DrawKind::Polygon => {
+                positions.extend(self.vertices.first().map(position));
+                json!({ "type": "Polygon", "coordinates": [positions] })
+            }
+        };
+        let measurement = self.measurement();
+
+        json!({
+            "type": "Feature",
+            "id": id.0,
+            "geometry": geometry,
+            "properties": {
+                "length": measurement.length,
+                "area": measurement.area,
+            },
+        })
+    }
+}
+
+/// Identifies a [`DrawnFeature`] of the [`Drawing`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DrawnFeatureId(u32);
+
+/// A vertex of the sketch or of a drawn feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Vertex {
+    Sketch(usize),
+    Feature(DrawnFeatureId, usize),
+}
+
+pub struct DrawOptions {
+    /// Distance in logical pixels within which the pointer hits vertices and snaps to features
+    pub tolerance: f32,
+    /// Whether new and moved vertices snap to the rendered and the drawn features
+    pub snap: bool,
+    /// Whether the lengths and areas are shown next to the features
+    pub show_measurements: bool,
+    pub color: Vec4,
+    /// The color of the geometry which is being drawn
+    pub sketch_color: Vec4,
+}
+
+impl Default for DrawOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 10.0,
+            snap: true,
+            show_measurements: true,
+            color: Vec4::new(0.2, 0.4, 0.9, 1.0),
+            sketch_color: Vec4::new(0.95, 0.5, 0.1, 1.0),
+        }
+    }
+}
+
+/// The drawn features and the state of the drawing tools.
+#[derive(Default)]
+pub struct Drawing {
+    mode: DrawMode,
+    pub options: DrawOptions,
+    features: BTreeMap<DrawnFeatureId, DrawnFeature>,
+    next_id: u32,
+    /// The vertices of the feature which is being drawn
+    sketch: Vec<LatLon>,
+    /// The location of the pointer, and whether it snapped to a feature
+    cursor: Option<(LatLon, bool)>,
+    /// A press which becomes a click if it is released nearby
+    pressed: Option<ScreenPoint>,
+    /// The vertex which is dragged
+    dragging: Option<Vertex>,
+}
+
+impl Drawing {
+    pub fn mode(&self) -> DrawMode {
+        self.mode
+    }
+
+    /// Changes the mode. The feature which is being drawn is discarded.
+    pub fn set_mode(&mut self, mode: DrawMode) {
+        self.mode = mode;
+        self.cancel();
+    }
+
+    pub fn add_feature(&mut self, feature: DrawnFeature) -> DrawnFeatureId {
+        let id = DrawnFeatureId(self.next_id);
+        self.next_id += 1;
+        self.features.insert(id, feature);
+        id
+    }
+
+    pub fn remove_feature(&mut self, id: DrawnFeatureId) -> Option<DrawnFeature> {
+        if matches!(self.dragging, Some(Vertex::Feature(dragged, _)) if dragged == id) {
+            self.dragging = None;
+        }
+        self.features.remove(&id)
+    }
+
+    pub fn feature(&self, id: DrawnFeatureId) -> Option<&DrawnFeature> {
+        self.features.get(&id)
+    }
+
+    pub fn features(&self) -> impl Iterator<Item = (DrawnFeatureId, &DrawnFeature)> {
+        self.features.iter().map(|(id, feature)| (*id, feature))
+    }
+
+    pub fn clear(&mut self) {
+        self.features.clear();
+        self.cancel();
+    }
+
+    /// Returns the feature which is being drawn, including the vertex at the pointer.
+    pub fn sketch(&self) -> Option<DrawnFeature> {
+        let kind = self.mode.kind()?;
+        let cursor = self.cursor.filter(|_| self.dragging.is_none());
+        let vertices = self
+            .sketch
+            .iter()
+            .copied()
+            .chain(cursor.map(|(lat_lon, _)| lat_lon))
+            .collect::<Vec<_>>();
+
+        (!vertices.is_empty() && kind != DrawKind::Point).then_some(DrawnFeature { kind, vertices })
+    }
+
+    /// Returns the drawn features as a GeoJSON feature collection. The properties of the features
+    /// contain their `length` in meters and their `area` in square meters.
+    pub fn to_geojson(&self) -> JsonValue {
+        json!({
+            "type": "FeatureCollection",
+            "features": self
+                .features
+                .iter()
+                .map(|(id, feature)| feature.to_geojson(*id))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// Turns the sketch into a feature. Returns `None` if the sketch has too few vertices.
+    fn finish(&mut self) -> Option<DrawnFeatureId> {
+        let kind = self.mode.kind()?;
+        if self.sketch.len() < kind.min_vertices() {
+            return None;
+        }
+
+        let vertices = std::mem::take(&mut self.sketch);
+        Some(self.add_feature(DrawnFeature { kind, vertices }))
+    }
+
+    fn cancel(&mut self) {
+        self.sketch.clear();
+        self.pressed = None;
+        self.dragging = None;
+    }
+
+    /// Returns the vertex which is closest to `point` within the tolerance. The vertices of the
+    /// sketch are preferred.
+    fn vertex_at(&self, point: Vec2, project: impl Fn(LatLon) -> Option<Vec2>) -> Option<Vertex> {
+        let sketch = self
+            .sketch
+            .iter()
+            .enumerate()
+            .map(|(index, vertex)| (Vertex::Sketch(index), vertex));
+        let features = self.features.iter().flat_map(|(id, feature)| {
+            feature
+                .vertices
+                .iter()
+                .enumerate()
+                .map(|(index, vertex)| (Vertex::Feature(*id, index), vertex))
+        });
+
+        sketch
+            .chain(features)
+            .filter_map(|(vertex, lat_lon)| Some((vertex, project(*lat_lon)?.distance(point))))
+            .filter(|(_, distance)| *distance <= self.options.tolerance)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(vertex, _)| vertex)
+    }
+
+    fn vertex_mut(&mut self, vertex: Vertex) -> Option<&mut LatLon> {
+        match vertex {
+            Vertex::Sketch(index) => self.sketch.get_mut(index),
+            Vertex::Feature(id, index) => self.features.get_mut(&id)?.vertices.get_mut(index),
+        }
+    }
+}
+
+/// Draws the [`Drawing`]. Requires the [`OverlayPlugin`](crate::render::overlay::OverlayPlugin).
+#[derive(Default)]
+pub struct DrawPlugin;
+
+impl<E: Environment> Plugin<E> for DrawPlugin {
+    fn build(
+        &self,
+        schedule: &mut Schedule,
+        _kernel: Rc<Kernel<E>>,
+        world: &mut World,
+        _graph: &mut RenderGraph,
+    ) {
+        world.resources.init::<Drawing>();
+
+        schedule.add_system_to_stage(RenderStageLabel::Queue, queue_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sketch() {
+        let mut drawing = Drawing::default();
+        drawing.set_mode(DrawMode::Polygon);
+        drawing.sketch = vec![LatLon::new(0.0, 0.0), LatLon::new(0.0, 1.0)];
+        drawing.cursor = Some((LatLon::new(1.0, 1.0), false));
+
+        // The pointer is part of the sketch, but not of the finished feature
+        assert_eq!(drawing.sketch().unwrap().vertices.len(), 3);
+        assert_eq!(drawing.finish(), None);
+
+        drawing.sketch.push(LatLon::new(1.0, 1.0));
+        let id = drawing.finish().unwrap();
+        assert!(drawing.sketch.is_empty());
+
+        // One pixel per degree
+        let project = |lat_lon: LatLon| {
+            Some(Vec2::new(
+                lat_lon.longitude as f32,
+                -lat_lon.latitude as f32,
+            ))
+        };
+        assert_eq!(
+            drawing.vertex_at(Vec2::new(1.0, -5.0), project),
+            Some(Vertex::Feature(id, 2))
+        );
+
+        let geojson = drawing.to_geojson();
+        let feature = &geojson["features"][0];
+        assert_eq!(feature["geometry"]["type"], "Polygon");
+        assert_eq!(
+            feature["geometry"]["coordinates"][0],
+            json!([[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]])
+        );
+        assert!(feature["properties"]["area"].as_f64().unwrap() > 0.0);
+    }
+}
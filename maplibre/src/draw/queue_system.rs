@@ -0,0 +1,174 @@
+//! Projects the drawn features and the sketch for the current camera and queues them into the
+//! overlay.
+
+use glam::{Vec2, Vec4};
+
+use crate::{
+    context::MapContext,
+    draw::{format_area, format_length, DrawKind, Drawing, DrawnFeature, DRAW_Z},
+    render::{
+        overlay::{OverlayFont, OverlayItem, OverlayText, Shape},
+        render_phase::RenderPhase,
+    },
+};
+
+const LINE_WIDTH: f32 = 3.0;
+const VERTEX_RADIUS: f32 = 4.0;
+const POINT_RADIUS: f32 = 6.0;
+const LABEL_SIZE: f32 = 12.0;
+const LABEL_PADDING: f32 = 3.0;
+/// Offset of the labels from the last vertex of lines
+const LABEL_OFFSET: Vec2 = Vec2::new(8.0, 8.0);
+
+const OUTLINE_COLOR: Vec4 = Vec4::ONE;
+const LABEL_COLOR: Vec4 = Vec4::new(0.1, 0.1, 0.1, 1.0);
+const LABEL_BACKGROUND_COLOR: Vec4 = Vec4::new(1.0, 1.0, 1.0, 0.85);
+
+/// Queues a feature whose vertices have been projected to `points`.
+fn queue_feature(
+    overlay_phase: &mut RenderPhase<OverlayItem>,
+    font: &OverlayFont,
+    feature: &DrawnFeature,
+    points: Vec<Vec2>,
+    color: Vec4,
+    show_measurements: bool,
+) {
+    let mut add = |kind: Shape| overlay_phase.add(OverlayItem::new(kind, DRAW_Z));
+
+    if feature.kind == DrawKind::Point {
+        for point in points {
+            add(Shape::Circle {
+                pos: point,
+                radius: POINT_RADIUS + 1.5,
+                color: OUTLINE_COLOR,
+            });
+            add(Shape::Circle {
+                pos: point,
+                radius: POINT_RADIUS,
+                color,
+            });
+        }
+        return;
+    }
+
+    add(Shape::Polyline {
+        points: points.clone(),
+        width: LINE_WIDTH,
+        color,
+        closed: feature.kind == DrawKind::Polygon,
+    });
+    for point in &points {
+        add(Shape::Circle {
+            pos: *point,
+            radius: VERTEX_RADIUS + 1.5,
+            color,
+        });
+        add(Shape::Circle {
+            pos: *point,
+            radius: VERTEX_RADIUS,
+            color: OUTLINE_COLOR,
+        });
+    }
+
+    if !show_measurements || points.len() < 2 {
+        return;
+    }
+
+    let measurement = feature.measurement();
+    let (text, position) = match feature.kind {
+        DrawKind::Polygon if points.len() > 2 => (
+            format!(
+                "{}\n{}",
+                format_area(measurement.area),
+                format_length(measurement.length)
+            ),
+            // The labels of polygons are centered on their vertices
+            points.iter().sum::<Vec2>() / points.len() as f32,
+        ),
+        _ => (
+            format_length(measurement.length),
+            points[points.len() - 1] + LABEL_OFFSET,
+        ),
+    };
+
+    let size = font.measure(&text, LABEL_SIZE) + Vec2::splat(LABEL_PADDING * 2.0);
+    let min = if feature.kind == DrawKind::Polygon {
+        position - size / 2.0
+    } else {
+        position
+    };
+    add(Shape::Rect {
+        pos: min,
+        size,
+        color: LABEL_BACKGROUND_COLOR,
+    });
+    overlay_phase.add(OverlayItem::new(
+        OverlayText {
+            text,
+            position: min + Vec2::splat(LABEL_PADDING),
+            color: LABEL_COLOR,
+            size: LABEL_SIZE,
+        },
+        DRAW_Z,
+    ));
+}
+
+pub fn queue_system(
+    MapContext {
+        world, view_state, ..
+    }: &mut MapContext,
+) {
+    let Some((drawing, font, overlay_phase)) =
+        world
+            .resources
+            .query_mut::<(&Drawing, &OverlayFont, &mut RenderPhase<OverlayItem>)>()
+    else {
+        return;
+    };
+
+    let project = |feature: &DrawnFeature| {
+        feature
+            .vertices
+            .iter()
+            .filter_map(|vertex| view_state.project(*vertex))
+            .map(|point| Vec2::new(point.x as f32, point.y as f32))
+            .collect::<Vec<_>>()
+    };
+    let options = &drawing.options;
+
+    for (_, feature) in drawing.features() {
+        queue_feature(
+            overlay_phase,
+            font,
+            feature,
+            project(feature),
+            options.color,
+            options.show_measurements,
+        );
+    }
+
+    if let Some(sketch) = drawing.sketch() {
+        queue_feature(
+            overlay_phase,
+            font,
+            &sketch,
+            project(&sketch),
+            options.sketch_color,
+            options.show_measurements,
+        );
+    }
+
+    // Shows where the next vertex is placed, and whether it snapped to a feature
+    if let Some((lat_lon, snapped)) = drawing.cursor.filter(|_| drawing.mode.kind().is_some()) {
+        if let Some(point) = view_state.project(lat_lon) {
+            overlay_phase.add(OverlayItem::new(
+                Shape::Circle {
+                    pos: Vec2::new(point.x as f32, point.y as f32),
+                    radius: if snapped { POINT_RADIUS } else { VERTEX_RADIUS },
+                    color: options.sketch_color,
+                },
+                DRAW_Z,
+            ));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Snaps the pointer to the vertices and edges of geometries near it on the screen.
+
+use geo::{CoordsIter, LinesIter};
+use geo_types::{Coord, Geometry, Line};
+use glam::Vec2;
+
+use crate::coords::LatLon;
+
+fn to_lat_lon(Coord { x, y }: Coord<f64>) -> LatLon {
+    LatLon::new(y, x)
+}
+
+/// Returns the edges of lines and polygons.
+fn lines(geometry: &Geometry<f64>) -> Vec<Line<f64>> {
+    match geometry {
+        Geometry::Line(line) => vec![*line],
+        Geometry::LineString(line_string) => line_string.lines_iter().collect(),
+        Geometry::Polygon(polygon) => polygon.lines_iter().collect(),
+        Geometry::MultiLineString(line_strings) => line_strings.lines_iter().collect(),
+        Geometry::MultiPolygon(polygons) => polygons.lines_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the location on `geometries` which is closest to `point` on the screen, if it is
+/// within `tolerance` logical pixels. Vertices are preferred over edges. The geometries are in
+/// longitude (x) and latitude (y), and `project` projects locations to the screen.
+pub fn snap<'a>(
+    point: Vec2,
+    geometries: impl IntoIterator<Item = &'a Geometry<f64>> + Clone,
+    project: impl Fn(LatLon) -> Option<Vec2>,
+    tolerance: f32,
+) -> Option<LatLon> {
+    let closest = |candidates: &mut dyn Iterator<Item = (f32, LatLon)>| {
+        candidates
+            .filter(|(distance, _)| *distance <= tolerance)
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, lat_lon)| lat_lon)
+    };
+
+    let vertex = closest(
+        &mut geometries
+            .clone()
+            .into_iter()
+            .flat_map(|geometry| geometry.coords_iter())
+            .filter_map(|coord| {
+                let lat_lon = to_lat_lon(coord);
+                Some((project(lat_lon)?.distance(point), lat_lon))
+            }),
+    );
+    if vertex.is_some() {
+        return vertex;
+    }
+
+    closest(
+        &mut geometries.into_iter().flat_map(lines).filter_map(|line| {
+            let (start, end) = (to_lat_lon(line.start), to_lat_lon(line.end));
+            let (a, b) = (project(start)?, project(end)?);
+            let t = ((point - a).dot(b - a) / (b - a).length_squared()).clamp(0.0, 1.0);
+            if !t.is_finite() {
+                return None;
+            }
+
+            // Interpolating the location is exact enough within a few pixels
+            let t = t as f64;
+            let lat_lon = LatLon::new(
+                start.latitude + (end.latitude - start.latitude) * t,
+                start.longitude + (end.longitude - start.longitude) * t,
+            );
+            Some((a.lerp(b, t as f32).distance(point), lat_lon))
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use geo_types::line_string;
+
+    use super::*;
+
+    #[test]
+    fn test_snap() {
+        let line: Geometry<f64> = line_string![(x: 0.0, y: 0.0), (x: 10.0, y: 0.0)].into();
+        // One degree is one pixel, with y pointing down
+        let project = |lat_lon: LatLon| {
+            Some(Vec2::new(
+                lat_lon.longitude as f32,
+                -lat_lon.latitude as f32,
+            ))
+        };
+
+        assert_eq!(
+            snap(Vec2::new(1.0, 1.0), [&line], project, 2.0),
+            Some(LatLon::new(0.0, 0.0))
+        );
+        assert_eq!(
+            snap(Vec2::new(5.0, 1.0), [&line], project, 2.0),
+            Some(LatLon::new(0.0, 5.0))
+        );
+        assert_eq!(snap(Vec2::new(5.0, 3.0), [&line], project, 2.0), None);
+    }
+}
@@ -0,0 +1,372 @@
+//! Events of the map, e.g. camera movements, clicks or loaded tiles, to which applications can
+//! listen.
+//!
+//! Systems emit events into the [`MapEvents`] resource while the schedule runs. Afterwards, the
+//! [`Map`](crate::map::Map) dispatches them to the listeners which are registered with
+//! [`Map::on`](crate::map::Map::on).
+
+use std::borrow::Cow;
+
+use crate::{
+    annotation::MarkerId,
+    camera::{animation::CameraAnimator, CameraState},
+    context::MapContext,
+    coords::{LatLon, ScreenPoint, WorldTileCoords},
+    draw::DrawnFeatureId,
+    query::QueriedFeature,
+    raster::{resource::RasterResources, RasterLayersDataComponent},
+    render::{eventually::Eventually, tile_view_pattern::DEFAULT_TILE_SIZE},
+    repaint::Repaint,
+    tcs::{system::System, world::World},
+    vector::{VectorBufferPool, VectorLayersDataComponent},
+};
+
+/// Changes of the camera which are smaller than this are ignored
+const CAMERA_EPSILON: f64 = 1e-9;
+
+/// The kind of a [`MapEvent`], which is used to register listeners.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MapEventKind {
+    MoveStart,
+    Move,
+    MoveEnd,
+    ZoomStart,
+    ZoomEnd,
+    RotateStart,
+    RotateEnd,
+    PitchStart,
+    PitchEnd,
+    Click,
+    DblClick,
+    ContextMenu,
+    MarkerDragStart,
+    MarkerDrag,
+    MarkerDragEnd,
+    DrawCreate,
+    DrawUpdate,
+    SourceData,
+    StyleData,
+    Idle,
+    Render,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub enum MapEvent {
+    /// The camera started to change, e.g. because of user input or an animation.
+    MoveStart,
+    /// The camera changed during the last frame.
+    Move,
+    /// The camera did not change during the last frame.
+    MoveEnd,
+    ZoomStart,
+    ZoomEnd,
+    RotateStart,
+    RotateEnd,
+    PitchStart,
+    PitchEnd,
+    Click(PointerEvent),
+    DblClick(PointerEvent),
+    /// The secondary button was clicked.
+    ContextMenu(PointerEvent),
+    MarkerDragStart(MarkerEvent),
+    /// A marker has been moved by dragging it.
+    MarkerDrag(MarkerEvent),
+    MarkerDragEnd(MarkerEvent),
+    /// A feature has been drawn.
+    DrawCreate(DrawEvent),
+    /// A vertex of a drawn feature has been moved.
+    DrawUpdate(DrawEvent),
+    /// Data of a tile has been loaded, or could not be loaded.
+    SourceData(SourceDataEvent),
+    /// The style has been changed.
+    StyleData,
+    /// All tiles in view are loaded and the camera is not moving.
+    Idle,
+    /// A frame has been rendered.
+    Render,
+    Error(String),
+}
+
+impl MapEvent {
+    pub fn kind(&self) -> MapEventKind {
+        match self {
+            MapEvent::MoveStart => MapEventKind::MoveStart,
+            MapEvent::Move => MapEventKind::Move,
+            MapEvent::MoveEnd => MapEventKind::MoveEnd,
+            MapEvent::ZoomStart => MapEventKind::ZoomStart,
+            MapEvent::ZoomEnd => MapEventKind::ZoomEnd,
+            MapEvent::RotateStart => MapEventKind::RotateStart,
+            MapEvent::RotateEnd => MapEventKind::RotateEnd,
+            MapEvent::PitchStart => MapEventKind::PitchStart,
+            MapEvent::PitchEnd => MapEventKind::PitchEnd,
+            MapEvent::Click(_) => MapEventKind::Click,
+            MapEvent::DblClick(_) => MapEventKind::DblClick,
+            MapEvent::ContextMenu(_) => MapEventKind::ContextMenu,
+            MapEvent::MarkerDragStart(_) => MapEventKind::MarkerDragStart,
+            MapEvent::MarkerDrag(_) => MapEventKind::MarkerDrag,
+            MapEvent::MarkerDragEnd(_) => MapEventKind::MarkerDragEnd,
+            MapEvent::DrawCreate(_) => MapEventKind::DrawCreate,
+            MapEvent::DrawUpdate(_) => MapEventKind::DrawUpdate,
+            MapEvent::SourceData(_) => MapEventKind::SourceData,
+            MapEvent::StyleData => MapEventKind::StyleData,
+            MapEvent::Idle => MapEventKind::Idle,
+            MapEvent::Render => MapEventKind::Render,
+            MapEvent::Error(_) => MapEventKind::Error,
+        }
+    }
+}
+
+/// A click or tap on the map.
+#[derive(Clone, Debug)]
+pub struct PointerEvent {
+    pub point: ScreenPoint,
+    /// The location on the ground, or `None` if the ground is not visible at the point.
+    pub lat_lon: Option<LatLon>,
+    /// The rendered features at the point, ordered from the top-most layer to the bottom-most.
+    pub features: Vec<QueriedFeature>,
+}
+
+/// A marker which is dragged, and its current location.
+#[derive(Clone, Copy, Debug)]
+pub struct MarkerEvent {
+    pub id: MarkerId,
+    pub lat_lon: LatLon,
+}
+
+/// A feature of the [`Drawing`](crate::draw::Drawing).
+#[derive(Clone, Copy, Debug)]
+pub struct DrawEvent {
+    pub id: DrawnFeatureId,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileStatus {
+    Loaded,
+    /// The tile could not be fetched, or does not contain the layer.
+    Missing,
+}
+
+#[derive(Clone, Debug)]
+pub struct SourceDataEvent {
+    pub coords: WorldTileCoords,
+    /// The layer of the source, or `None` if all layers of a vector tile have been loaded.
+    pub source_layer: Option<String>,
+    pub status: TileStatus,
+}
+
+/// Events which have been emitted, but not dispatched to the listeners yet.
+#[derive(Default)]
+pub struct MapEvents {
+    events: Vec<MapEvent>,
+}
+
+impl MapEvents {
+    pub fn emit(&mut self, event: MapEvent) {
+        self.events.push(event);
+    }
+
+    pub fn take(&mut self) -> Vec<MapEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+/// Emits an event, if the [`MapEvents`] resource is available.
+pub fn emit(world: &mut World, event: MapEvent) {
+    if let Some(events) = world.resources.get_mut::<MapEvents>() {
+        events.emit(event);
+    }
+}
+
+/// Identifies a listener, such that it can be removed again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ListenerId(u64);
+
+type Listener = Box<dyn FnMut(&MapEvent)>;
+
+/// Listeners which are registered for kinds of events.
+#[derive(Default)]
+pub struct EventListeners {
+    next_id: u64,
+    listeners: Vec<(ListenerId, MapEventKind, Listener)>,
+}
+
+impl EventListeners {
+    pub fn add(
+        &mut self,
+        kind: MapEventKind,
+        listener: impl FnMut(&MapEvent) + 'static,
+    ) -> ListenerId {
+        let id = ListenerId(self.next_id);
+        self.next_id += 1;
+        self.listeners.push((id, kind, Box::new(listener)));
+        id
+    }
+
+    /// Removes a listener. Returns `false` if the listener has already been removed.
+    pub fn remove(&mut self, id: ListenerId) -> bool {
+        let len = self.listeners.len();
+        self.listeners
+            .retain(|(listener_id, ..)| *listener_id != id);
+        self.listeners.len() != len
+    }
+
+    pub fn dispatch(&mut self, event: &MapEvent) {
+        let kind = event.kind();
+        for (_, listener_kind, listener) in &mut self.listeners {
+            if *listener_kind == kind {
+                listener(event);
+            }
+        }
+    }
+}
+
+/// Whether a kind of change of the camera is in progress.
+#[derive(Default)]
+struct Transition {
+    active: bool,
+}
+
+impl Transition {
+    /// Emits `start` when the camera started to change and `end` when it stopped changing.
+    fn update(&mut self, changed: bool, start: MapEvent, end: MapEvent, world: &mut World) {
+        if changed && !self.active {
+            emit(world, start);
+        } else if !changed && self.active {
+            emit(world, end);
+        }
+        self.active = changed;
+    }
+}
+
+/// Detects changes of the camera of the [`ViewState`](crate::render::view_state::ViewState) and
+/// whether the map is idle, after a frame has been rendered. The [`Repaint`] stays requested
+/// until the map is idle.
+#[derive(Default)]
+pub struct MapEventSystem {
+    previous: Option<CameraState>,
+    moving: Transition,
+    zooming: Transition,
+    rotating: Transition,
+    pitching: Transition,
+    idle: bool,
+}
+
+impl System for MapEventSystem {
+    fn name(&self) -> Cow<'static, str> {
+        "map_event_system".into()
+    }
+
+    fn run(
+        &mut self,
+        MapContext {
+            world, view_state, ..
+        }: &mut MapContext,
+    ) {
+        let current = CameraState::read(view_state);
+        let previous = self.previous.replace(current).unwrap_or(current);
+
+        let changed = |a: f64, b: f64| (a - b).abs() > CAMERA_EPSILON;
+        let zoomed = changed(current.zoom, previous.zoom);
+        let rotated = changed(current.bearing, previous.bearing);
+        let pitched = changed(current.pitch, previous.pitch);
+        let moved = zoomed
+            || rotated
+            || pitched
+            || changed(current.center.x, previous.center.x)
+            || changed(current.center.y, previous.center.y)
+            || current.padding != previous.padding;
+
+        self.moving
+            .update(moved, MapEvent::MoveStart, MapEvent::MoveEnd, world);
+        if moved {
+            emit(world, MapEvent::Move);
+        }
+        self.zooming
+            .update(zoomed, MapEvent::ZoomStart, MapEvent::ZoomEnd, world);
+        self.rotating
+            .update(rotated, MapEvent::RotateStart, MapEvent::RotateEnd, world);
+        self.pitching
+            .update(pitched, MapEvent::PitchStart, MapEvent::PitchEnd, world);
+
+        emit(world, MapEvent::Render);
+
+        let animating = world
+            .resources
+            .get::<CameraAnimator>()
+            .is_some_and(CameraAnimator::is_animating);
+        let loaded = view_state
+            .create_view_region(view_state.zoom().zoom_level(DEFAULT_TILE_SIZE))
+            .map_or(true, |view_region| {
+                view_region
+                    .iter_wrapped()
+                    .all(|coords| is_tile_loaded(world, coords))
+            });
+
+        let idle = !moved && !animating && loaded;
+        if let Some(repaint) = world.resources.get_mut::<Repaint>() {
+            repaint.finish_frame(!idle);
+        }
+        if idle && !self.idle {
+            emit(world, MapEvent::Idle);
+        }
+        self.idle = idle;
+    }
+}
+
+/// Returns whether all data of a tile in view has been loaded or could not be loaded. The vector
+/// and raster plugins request every tile in view, so a tile is loading until their data arrives.
+fn is_tile_loaded(world: &World, coords: WorldTileCoords) -> bool {
+    // Tiles outside of the world are never requested
+    if coords.build_quad_key().is_none() {
+        return true;
+    }
+
+    let vector_loaded = !world.resources.exists::<Eventually<VectorBufferPool>>()
+        || world
+            .tiles
+            .query::<&VectorLayersDataComponent>(coords)
+            .is_some_and(|component| component.done);
+    let raster_loaded = !world.resources.exists::<Eventually<RasterResources>>()
+        || world
+            .tiles
+            .query::<&RasterLayersDataComponent>(coords)
+            .is_some_and(RasterLayersDataComponent::is_loaded);
+
+    vector_loaded && raster_loaded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn test_listeners() {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let mut listeners = EventListeners::default();
+
+        let clicks = received.clone();
+        let id = listeners.add(MapEventKind::Idle, move |event| {
+            clicks.borrow_mut().push(event.kind())
+        });
+        let renders = received.clone();
+        listeners.add(MapEventKind::Render, move |event| {
+            renders.borrow_mut().push(event.kind())
+        });
+
+        listeners.dispatch(&MapEvent::Idle);
+        listeners.dispatch(&MapEvent::Render);
+        listeners.dispatch(&MapEvent::MoveStart);
+        assert_eq!(
+            *received.borrow(),
+            vec![MapEventKind::Idle, MapEventKind::Render]
+        );
+
+        assert!(listeners.remove(id));
+        assert!(!listeners.remove(id));
+        listeners.dispatch(&MapEvent::Idle);
+        assert_eq!(received.borrow().len(), 2);
+    }
+}
@@ -0,0 +1,143 @@
+//! The state of features, e.g. whether a feature is hovered or selected.
+//!
+//! The state is set by the application and can be used in paint properties through the
+//! `["feature-state", name]` expression. Changing the state only rewrites the metadata of the
+//! affected features, their tiles are not tessellated again.
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value as JsonValue;
+
+/// Identifies a feature by its id within a layer of a source.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FeatureKey {
+    pub source: String,
+    pub source_layer: String,
+    pub id: u64,
+}
+
+impl FeatureKey {
+    pub fn new(source: &str, source_layer: &str, id: u64) -> Self {
+        Self {
+            source: source.to_string(),
+            source_layer: source_layer.to_string(),
+            id,
+        }
+    }
+}
+
+/// Stores the state of features and which of them changed since the state has been applied to
+/// the uploaded tiles.
+#[derive(Default)]
+pub struct FeatureStates {
+    states: HashMap<FeatureKey, HashMap<String, JsonValue>>,
+    changed: HashSet<FeatureKey>,
+}
+
+impl FeatureStates {
+    /// Merges `state` into the state of a feature. Existing values which are not part of `state`
+    /// are kept.
+    pub fn set(&mut self, key: FeatureKey, state: HashMap<String, JsonValue>) {
+        let current = self.states.entry(key.clone()).or_default();
+        let previous = current.clone();
+        current.extend(state);
+
+        if *current != previous {
+            self.changed.insert(key);
+        }
+    }
+
+    pub fn get(&self, key: &FeatureKey) -> Option<&HashMap<String, JsonValue>> {
+        self.states.get(key)
+    }
+
+    /// Removes the value `name` from the state of a feature, or the whole state if `name` is
+    /// `None`.
+    pub fn remove(&mut self, key: &FeatureKey, name: Option<&str>) {
+        let Some(state) = self.states.get_mut(key) else {
+            return;
+        };
+
+        let is_removed = match name {
+            Some(name) => state.remove(name).is_some(),
+            None => {
+                state.clear();
+                true
+            }
+        };
+        if state.is_empty() {
+            self.states.remove(key);
+        }
+        if is_removed {
+            self.changed.insert(key.clone());
+        }
+    }
+
+    /// Marks the state of all features of a layer of a source as changed, e.g. because the
+    /// colors of the layer have been written again.
+    pub fn touch_layer(&mut self, source: &str, source_layer: &str) {
+        self.changed.extend(
+            self.states
+                .keys()
+                .filter(|key| key.source == source && key.source_layer == source_layer)
+                .cloned(),
+        );
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.changed.is_empty()
+    }
+
+    /// Returns the features whose state changed since the last call.
+    pub fn take_changed(&mut self) -> HashSet<FeatureKey> {
+        std::mem::take(&mut self.changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_remove() {
+        let mut states = FeatureStates::default();
+        let key = FeatureKey::new("parcels", "parcel", 3);
+
+        states.set(
+            key.clone(),
+            HashMap::from([("hover".to_string(), JsonValue::from(true))]),
+        );
+        states.set(
+            key.clone(),
+            HashMap::from([("selected".to_string(), JsonValue::from(true))]),
+        );
+        assert_eq!(states.get(&key).unwrap().len(), 2);
+        assert_eq!(states.take_changed(), HashSet::from([key.clone()]));
+
+        // Setting the same state again is not a change
+        states.set(
+            key.clone(),
+            HashMap::from([("hover".to_string(), JsonValue::from(true))]),
+        );
+        assert!(!states.has_changes());
+
+        states.remove(&key, Some("hover"));
+        assert_eq!(
+            states.get(&key).unwrap().keys().collect::<Vec<_>>(),
+            vec!["selected"]
+        );
+        states.remove(&key, None);
+        assert!(states.get(&key).is_none());
+        assert!(states.has_changes());
+
+        states.take_changed();
+        states.set(
+            key.clone(),
+            HashMap::from([("hover".to_string(), JsonValue::from(false))]),
+        );
+        states.take_changed();
+        states.touch_layer("parcels", "parcel");
+        states.touch_layer("routes", "parcel");
+        assert_eq!(states.take_changed(), HashSet::from([key]));
+    }
+}
@@ -1,8 +1,18 @@
-use std::{cell::RefCell, ops::Deref, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    ops::Deref,
+    rc::Rc,
+};
+
+use serde_json::Value as JsonValue;
 
 use crate::{
     context::MapContext,
-    coords::{WorldCoords, WorldTileCoords, Zoom, ZoomLevel, TILE_SIZE},
+    coords::{
+        LatLon, LatLonBounds, ScreenPoint, WorldCoords, WorldTileCoords, Zoom, ZoomLevel, TILE_SIZE,
+    },
+    events::{EventListeners, ListenerId, MapEvent, MapEventKind, MapEvents},
     headless::environment::HeadlessEnvironment,
     io::{
         apc::{Context, IntoMessage, Message, SendError},
@@ -12,14 +22,15 @@ use crate::{
     kernel::Kernel,
     map::MapError,
     plugin::Plugin,
+    query::{QueriedFeature, QueryGeometry},
     render::{eventually::Eventually, view_state::ViewState, Renderer},
     schedule::{Schedule, Stage},
-    style::Style,
+    style::{layer::LayerPaint, source::Source, Style},
     tcs::world::World,
     vector::{
-        process_vector_tile, AvailableVectorLayerData, DefaultVectorTransferables,
-        LayerTessellated, ProcessVectorContext, VectorBufferPool, VectorLayerData,
-        VectorLayersDataComponent, VectorTileRequest, VectorTransferables,
+        process_vector_tile, DefaultVectorTransferables, LayerTessellated, ProcessVectorContext,
+        VectorBufferPool, VectorLayerData, VectorLayersDataComponent, VectorTileRequest,
+        VectorTransferables,
     },
 };
 
@@ -27,6 +38,7 @@ pub struct HeadlessMap {
     kernel: Rc<Kernel<HeadlessEnvironment>>,
     schedule: Schedule,
     map_context: MapContext,
+    listeners: EventListeners,
 }
 
 impl HeadlessMap {
@@ -68,9 +80,125 @@ impl HeadlessMap {
                 renderer,
             },
             schedule,
+            listeners: EventListeners::default(),
         })
     }
 
+    /// Registers a listener for a kind of events, which are dispatched after a tile has been
+    /// rendered.
+    pub fn on(
+        &mut self,
+        kind: MapEventKind,
+        listener: impl FnMut(&MapEvent) + 'static,
+    ) -> ListenerId {
+        self.listeners.add(kind, listener)
+    }
+
+    /// Removes a listener. Returns `false` if the listener has already been removed.
+    pub fn off(&mut self, id: ListenerId) -> bool {
+        self.listeners.remove(id)
+    }
+
+    /// Whether the map is idle, i.e. nothing changed since the last frame, no animation runs and
+    /// all tiles in view are loaded. See [`MapContext::needs_repaint`].
+    pub fn is_idle(&self) -> bool {
+        !self.map_context.needs_repaint()
+    }
+
+    /// Returns the point on the screen at which `lat_lon` is shown, or `None` if it is behind
+    /// the camera.
+    pub fn project(&self, lat_lon: LatLon) -> Option<ScreenPoint> {
+        self.map_context.view_state.project(lat_lon)
+    }
+
+    /// Returns the location on the ground at `point`, or `None` if the ground is not visible
+    /// there.
+    pub fn unproject(&self, point: ScreenPoint) -> Option<LatLon> {
+        self.map_context.view_state.unproject(point)
+    }
+
+    /// Returns a box which contains the visible area of the map.
+    pub fn get_bounds(&self) -> LatLonBounds {
+        self.map_context.view_state.bounds()
+    }
+
+    pub fn get_center(&self) -> LatLon {
+        self.map_context.view_state.center()
+    }
+
+    pub fn get_zoom(&self) -> Zoom {
+        self.map_context.view_state.zoom()
+    }
+
+    /// Returns the rotation of the map in degrees counter-clockwise from north.
+    pub fn get_bearing(&self) -> cgmath::Deg<f64> {
+        self.map_context.view_state.bearing()
+    }
+
+    pub fn get_pitch(&self) -> cgmath::Deg<f64> {
+        self.map_context.view_state.pitch()
+    }
+
+    /// Returns the features which are rendered at a point or within a box on the screen, ordered
+    /// from the top-most layer to the bottom-most.
+    pub fn query_rendered_features(
+        &self,
+        geometry: impl Into<QueryGeometry>,
+        layers: Option<&[&str]>,
+        filter: Option<&JsonValue>,
+    ) -> Vec<QueriedFeature> {
+        self.map_context
+            .query_rendered_features(geometry, layers, filter)
+    }
+
+    /// Returns the features of the loaded tiles of a source, regardless of whether they are
+    /// visible.
+    pub fn query_source_features(
+        &self,
+        source_id: &str,
+        source_layer: Option<&str>,
+        filter: Option<&JsonValue>,
+    ) -> Vec<QueriedFeature> {
+        self.map_context
+            .query_source_features(source_id, source_layer, filter)
+    }
+
+    /// Merges `state` into the state of a feature, which paint properties can use through the
+    /// `["feature-state", name]` expression.
+    pub fn set_feature_state(
+        &mut self,
+        source: &str,
+        source_layer: &str,
+        feature_id: u64,
+        state: HashMap<String, JsonValue>,
+    ) {
+        self.map_context
+            .set_feature_state(source, source_layer, feature_id, state)
+    }
+
+    pub fn get_feature_state(
+        &self,
+        source: &str,
+        source_layer: &str,
+        feature_id: u64,
+    ) -> Option<&HashMap<String, JsonValue>> {
+        self.map_context
+            .get_feature_state(source, source_layer, feature_id)
+    }
+
+    /// Removes the value `key` from the state of a feature, or the whole state if `key` is
+    /// `None`.
+    pub fn remove_feature_state(
+        &mut self,
+        source: &str,
+        source_layer: &str,
+        feature_id: u64,
+        key: Option<&str>,
+    ) {
+        self.map_context
+            .remove_feature_state(source, source_layer, feature_id, key)
+    }
+
     pub fn render_tile(
         &mut self,
         layers: Vec<Box<<DefaultVectorTransferables as VectorTransferables>::LayerTessellated>>,
@@ -85,19 +213,22 @@ impl HeadlessMap {
                 done: true,
                 layers: layers
                     .into_iter()
-                    .map(|layer| {
-                        VectorLayerData::Available(AvailableVectorLayerData {
-                            coords: layer.coords,
-                            source_layer: layer.layer_data.name,
-                            buffer: layer.buffer,
-                            feature_indices: layer.feature_indices,
-                        })
-                    })
+                    .map(|layer| VectorLayerData::Available(layer.to_layer()))
                     .collect::<Vec<_>>(),
             });
 
         self.schedule.run(context);
 
+        let events = context
+            .world
+            .resources
+            .get_mut::<MapEvents>()
+            .map(MapEvents::take)
+            .unwrap_or_default();
+        for event in &events {
+            self.listeners.dispatch(event);
+        }
+
         let resources = &mut context.world.resources;
         let tiles = &mut context.world.tiles;
 
@@ -111,13 +242,26 @@ impl HeadlessMap {
         pool.clear();
     }
 
+    /// Fetches a tile of the first vector source of the style which defines its tiles. If there
+    /// is no such source, then the default tiles are fetched.
     pub async fn fetch_tile(&self, coords: WorldTileCoords) -> Result<Box<[u8]>, SourceFetchError> {
+        let source = self
+            .map_context
+            .style
+            .layers
+            .iter()
+            .filter_map(|layer| layer.source.as_ref())
+            .find_map(
+                |source_id| match self.map_context.style.sources.get(source_id) {
+                    Some(Source::Vector(source)) => source.tile_source(),
+                    _ => None,
+                },
+            )
+            .unwrap_or_else(|| SourceType::Tessellate(TessellateSource::default()));
+
         let source_client = self.kernel.source_client();
         let data = source_client
-            .fetch(
-                &coords,
-                &SourceType::Tessellate(TessellateSource::default()),
-            )
+            .fetch(&coords, &source)
             .await?
             .into_boxed_slice();
         Ok(data)
@@ -133,18 +277,70 @@ impl HeadlessMap {
             ProcessVectorContext::<DefaultVectorTransferables, HeadlessContext>::new(context);
 
         let target_coords = WorldTileCoords::default(); // load to 0,0,0
-        process_vector_tile(
-            &tile_data,
-            VectorTileRequest {
-                coords: target_coords,
-                layers: source_layers
-                    .iter()
-                    .map(|layer| layer.to_string())
-                    .collect(),
-            },
-            &mut processor,
-        )
-        .expect("Failed to process!");
+
+        // The fetched tile provides the layers of all sources of the style
+        let sources = self
+            .map_context
+            .style
+            .layers
+            .iter()
+            .filter(|layer| {
+                layer
+                    .source_layer
+                    .as_deref()
+                    .is_some_and(|source_layer| source_layers.contains(&source_layer))
+            })
+            .map(|layer| layer.source.clone())
+            .collect::<HashSet<_>>();
+
+        for source in sources {
+            let style_layers = self
+                .map_context
+                .style
+                .layers
+                .iter()
+                .filter(|layer| layer.source == source)
+                .filter(|layer| {
+                    layer
+                        .source_layer
+                        .as_deref()
+                        .is_some_and(|source_layer| source_layers.contains(&source_layer))
+                })
+                .collect::<Vec<_>>();
+
+            let mut filters: HashMap<String, Vec<Option<JsonValue>>> = HashMap::new();
+            for layer in &style_layers {
+                let Some(source_layer) = &layer.source_layer else {
+                    continue;
+                };
+                let layer_filters = filters.entry(source_layer.clone()).or_default();
+                if !layer_filters.contains(&layer.filter) {
+                    layer_filters.push(layer.filter.clone());
+                }
+            }
+
+            let point_layers = style_layers
+                .iter()
+                .filter(|layer| matches!(layer.paint, Some(LayerPaint::Heatmap(_))))
+                .filter_map(|layer| layer.source_layer.clone())
+                .collect();
+
+            process_vector_tile(
+                &tile_data,
+                VectorTileRequest {
+                    coords: target_coords,
+                    source,
+                    layers: source_layers
+                        .iter()
+                        .map(|layer| layer.to_string())
+                        .collect(),
+                    filters,
+                    point_layers,
+                },
+                &mut processor,
+            )
+            .expect("Failed to process!");
+        }
 
         let messages = processor.take_context().messages.deref().take();
         let layers = messages.into_iter()
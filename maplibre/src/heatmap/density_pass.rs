@@ -0,0 +1,72 @@
+//! Accumulates the density of the points of each heatmap layer in its offscreen texture.
+
+use crate::{
+    heatmap::resource::HeatmapResources,
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        graph::{Node, NodeRunError, RenderContext, RenderGraphContext, SlotInfo},
+        RenderResources,
+    },
+    tcs::world::World,
+};
+
+pub struct DensityPassNode {}
+
+impl DensityPassNode {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Node for DensityPassNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![]
+    }
+
+    fn update(&mut self, _state: &mut RenderResources) {}
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        _state: &RenderResources,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some(Initialized(heatmap_resources)) =
+            world.resources.get::<Eventually<HeatmapResources>>()
+        else {
+            return Ok(());
+        };
+
+        for layer in heatmap_resources.iter() {
+            // The texture is cleared even if there are no points, such that nothing is colored
+            let mut render_pass =
+                render_context
+                    .command_encoder
+                    .begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("heatmap_pass"),
+                        color_attachments: &[Some(
+                            layer.target().color_attachment(wgpu::Color::TRANSPARENT),
+                        )],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+
+            render_pass.set_pipeline(heatmap_resources.density_pipeline());
+            render_pass.set_bind_group(0, layer.density_bind_group(), &[]);
+
+            for tile in layer.tiles() {
+                if tile.points_count() == 0 {
+                    continue;
+                }
+
+                render_pass.set_bind_group(1, tile.bind_group(), &[]);
+                render_pass.set_vertex_buffer(0, tile.points().slice(..));
+                render_pass.draw(0..6, 0..tile.points_count());
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! Renders heatmap layers.
+//!
+//! The points of a layer are splatted with a gaussian kernel into an offscreen texture which
+//! accumulates their density. Afterwards, the density is colored by the color ramp of the layer
+//! and drawn onto the map.
+
+use std::rc::Rc;
+
+use crate::{
+    environment::Environment,
+    heatmap::{
+        density_pass::DensityPassNode, queue_system::queue_system, resource::HeatmapResources,
+        resource_system::resource_system,
+    },
+    kernel::Kernel,
+    plugin::Plugin,
+    render::{
+        eventually::{Eventually, GpuResources},
+        graph::RenderGraph,
+        main_graph, RenderStageLabel,
+    },
+    schedule::Schedule,
+    style::expression::{EvaluationContext, Expression},
+    tcs::world::World,
+};
+
+mod density_pass;
+mod queue_system;
+mod render_commands;
+mod resource;
+mod resource_system;
+
+/// The density is accumulated at a fraction of the resolution of the surface.
+pub const DENSITY_DOWNSCALE: u32 = 4;
+
+/// Evaluates the color of a heatmap for `size` densities between `0` and `1`. The colors are
+/// premultiplied RGBA.
+pub fn color_ramp(expression: &Expression, size: u32) -> Vec<u8> {
+    (0..size)
+        .flat_map(|i| {
+            let color = expression
+                .evaluate_color(&EvaluationContext {
+                    heatmap_density: i as f64 / (size - 1) as f64,
+                    ..Default::default()
+                })
+                .unwrap_or(csscolorparser::Color::new(0.0, 0.0, 0.0, 0.0));
+
+            [
+                color.r * color.a,
+                color.g * color.a,
+                color.b * color.a,
+                color.a,
+            ]
+            .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+        })
+        .collect()
+}
+
+/// Renders the heatmap layers of the style. Requires the
+/// [`VectorPlugin`](crate::vector::VectorPlugin) for fetching the points.
+#[derive(Default)]
+pub struct HeatmapPlugin;
+
+impl<E: Environment> Plugin<E> for HeatmapPlugin {
+    fn build(
+        &self,
+        schedule: &mut Schedule,
+        _kernel: Rc<Kernel<E>>,
+        world: &mut World,
+        graph: &mut RenderGraph,
+    ) {
+        world
+            .resources
+            .insert(Eventually::<HeatmapResources>::Uninitialized);
+        world
+            .resources
+            .get_or_init_mut::<GpuResources>()
+            .add::<HeatmapResources>();
+
+        graph.add_node(main_graph::node::HEATMAP_PASS, DensityPassNode::new());
+        graph
+            .add_node_edge(
+                main_graph::node::HEATMAP_PASS,
+                main_graph::node::MAIN_PASS_DEPENDENCIES,
+            )
+            .expect("main pass dependencies do not exist");
+
+        schedule.add_system_to_stage(RenderStageLabel::Prepare, resource_system);
+        // Must run after the tile view pattern has been updated
+        schedule.add_system_to_stage(RenderStageLabel::Queue, queue_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::heatmap::HeatmapLayer;
+
+    #[test]
+    fn test_default_color_ramp() {
+        let ramp = color_ramp(&HeatmapLayer::default_color(), 256);
+
+        assert_eq!(ramp.len(), 256 * 4);
+        // No density is transparent
+        assert_eq!(&ramp[..4], &[0, 0, 0, 0]);
+        // The highest density is red
+        assert_eq!(&ramp[255 * 4..], &[255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,162 @@
+//! Uploads the points of the heatmap layers in view once per tile and queues the heatmaps for
+//! rendering.
+use std::collections::HashSet;
+
+use crate::{
+    context::MapContext,
+    coords::WorldTileCoords,
+    heatmap::{render_commands::DrawHeatmaps, resource::HeatmapResources, DENSITY_DOWNSCALE},
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        render_phase::{DrawState, LayerItem, RenderPhase},
+        shaders::{
+            ShaderHeatmapColor, ShaderHeatmapDensity, ShaderHeatmapPoint, ShaderHeatmapTile,
+        },
+        tile_view_pattern::{TileShape, WgpuTileViewPattern},
+        RenderResources, Renderer,
+    },
+    style::{expression::EvaluationContext, layer::LayerPaint},
+    tcs::tiles::Tile,
+    vector::VectorLayersDataComponent,
+};
+
+pub fn queue_system(
+    MapContext {
+        world,
+        style,
+        view_state,
+        renderer:
+            Renderer {
+                device,
+                queue,
+                resources: RenderResources { surface, .. },
+                ..
+            },
+        ..
+    }: &mut MapContext,
+) {
+    let Some((Initialized(heatmap_resources), Initialized(tile_view_pattern), layer_item_phase)) =
+        world.resources.query_mut::<(
+            &mut Eventually<HeatmapResources>,
+            &Eventually<WgpuTileViewPattern>,
+            &mut RenderPhase<LayerItem>,
+        )>()
+    else {
+        return;
+    };
+
+    let zoom = view_state.zoom();
+    let heatmap_layers = style
+        .layers
+        .iter()
+        .filter(|layer| layer.is_visible_at(zoom.into()))
+        .filter_map(|layer| match &layer.paint {
+            Some(LayerPaint::Heatmap(paint)) => Some((layer, paint)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    heatmap_resources.begin_frame(
+        &heatmap_layers
+            .iter()
+            .map(|(layer, _)| layer.id.as_str())
+            .collect::<Vec<_>>(),
+    );
+
+    if heatmap_layers.is_empty() {
+        return;
+    }
+
+    // Tiles which are rendered for the tiles in view, e.g. a parent tile
+    let mut source_tiles = Vec::new();
+    let mut visited = HashSet::new();
+    for view_tile in tile_view_pattern.iter() {
+        view_tile.render(|source_shape| {
+            let coords = source_shape.coords();
+            if visited.insert(coords) {
+                source_tiles.push(coords);
+            }
+        });
+    }
+
+    let size = surface.size();
+    let (width, height) = (size.width(), size.height());
+    let view_proj = view_state.view_projection();
+
+    for (layer, paint) in heatmap_layers {
+        let context = EvaluationContext {
+            zoom: zoom.into(),
+            ..Default::default()
+        };
+        let radius = paint.radius(&context);
+        let extrude_scale = [
+            (radius * 2.0 / width as f64) as f32,
+            (radius * 2.0 / height as f64) as f32,
+        ];
+
+        heatmap_resources.write_layer(
+            device,
+            queue,
+            &layer.id,
+            (width / DENSITY_DOWNSCALE, height / DENSITY_DOWNSCALE),
+            &paint.color(),
+            ShaderHeatmapDensity {
+                extrude_scale,
+                intensity: paint.intensity(&context) as f32,
+                _padding: 0.0,
+            },
+            ShaderHeatmapColor {
+                opacity: paint.opacity(&context) as f32,
+                _padding: [0.0; 3],
+            },
+        );
+
+        for coords in &source_tiles {
+            let Some(vector_layers) = world.tiles.query::<&VectorLayersDataComponent>(*coords)
+            else {
+                continue;
+            };
+
+            let Some(data) = vector_layers.available_layer(layer) else {
+                continue;
+            };
+
+            // The weights are evaluated at the zoom at which the points are uploaded
+            heatmap_resources.write_tile(
+                device,
+                queue,
+                &layer.id,
+                *coords,
+                ShaderHeatmapTile {
+                    transform: view_proj
+                        .to_model_view_projection(coords.transform_for_zoom(zoom))
+                        .downcast()
+                        .into(),
+                },
+                &paint.heatmap_weight,
+                || {
+                    data.points
+                        .iter()
+                        .map(|point| ShaderHeatmapPoint {
+                            position: point.position,
+                            weight: paint.weight(&EvaluationContext {
+                                properties: Some(&point.properties),
+                                ..context
+                            }) as f32,
+                        })
+                        .collect()
+                },
+            );
+        }
+
+        // Heatmaps are not split into tiles, so each layer is drawn once
+        let coords = WorldTileCoords::default();
+        layer_item_phase.add(LayerItem {
+            draw_function: Box::new(DrawState::<LayerItem, DrawHeatmaps>::new()),
+            index: layer.index,
+            style_layer: layer.id.clone(),
+            tile: Tile { coords },
+            source_shape: TileShape::new(coords, zoom),
+        });
+    }
+}
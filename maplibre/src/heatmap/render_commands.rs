@@ -0,0 +1,54 @@
+use crate::{
+    heatmap::resource::HeatmapResources,
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        render_phase::{LayerItem, PhaseItem, RenderCommand, RenderCommandResult},
+        resource::TrackedRenderPass,
+    },
+    tcs::world::World,
+};
+
+pub struct SetHeatmapColorPipeline;
+impl<P: PhaseItem> RenderCommand<P> for SetHeatmapColorPipeline {
+    fn render<'w>(
+        world: &'w World,
+        _item: &P,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(Initialized(heatmap_resources)) =
+            world.resources.get::<Eventually<HeatmapResources>>()
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_render_pipeline(heatmap_resources.color_pipeline());
+        RenderCommandResult::Success
+    }
+}
+
+pub struct DrawHeatmap;
+impl RenderCommand<LayerItem> for DrawHeatmap {
+    fn render<'w>(
+        world: &'w World,
+        item: &LayerItem,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(Initialized(heatmap_resources)) =
+            world.resources.get::<Eventually<HeatmapResources>>()
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        let Some(layer) = heatmap_resources.get(&item.style_layer) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(0, layer.color_bind_group(), &[]);
+        // Fullscreen triangle
+        pass.draw(0..3, 0..1);
+
+        RenderCommandResult::Success
+    }
+}
+
+pub type DrawHeatmaps = (SetHeatmapColorPipeline, DrawHeatmap);
@@ -0,0 +1,374 @@
+use std::collections::{HashMap, HashSet};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    coords::WorldTileCoords,
+    render::{
+        eventually::HasChanged,
+        resource::OffscreenTarget,
+        shaders::{
+            ShaderHeatmapColor, ShaderHeatmapDensity, ShaderHeatmapPoint, ShaderHeatmapTile,
+            HEATMAP_DENSITY_FORMAT,
+        },
+    },
+    style::expression::Expression,
+};
+
+/// Amount of texels of the color ramp.
+pub const COLOR_RAMP_SIZE: u32 = 256;
+
+/// Resources of a single heatmap layer.
+pub struct HeatmapLayerResources {
+    /// Density of the points which is accumulated by the density pass
+    target: OffscreenTarget,
+    /// Texture which maps the density to a color
+    color_ramp: wgpu::Texture,
+    /// Expression from which the `color_ramp` has been built
+    color_ramp_expression: Option<Expression>,
+
+    density_uniform: wgpu::Buffer,
+    density_bind_group: wgpu::BindGroup,
+    color_uniform: wgpu::Buffer,
+    color_bind_group: wgpu::BindGroup,
+
+    tiles: HashMap<WorldTileCoords, HeatmapTileResources>,
+    /// Tiles which are rendered in the current frame
+    visible_tiles: HashSet<WorldTileCoords>,
+}
+
+/// Points of a heatmap layer within a single tile. The points are uploaded once in tile
+/// coordinates and projected by the shader with the uniform of the tile.
+pub struct HeatmapTileResources {
+    points: wgpu::Buffer,
+    points_count: u32,
+    /// Expression with which the weights of the points have been evaluated
+    weight_expression: Option<Expression>,
+
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl HeatmapTileResources {
+    pub fn points(&self) -> &wgpu::Buffer {
+        &self.points
+    }
+
+    pub fn points_count(&self) -> u32 {
+        self.points_count
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+impl HeatmapLayerResources {
+    pub fn target(&self) -> &OffscreenTarget {
+        &self.target
+    }
+
+    pub fn density_bind_group(&self) -> &wgpu::BindGroup {
+        &self.density_bind_group
+    }
+
+    pub fn color_bind_group(&self) -> &wgpu::BindGroup {
+        &self.color_bind_group
+    }
+
+    /// Returns the tiles which are rendered in the current frame.
+    pub fn tiles(&self) -> impl Iterator<Item = &HeatmapTileResources> + '_ {
+        self.visible_tiles
+            .iter()
+            .flat_map(|coords| self.tiles.get(coords))
+    }
+}
+
+/// Holds the resources necessary for rendering heatmaps:
+/// * pipeline which accumulates the density
+/// * pipeline which colors the density
+/// * density textures and color ramps per layer
+/// * points per layer and tile
+pub struct HeatmapResources {
+    density_pipeline: wgpu::RenderPipeline,
+    color_pipeline: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+
+    layers: HashMap<String, HeatmapLayerResources>,
+    /// Layers which are rendered in the current frame
+    order: Vec<String>,
+}
+
+impl HeatmapResources {
+    pub fn new(
+        device: &wgpu::Device,
+        density_pipeline: wgpu::RenderPipeline,
+        color_pipeline: wgpu::RenderPipeline,
+    ) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            density_pipeline,
+            color_pipeline,
+            sampler,
+            layers: Default::default(),
+            order: Vec::new(),
+        }
+    }
+
+    pub fn density_pipeline(&self) -> &wgpu::RenderPipeline {
+        &self.density_pipeline
+    }
+
+    pub fn color_pipeline(&self) -> &wgpu::RenderPipeline {
+        &self.color_pipeline
+    }
+
+    /// Forgets the layers of the previous frame and removes the resources of layers which no
+    /// longer exist. The points of tiles which have not been rendered in the previous frame are
+    /// removed, such that tiles whose data has been loaded again are uploaded again.
+    pub fn begin_frame(&mut self, layer_ids: &[&str]) {
+        self.order.clear();
+        self.layers.retain(|id, _| layer_ids.contains(&id.as_str()));
+        for layer in self.layers.values_mut() {
+            let visible_tiles = std::mem::take(&mut layer.visible_tiles);
+            layer
+                .tiles
+                .retain(|coords, _| visible_tiles.contains(coords));
+        }
+    }
+
+    /// Creates or updates the resources of a layer and schedules it for rendering in the current
+    /// frame. The density texture has the size `target_size`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn write_layer(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: &str,
+        target_size: (u32, u32),
+        color_ramp: &Expression,
+        density: ShaderHeatmapDensity,
+        color: ShaderHeatmapColor,
+    ) {
+        let is_compatible = self
+            .layers
+            .get(id)
+            .is_some_and(|layer| !layer.target.has_changed(&target_size));
+
+        if !is_compatible {
+            let layer = self.create_layer(device, target_size);
+            self.layers.insert(id.to_string(), layer);
+        }
+
+        let layer = self.layers.get_mut(id).expect("layer was inserted");
+
+        if layer.color_ramp_expression.as_ref() != Some(color_ramp) {
+            Self::write_color_ramp(queue, &layer.color_ramp, color_ramp);
+            layer.color_ramp_expression = Some(color_ramp.clone());
+        }
+
+        queue.write_buffer(&layer.density_uniform, 0, bytemuck::bytes_of(&density));
+        queue.write_buffer(&layer.color_uniform, 0, bytemuck::bytes_of(&color));
+
+        self.order.push(id.to_string());
+    }
+
+    /// Schedules the points of a tile for rendering in the current frame of the layer `id`, which
+    /// must have been written before. The `points` are only uploaded if the tile has not been
+    /// rendered in the previous frame or if the `weight_expression` changed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn write_tile(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: &str,
+        coords: WorldTileCoords,
+        tile: ShaderHeatmapTile,
+        weight_expression: &Option<Expression>,
+        points: impl FnOnce() -> Vec<ShaderHeatmapPoint>,
+    ) {
+        let Some(layer) = self.layers.get_mut(id) else {
+            return;
+        };
+
+        let is_uploaded = layer
+            .tiles
+            .get(&coords)
+            .is_some_and(|tile| tile.weight_expression == *weight_expression);
+
+        if !is_uploaded {
+            let tile = Self::create_tile(
+                device,
+                &self.density_pipeline,
+                &points(),
+                weight_expression.clone(),
+            );
+            layer.tiles.insert(coords, tile);
+        }
+
+        let tile_resources = layer.tiles.get(&coords).expect("tile was inserted");
+        queue.write_buffer(&tile_resources.uniform, 0, bytemuck::bytes_of(&tile));
+        layer.visible_tiles.insert(coords);
+    }
+
+    /// Returns the layers which are rendered in the current frame.
+    pub fn iter(&self) -> impl Iterator<Item = &HeatmapLayerResources> + '_ {
+        self.order.iter().flat_map(|id| self.layers.get(id))
+    }
+
+    pub fn get(&self, id: &str) -> Option<&HeatmapLayerResources> {
+        self.layers.get(id)
+    }
+
+    fn create_tile(
+        device: &wgpu::Device,
+        density_pipeline: &wgpu::RenderPipeline,
+        points: &[ShaderHeatmapPoint],
+        weight_expression: Option<Expression>,
+    ) -> HeatmapTileResources {
+        let points_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("heatmap points buffer"),
+            contents: bytemuck::cast_slice(points),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("heatmap tile uniform buffer"),
+            size: std::mem::size_of::<ShaderHeatmapTile>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &density_pipeline.get_bind_group_layout(1),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            }],
+            label: None,
+        });
+
+        HeatmapTileResources {
+            points: points_buffer,
+            points_count: points.len() as u32,
+            weight_expression,
+            uniform,
+            bind_group,
+        }
+    }
+
+    fn create_layer(
+        &self,
+        device: &wgpu::Device,
+        target_size: (u32, u32),
+    ) -> HeatmapLayerResources {
+        let target = OffscreenTarget::new(
+            Some("heatmap density texture"),
+            device,
+            HEATMAP_DENSITY_FORMAT,
+            target_size.0,
+            target_size.1,
+        );
+
+        let color_ramp = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("heatmap color ramp"),
+            size: wgpu::Extent3d {
+                width: COLOR_RAMP_SIZE,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D1,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let color_ramp_view = color_ramp.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let density_uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("heatmap density uniform buffer"),
+            size: std::mem::size_of::<ShaderHeatmapDensity>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let density_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.density_pipeline.get_bind_group_layout(0),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: density_uniform.as_entire_binding(),
+            }],
+            label: None,
+        });
+
+        let color_uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("heatmap color uniform buffer"),
+            size: std::mem::size_of::<ShaderHeatmapColor>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let color_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.color_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(target.view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&color_ramp_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: color_uniform.as_entire_binding(),
+                },
+            ],
+            label: None,
+        });
+
+        HeatmapLayerResources {
+            target,
+            color_ramp,
+            color_ramp_expression: None,
+            density_uniform,
+            density_bind_group,
+            color_uniform,
+            color_bind_group,
+            tiles: Default::default(),
+            visible_tiles: Default::default(),
+        }
+    }
+
+    fn write_color_ramp(queue: &wgpu::Queue, texture: &wgpu::Texture, expression: &Expression) {
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &super::color_ramp(expression, COLOR_RAMP_SIZE),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * COLOR_RAMP_SIZE),
+                rows_per_image: None,
+            },
+            texture.size(),
+        );
+    }
+}
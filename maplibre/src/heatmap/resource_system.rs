@@ -0,0 +1,153 @@
+//! Prepares GPU-owned resources by initializing them if they are uninitialized or out-of-date.
+use crate::{
+    context::MapContext,
+    heatmap::resource::HeatmapResources,
+    render::{
+        eventually::Eventually,
+        resource::{RenderPipeline, TilePipeline},
+        shaders,
+        shaders::Shader,
+        RenderResources, Renderer,
+    },
+};
+
+pub fn resource_system(
+    MapContext {
+        world,
+        renderer:
+            Renderer {
+                device,
+                resources: RenderResources { surface, .. },
+                settings,
+                ..
+            },
+        ..
+    }: &mut MapContext,
+) {
+    let Some(heatmap_resources) = world
+        .resources
+        .query_mut::<&mut Eventually<HeatmapResources>>()
+    else {
+        return;
+    };
+
+    heatmap_resources.initialize(|| {
+        let density_shader = shaders::HeatmapShader;
+
+        let mut density_descriptor = TilePipeline::new(
+            "heatmap_density_pipeline".into(),
+            *settings,
+            density_shader.describe_vertex(),
+            density_shader.describe_fragment(),
+            false,
+            false,
+            false,
+            false,
+            false,
+            false,
+        )
+        .describe_render_pipeline();
+
+        density_descriptor.layout = Some(vec![
+            vec![
+                // density uniform
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            vec![
+                // tile uniform
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        ]);
+
+        let color_shader = shaders::HeatmapColorShader {
+            format: surface.surface_format(),
+        };
+
+        let mut color_descriptor = TilePipeline::new(
+            "heatmap_color_pipeline".into(),
+            *settings,
+            color_shader.describe_vertex(),
+            color_shader.describe_fragment(),
+            true,
+            false,
+            false,
+            false,
+            surface.is_multisampling_supported(settings.msaa),
+            false,
+        )
+        .describe_render_pipeline();
+
+        // The heatmap covers the whole viewport and is neither masked by tiles nor occluded
+        if let Some(depth_stencil) = &mut color_descriptor.depth_stencil {
+            depth_stencil.depth_write_enabled = false;
+            depth_stencil.depth_compare = wgpu::CompareFunction::Always;
+            depth_stencil.stencil = wgpu::StencilState::default();
+        }
+
+        color_descriptor.layout = Some(vec![vec![
+            // density texture
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            // color ramp
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D1,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            // color uniform
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ]]);
+
+        HeatmapResources::new(
+            device,
+            density_descriptor.initialize(device),
+            color_descriptor.initialize(device),
+        )
+    });
+}
@@ -0,0 +1,64 @@
+//! Configures which user interactions change the camera, e.g. to embed the map in kiosks in
+//! which it must not be rotated.
+//!
+//! The interactions are implemented by the event loop of the platform, e.g. maplibre-winit,
+//! which reads the [`InteractionHandlers`] resource before every frame. Therefore, they can be
+//! toggled at runtime with
+//! [`MapContext::set_interaction_handlers`](crate::context::MapContext::set_interaction_handlers).
+
+/// The user interactions which are enabled. By default, all interactions are enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InteractionHandlers {
+    /// Panning by dragging with the primary mouse button or a single finger.
+    pub drag_pan: bool,
+    /// Rotating and pitching by dragging with the secondary or middle mouse button.
+    pub drag_rotate: bool,
+    /// Zooming with the scroll wheel or the touchpad.
+    pub scroll_zoom: bool,
+    /// Zooming and rotating by pinching with two fingers.
+    pub touch_zoom_rotate: bool,
+    /// Pitching by moving two fingers vertically.
+    pub touch_pitch: bool,
+    /// Panning with the arrow keys, zooming with `+` and `-`, and rotating and pitching with the
+    /// arrow keys while shift is held.
+    pub keyboard: bool,
+    /// Zooming in by double-clicking or double-tapping, or out if shift is held.
+    pub double_click_zoom: bool,
+    /// Zooming to a box which is spanned by dragging with the primary mouse button while shift
+    /// is held.
+    pub box_zoom: bool,
+    /// Whether the interactions above may change the bearing. If `false`, they only pan, zoom
+    /// and pitch the map.
+    pub rotation: bool,
+}
+
+impl InteractionHandlers {
+    /// Disables all interactions, e.g. for static maps.
+    pub const NONE: InteractionHandlers = InteractionHandlers {
+        drag_pan: false,
+        drag_rotate: false,
+        scroll_zoom: false,
+        touch_zoom_rotate: false,
+        touch_pitch: false,
+        keyboard: false,
+        double_click_zoom: false,
+        box_zoom: false,
+        rotation: false,
+    };
+}
+
+impl Default for InteractionHandlers {
+    fn default() -> Self {
+        Self {
+            drag_pan: true,
+            drag_rotate: true,
+            scroll_zoom: true,
+            touch_zoom_rotate: true,
+            touch_pitch: true,
+            keyboard: true,
+            double_click_zoom: true,
+            box_zoom: true,
+            rotation: true,
+        }
+    }
+}
@@ -76,7 +76,8 @@ pub enum Input {
         coords: WorldTileCoords,
         style: Style, // TODO
     },
-    NotYetImplemented, // TODO: Placeholder, should be removed when second input is added
+    /// Requests the image of an image source.
+    ImageRequest { source_id: String, url: String },
 }
 
 #[derive(Error, Debug)]
@@ -1,26 +1,25 @@
 //! Geometry index.
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use cgmath::{num_traits::Signed, Bounded};
 use geo::prelude::*;
-use geo_types::{Coord, CoordFloat, Geometry, LineString, Point, Polygon};
+use geo_types::{CoordFloat, Geometry, LineString, Point, Polygon};
 use geozero::{
-    error::GeozeroError, geo_types::GeoWriter, ColumnValue, FeatureProcessor, GeomProcessor,
-    PropertyProcessor,
+    error::GeozeroError, geo_types::GeoWriter, mvt::tile, ColumnValue, FeatureProcessor,
+    GeomProcessor, GeozeroDatasource, PropertyProcessor,
 };
 use rstar::{Envelope, PointDistance, RTree, RTreeObject, AABB};
+use serde_json::Value as JsonValue;
 
 use crate::{
-    coords::{
-        InnerCoords, Quadkey, WorldCoords, WorldTileCoords, Zoom, ZoomLevel, EXTENT, TILE_SIZE,
-    },
+    coords::{Quadkey, WorldTileCoords},
     util::math::bounds_from_points,
 };
 
 /// A quad tree storing the currently loaded tiles.
 pub struct GeometryIndex {
-    index: BTreeMap<Quadkey, TileIndex>,
+    index: BTreeMap<Quadkey, (WorldTileCoords, TileIndex)>,
 }
 
 impl GeometryIndex {
@@ -30,35 +29,33 @@ impl GeometryIndex {
         }
     }
 
+    /// Indexes the geometries of a tile. Geometries of the same layers of the same sources which
+    /// have been indexed before are replaced, such that a tile can contain several sources.
     pub fn index_tile(&mut self, coords: &WorldTileCoords, tile_index: TileIndex) {
-        coords
-            .build_quad_key()
-            .and_then(|key| self.index.insert(key, tile_index));
-    }
+        let Some(key) = coords.build_quad_key() else {
+            return;
+        };
 
-    pub fn query_point(
-        &self,
-        world_coords: &WorldCoords,
-        z: ZoomLevel,
-        zoom: Zoom,
-    ) -> Option<Vec<&IndexedGeometry<f64>>> {
-        let world_tile_coords = world_coords.into_world_tile(z, zoom);
+        let tile_index = match self.index.remove(&key) {
+            Some((_, previous)) => previous.merge(tile_index),
+            None => tile_index,
+        };
+        self.index.insert(key, (coords.wrap(), tile_index));
+    }
 
-        if let Some(index) = world_tile_coords
+    /// Returns the index of a tile. Tiles of copies of the world share their index.
+    pub fn get_tile(&self, coords: &WorldTileCoords) -> Option<&TileIndex> {
+        coords
             .build_quad_key()
             .and_then(|key| self.index.get(&key))
-        {
-            let scale = zoom.scale_delta(&Zoom::from(z)); // FIXME: can be wrong, if tiles of different z are visible
-
-            let delta_x = world_coords.x / TILE_SIZE * scale - world_tile_coords.x as f64;
-            let delta_y = world_coords.y / TILE_SIZE * scale - world_tile_coords.y as f64;
+            .map(|(_, tile_index)| tile_index)
+    }
 
-            let x = delta_x * EXTENT;
-            let y = delta_y * EXTENT;
-            Some(index.point_query(InnerCoords { x, y }))
-        } else {
-            None
-        }
+    /// Returns the indices of all tiles together with their coordinates.
+    pub fn iter(&self) -> impl Iterator<Item = (WorldTileCoords, &TileIndex)> + '_ {
+        self.index
+            .values()
+            .map(|(coords, tile_index)| (*coords, tile_index))
     }
 }
 
@@ -72,39 +69,76 @@ impl Default for GeometryIndex {
 /// Spatial tiles are stored in a multi-dimentional tree which represents their position in the tile.
 /// Linear tiles are simply stored in a vector.
 ///
-/// A spatial tile index can theoretically improve query performance on tiles. Practically it could be slower though. The `Spatial` index is experimental and currently unused.
+/// Vector tiles are indexed spatially, such that queries only look at the geometries whose bounds
+/// intersect the queried area.
 pub enum TileIndex {
     Spatial { tree: RTree<IndexedGeometry<f64>> },
     Linear { list: Vec<IndexedGeometry<f64>> },
 }
 
 impl TileIndex {
-    pub fn point_query(&self, inner_coords: InnerCoords) -> Vec<&IndexedGeometry<f64>> {
-        let point = Point::new(inner_coords.x, inner_coords.y);
-        let coordinate: Coord<_> = point.into();
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &IndexedGeometry<f64>> + '_> {
+        match self {
+            TileIndex::Spatial { tree } => Box::new(tree.iter()),
+            TileIndex::Linear { list } => Box::new(list.iter()),
+        }
+    }
 
-        // FIXME: Respect layer order of style
+    /// Returns the geometries whose bounds intersect `envelope`, in tile coordinates.
+    pub fn locate_in_envelope(
+        &self,
+        envelope: AABB<Point<f64>>,
+    ) -> Box<dyn Iterator<Item = &IndexedGeometry<f64>> + '_> {
         match self {
-            TileIndex::Spatial { tree } => tree
-                .nearest_neighbor_iter(&point)
-                .filter(|geometry| match &geometry.exact {
-                    ExactGeometry::Polygon(exact) => exact.contains(&coordinate),
-                    ExactGeometry::LineString(exact) => exact.distance_2(&point) <= 64.0,
-                })
-                .collect::<Vec<_>>(),
-            TileIndex::Linear { list } => list
-                .iter()
-                .filter(|geometry| match &geometry.exact {
-                    ExactGeometry::Polygon(exact) => exact.contains(&coordinate),
-                    ExactGeometry::LineString(exact) => exact.distance_2(&point) <= 64.0,
-                })
-                .collect::<Vec<_>>(),
+            TileIndex::Spatial { tree } => {
+                Box::new(tree.locate_in_envelope_intersecting(&envelope))
+            }
+            TileIndex::Linear { list } => Box::new(
+                list.iter()
+                    .filter(move |geometry| geometry.bounds.intersects(&envelope)),
+            ),
+        }
+    }
+
+    /// Returns the geometries which are within `tolerance` of `point`, in tile coordinates.
+    pub fn point_query(&self, point: Point<f64>, tolerance: f64) -> Vec<&IndexedGeometry<f64>> {
+        let envelope = AABB::from_corners(
+            Point::new(point.x() - tolerance, point.y() - tolerance),
+            Point::new(point.x() + tolerance, point.y() + tolerance),
+        );
+        self.locate_in_envelope(envelope)
+            .filter(|geometry| geometry.exact.distance_to_point(&point) <= tolerance)
+            .collect()
+    }
+
+    /// Replaces the geometries of the layers of `other` with the geometries of `other`.
+    fn merge(self, other: TileIndex) -> TileIndex {
+        let layers = other
+            .iter()
+            .map(|geometry| (&geometry.source, &geometry.source_layer))
+            .collect::<HashSet<_>>();
+
+        let mut geometries = self
+            .iter()
+            .filter(|geometry| !layers.contains(&(&geometry.source, &geometry.source_layer)))
+            .cloned()
+            .collect::<Vec<_>>();
+        geometries.extend(other.iter().cloned());
+
+        match other {
+            TileIndex::Spatial { .. } => TileIndex::Spatial {
+                tree: RTree::bulk_load(geometries),
+            },
+            TileIndex::Linear { .. } => TileIndex::Linear { list: geometries },
         }
     }
 }
 
 /// An indexed geometry contains an exact vector geometry, computed bounds which
 /// can be helpful when interacting with the geometry and a hashmap of properties.
+///
+/// Features which consist of several parts, e.g. multi-polygons, are indexed as one geometry per
+/// part, which share the `feature_index`.
 #[derive(Debug, Clone)]
 pub struct IndexedGeometry<T>
 where
@@ -112,10 +146,18 @@ where
 {
     pub bounds: AABB<Point<T>>,
     pub exact: ExactGeometry<T>,
-    pub properties: HashMap<String, String>,
+    pub properties: HashMap<String, JsonValue>,
+    /// The style source whose tile contains the feature
+    pub source: Option<String>,
+    /// Name of the layer of the vector tile which contains the feature
+    pub source_layer: String,
+    /// Index of the feature within its layer
+    pub feature_index: u64,
+    /// The id of the feature, if the vector tile contains one
+    pub id: Option<u64>,
 }
 
-/// Contains either a polygon or line vector.
+/// Contains either a polygon, line or point vector.
 #[derive(Debug, Clone)]
 pub enum ExactGeometry<T>
 where
@@ -123,31 +165,70 @@ where
 {
     Polygon(Polygon<T>),
     LineString(LineString<T>),
+    Point(Point<T>),
+}
+
+impl ExactGeometry<f64> {
+    /// Returns the distance to `point`, which is zero within polygons.
+    pub fn distance_to_point(&self, point: &Point<f64>) -> f64 {
+        match self {
+            ExactGeometry::Polygon(exact) => point.euclidean_distance(exact),
+            ExactGeometry::LineString(exact) => point.euclidean_distance(exact),
+            ExactGeometry::Point(exact) => point.euclidean_distance(exact),
+        }
+    }
+
+    /// Returns the distance to `polygon`, which is zero if they intersect.
+    pub fn distance_to_polygon(&self, polygon: &Polygon<f64>) -> f64 {
+        match self {
+            ExactGeometry::Polygon(exact) => polygon.euclidean_distance(exact),
+            ExactGeometry::LineString(exact) => polygon.euclidean_distance(exact),
+            ExactGeometry::Point(exact) => exact.euclidean_distance(polygon),
+        }
+    }
+
+    /// Returns the type of the geometry as it is named by GeoJSON and style filters.
+    pub fn geometry_type(&self) -> &'static str {
+        match self {
+            ExactGeometry::Polygon(_) => "Polygon",
+            ExactGeometry::LineString(_) => "LineString",
+            ExactGeometry::Point(_) => "Point",
+        }
+    }
+
+    pub fn to_geometry(&self) -> Geometry<f64> {
+        match self {
+            ExactGeometry::Polygon(exact) => Geometry::Polygon(exact.clone()),
+            ExactGeometry::LineString(exact) => Geometry::LineString(exact.clone()),
+            ExactGeometry::Point(exact) => Geometry::Point(*exact),
+        }
+    }
 }
 
 impl<T> IndexedGeometry<T>
 where
     T: CoordFloat + Bounded + Signed + PartialOrd,
 {
-    fn from_polygon(polygon: Polygon<T>, properties: HashMap<String, String>) -> Option<Self> {
-        let (min, max) = bounds_from_points(polygon.exterior().points())?;
-
-        Some(Self {
-            exact: ExactGeometry::Polygon(polygon),
-            bounds: AABB::from_corners(Point::from(min), Point::from(max)),
-            properties,
-        })
-    }
-    fn from_linestring(
-        linestring: LineString<T>,
-        properties: HashMap<String, String>,
+    fn new(
+        exact: ExactGeometry<T>,
+        properties: HashMap<String, JsonValue>,
+        source_layer: String,
+        feature_index: u64,
     ) -> Option<Self> {
-        let bounds = linestring.envelope();
+        let (min, max) = match &exact {
+            ExactGeometry::Polygon(polygon) => bounds_from_points(polygon.exterior().points())?,
+            ExactGeometry::LineString(linestring) => bounds_from_points(linestring.points())?,
+            ExactGeometry::Point(point) => (point.x_y().into(), point.x_y().into()),
+        };
 
         Some(Self {
-            exact: ExactGeometry::LineString(linestring),
-            bounds,
+            bounds: AABB::from_corners(Point::from(min), Point::from(max)),
+            exact,
             properties,
+            source: None,
+            source_layer,
+            feature_index,
+            id: None,
         })
     }
 }
@@ -183,7 +264,11 @@ where
 pub struct IndexProcessor {
     geo_writer: GeoWriter,
     geometries: Vec<IndexedGeometry<f64>>,
-    properties: Option<HashMap<String, String>>,
+    properties: Option<HashMap<String, JsonValue>>,
+    source_layer: String,
+    feature_index: u64,
+    /// The ids of the features of the layer which is processed
+    feature_ids: Vec<Option<u64>>,
 }
 
 impl IndexProcessor {
@@ -192,6 +277,31 @@ impl IndexProcessor {
             geo_writer: GeoWriter::new(),
             geometries: Vec::new(),
             properties: None,
+            source_layer: String::new(),
+            feature_index: 0,
+            feature_ids: Vec::new(),
+        }
+    }
+
+    /// Indexes the features of a layer of a vector tile together with their ids.
+    pub fn process_layer(&mut self, layer: &mut tile::Layer) -> Result<(), GeozeroError> {
+        self.feature_ids = layer.features.iter().map(|feature| feature.id).collect();
+        layer.process(self)
+    }
+
+    fn push(&mut self, exact: ExactGeometry<f64>, properties: &HashMap<String, JsonValue>) {
+        if let Some(mut geometry) = IndexedGeometry::new(
+            exact,
+            properties.clone(),
+            self.source_layer.clone(),
+            self.feature_index,
+        ) {
+            geometry.id = self
+                .feature_ids
+                .get(self.feature_index as usize)
+                .copied()
+                .flatten();
+            self.geometries.push(geometry);
         }
     }
 
@@ -264,14 +374,35 @@ impl PropertyProcessor for IndexProcessor {
         self.properties
             .as_mut()
             .unwrap()
-            .insert(name.to_string(), value.to_string());
+            .insert(name.to_string(), column_value_to_json(value));
         Ok(true)
     }
 }
 
+/// Converts a property of a feature to a typed value.
+fn column_value_to_json(value: &ColumnValue) -> JsonValue {
+    match *value {
+        ColumnValue::Byte(value) => value.into(),
+        ColumnValue::UByte(value) => value.into(),
+        ColumnValue::Bool(value) => value.into(),
+        ColumnValue::Short(value) => value.into(),
+        ColumnValue::UShort(value) => value.into(),
+        ColumnValue::Int(value) => value.into(),
+        ColumnValue::UInt(value) => value.into(),
+        ColumnValue::Long(value) => value.into(),
+        ColumnValue::ULong(value) => value.into(),
+        ColumnValue::Float(value) => value.into(),
+        ColumnValue::Double(value) => value.into(),
+        ColumnValue::String(value) | ColumnValue::DateTime(value) => value.into(),
+        ColumnValue::Json(value) => serde_json::from_str(value).unwrap_or_else(|_| value.into()),
+        ColumnValue::Binary(_) => value.to_string().into(),
+    }
+}
+
 impl FeatureProcessor for IndexProcessor {
     /// Begin of dataset processing.
-    fn dataset_begin(&mut self, _name: Option<&str>) -> Result<(), GeozeroError> {
+    fn dataset_begin(&mut self, name: Option<&str>) -> Result<(), GeozeroError> {
+        self.source_layer = name.unwrap_or_default().to_string();
         Ok(())
     }
     /// End of dataset processing.
@@ -279,7 +410,8 @@ impl FeatureProcessor for IndexProcessor {
         Ok(())
     }
     /// Begin of feature processing.
-    fn feature_begin(&mut self, _idx: u64) -> Result<(), GeozeroError> {
+    fn feature_begin(&mut self, idx: u64) -> Result<(), GeozeroError> {
+        self.feature_index = idx;
         Ok(())
     }
     /// End of feature processing.
@@ -302,20 +434,32 @@ impl FeatureProcessor for IndexProcessor {
     /// End of feature geometry processing.
     fn geometry_end(&mut self) -> Result<(), GeozeroError> {
         let geometry = self.geo_writer.take_geometry();
+        let properties = self.properties.take().unwrap_or_default();
 
         match geometry {
-            Some(Geometry::Polygon(polygon)) => self.geometries.push(
-                IndexedGeometry::from_polygon(polygon, self.properties.take().unwrap()).unwrap(),
-            ),
-            Some(Geometry::LineString(linestring)) => self.geometries.push(
-                IndexedGeometry::from_linestring(linestring, self.properties.take().unwrap())
-                    .unwrap(),
-            ),
-            Some(Geometry::Point(_))
-            | Some(Geometry::Line(_))
-            | Some(Geometry::MultiPoint(_))
-            | Some(Geometry::MultiLineString(_))
-            | Some(Geometry::MultiPolygon(_))
+            Some(Geometry::Polygon(polygon)) => {
+                self.push(ExactGeometry::Polygon(polygon), &properties)
+            }
+            Some(Geometry::MultiPolygon(polygons)) => {
+                for polygon in polygons {
+                    self.push(ExactGeometry::Polygon(polygon), &properties)
+                }
+            }
+            Some(Geometry::LineString(linestring)) => {
+                self.push(ExactGeometry::LineString(linestring), &properties)
+            }
+            Some(Geometry::MultiLineString(linestrings)) => {
+                for linestring in linestrings {
+                    self.push(ExactGeometry::LineString(linestring), &properties)
+                }
+            }
+            Some(Geometry::Point(point)) => self.push(ExactGeometry::Point(point), &properties),
+            Some(Geometry::MultiPoint(points)) => {
+                for point in points {
+                    self.push(ExactGeometry::Point(point), &properties)
+                }
+            }
+            Some(Geometry::Line(_))
             | Some(Geometry::GeometryCollection(_))
             | Some(Geometry::Rect(_))
             | Some(Geometry::Triangle(_)) => {
@@ -329,3 +473,69 @@ impl FeatureProcessor for IndexProcessor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use geo_types::{line_string, polygon};
+
+    use super::*;
+    use crate::coords::ZoomLevel;
+
+    fn indexed(exact: ExactGeometry<f64>, source_layer: &str) -> IndexedGeometry<f64> {
+        let properties = HashMap::from([("name".to_string(), JsonValue::from(source_layer))]);
+        IndexedGeometry::new(exact, properties, source_layer.to_string(), 0).unwrap()
+    }
+
+    #[test]
+    fn test_index_tile() {
+        let coords = WorldTileCoords::from((1, 0, ZoomLevel::from(1)));
+        let water = ExactGeometry::Polygon(polygon![
+            (x: 0.0, y: 0.0), (x: 100.0, y: 0.0), (x: 100.0, y: 100.0), (x: 0.0, y: 100.0)
+        ]);
+        let road =
+            ExactGeometry::LineString(line_string![(x: 200.0, y: 0.0), (x: 200.0, y: 100.0)]);
+
+        let mut index = GeometryIndex::new();
+        index.index_tile(
+            &coords,
+            TileIndex::Linear {
+                list: vec![indexed(water.clone(), "water"), indexed(road, "road")],
+            },
+        );
+        // Indexing a layer again replaces only its geometries
+        index.index_tile(
+            &coords,
+            TileIndex::Spatial {
+                tree: RTree::bulk_load(vec![indexed(water.clone(), "water")]),
+            },
+        );
+        // Layers of different sources can have the same name
+        let mut other_water = indexed(water, "water");
+        other_water.source = Some("other".to_string());
+        index.index_tile(
+            &coords,
+            TileIndex::Linear {
+                list: vec![other_water],
+            },
+        );
+
+        // Copies of the world share the index
+        let tile_index = index
+            .get_tile(&WorldTileCoords::from((3, 0, ZoomLevel::from(1))))
+            .unwrap();
+        assert_eq!(tile_index.iter().count(), 3);
+
+        let hits = tile_index.point_query(Point::new(50.0, 50.0), 0.0);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].properties["name"], "water");
+
+        assert!(tile_index
+            .point_query(Point::new(205.0, 50.0), 4.0)
+            .is_empty());
+        let hits = tile_index.point_query(Point::new(205.0, 50.0), 8.0);
+        assert_eq!(hits[0].source_layer, "road");
+
+        let envelope = AABB::from_corners(Point::new(150.0, 150.0), Point::new(300.0, 300.0));
+        assert_eq!(tile_index.locate_in_envelope(envelope).count(), 0);
+    }
+}
@@ -59,6 +59,11 @@ where
     ) -> Result<Vec<u8>, SourceFetchError> {
         self.http.fetch(coords, source_type).await
     }
+
+    /// Fetches a resource which is not a tile, like a style or TileJSON.
+    pub async fn fetch_url(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
+        self.http.fetch_url(url).await
+    }
 }
 
 impl<HC> HttpSourceClient<HC>
@@ -76,8 +81,13 @@ where
         coords: &WorldTileCoords,
         source_type: &SourceType,
     ) -> Result<Vec<u8>, SourceFetchError> {
-        self.inner_client
-            .fetch(source_type.format(coords).as_str())
-            .await
+        let url = source_type
+            .format(coords)
+            .ok_or_else(|| SourceFetchError(format!("there is no tile at {coords}").into()))?;
+        self.inner_client.fetch(&url).await
+    }
+
+    pub async fn fetch_url(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
+        self.inner_client.fetch(url).await
     }
 }
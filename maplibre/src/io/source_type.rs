@@ -75,18 +75,50 @@ impl Default for RasterSource {
     }
 }
 
+/// Represents a source whose tiles are addressed by an url containing `{z}`, `{x}` and `{y}`
+/// place holders, like the `tiles` of a style source.
+#[derive(Clone)]
+pub struct UrlTemplateSource {
+    pub template: String,
+    pub scheme: TileAddressingScheme,
+}
+
+impl UrlTemplateSource {
+    pub fn new(template: &str, scheme: TileAddressingScheme) -> Self {
+        Self {
+            template: template.to_string(),
+            scheme,
+        }
+    }
+
+    /// Returns the url of the tile at `coords`. The copies of the world share their tiles. Returns
+    /// `None` if `coords` are outside of the world.
+    pub fn format(&self, coords: &WorldTileCoords) -> Option<String> {
+        let tile_coords = coords.wrap().into_tile(self.scheme.clone())?;
+        Some(
+            self.template
+                .replace("{z}", &tile_coords.z.to_string())
+                .replace("{x}", &tile_coords.x.to_string())
+                .replace("{y}", &tile_coords.y.to_string()),
+        )
+    }
+}
+
 /// Represents the tiles' different types of source.
 #[derive(Clone)]
 pub enum SourceType {
     Raster(RasterSource),
     Tessellate(TessellateSource),
+    UrlTemplate(UrlTemplateSource),
 }
 
 impl SourceType {
-    pub fn format(&self, coords: &WorldTileCoords) -> String {
+    /// Returns the url of the tile at `coords`, or `None` if there is no such tile.
+    pub fn format(&self, coords: &WorldTileCoords) -> Option<String> {
         match self {
-            SourceType::Raster(raster_source) => raster_source.format(coords),
-            SourceType::Tessellate(tessellate_source) => tessellate_source.format(coords),
+            SourceType::Raster(raster_source) => Some(raster_source.format(coords)),
+            SourceType::Tessellate(tessellate_source) => Some(tessellate_source.format(coords)),
+            SourceType::UrlTemplate(template_source) => template_source.format(coords),
         }
     }
 }
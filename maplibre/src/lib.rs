@@ -26,6 +26,7 @@ pub use geozero::mvt::tile; // Used in transferables.rs in web/singlethreaded
 // Internal modules
 pub(crate) mod tessellation;
 
+pub mod camera;
 pub mod context;
 pub mod coords;
 #[cfg(feature = "headless")]
@@ -42,6 +43,9 @@ pub mod window;
 pub mod schedule;
 
 pub mod environment;
+pub mod events;
+pub mod feature_state;
+pub mod interaction;
 
 // Used for benchmarking
 pub mod benchmarking;
@@ -50,9 +54,17 @@ pub mod event_loop;
 pub mod kernel;
 pub mod map;
 pub mod plugin;
+pub mod query;
+pub mod repaint;
 pub mod tcs;
 
 // Plugins
+pub mod annotation;
+pub mod background;
+pub mod control;
 pub mod debug;
+pub mod draw;
+pub mod heatmap;
 pub mod raster;
+pub mod terrain;
 pub mod vector;
@@ -1,25 +1,44 @@
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc};
 
+use cgmath::Deg;
+use image::RgbaImage;
 use log::info;
+use serde_json::Value as JsonValue;
 use thiserror::Error;
 
 use crate::{
+    annotation::{Marker, MarkerId, Popup, PopupId},
     context::MapContext,
-    coords::{LatLon, WorldCoords, Zoom},
+    control::{Control, Corner},
+    coords::{LatLon, LatLonBounds, ScreenPoint, WorldCoords, Zoom},
     debug::{text_renderer::TextRenderer, text_resource::TextRendererResource},
+    draw::{DrawMode, DrawnFeature, DrawnFeatureId},
     environment::Environment,
+    events::{EventListeners, ListenerId, MapEvent, MapEventKind, MapEvents},
+    interaction::InteractionHandlers,
     kernel::Kernel,
     plugin::Plugin,
+    query::{QueriedFeature, QueryGeometry},
     render::{
         builder::{
             InitializationResult, InitializedRenderer, RendererBuilder, UninitializedRenderer,
         },
         error::RenderError,
+        eventually::release_gpu_resources,
         graph::RenderGraphError,
+        overlay::{OverlayTexture, OverlayTextureId},
         view_state::ViewState,
+        Renderer,
     },
+    repaint::Repaint,
     schedule::{Schedule, Stage},
-    style::Style,
+    style::{
+        diff::diff_change,
+        layer::StyleLayer,
+        mutation::{StyleChange, StyleError},
+        source::Source,
+        Style,
+    },
     tcs::world::World,
     window::{HeadedMapWindow, MapWindow, MapWindowConfig, WindowCreateError},
 };
@@ -31,10 +50,14 @@ pub enum MapError {
     RendererAlreadySet,
     #[error("renderer is not fully initialized")]
     RendererNotReady,
+    #[error("map is suspended")]
+    Suspended,
     #[error("initializing render graph failed")]
     RenderGraphInit(RenderGraphError),
     #[error("initializing device failed")]
     DeviceInit(RenderError),
+    #[error("recreating surface failed")]
+    SurfaceRecreate(RenderError),
     #[error("creating window failed")]
     Window(#[from] WindowCreateError),
 }
@@ -59,6 +82,9 @@ pub struct Map<E: Environment> {
     map_context: CurrentMapContext,
     window: <E::MapWindowConfig as MapWindowConfig>::MapWindow,
     pub labels: Vec<TextLabel>,
+    listeners: EventListeners,
+    /// Set while the surface and the GPU resources are released.
+    suspended: bool,
 
     plugins: Vec<Box<dyn Plugin<E>>>,
 }
@@ -89,95 +115,90 @@ where
             window,
             plugins,
             labels: Vec::new(),
+            listeners: EventListeners::default(),
+            suspended: false,
         };
         Ok(map)
     }
 
     pub async fn initialize_renderer(&mut self) -> Result<(), MapError> {
-        match &mut self.map_context {
-            CurrentMapContext::Ready(_) => Err(MapError::RendererAlreadySet),
-            CurrentMapContext::Pending {
-                style,
-                renderer_builder,
-            } => {
-                let init_result = renderer_builder
-                    .clone()
-                    .build()
-                    .initialize_renderer::<E::MapWindowConfig>(&self.window)
-                    .await
-                    .map_err(MapError::DeviceInit)?;
-
-                let window_size = self.window.size();
-
-                let center = style.center.unwrap_or_default();
-                let initial_zoom = style.zoom.map(Zoom::new).unwrap_or_default();
-                let view_state = ViewState::new(
-                    window_size,
-                    WorldCoords::from_lat_lon(LatLon::new(center[0], center[1]), initial_zoom),
-                    initial_zoom,
-                    cgmath::Deg::<f64>(style.pitch.unwrap_or_default()),
-                    cgmath::Rad(0.6435011087932844),
-                );
-
-                let mut world = World::default();
-
-                match init_result {
-                    InitializationResult::Initialized(InitializedRenderer {
-                        mut renderer, ..
-                    }) => {
-                        for plugin in &self.plugins {
-                            plugin.build(
-                                &mut self.schedule,
-                                self.kernel.clone(),
-                                &mut world,
-                                &mut renderer.render_graph,
-                            );
-                        }
-
-                        //
-                        // TEXT RENDERER INITIALIZATION (this must happen ONCE, HERE)
-                        //
-                        let surface_format = renderer.resources.surface.surface_format();
-                        world.resources.insert(TextRendererResource {
-                            renderer: std::sync::RwLock::new(Some(TextRenderer::new(
-                                &renderer.device,
-                                &renderer.queue,
-                                surface_format,
-                                window_size.width(),
-                                window_size.height(),
-                            ))),
-                        });
-
-                        self.map_context = CurrentMapContext::Ready(MapContext {
-                            world,
-                            view_state,
-                            style: std::mem::take(style),
-                            renderer,
-                        });
-                    }
-                    InitializationResult::Uninitialized(UninitializedRenderer { .. }) => {}
-                    _ => panic!("Rendering context gone"),
-                };
-                Ok(())
+        let CurrentMapContext::Pending {
+            renderer_builder, ..
+        } = &self.map_context
+        else {
+            return Err(MapError::RendererAlreadySet);
+        };
+
+        let init_result = renderer_builder
+            .clone()
+            .build()
+            .initialize_renderer::<E::MapWindowConfig>(&self.window)
+            .await
+            .map_err(MapError::DeviceInit)?;
+
+        match init_result {
+            InitializationResult::Initialized(InitializedRenderer { renderer, .. }) => {
+                self.initialize_context(renderer)
             }
-        }
+            InitializationResult::Uninitialized(UninitializedRenderer { .. }) => {}
+            _ => panic!("Rendering context gone"),
+        };
+        Ok(())
     }
 
-    pub fn on_user_click(&mut self, x: f32, y: f32) {
-        info!("User clicked at ({}, {})", x, y);
-
-        let ctx = match &mut self.map_context {
-            CurrentMapContext::Ready(c) => c,
-            _ => return,
+    /// Builds the plugins and creates the context of the map for the pending style.
+    fn initialize_context(&mut self, mut renderer: Renderer) {
+        let CurrentMapContext::Pending { style, .. } = &mut self.map_context else {
+            return;
         };
 
-        if let Some(res) = ctx.world.resources.get_mut::<TextRendererResource>() {
-            let mut guard = res.renderer.write().unwrap();
-            if let Some(r) = guard.as_mut() {
-                r.set_text(&ctx.renderer.queue, "Hello", x, y);
-                info!("Text rendered");
-            }
+        let window_size = self.window.size();
+
+        let initial_zoom = style.zoom.map(Zoom::new).unwrap_or_default();
+        let view_state = ViewState::new(
+            window_size,
+            WorldCoords::from_lat_lon(style.initial_center(), initial_zoom),
+            initial_zoom,
+            cgmath::Deg::<f64>(style.pitch.unwrap_or_default()),
+            cgmath::Rad(0.6435011087932844),
+        );
+
+        let mut world = World::default();
+
+        for plugin in &self.plugins {
+            plugin.build(
+                &mut self.schedule,
+                self.kernel.clone(),
+                &mut world,
+                &mut renderer.render_graph,
+            );
+        }
+
+        // Changes while the event loop is idle have to wake it up
+        if let Some(repaint) = world.resources.get_mut::<Repaint>() {
+            repaint.set_waker(self.window.redraw_waker());
         }
+
+        //
+        // TEXT RENDERER INITIALIZATION (this must happen ONCE, HERE)
+        //
+        let surface_format = renderer.resources.surface.surface_format();
+        world.resources.insert(TextRendererResource {
+            renderer: std::sync::RwLock::new(Some(TextRenderer::new(
+                &renderer.device,
+                &renderer.queue,
+                surface_format,
+                window_size.width(),
+                window_size.height(),
+            ))),
+        });
+
+        self.map_context = CurrentMapContext::Ready(MapContext {
+            world,
+            view_state,
+            style: std::mem::take(style),
+            renderer,
+        });
     }
 
     pub fn window_mut(&mut self) -> &mut <E::MapWindowConfig as MapWindowConfig>::MapWindow {
@@ -195,9 +216,11 @@ where
     }
 
     /// Resets the complete state of this map - a new renderer and schedule needs to be created.
-    /// The complete state of the app is reset.
+    /// The complete state of the app is reset. In order to only release the renderer, use
+    /// [`Map::suspend`].
     pub fn reset(&mut self) {
         self.schedule.clear();
+        self.suspended = false;
         match &self.map_context {
             CurrentMapContext::Ready(c) => {
                 self.map_context = CurrentMapContext::Pending {
@@ -211,17 +234,102 @@ where
         }
     }
 
+    /// Releases the surface and the GPU resources of the map, e.g. because the app has been
+    /// moved to the background. The world, including the loaded tiles, the view and the style
+    /// are kept, such that [`Map::resume`] can continue without fetching the tiles again.
+    ///
+    /// A map whose renderer is not ready yet is not suspended.
+    pub fn suspend(&mut self) {
+        let CurrentMapContext::Ready(map_context) = &mut self.map_context else {
+            return;
+        };
+
+        release_gpu_resources(&mut map_context.world);
+        map_context.renderer.resources.drop_surface();
+        self.suspended = true;
+    }
+
+    /// Recreates the surface for the current size of the window after [`Map::suspend`]. The GPU
+    /// resources are initialized again and the tiles are uploaded from the world before the
+    /// next frame.
+    pub fn resume(&mut self) -> Result<(), MapError> {
+        if !self.suspended {
+            return Ok(());
+        }
+        let CurrentMapContext::Ready(map_context) = &mut self.map_context else {
+            return Err(MapError::RendererNotReady);
+        };
+
+        // The window might have been resized, e.g. rotated, while the map was suspended
+        map_context.resize(self.window.size(), self.window.scale_factor());
+
+        let renderer = &mut map_context.renderer;
+        renderer
+            .resources
+            .recreate_surface(&self.window, &renderer.instance, &renderer.device)
+            .map_err(MapError::SurfaceRecreate)?;
+        self.suspended = false;
+        Ok(())
+    }
+
+    /// Whether the map is suspended. The schedule can not run while the map is suspended.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
     #[tracing::instrument(name = "update_and_redraw", skip_all)]
     pub fn run_schedule(&mut self) -> Result<(), MapError> {
+        if self.suspended {
+            return Err(MapError::Suspended);
+        }
+
         match &mut self.map_context {
             CurrentMapContext::Ready(map_context) => {
                 self.schedule.run(map_context);
+
+                let events = map_context
+                    .world
+                    .resources
+                    .get_mut::<MapEvents>()
+                    .map(MapEvents::take)
+                    .unwrap_or_default();
+                for event in &events {
+                    self.listeners.dispatch(event);
+                }
                 Ok(())
             }
             CurrentMapContext::Pending { .. } => Err(MapError::RendererNotReady),
         }
     }
 
+    /// Whether the map needs to be rendered again. Event loops which render on demand only run
+    /// the schedule while this is set. A map whose renderer is not ready yet needs a repaint.
+    pub fn needs_repaint(&self) -> bool {
+        self.context().map_or(true, MapContext::needs_repaint)
+    }
+
+    /// Requests that the map is rendered again, e.g. because the view has been changed directly.
+    pub fn request_repaint(&mut self) {
+        if let Ok(map_context) = self.context_mut() {
+            map_context.request_repaint();
+        }
+    }
+
+    /// Registers a listener for a kind of events. The events which are emitted during a frame are
+    /// dispatched after the frame has been rendered.
+    pub fn on(
+        &mut self,
+        kind: MapEventKind,
+        listener: impl FnMut(&MapEvent) + 'static,
+    ) -> ListenerId {
+        self.listeners.add(kind, listener)
+    }
+
+    /// Removes a listener. Returns `false` if the listener has already been removed.
+    pub fn off(&mut self, id: ListenerId) -> bool {
+        self.listeners.remove(id)
+    }
+
     pub fn context(&self) -> Result<&MapContext, MapError> {
         match &self.map_context {
             CurrentMapContext::Ready(map_context) => Ok(map_context),
@@ -239,4 +347,524 @@ where
     pub fn kernel(&self) -> &Rc<Kernel<E>> {
         &self.kernel
     }
+
+    /// Returns the point on the screen at which `lat_lon` is shown, or `None` if it is behind
+    /// the camera.
+    pub fn project(&self, lat_lon: LatLon) -> Result<Option<ScreenPoint>, MapError> {
+        Ok(self.context()?.view_state.project(lat_lon))
+    }
+
+    /// Returns the location on the ground at `point`, or `None` if the ground is not visible
+    /// there.
+    pub fn unproject(&self, point: ScreenPoint) -> Result<Option<LatLon>, MapError> {
+        Ok(self.context()?.view_state.unproject(point))
+    }
+
+    /// Returns a box which contains the visible area of the map.
+    pub fn get_bounds(&self) -> Result<LatLonBounds, MapError> {
+        Ok(self.context()?.view_state.bounds())
+    }
+
+    pub fn get_center(&self) -> Result<LatLon, MapError> {
+        Ok(self.context()?.view_state.center())
+    }
+
+    pub fn get_zoom(&self) -> Result<Zoom, MapError> {
+        Ok(self.context()?.view_state.zoom())
+    }
+
+    /// Returns the rotation of the map in degrees counter-clockwise from north.
+    pub fn get_bearing(&self) -> Result<Deg<f64>, MapError> {
+        Ok(self.context()?.view_state.bearing())
+    }
+
+    pub fn get_pitch(&self) -> Result<Deg<f64>, MapError> {
+        Ok(self.context()?.view_state.pitch())
+    }
+
+    /// Returns the user interactions which are enabled.
+    pub fn interaction_handlers(&self) -> Result<InteractionHandlers, MapError> {
+        Ok(self.context()?.interaction_handlers())
+    }
+
+    /// Enables or disables user interactions, e.g. rotating the map. This takes effect before
+    /// the next frame.
+    pub fn set_interaction_handlers(
+        &mut self,
+        handlers: InteractionHandlers,
+    ) -> Result<(), MapError> {
+        self.context_mut()?.set_interaction_handlers(handlers);
+        Ok(())
+    }
+
+    /// Returns the features which are rendered at a point or within a box on the screen, ordered
+    /// from the top-most layer to the bottom-most.
+    pub fn query_rendered_features(
+        &self,
+        geometry: impl Into<QueryGeometry>,
+        layers: Option<&[&str]>,
+        filter: Option<&JsonValue>,
+    ) -> Result<Vec<QueriedFeature>, MapError> {
+        Ok(self
+            .context()?
+            .query_rendered_features(geometry, layers, filter))
+    }
+
+    /// Returns the features of the loaded tiles of a source, regardless of whether they are
+    /// visible.
+    pub fn query_source_features(
+        &self,
+        source_id: &str,
+        source_layer: Option<&str>,
+        filter: Option<&JsonValue>,
+    ) -> Result<Vec<QueriedFeature>, MapError> {
+        Ok(self
+            .context()?
+            .query_source_features(source_id, source_layer, filter))
+    }
+
+    /// Merges `state` into the state of a feature, which paint properties can use through the
+    /// `["feature-state", name]` expression, e.g. to highlight hovered features.
+    pub fn set_feature_state(
+        &mut self,
+        source: &str,
+        source_layer: &str,
+        feature_id: u64,
+        state: HashMap<String, JsonValue>,
+    ) -> Result<(), MapError> {
+        self.context_mut()?
+            .set_feature_state(source, source_layer, feature_id, state);
+        Ok(())
+    }
+
+    pub fn get_feature_state(
+        &self,
+        source: &str,
+        source_layer: &str,
+        feature_id: u64,
+    ) -> Result<Option<&HashMap<String, JsonValue>>, MapError> {
+        Ok(self
+            .context()?
+            .get_feature_state(source, source_layer, feature_id))
+    }
+
+    /// Removes the value `key` from the state of a feature, or the whole state if `key` is
+    /// `None`.
+    pub fn remove_feature_state(
+        &mut self,
+        source: &str,
+        source_layer: &str,
+        feature_id: u64,
+        key: Option<&str>,
+    ) -> Result<(), MapError> {
+        self.context_mut()?
+            .remove_feature_state(source, source_layer, feature_id, key);
+        Ok(())
+    }
+
+    /// Replaces the frame of an image or canvas source, e.g. the next frame of a video.
+    pub fn set_image_source_frame(
+        &mut self,
+        source_id: &str,
+        frame: RgbaImage,
+    ) -> Result<(), MapError> {
+        self.context_mut()?.set_image_source_frame(source_id, frame);
+        Ok(())
+    }
+
+    /// Moves an image or canvas source to new corners, which are listed clockwise starting with
+    /// the top left corner of the image.
+    pub fn set_image_source_coordinates(
+        &mut self,
+        source_id: &str,
+        corners: [LatLon; 4],
+    ) -> Result<(), MapError> {
+        self.context_mut()?
+            .set_image_source_coordinates(source_id, corners);
+        Ok(())
+    }
+
+    /// Adds a texture which [`OverlayImages`](crate::render::overlay::OverlayImage) can draw.
+    pub fn add_overlay_texture(
+        &mut self,
+        texture: OverlayTexture,
+    ) -> Result<OverlayTextureId, MapError> {
+        Ok(self.context_mut()?.add_overlay_texture(texture))
+    }
+
+    pub fn remove_overlay_texture(
+        &mut self,
+        id: OverlayTextureId,
+    ) -> Result<Option<OverlayTexture>, MapError> {
+        Ok(self.context_mut()?.remove_overlay_texture(id))
+    }
+
+    /// Adds a marker, which is drawn by the [`AnnotationPlugin`](crate::annotation::AnnotationPlugin).
+    pub fn add_marker(&mut self, marker: Marker) -> Result<MarkerId, MapError> {
+        Ok(self.context_mut()?.add_marker(marker))
+    }
+
+    pub fn remove_marker(&mut self, id: MarkerId) -> Result<Option<Marker>, MapError> {
+        Ok(self.context_mut()?.remove_marker(id))
+    }
+
+    /// Moves a marker. Returns `false` if the marker does not exist.
+    pub fn set_marker_position(&mut self, id: MarkerId, lat_lon: LatLon) -> Result<bool, MapError> {
+        Ok(self.context_mut()?.set_marker_position(id, lat_lon))
+    }
+
+    /// Adds a popup, which is drawn by the [`AnnotationPlugin`](crate::annotation::AnnotationPlugin).
+    pub fn add_popup(&mut self, popup: Popup) -> Result<PopupId, MapError> {
+        Ok(self.context_mut()?.add_popup(popup))
+    }
+
+    pub fn remove_popup(&mut self, id: PopupId) -> Result<Option<Popup>, MapError> {
+        Ok(self.context_mut()?.remove_popup(id))
+    }
+
+    /// Adds a control, which is drawn by the [`ControlPlugin`](crate::control::ControlPlugin).
+    pub fn add_control(&mut self, control: Control, corner: Corner) -> Result<(), MapError> {
+        self.context_mut()?.add_control(control, corner);
+        Ok(())
+    }
+
+    /// Removes a control. Returns `false` if the control has not been added.
+    pub fn remove_control(&mut self, control: Control) -> Result<bool, MapError> {
+        Ok(self.context_mut()?.remove_control(control))
+    }
+
+    /// Changes how the input of the [`DrawPlugin`](crate::draw::DrawPlugin) is handled.
+    pub fn set_draw_mode(&mut self, mode: DrawMode) -> Result<(), MapError> {
+        self.context_mut()?.set_draw_mode(mode);
+        Ok(())
+    }
+
+    pub fn draw_mode(&self) -> Result<DrawMode, MapError> {
+        Ok(self.context()?.draw_mode())
+    }
+
+    /// Returns the features which have been drawn, with their geodesic
+    /// [`measurement`](DrawnFeature::measurement).
+    pub fn drawn_features(&self) -> Result<Vec<(DrawnFeatureId, DrawnFeature)>, MapError> {
+        Ok(self.context()?.drawn_features())
+    }
+
+    /// Returns the drawn features as a GeoJSON feature collection, whose properties contain
+    /// their length and area.
+    pub fn drawn_features_geojson(&self) -> Result<JsonValue, MapError> {
+        Ok(self.context()?.drawn_features_geojson())
+    }
+
+    pub fn remove_drawn_feature(
+        &mut self,
+        id: DrawnFeatureId,
+    ) -> Result<Option<DrawnFeature>, MapError> {
+        Ok(self.context_mut()?.remove_drawn_feature(id))
+    }
+
+    pub fn clear_drawn_features(&mut self) -> Result<(), MapError> {
+        self.context_mut()?.clear_drawn_features();
+        Ok(())
+    }
+
+    /// Replaces the style of the map. The differences to the current style are applied
+    /// incrementally, such that fetched tiles are kept whenever possible. The data of sources
+    /// which changed is fetched again.
+    pub fn set_style(&mut self, mut style: Style) {
+        style.update_layer_indices();
+
+        let map_context = match &mut self.map_context {
+            CurrentMapContext::Ready(map_context) => map_context,
+            CurrentMapContext::Pending {
+                style: pending_style,
+                ..
+            } => {
+                *pending_style = style;
+                self.listeners.dispatch(&MapEvent::StyleData);
+                return;
+            }
+        };
+
+        let change = diff_change(&map_context.style, &style).unwrap_or_else(|_| {
+            info!("style can not be applied incrementally, reloading all sources");
+            StyleChange {
+                retessellate: true,
+                reloaded_sources: style.sources.keys().cloned().collect(),
+                ..Default::default()
+            }
+        });
+
+        // Properties which are not diffed, e.g. the name, are taken over as well
+        let _ = map_context.mutate_style(|live| {
+            *live = style;
+            Ok(change)
+        });
+    }
+
+    /// Applies a mutation to the live style. While the renderer is pending, the style which is
+    /// used once the renderer is ready is mutated.
+    fn mutate_style(
+        &mut self,
+        mutate: impl FnOnce(&mut Style) -> Result<StyleChange, StyleError>,
+    ) -> Result<(), StyleError> {
+        match &mut self.map_context {
+            CurrentMapContext::Ready(map_context) => map_context.mutate_style(mutate),
+            CurrentMapContext::Pending { style, .. } => mutate(style).map(|_| ()),
+        }
+    }
+
+    /// Inserts a layer below the layer `before_id`, or on top if `before_id` is `None`.
+    pub fn add_layer(
+        &mut self,
+        layer: StyleLayer,
+        before_id: Option<&str>,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.add_layer(layer, before_id))
+    }
+
+    pub fn remove_layer(&mut self, layer_id: &str) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.remove_layer(layer_id))
+    }
+
+    /// Moves a layer below the layer `before_id`, or on top if `before_id` is `None`.
+    pub fn move_layer(
+        &mut self,
+        layer_id: &str,
+        before_id: Option<&str>,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.move_layer(layer_id, before_id))
+    }
+
+    pub fn set_paint_property(
+        &mut self,
+        layer_id: &str,
+        name: &str,
+        value: JsonValue,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.set_paint_property(layer_id, name, value))
+    }
+
+    pub fn set_layout_property(
+        &mut self,
+        layer_id: &str,
+        name: &str,
+        value: JsonValue,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.set_layout_property(layer_id, name, value))
+    }
+
+    /// Sets the filter of a layer. Only the features which match the filter are drawn and
+    /// queried.
+    pub fn set_filter(
+        &mut self,
+        layer_id: &str,
+        filter: Option<JsonValue>,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.set_filter(layer_id, filter))
+    }
+
+    pub fn set_layer_zoom_range(
+        &mut self,
+        layer_id: &str,
+        minzoom: Option<u8>,
+        maxzoom: Option<u8>,
+    ) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.set_layer_zoom_range(layer_id, minzoom, maxzoom))
+    }
+
+    pub fn add_source(&mut self, id: &str, source: Source) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.add_source(id, source))
+    }
+
+    pub fn remove_source(&mut self, id: &str) -> Result<(), StyleError> {
+        self.mutate_style(|style| style.remove_source(id))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use serde_json::json;
+    use wgpu::rwh::{DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle};
+
+    use super::*;
+    use crate::{
+        environment::OffscreenKernelConfig,
+        io::apc::SchedulerAsyncProcedureCall,
+        kernel::KernelBuilder,
+        platform::{
+            http_client::ReqwestHttpClient, scheduler::TokioScheduler,
+            ReqwestOffscreenKernelEnvironment,
+        },
+        render::{
+            settings::{RendererSettings, WgpuSettings},
+            RenderPlugin,
+        },
+        window::PhysicalSize,
+    };
+
+    /// A window without a handle. The renderer of the map draws into a texture instead.
+    struct TestWindow {
+        /// Counts the redraws which have been requested by the map.
+        redraws: Arc<AtomicUsize>,
+    }
+
+    impl HasWindowHandle for TestWindow {
+        fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+            Err(HandleError::Unavailable)
+        }
+    }
+
+    impl HasDisplayHandle for TestWindow {
+        fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+            Err(HandleError::Unavailable)
+        }
+    }
+
+    impl MapWindow for TestWindow {
+        fn size(&self) -> PhysicalSize {
+            PhysicalSize::new(100, 100).unwrap()
+        }
+    }
+
+    impl HeadedMapWindow for TestWindow {
+        type WindowHandle = Self;
+
+        fn handle(&self) -> &Self::WindowHandle {
+            self
+        }
+
+        fn request_redraw(&self) {
+            self.redraws.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn redraw_waker(&self) -> Box<dyn Fn()> {
+            let redraws = self.redraws.clone();
+            Box::new(move || {
+                redraws.fetch_add(1, Ordering::Relaxed);
+            })
+        }
+
+        fn scale_factor(&self) -> f64 {
+            1.0
+        }
+
+        fn id(&self) -> u64 {
+            0
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct TestWindowConfig {
+        redraws: Arc<AtomicUsize>,
+    }
+
+    impl MapWindowConfig for TestWindowConfig {
+        type MapWindow = TestWindow;
+
+        fn create(&self) -> Result<Self::MapWindow, WindowCreateError> {
+            Ok(TestWindow {
+                redraws: self.redraws.clone(),
+            })
+        }
+    }
+
+    struct TestEnvironment;
+
+    impl Environment for TestEnvironment {
+        type MapWindowConfig = TestWindowConfig;
+        type AsyncProcedureCall =
+            SchedulerAsyncProcedureCall<Self::OffscreenKernelEnvironment, Self::Scheduler>;
+        type Scheduler = TokioScheduler;
+        type HttpClient = ReqwestHttpClient;
+        type OffscreenKernelEnvironment = ReqwestOffscreenKernelEnvironment;
+    }
+
+    fn style_with_tiles(tiles: &str) -> Style {
+        let mut style = Style::default();
+        style.sources.insert(
+            "openmaptiles".to_string(),
+            serde_json::from_value(json!({"type": "vector", "tiles": [tiles]})).unwrap(),
+        );
+        style
+    }
+
+    /// Creates a map whose context has been initialized with a headless renderer.
+    async fn initialized_map(
+        style: Style,
+        window_config: TestWindowConfig,
+        plugins: Vec<Box<dyn Plugin<TestEnvironment>>>,
+    ) -> Map<TestEnvironment> {
+        let kernel = KernelBuilder::new()
+            .with_map_window_config(window_config)
+            .with_http_client(ReqwestHttpClient::new(None::<String>))
+            .with_apc(SchedulerAsyncProcedureCall::new(
+                TokioScheduler::new(),
+                OffscreenKernelConfig {
+                    cache_directory: None,
+                },
+            ))
+            .with_scheduler(TokioScheduler::new())
+            .build();
+        let mut map =
+            Map::<TestEnvironment>::new(style, kernel, RendererBuilder::new(), plugins).unwrap();
+
+        let renderer = Renderer::initialize_headless(
+            map.window(),
+            WgpuSettings::default(),
+            RendererSettings::default(),
+        )
+        .await
+        .unwrap();
+        map.initialize_context(renderer);
+        assert!(map.is_initialized());
+        map
+    }
+
+    #[tokio::test]
+    async fn test_set_style_with_changed_source() {
+        let mut map = initialized_map(
+            style_with_tiles("https://a.example.com/{z}/{x}/{y}.pbf"),
+            TestWindowConfig::default(),
+            vec![],
+        )
+        .await;
+
+        // The renderer is kept while the tiles of the source are fetched again
+        let style = style_with_tiles("https://b.example.com/{z}/{x}/{y}.pbf");
+        map.set_style(style.clone());
+        assert!(map.is_initialized());
+        assert_eq!(
+            serde_json::to_value(&map.context().unwrap().style.sources).unwrap(),
+            serde_json::to_value(&style.sources).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wake_idle_map_after_style_mutation() {
+        let window_config = TestWindowConfig::default();
+        let redraws = window_config.redraws.clone();
+        let mut map = initialized_map(
+            Style::default(),
+            window_config,
+            vec![Box::new(RenderPlugin)],
+        )
+        .await;
+
+        // The map is idle once the frame which has been requested initially is rendered
+        while map.needs_repaint() {
+            map.run_schedule().unwrap();
+        }
+        assert_eq!(redraws.load(Ordering::Relaxed), 0);
+
+        map.set_layout_property("water", "visibility", json!("none"))
+            .unwrap();
+        assert!(map.needs_repaint());
+        assert_eq!(redraws.load(Ordering::Relaxed), 1);
+    }
 }
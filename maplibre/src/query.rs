@@ -0,0 +1,435 @@
+//! Queries the features of the map, e.g. the features at the position of the cursor.
+//!
+//! Features are looked up in the [`GeometryIndex`](crate::io::geometry_index::GeometryIndex) of
+//! the loaded vector tiles.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+use cgmath::Vector2;
+use geo::MapCoords;
+use geo_types::{Coord, Geometry, LineString, Point, Polygon};
+use rstar::AABB;
+use serde_json::Value as JsonValue;
+
+use crate::{
+    context::MapContext,
+    coords::{ScreenPoint, WorldCoords, WorldTileCoords, Zoom, EXTENT, TILE_SIZE},
+    io::geometry_index::{ExactGeometry, IndexedGeometry},
+    render::{eventually::Eventually, tile_view_pattern::WgpuTileViewPattern},
+    style::{
+        expression::EvaluationContext,
+        filter::{self, FilterFeature},
+        layer::{LayerPaint, StyleLayer},
+    },
+    util::math::bounds_from_points,
+};
+
+/// The area of the screen in which features are queried.
+#[derive(Clone, Copy, Debug)]
+pub enum QueryGeometry {
+    Point(ScreenPoint),
+    /// A box which is spanned by two corners.
+    Box(ScreenPoint, ScreenPoint),
+}
+
+impl From<ScreenPoint> for QueryGeometry {
+    fn from(point: ScreenPoint) -> Self {
+        QueryGeometry::Point(point)
+    }
+}
+
+impl From<(ScreenPoint, ScreenPoint)> for QueryGeometry {
+    fn from((a, b): (ScreenPoint, ScreenPoint)) -> Self {
+        QueryGeometry::Box(a, b)
+    }
+}
+
+/// A feature of a vector tile.
+#[derive(Clone, Debug)]
+pub struct QueriedFeature {
+    /// The style layer which renders the feature, or `None` if the feature has been queried from
+    /// its source.
+    pub layer_id: Option<String>,
+    pub source: Option<String>,
+    pub source_layer: String,
+    /// The id of the feature within its source, which identifies the feature e.g. when setting
+    /// its [feature state](crate::feature_state).
+    pub id: Option<u64>,
+    /// The tile which contains the feature. Features which cross tiles are returned once per tile.
+    pub coords: WorldTileCoords,
+    /// The geometry in longitude (x) and latitude (y). For features which consist of several
+    /// parts, e.g. multi-polygons, this is the first part which matched the query.
+    pub geometry: Geometry<f64>,
+    pub properties: HashMap<String, JsonValue>,
+}
+
+impl QueriedFeature {
+    fn new(
+        layer_id: Option<String>,
+        source: Option<String>,
+        coords: WorldTileCoords,
+        indexed: &IndexedGeometry<f64>,
+    ) -> Self {
+        // The size of the tile in world coordinates at zoom 0
+        let tile_size = TILE_SIZE / f64::from(1u32 << u8::from(coords.z));
+        let geometry = indexed.exact.to_geometry().map_coords(|Coord { x, y }| {
+            let lat_lon = WorldCoords::at_ground(
+                (coords.x as f64 + x / EXTENT) * tile_size,
+                (coords.y as f64 + y / EXTENT) * tile_size,
+            )
+            .into_lat_lon(Zoom::new(0.0));
+            Coord {
+                x: lat_lon.longitude,
+                y: lat_lon.latitude,
+            }
+        });
+
+        Self {
+            layer_id,
+            source,
+            source_layer: indexed.source_layer.clone(),
+            id: indexed.id,
+            coords,
+            geometry,
+            properties: indexed.properties.clone(),
+        }
+    }
+}
+
+/// The queried area in world coordinates at the zoom of the view.
+enum WorldShape {
+    Point(Vector2<f64>),
+    Polygon(Vec<Vector2<f64>>),
+}
+
+impl WorldShape {
+    /// Transforms the shape to the coordinates of the tile at `coords`.
+    fn to_tile(&self, coords: WorldTileCoords, zoom: Zoom) -> TileShape {
+        let tile_size = TILE_SIZE / zoom.scale_to_tile(&coords);
+        let to_tile = |point: &Vector2<f64>| Coord {
+            x: (point.x / tile_size - coords.x as f64) * EXTENT,
+            y: (point.y / tile_size - coords.y as f64) * EXTENT,
+        };
+
+        match self {
+            WorldShape::Point(point) => TileShape::Point(Point(to_tile(point))),
+            WorldShape::Polygon(points) => TileShape::Polygon(Polygon::new(
+                LineString::new(points.iter().map(to_tile).collect()),
+                vec![],
+            )),
+        }
+    }
+}
+
+/// A layer whose features are queried.
+struct QueriedLayer<'a> {
+    /// The position of the layer within the style
+    position: usize,
+    layer: &'a StyleLayer,
+    paint: &'a LayerPaint,
+    source_layer: &'a String,
+    /// The distance in logical pixels within which the layer hits features
+    radius: f64,
+}
+
+/// The queried area in the coordinates of a tile.
+enum TileShape {
+    Point(Point<f64>),
+    Polygon(Polygon<f64>),
+}
+
+impl TileShape {
+    /// Returns the bounds of the shape, grown by `tolerance` on each side.
+    fn envelope(&self, tolerance: f64) -> AABB<Point<f64>> {
+        let (min, max) = match self {
+            TileShape::Point(point) => (point.x_y().into(), point.x_y().into()),
+            TileShape::Polygon(polygon) => {
+                bounds_from_points(polygon.exterior().points()).unwrap_or_default()
+            }
+        };
+        let [min_x, min_y]: [f64; 2] = min;
+        let [max_x, max_y]: [f64; 2] = max;
+        AABB::from_corners(
+            Point::new(min_x - tolerance, min_y - tolerance),
+            Point::new(max_x + tolerance, max_y + tolerance),
+        )
+    }
+
+    fn distance(&self, geometry: &ExactGeometry<f64>) -> f64 {
+        match self {
+            TileShape::Point(point) => geometry.distance_to_point(point),
+            TileShape::Polygon(polygon) => geometry.distance_to_polygon(polygon),
+        }
+    }
+}
+
+/// Returns the features which are rendered in `geometry`, ordered from the top-most layer to the
+/// bottom-most. Only the tiles which are currently rendered are considered.
+///
+/// Lines are hit within half of their `line-width` and the points of heatmaps within their
+/// `heatmap-radius`. Features have to match the filter of their layer and `filter`. If `layers` is
+/// set, then only features of these layers are returned.
+pub fn query_rendered_features(
+    context: &MapContext,
+    geometry: QueryGeometry,
+    layers: Option<&[&str]>,
+    filter: Option<&JsonValue>,
+) -> Vec<QueriedFeature> {
+    let MapContext {
+        style,
+        world,
+        view_state,
+        ..
+    } = context;
+
+    let inverted_view_proj = view_state.view_projection().invert();
+    let ground = |point: ScreenPoint, bound: bool| {
+        view_state.window_to_world_at_ground(
+            &Vector2::new(point.x, point.y),
+            &inverted_view_proj,
+            bound,
+        )
+    };
+    let shape = match geometry {
+        QueryGeometry::Point(point) => match ground(point, true) {
+            Some(point) => WorldShape::Point(point),
+            None => return Vec::new(),
+        },
+        QueryGeometry::Box(a, b) => {
+            let corners = [
+                ScreenPoint::new(a.x, a.y),
+                ScreenPoint::new(b.x, a.y),
+                ScreenPoint::new(b.x, b.y),
+                ScreenPoint::new(a.x, b.y),
+            ];
+            match corners
+                .into_iter()
+                .map(|corner| ground(corner, false))
+                .collect::<Option<Vec<_>>>()
+            {
+                Some(corners) => WorldShape::Polygon(corners),
+                None => return Vec::new(),
+            }
+        }
+    };
+
+    let Some(Eventually::Initialized(tile_view_pattern)) =
+        world.resources.get::<Eventually<WgpuTileViewPattern>>()
+    else {
+        return Vec::new();
+    };
+
+    // The tiles from which the visible tiles are rendered, which can be parents or children
+    let mut rendered_tiles = Vec::new();
+    let mut seen_tiles = HashSet::new();
+    for view_tile in tile_view_pattern.iter() {
+        view_tile.render(|tile_shape| {
+            if seen_tiles.insert(tile_shape.coords()) {
+                rendered_tiles.push(tile_shape.coords());
+            }
+        });
+    }
+
+    let zoom = view_state.zoom();
+    let evaluation_context = EvaluationContext {
+        zoom: zoom.into(),
+        ..EvaluationContext::default()
+    };
+
+    // The queried layers from the top-most to the bottom-most, together with the distance in
+    // logical pixels within which they hit features
+    let queried_layers = style
+        .layers
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, layer)| {
+            layers.map_or(true, |layers| layers.contains(&layer.id.as_str()))
+                && layer.is_visible_at(zoom.into())
+        })
+        .filter_map(|(position, layer)| {
+            let (Some(paint), Some(source_layer)) = (&layer.paint, &layer.source_layer) else {
+                return None;
+            };
+            let radius = match paint {
+                LayerPaint::Fill(_) => 0.0,
+                LayerPaint::Line(paint) => paint.width(&evaluation_context) / 2.0,
+                LayerPaint::Heatmap(paint) => paint.radius(&evaluation_context),
+                _ => return None,
+            };
+            Some(QueriedLayer {
+                position,
+                layer,
+                paint,
+                source_layer,
+                radius,
+            })
+        })
+        .collect::<Vec<_>>();
+    let Some(max_radius) = queried_layers
+        .iter()
+        .map(|queried| queried.radius)
+        .reduce(f64::max)
+    else {
+        return Vec::new();
+    };
+
+    // Each tile is searched once for the features of all layers
+    let mut layer_features = vec![Vec::new(); queried_layers.len()];
+    let mut seen_features = HashSet::new();
+    for coords in &rendered_tiles {
+        let Some(tile_index) = world.tiles.geometry_index.get_tile(coords) else {
+            continue;
+        };
+
+        let tile_shape = shape.to_tile(*coords, zoom);
+        // Converts logical pixels to the coordinates of the tile
+        let scale = EXTENT * zoom.scale_to_tile(coords) / TILE_SIZE;
+
+        for indexed in tile_index.locate_in_envelope(tile_shape.envelope(max_radius * scale)) {
+            for (queried, features) in queried_layers.iter().zip(&mut layer_features) {
+                if indexed.source != queried.layer.source
+                    || indexed.source_layer != *queried.source_layer
+                {
+                    continue;
+                }
+                let Some(drawn) = drawn_geometry(queried.paint, &indexed.exact) else {
+                    continue;
+                };
+                if tile_shape.distance(&drawn) > queried.radius * scale
+                    || !matches_filters(queried.layer, filter, indexed)
+                    || !seen_features.insert((
+                        queried.position,
+                        coords.wrap(),
+                        indexed.feature_index,
+                    ))
+                {
+                    continue;
+                }
+
+                features.push(QueriedFeature::new(
+                    Some(queried.layer.id.clone()),
+                    queried.layer.source.clone(),
+                    *coords,
+                    indexed,
+                ));
+            }
+        }
+    }
+
+    layer_features.into_iter().flatten().collect()
+}
+
+/// Returns the features of the loaded tiles of the source `source_id` which match `filter`,
+/// regardless of whether they are visible. If `source_layer` is set, then only features of this
+/// layer of the source are returned.
+pub fn query_source_features(
+    context: &MapContext,
+    source_id: &str,
+    source_layer: Option<&str>,
+    filter: Option<&JsonValue>,
+) -> Vec<QueriedFeature> {
+    let MapContext { style, world, .. } = context;
+
+    // Only the layers of a source which are used by the style are loaded
+    let source_layers = style
+        .layers
+        .iter()
+        .filter(|layer| layer.source.as_deref() == Some(source_id))
+        .filter_map(|layer| layer.source_layer.as_deref())
+        .filter(|name| source_layer.map_or(true, |source_layer| source_layer == *name))
+        .collect::<HashSet<_>>();
+
+    let mut features = Vec::new();
+    let mut seen_features = HashSet::new();
+    for (coords, tile_index) in world.tiles.geometry_index.iter() {
+        for indexed in tile_index.iter() {
+            if indexed.source.as_deref() != Some(source_id)
+                || !source_layers.contains(indexed.source_layer.as_str())
+                || filter.is_some_and(|filter| !filter::matches(filter, filter_feature(indexed)))
+                || !seen_features.insert((coords, &indexed.source_layer, indexed.feature_index))
+            {
+                continue;
+            }
+
+            features.push(QueriedFeature::new(
+                None,
+                Some(source_id.to_string()),
+                coords,
+                indexed,
+            ));
+        }
+    }
+
+    features
+}
+
+/// Returns the geometry which a layer draws for a feature, e.g. the outline of polygons for
+/// line layers. Returns `None` if the layer does not draw the feature.
+fn drawn_geometry<'a>(
+    paint: &LayerPaint,
+    exact: &'a ExactGeometry<f64>,
+) -> Option<Cow<'a, ExactGeometry<f64>>> {
+    match (paint, exact) {
+        (LayerPaint::Fill(_), ExactGeometry::Polygon(_))
+        | (LayerPaint::Line(_), ExactGeometry::LineString(_))
+        | (LayerPaint::Heatmap(_), ExactGeometry::Point(_)) => Some(Cow::Borrowed(exact)),
+        (LayerPaint::Line(_), ExactGeometry::Polygon(polygon)) => Some(Cow::Owned(
+            ExactGeometry::LineString(polygon.exterior().clone()),
+        )),
+        _ => None,
+    }
+}
+
+fn matches_filters(
+    layer: &StyleLayer,
+    filter: Option<&JsonValue>,
+    indexed: &IndexedGeometry<f64>,
+) -> bool {
+    let feature = filter_feature(indexed);
+    [layer.filter.as_ref(), filter]
+        .into_iter()
+        .flatten()
+        .all(|filter| filter::matches(filter, feature))
+}
+
+fn filter_feature(indexed: &IndexedGeometry<f64>) -> FilterFeature<'_> {
+    FilterFeature {
+        geometry_type: indexed.exact.geometry_type(),
+        properties: &indexed.properties,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coords::ZoomLevel;
+
+    #[test]
+    fn test_feature_geometry() {
+        let coords = WorldTileCoords::from((1, 1, ZoomLevel::from(1)));
+        let indexed = IndexedGeometry {
+            bounds: rstar::AABB::from_point(Point::new(0.0, 0.0)),
+            exact: ExactGeometry::Point(Point::new(0.0, EXTENT / 2.0)),
+            properties: HashMap::from([("name".to_string(), JsonValue::from("a"))]),
+            source: Some("source".to_string()),
+            source_layer: "places".to_string(),
+            feature_index: 0,
+            id: Some(7),
+        };
+
+        let feature = QueriedFeature::new(None, Some("source".to_string()), coords, &indexed);
+        let Geometry::Point(point) = feature.geometry else {
+            panic!("expected a point");
+        };
+        // The left edge of the south-east tile at zoom 1
+        assert!(point.x().abs() < 1e-9);
+        assert!(point.y() < 0.0);
+        assert_eq!(feature.source_layer, "places");
+        assert_eq!(feature.id, Some(7));
+        assert_eq!(feature.properties["name"], "a");
+    }
+}
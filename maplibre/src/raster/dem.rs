@@ -0,0 +1,232 @@
+//! Decoding of raster-dem tiles into elevation grids.
+
+use image::RgbaImage;
+use thiserror::Error;
+
+use crate::style::source::DemEncoding;
+
+#[derive(Error, Debug)]
+pub enum DemError {
+    #[error("raster-dem tiles must be square, got {0}x{1}")]
+    NotSquare(u32, u32),
+    #[error("raster-dem tile is empty")]
+    Empty,
+}
+
+impl DemEncoding {
+    /// Decodes the elevation in meters from a single RGB pixel.
+    pub fn decode(&self, r: u8, g: u8, b: u8) -> f32 {
+        let (r, g, b) = (r as f64, g as f64, b as f64);
+        let elevation = match self {
+            DemEncoding::Mapbox => (r * 256.0 * 256.0 + g * 256.0 + b) * 0.1 - 10000.0,
+            DemEncoding::Terrarium => (r * 256.0 + g + b / 256.0) - 32768.0,
+        };
+        elevation as f32
+    }
+}
+
+/// Elevation grid of a single tile.
+///
+/// The grid is surrounded by a border of one pixel. Initially the border repeats the closest edge
+/// pixel of the tile. Once neighbouring tiles are available the border is backfilled using
+/// [`DemData::backfill_border`], which avoids seams when computing slopes at the tile edges.
+#[derive(Clone, Debug)]
+pub struct DemData {
+    dim: u32,
+    data: Vec<f32>,
+}
+
+impl DemData {
+    pub fn from_image(image: &RgbaImage, encoding: DemEncoding) -> Result<Self, DemError> {
+        let (width, height) = image.dimensions();
+        if width != height {
+            return Err(DemError::NotSquare(width, height));
+        }
+        if width == 0 {
+            return Err(DemError::Empty);
+        }
+
+        let dim = width;
+        let stride = dim + 2;
+        let mut dem = Self {
+            dim,
+            data: vec![0.0; (stride * stride) as usize],
+        };
+
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let [r, g, b, _] = pixel.0;
+            let index = dem.index(x as i32, y as i32);
+            dem.data[index] = encoding.decode(r, g, b);
+        }
+
+        let dim = dim as i32;
+        for i in 0..dim {
+            // left and right border
+            dem.set(-1, i, dem.get(0, i));
+            dem.set(dim, i, dem.get(dim - 1, i));
+            // top and bottom border
+            dem.set(i, -1, dem.get(i, 0));
+            dem.set(i, dim, dem.get(i, dim - 1));
+        }
+
+        // corners
+        dem.set(-1, -1, dem.get(0, 0));
+        dem.set(dim, -1, dem.get(dim - 1, 0));
+        dem.set(-1, dim, dem.get(0, dim - 1));
+        dem.set(dim, dim, dem.get(dim - 1, dim - 1));
+
+        Ok(dem)
+    }
+
+    /// Creates a tile which is at sea level everywhere.
+    pub fn flat(dim: u32) -> Self {
+        let stride = dim + 2;
+        Self {
+            dim,
+            data: vec![0.0; (stride * stride) as usize],
+        }
+    }
+
+    /// Amount of pixels along one side of the tile, not including the border.
+    pub fn dim(&self) -> u32 {
+        self.dim
+    }
+
+    /// Amount of pixels along one side of the tile, including the border.
+    pub fn stride(&self) -> u32 {
+        self.dim + 2
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        let stride = self.stride() as i32;
+        debug_assert!(x >= -1 && x < stride - 1 && y >= -1 && y < stride - 1);
+        ((y + 1) * stride + (x + 1)) as usize
+    }
+
+    /// Returns the elevation in meters. Coordinates range from -1 to [`DemData::dim`] inclusive.
+    pub fn get(&self, x: i32, y: i32) -> f32 {
+        self.data[self.index(x, y)]
+    }
+
+    fn set(&mut self, x: i32, y: i32, elevation: f32) {
+        let index = self.index(x, y);
+        self.data[index] = elevation;
+    }
+
+    /// Copies the pixels which touch this tile from the `neighbour` into the border of this tile.
+    ///
+    /// `dx` and `dy` describe the position of the neighbour relative to this tile and range from
+    /// -1 to 1.
+    pub fn backfill_border(&mut self, neighbour: &DemData, dx: i32, dy: i32) {
+        if self.dim != neighbour.dim || (dx == 0 && dy == 0) {
+            return;
+        }
+
+        let dim = self.dim as i32;
+
+        let mut x_min = dx * dim;
+        let mut x_max = dx * dim + dim;
+        let mut y_min = dy * dim;
+        let mut y_max = dy * dim + dim;
+
+        match dx {
+            -1 => x_min = x_max - 1,
+            1 => x_max = x_min + 1,
+            _ => {}
+        }
+
+        match dy {
+            -1 => y_min = y_max - 1,
+            1 => y_max = y_min + 1,
+            _ => {}
+        }
+
+        let ox = -dx * dim;
+        let oy = -dy * dim;
+
+        for y in y_min..y_max {
+            for x in x_min..x_max {
+                self.set(x, y, neighbour.get(x + ox, y + oy));
+            }
+        }
+    }
+
+    /// Packs the grid including the border into an image using the Mapbox Terrain-RGB encoding.
+    /// This allows uploading the elevation as a regular 8-bit texture.
+    pub fn to_image(&self) -> RgbaImage {
+        let stride = self.stride();
+        RgbaImage::from_fn(stride, stride, |x, y| {
+            let elevation = self.get(x as i32 - 1, y as i32 - 1);
+            let value = ((elevation + 10000.0) * 10.0)
+                .round()
+                .clamp(0.0, 16777215.0) as u32;
+            image::Rgba([
+                ((value >> 16) & 0xff) as u8,
+                ((value >> 8) & 0xff) as u8,
+                (value & 0xff) as u8,
+                255,
+            ])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use super::DemData;
+    use crate::style::source::DemEncoding;
+
+    fn mapbox_tile(dim: u32, elevation: impl Fn(u32, u32) -> f32) -> RgbaImage {
+        RgbaImage::from_fn(dim, dim, |x, y| {
+            let value = ((elevation(x, y) + 10000.0) * 10.0).round() as u32;
+            image::Rgba([(value >> 16) as u8, (value >> 8) as u8, value as u8, 255])
+        })
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(DemEncoding::Mapbox.decode(1, 134, 160), 0.0);
+        assert_eq!(DemEncoding::Terrarium.decode(128, 0, 0), 0.0);
+        assert_eq!(DemEncoding::Terrarium.decode(128, 100, 128), 100.5);
+    }
+
+    #[test]
+    fn test_border() {
+        let image = mapbox_tile(4, |x, y| (x + y * 4) as f32);
+        let mut dem = DemData::from_image(&image, DemEncoding::Mapbox).unwrap();
+
+        assert_eq!(dem.get(-1, 0), 0.0);
+        assert_eq!(dem.get(4, 3), 15.0);
+        assert_eq!(dem.get(4, 4), 15.0);
+
+        let right = DemData::from_image(
+            &mapbox_tile(4, |_, y| 100.0 + y as f32),
+            DemEncoding::Mapbox,
+        )
+        .unwrap();
+        dem.backfill_border(&right, 1, 0);
+        assert_eq!(dem.get(4, 0), 100.0);
+        assert_eq!(dem.get(4, 3), 103.0);
+        // the corners are not touched by horizontal neighbours
+        assert_eq!(dem.get(4, -1), 3.0);
+
+        let bottom_left =
+            DemData::from_image(&mapbox_tile(4, |_, _| 42.0), DemEncoding::Mapbox).unwrap();
+        dem.backfill_border(&bottom_left, -1, 1);
+        assert_eq!(dem.get(-1, 4), 42.0);
+        assert_eq!(dem.get(0, 4), 12.0);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let image = mapbox_tile(2, |x, y| x as f32 * 10.5 - y as f32 * 3.0);
+        let dem = DemData::from_image(&image, DemEncoding::Mapbox).unwrap();
+        let packed = DemData::from_image(&dem.to_image(), DemEncoding::Mapbox).unwrap();
+        // the border is part of the packed image
+        assert_eq!(packed.dim(), 4);
+        assert_eq!(packed.get(2, 1), 10.5);
+        assert_eq!(packed.get(2, 2), 7.5);
+        assert_eq!(packed.get(3, 2), 7.5);
+    }
+}
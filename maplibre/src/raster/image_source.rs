@@ -0,0 +1,300 @@
+//! Runtime state of image and canvas sources.
+
+use std::{collections::HashMap, time::Duration};
+
+use cgmath::Matrix4;
+use image::RgbaImage;
+use instant::Instant;
+use thiserror::Error;
+
+use crate::{
+    coords::{LatLon, WorldCoords, EXTENT},
+    style::Style,
+};
+
+/// Time after which the image of a source is requested again if fetching it failed
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum ImageSourceError {
+    /// The bytes could not be decoded to an image
+    #[error("decoding image failed")]
+    Decode(#[from] image::ImageError),
+}
+
+/// State of a single image or canvas source.
+#[derive(Default)]
+struct ImageSourceState {
+    /// Corners which override the coordinates of the style
+    corners: Option<[LatLon; 4]>,
+    frame: Option<RgbaImage>,
+    /// Incremented each time the frame changes
+    revision: u64,
+    /// Whether the source is part of the style. Sources which are not part of the style yet
+    /// keep the frame and corners which have been set.
+    in_style: bool,
+    /// The url of the image which has been requested last
+    requested: Option<String>,
+    /// When fetching the requested image failed
+    failed_at: Option<Instant>,
+}
+
+/// Holds the frames of the image and canvas sources of the style. Frames can be replaced at any
+/// time without recreating the source. The latest frame is uploaded before the next render.
+///
+/// Frames and corners can be set before the source is added to the style. They are discarded
+/// once the source is removed from the style.
+#[derive(Default)]
+pub struct ImageSources {
+    sources: HashMap<String, ImageSourceState>,
+}
+
+impl ImageSources {
+    /// Creates the state of new image and canvas sources and removes the state of sources which
+    /// have been removed from the `style`.
+    pub(crate) fn sync(&mut self, style: &Style) {
+        self.sources.retain(|id, source| {
+            let in_style =
+                matches!(style.sources.get(id), Some(source) if source.corners().is_some());
+            // Pending sources have not been added to the style yet
+            let keep = in_style || !source.in_style;
+            source.in_style = in_style;
+            keep
+        });
+
+        for (id, source) in &style.sources {
+            if source.corners().is_some() && !self.sources.contains_key(id) {
+                self.sources.insert(
+                    id.clone(),
+                    ImageSourceState {
+                        in_style: true,
+                        ..ImageSourceState::default()
+                    },
+                );
+            }
+        }
+    }
+
+    fn get_or_insert(&mut self, id: &str) -> &mut ImageSourceState {
+        if !self.sources.contains_key(id) {
+            self.sources
+                .insert(id.to_string(), ImageSourceState::default());
+        }
+        self.sources.get_mut(id).expect("source was inserted")
+    }
+
+    /// Replaces the frame of a source.
+    pub fn set_frame(&mut self, id: &str, frame: RgbaImage) {
+        let source = self.get_or_insert(id);
+        source.frame = Some(frame);
+        source.revision += 1;
+    }
+
+    /// Decodes an encoded image, e.g. a PNG or JPEG, and replaces the frame of a source.
+    pub fn set_image_bytes(&mut self, id: &str, bytes: &[u8]) -> Result<(), ImageSourceError> {
+        let frame = image::load_from_memory(bytes)?.to_rgba8();
+        self.set_frame(id, frame);
+        Ok(())
+    }
+
+    /// Moves a source to new corners. The corners are listed clockwise, starting with the top
+    /// left corner of the image.
+    pub fn set_corners(&mut self, id: &str, corners: [LatLon; 4]) {
+        self.get_or_insert(id).corners = Some(corners);
+    }
+
+    /// Returns whether the source is part of the style.
+    pub fn contains(&self, id: &str) -> bool {
+        self.sources.get(id).is_some_and(|source| source.in_style)
+    }
+
+    /// Returns the current frame of a source together with its revision.
+    pub fn frame(&self, id: &str) -> Option<(&RgbaImage, u64)> {
+        let source = self.sources.get(id)?;
+        source.frame.as_ref().map(|frame| (frame, source.revision))
+    }
+
+    /// Returns the corners of a source. Falls back to the coordinates in the `style`.
+    pub fn corners(&self, id: &str, style: &Style) -> Option<[LatLon; 4]> {
+        self.sources
+            .get(id)?
+            .corners
+            .or_else(|| style.sources.get(id)?.corners())
+    }
+
+    /// Marks the image at `url` as requested for a source and returns whether it has been
+    /// requested before. Images are requested again if the url changes, or a while after
+    /// fetching them failed.
+    pub(crate) fn mark_requested(&mut self, id: &str, url: &str) -> bool {
+        let Some(source) = self.sources.get_mut(id) else {
+            return true;
+        };
+
+        if source.requested.as_deref() == Some(url)
+            && source
+                .failed_at
+                .map_or(true, |failed_at| failed_at.elapsed() < RETRY_INTERVAL)
+        {
+            return true;
+        }
+
+        source.requested = Some(url.to_string());
+        source.failed_at = None;
+        false
+    }
+
+    /// Records that fetching the image of a source failed, such that it is requested again.
+    pub(crate) fn request_failed(&mut self, id: &str) {
+        if let Some(source) = self.sources.get_mut(id) {
+            source.failed_at = Some(Instant::now());
+        }
+    }
+}
+
+/// Returns the transform which maps the coordinates of a tile, i.e. from `0` to [`EXTENT`], onto
+/// the quad which is spanned by the `corners`.
+///
+/// The transform is a projective transform which maps the square onto an arbitrary quad. The
+/// homogeneous component carries the perspective, such that textures are mapped without seams
+/// along the diagonal.
+pub fn image_transform(corners: [WorldCoords; 4]) -> Matrix4<f64> {
+    let [p0, p1, p2, p3] = corners;
+
+    let dx3 = p0.x - p1.x + p2.x - p3.x;
+    let dy3 = p0.y - p1.y + p2.y - p3.y;
+
+    let (g, h) = if dx3 == 0.0 && dy3 == 0.0 {
+        // The quad is a parallelogram
+        (0.0, 0.0)
+    } else {
+        let dx1 = p1.x - p2.x;
+        let dx2 = p3.x - p2.x;
+        let dy1 = p1.y - p2.y;
+        let dy2 = p3.y - p2.y;
+        let det = dx1 * dy2 - dx2 * dy1;
+        ((dx3 * dy2 - dx2 * dy3) / det, (dx1 * dy3 - dx3 * dy1) / det)
+    };
+
+    let a = p1.x - p0.x + g * p1.x;
+    let b = p3.x - p0.x + h * p3.x;
+    let d = p1.y - p0.y + g * p1.y;
+    let e = p3.y - p0.y + h * p3.y;
+
+    let scale = 1.0 / EXTENT;
+
+    #[rustfmt::skip]
+    let transform = Matrix4::new(
+        a * scale, d * scale, 0.0, g * scale,
+        b * scale, e * scale, 0.0, h * scale,
+        0.0, 0.0, 1.0, 0.0,
+        p0.x, p0.y, 0.0, 1.0,
+    );
+
+    transform
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector4;
+
+    use super::*;
+
+    fn project(transform: Matrix4<f64>, x: f64, y: f64) -> (f64, f64) {
+        let projected = transform * Vector4::new(x * EXTENT, y * EXTENT, 0.0, 1.0);
+        (projected.x / projected.w, projected.y / projected.w)
+    }
+
+    #[test]
+    fn test_image_transform_maps_corners() {
+        for corners in [
+            // rectangle
+            [(10.0, 20.0), (110.0, 20.0), (110.0, 70.0), (10.0, 70.0)],
+            // arbitrary quad
+            [(10.0, 20.0), (150.0, 5.0), (120.0, 90.0), (-20.0, 60.0)],
+        ] {
+            let transform = image_transform(corners.map(|(x, y)| WorldCoords::at_ground(x, y)));
+
+            for ((x, y), expected) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+                .into_iter()
+                .zip(corners)
+            {
+                let (px, py) = project(transform, x, y);
+                assert!((px - expected.0).abs() < 1e-9, "{px} != {}", expected.0);
+                assert!((py - expected.1).abs() < 1e-9, "{py} != {}", expected.1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_frame_updates() {
+        let style: Style = serde_json::from_str(
+            r#"{
+              "version": 8,
+              "name": "image",
+              "metadata": {},
+              "sources": {
+                "radar": {
+                  "type": "canvas",
+                  "coordinates": [[-10.0, 10.0], [10.0, 10.0], [10.0, -10.0], [-10.0, -10.0]]
+                }
+              },
+              "layers": []
+            }"#,
+        )
+        .unwrap();
+
+        let mut sources = ImageSources::default();
+        sources.set_frame("radar", RgbaImage::new(1, 1));
+        sources.set_frame("unknown", RgbaImage::new(1, 1));
+
+        sources.sync(&style);
+        assert!(!sources.contains("unknown"));
+        // The frame is kept until the source is added to the style
+        assert!(sources.frame("unknown").is_some());
+        sources.set_frame("radar", RgbaImage::new(2, 2));
+
+        let (frame, revision) = sources.frame("radar").unwrap();
+        assert_eq!(frame.dimensions(), (2, 2));
+        assert_eq!(revision, 2);
+        let [top_left, ..] = sources.corners("radar", &style).unwrap();
+        assert_eq!((top_left.latitude, top_left.longitude), (10.0, -10.0));
+
+        sources.sync(&Style {
+            sources: Default::default(),
+            ..style
+        });
+        assert!(!sources.contains("radar"));
+        assert!(sources.frame("radar").is_none());
+    }
+
+    #[test]
+    fn test_mark_requested() {
+        let style: Style = serde_json::from_str(
+            r#"{
+              "version": 8,
+              "name": "image",
+              "metadata": {},
+              "sources": {
+                "photo": {
+                  "type": "image",
+                  "url": "https://example.com/a.png",
+                  "coordinates": [[-10.0, 10.0], [10.0, 10.0], [10.0, -10.0], [-10.0, -10.0]]
+                }
+              },
+              "layers": []
+            }"#,
+        )
+        .unwrap();
+
+        let mut sources = ImageSources::default();
+        sources.sync(&style);
+        assert!(!sources.mark_requested("photo", "https://example.com/a.png"));
+        assert!(sources.mark_requested("photo", "https://example.com/a.png"));
+
+        // Failed requests are retried after a while
+        sources.request_failed("photo");
+        assert!(sources.mark_requested("photo", "https://example.com/a.png"));
+
+        assert!(!sources.mark_requested("photo", "https://example.com/b.png"));
+    }
+}
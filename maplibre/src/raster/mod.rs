@@ -8,21 +8,32 @@ use crate::{
     kernel::Kernel,
     plugin::Plugin,
     raster::{
-        populate_world_system::PopulateWorldSystem, queue_system::queue_system,
-        request_system::RequestSystem, resource::RasterResources, resource_system::resource_system,
+        image_source::ImageSources,
+        populate_world_system::PopulateWorldSystem,
+        queue_system::queue_system,
+        request_system::RequestSystem,
+        resource::{HillshadeResources, ImageResources, RasterResources},
+        resource_system::resource_system,
         upload_system::upload_system,
     },
-    render::{eventually::Eventually, tile_view_pattern::ViewTileSources, RenderStageLabel},
+    render::{
+        eventually::{Eventually, GpuResources},
+        tile_view_pattern::ViewTileSources,
+        RenderStageLabel,
+    },
     schedule::Schedule,
+    style::mutation::StyleChange,
     tcs::{system::SystemContainer, tiles::TileComponent, world::World},
 };
 
+pub mod dem;
+pub mod image_source;
 mod populate_world_system;
 mod process_raster;
 mod queue_system;
 mod render_commands;
 mod request_system;
-mod resource;
+pub(crate) mod resource;
 mod resource_system;
 mod transferables;
 mod upload_system;
@@ -52,6 +63,21 @@ impl<E: Environment, T: RasterTransferables> Plugin<E> for RasterPlugin<T> {
         world
             .resources
             .insert(Eventually::<RasterResources>::Uninitialized);
+        world
+            .resources
+            .insert(Eventually::<HillshadeResources>::Uninitialized);
+        world
+            .resources
+            .insert(Eventually::<ImageResources>::Uninitialized);
+        world.resources.init::<ImageSources>();
+        // Records which sources have to be fetched again, shared with the vector plugin
+        world.resources.get_or_init_mut::<StyleChange>();
+        world
+            .resources
+            .get_or_init_mut::<GpuResources>()
+            .add::<RasterResources>()
+            .add::<HillshadeResources>()
+            .add::<ImageResources>();
 
         world
             .resources
@@ -91,6 +117,15 @@ pub enum RasterLayerData {
 #[derive(Default)]
 pub struct RasterLayersDataComponent {
     pub layers: Vec<RasterLayerData>,
+    /// The number of layers which have been requested for the tile.
+    pub requested_layers: usize,
+}
+
+impl RasterLayersDataComponent {
+    /// Whether every requested layer has been loaded or could not be loaded.
+    pub fn is_loaded(&self) -> bool {
+        self.layers.len() >= self.requested_layers
+    }
 }
 
 impl TileComponent for RasterLayersDataComponent {}
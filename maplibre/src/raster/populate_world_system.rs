@@ -3,12 +3,16 @@ use std::{borrow::Cow, marker::PhantomData, rc::Rc};
 use crate::{
     context::MapContext,
     environment::Environment,
+    events::{self, MapEvent, SourceDataEvent, TileStatus},
     io::apc::{AsyncProcedureCall, Message},
     kernel::Kernel,
     raster::{
+        image_source::ImageSources,
         transferables::{LayerRaster, LayerRasterMissing, RasterTransferables},
         RasterLayerData, RasterLayersDataComponent,
     },
+    repaint,
+    style::source::Source,
     tcs::system::System,
 };
 
@@ -31,36 +35,66 @@ impl<E: Environment, T: RasterTransferables> System for PopulateWorldSystem<E, T
         "populate_world_system".into()
     }
 
-    fn run(&mut self, MapContext { world, .. }: &mut MapContext) {
+    fn run(&mut self, MapContext { world, style, .. }: &mut MapContext) {
         for message in self.kernel.apc().receive(|message| {
             message.has_tag(T::LayerRaster::message_tag())
                 || message.has_tag(T::LayerRasterMissing::message_tag())
         }) {
             let message: Message = message;
+            // The data of a tile changed, which becomes visible once it has been uploaded
+            repaint::request_repaint(world);
             if message.has_tag(T::LayerRaster::message_tag()) {
-                let message = message.into_transferable::<T::LayerRaster>();
+                let layer = message.into_transferable::<T::LayerRaster>().to_layer();
+                let event = SourceDataEvent {
+                    coords: layer.coords,
+                    source_layer: Some(layer.source_layer.clone()),
+                    status: TileStatus::Loaded,
+                };
+
+                // Images of image sources are stored under the id of their source
+                if let Some(Source::Image(_)) = style.sources.get(&layer.source_layer) {
+                    if let Some(image_sources) = world.resources.get_mut::<ImageSources>() {
+                        image_sources.set_frame(&layer.source_layer, layer.image);
+                    }
+                    events::emit(world, MapEvent::SourceData(event));
+                    continue;
+                }
+
                 let Some(component) = world
                     .tiles
-                    .query_mut::<&mut RasterLayersDataComponent>(message.coords())
+                    .query_mut::<&mut RasterLayersDataComponent>(layer.coords)
                 else {
                     continue;
                 };
 
-                component
-                    .layers
-                    .push(RasterLayerData::Available(message.to_layer()));
-            } else if message.has_tag(T::LayerRaster::message_tag()) {
+                component.layers.push(RasterLayerData::Available(layer));
+                events::emit(world, MapEvent::SourceData(event));
+            } else if message.has_tag(T::LayerRasterMissing::message_tag()) {
                 let message = message.into_transferable::<T::LayerRasterMissing>();
+                let layer = message.to_layer();
+                let event = SourceDataEvent {
+                    coords: layer.coords,
+                    source_layer: Some(layer.source_layer.clone()),
+                    status: TileStatus::Missing,
+                };
+
+                if let Some(Source::Image(_)) = style.sources.get(&layer.source_layer) {
+                    if let Some(image_sources) = world.resources.get_mut::<ImageSources>() {
+                        image_sources.request_failed(&layer.source_layer);
+                    }
+                    events::emit(world, MapEvent::SourceData(event));
+                    continue;
+                }
+
                 let Some(component) = world
                     .tiles
-                    .query_mut::<&mut RasterLayersDataComponent>(message.coords())
+                    .query_mut::<&mut RasterLayersDataComponent>(layer.coords)
                 else {
                     continue;
                 };
 
-                component
-                    .layers
-                    .push(RasterLayerData::Missing(message.to_layer()));
+                component.layers.push(RasterLayerData::Missing(layer));
+                events::emit(world, MapEvent::SourceData(event));
             }
         }
     }
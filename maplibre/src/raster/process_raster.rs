@@ -6,7 +6,7 @@ use thiserror::Error;
 use crate::{
     coords::WorldTileCoords,
     io::apc::Context,
-    raster::transferables::{LayerRaster, RasterTransferables},
+    raster::transferables::{LayerRaster, LayerRasterMissing, RasterTransferables},
 };
 
 #[derive(Error, Debug)]
@@ -14,10 +14,16 @@ pub enum ProcessRasterError {
     /// Error during processing of the pipeline
     #[error("processing data in pipeline failed")]
     Processing(Box<dyn std::error::Error>),
+    /// The fetched data is not an image, e.g. an error page
+    #[error("decoding image failed")]
+    Decoding(#[source] image::ImageError),
 }
 
 pub struct RasterTileRequest {
     pub coords: WorldTileCoords,
+    /// Name under which the decoded image is stored. For raster-dem tiles this is the id of the
+    /// source.
+    pub layer_name: String,
 }
 
 pub fn process_raster_tile<T: RasterTransferables, C: Context>(
@@ -26,10 +32,17 @@ pub fn process_raster_tile<T: RasterTransferables, C: Context>(
     context: &mut ProcessRasterContext<T, C>,
 ) -> Result<(), ProcessRasterError> {
     let coords = &tile_request.coords;
-    let img = image::load_from_memory(data).unwrap();
+    let img = match image::load_from_memory(data) {
+        Ok(img) => img,
+        Err(e) => {
+            // The layer is missing just like if fetching it failed
+            context.layer_raster_missing(coords, tile_request.layer_name)?;
+            return Err(ProcessRasterError::Decoding(e));
+        }
+    };
     let rgba = img.to_rgba8();
 
-    context.layer_raster_finished(coords, "raster".to_string(), rgba)?;
+    context.layer_raster_finished(coords, tile_request.layer_name, rgba)?;
 
     Ok(())
 }
@@ -58,6 +71,16 @@ impl<T: RasterTransferables, C: Context> ProcessRasterContext<T, C> {
             .send_back(T::LayerRaster::build_from(*coords, layer_name, image_data))
             .map_err(|e| ProcessRasterError::Processing(Box::new(e)))
     }
+
+    fn layer_raster_missing(
+        &mut self,
+        coords: &WorldTileCoords,
+        layer_name: String,
+    ) -> Result<(), ProcessRasterError> {
+        self.context
+            .send_back(T::LayerRasterMissing::build_from(*coords, layer_name))
+            .map_err(|e| ProcessRasterError::Processing(Box::new(e)))
+    }
 }
 
 #[cfg(test)]
@@ -67,7 +90,7 @@ mod tests {
         coords::ZoomLevel,
         io::apc::tests::DummyContext,
         raster::{
-            process_raster::{ProcessRasterContext, RasterTileRequest},
+            process_raster::{ProcessRasterContext, ProcessRasterError, RasterTileRequest},
             DefaultRasterTransferables,
         },
     };
@@ -79,8 +102,22 @@ mod tests {
             &[0],
             RasterTileRequest {
                 coords: (0, 0, ZoomLevel::default()).into(),
+                layer_name: "raster".to_string(),
+            },
+            &mut ProcessRasterContext::<DefaultRasterTransferables, _>::new(DummyContext),
+        );
+    }
+
+    #[test]
+    fn test_not_an_image() {
+        let result = process_raster_tile(
+            b"<html>Not Found</html>",
+            RasterTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layer_name: "raster".to_string(),
             },
             &mut ProcessRasterContext::<DefaultRasterTransferables, _>::new(DummyContext),
         );
+        assert!(matches!(result, Err(ProcessRasterError::Decoding(_))));
     }
 }
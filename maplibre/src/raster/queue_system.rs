@@ -2,17 +2,26 @@
 
 use crate::{
     context::MapContext,
-    raster::render_commands::DrawRasterTiles,
+    coords::WorldTileCoords,
+    raster::render_commands::{DrawHillshadeTiles, DrawImages, DrawRasterTiles},
     render::{
         eventually::{Eventually, Eventually::Initialized},
         render_commands::DrawMasks,
         render_phase::{DrawState, LayerItem, RenderPhase, TileMaskItem},
-        tile_view_pattern::WgpuTileViewPattern,
+        tile_view_pattern::{TileShape, WgpuTileViewPattern},
     },
+    style::layer::LayerPaint,
     tcs::tiles::Tile,
 };
 
-pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
+pub fn queue_system(
+    MapContext {
+        world,
+        style,
+        view_state,
+        ..
+    }: &mut MapContext,
+) {
     let Some((Initialized(tile_view_pattern),)) = world
         .resources
         .query::<(&Eventually<WgpuTileViewPattern>,)>()
@@ -20,7 +29,57 @@ pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
         return;
     };
 
+    let zoom = f64::from(view_state.zoom());
+    let hillshade_layers = style
+        .layers
+        .iter()
+        .filter(|layer| layer.is_visible_at(zoom))
+        .filter(|layer| matches!(layer.paint, Some(LayerPaint::Hillshade(_))))
+        .collect::<Vec<_>>();
+
+    // Raster layers of tiled sources
+    let raster_layers = style
+        .layers
+        .iter()
+        .filter(|layer| layer.is_visible_at(zoom))
+        .filter(|layer| matches!(layer.paint, Some(LayerPaint::Raster(_))))
+        .filter(|layer| {
+            !layer
+                .source
+                .as_ref()
+                .and_then(|source| style.sources.get(source))
+                .is_some_and(|source| source.corners().is_some())
+        })
+        .collect::<Vec<_>>();
+
     let mut items = Vec::new();
+    let mut masks = Vec::new();
+    let mut hillshade_items = Vec::new();
+
+    // Images are not split into tiles, so each layer is drawn once
+    let image_items = style
+        .layers
+        .iter()
+        .filter(|layer| layer.is_visible_at(zoom))
+        .filter(|layer| matches!(layer.paint, Some(LayerPaint::Raster(_))))
+        .filter(|layer| {
+            layer
+                .source
+                .as_ref()
+                .and_then(|source| style.sources.get(source))
+                .is_some_and(|source| source.corners().is_some())
+        })
+        .map(|layer| {
+            let coords = WorldTileCoords::default();
+            LayerItem {
+                draw_function: Box::new(DrawState::<LayerItem, DrawImages>::new()),
+                index: layer.index,
+                style_layer: layer.id.clone(),
+                tile: Tile { coords },
+                source_shape: TileShape::new(coords, view_state.zoom()),
+            }
+        })
+        .collect::<Vec<_>>();
 
     for view_tile in tile_view_pattern.iter() {
         let coords = &view_tile.coords();
@@ -28,23 +87,36 @@ pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
 
         // draw tile normal or the source e.g. parent or children
         view_tile.render(|source_shape| {
+            // FIXME tsc: Tile masks are currently drawn twice by each plugin
+            masks.push(TileMaskItem {
+                draw_function: Box::new(DrawState::<TileMaskItem, DrawMasks>::new()),
+                source_shape: source_shape.clone(),
+            });
+
             // FIXME if raster_resources.has_tile(source_shape.coords(), world) {
-            items.push((
-                LayerItem {
+            for style_layer in &raster_layers {
+                items.push(LayerItem {
                     draw_function: Box::new(DrawState::<LayerItem, DrawRasterTiles>::new()),
-                    index: 0,
-                    style_layer: "raster".to_string(),
+                    index: style_layer.index,
+                    style_layer: style_layer.id.clone(),
                     tile: Tile {
                         coords: source_shape.coords(),
                     },
                     source_shape: source_shape.clone(),
-                },
-                // FIXME tsc: Tile masks are currently drawn twice by each plugin
-                TileMaskItem {
-                    draw_function: Box::new(DrawState::<TileMaskItem, DrawMasks>::new()),
+                });
+            }
+
+            for style_layer in &hillshade_layers {
+                hillshade_items.push(LayerItem {
+                    draw_function: Box::new(DrawState::<LayerItem, DrawHillshadeTiles>::new()),
+                    index: style_layer.index,
+                    style_layer: style_layer.id.clone(),
+                    tile: Tile {
+                        coords: source_shape.coords(),
+                    },
                     source_shape: source_shape.clone(),
-                },
-            ));
+                });
+            }
         });
     }
 
@@ -55,8 +127,19 @@ pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
         return;
     };
 
-    for (layer, mask) in items {
+    for layer in items {
         layer_item_phase.add(layer);
+    }
+
+    for mask in masks {
         tile_mask_phase.add(mask);
     }
+
+    for layer in hillshade_items {
+        layer_item_phase.add(layer);
+    }
+
+    for layer in image_items {
+        layer_item_phase.add(layer);
+    }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    raster::resource::RasterResources,
+    raster::resource::{HillshadeResources, ImageResources, RasterResources},
     render::{
         eventually::{Eventually, Eventually::Initialized},
         render_phase::{LayerItem, PhaseItem, RenderCommand, RenderCommandResult},
@@ -56,12 +56,19 @@ impl RenderCommand<LayerItem> for DrawRasterTile {
         item: &LayerItem,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        let Some(Initialized(tile_view_pattern)) =
-            world.resources.get::<Eventually<WgpuTileViewPattern>>()
+        let Some((Initialized(tile_view_pattern), Initialized(raster_resources))) =
+            world.resources.query::<(
+                &Eventually<WgpuTileViewPattern>,
+                &Eventually<RasterResources>,
+            )>()
         else {
             return RenderCommandResult::Failure;
         };
 
+        let Some(layer_metadata) = raster_resources.get_layer_metadata(&item.style_layer) else {
+            return RenderCommandResult::Failure;
+        };
+
         let source_shape = &item.source_shape;
 
         let reference = source_shape.coords().stencil_reference_value_3d() as u32;
@@ -76,15 +83,7 @@ impl RenderCommand<LayerItem> for DrawRasterTile {
             tile_view_pattern.buffer().slice(tile_view_pattern_buffer),
         );
 
-        let tile_view_pattern_buffer = source_shape
-            .buffer_range()
-            .expect("tile_view_pattern needs to be uploaded first"); // FIXME tcs
-
-        // FIXME tcs: I passin random data here right now, but instead we need the correct metadata here
-        pass.set_vertex_buffer(
-            1,
-            tile_view_pattern.buffer().slice(tile_view_pattern_buffer),
-        );
+        pass.set_vertex_buffer(1, layer_metadata.slice(..));
 
         const TILE_MASK_SHADER_VERTICES: u32 = 6;
         pass.draw(0..TILE_MASK_SHADER_VERTICES, 0..1);
@@ -98,3 +97,103 @@ pub type DrawRasterTiles = (
     SetRasterViewBindGroup<0>,
     DrawRasterTile,
 );
+
+pub struct SetHillshadeTilePipeline;
+impl<P: PhaseItem> RenderCommand<P> for SetHillshadeTilePipeline {
+    fn render<'w>(
+        world: &'w World,
+        _item: &P,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(Initialized(hillshade_resources)) =
+            world.resources.get::<Eventually<HillshadeResources>>()
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_render_pipeline(hillshade_resources.pipeline());
+        RenderCommandResult::Success
+    }
+}
+
+pub struct SetHillshadeBindGroups;
+impl RenderCommand<LayerItem> for SetHillshadeBindGroups {
+    fn render<'w>(
+        world: &'w World,
+        item: &LayerItem,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(Initialized(hillshade_resources)) =
+            world.resources.get::<Eventually<HillshadeResources>>()
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        let Some((tile_bind_group, layer_bind_group)) =
+            hillshade_resources.get_bind_groups(&item.style_layer, item.tile.coords)
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(0, tile_bind_group, &[]);
+        pass.set_bind_group(1, layer_bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+pub type DrawHillshadeTiles = (
+    SetHillshadeTilePipeline,
+    SetHillshadeBindGroups,
+    DrawRasterTile,
+);
+
+pub struct SetImagePipeline;
+impl<P: PhaseItem> RenderCommand<P> for SetImagePipeline {
+    fn render<'w>(
+        world: &'w World,
+        _item: &P,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(Initialized(image_resources)) =
+            world.resources.get::<Eventually<ImageResources>>()
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_render_pipeline(image_resources.pipeline());
+        RenderCommandResult::Success
+    }
+}
+
+pub struct DrawImage;
+impl RenderCommand<LayerItem> for DrawImage {
+    fn render<'w>(
+        world: &'w World,
+        item: &LayerItem,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(Initialized(image_resources)) =
+            world.resources.get::<Eventually<ImageResources>>()
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        // The image is not available until its first frame has been uploaded
+        let Some((bind_group, tile_metadata, layer_metadata)) =
+            image_resources.get_for_layer(&item.style_layer)
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_vertex_buffer(0, tile_metadata.slice(..));
+        pass.set_vertex_buffer(1, layer_metadata.slice(..));
+
+        const IMAGE_SHADER_VERTICES: u32 = 6;
+        pass.draw(0..IMAGE_SHADER_VERTICES, 0..1);
+
+        RenderCommandResult::Success
+    }
+}
+
+pub type DrawImages = (SetImagePipeline, DrawImage);
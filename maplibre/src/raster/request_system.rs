@@ -1,23 +1,38 @@
 //! Requests tiles which are currently in view
 
-use std::{borrow::Cow, collections::HashSet, marker::PhantomData, rc::Rc};
+use std::{borrow::Cow, collections::HashMap, marker::PhantomData, rc::Rc};
 
 use crate::{
     context::MapContext,
+    coords::WorldTileCoords,
     environment::{Environment, OffscreenKernel},
+    events::{self, MapEvent},
     io::{
         apc::{AsyncProcedureCall, AsyncProcedureFuture, Context, Input, ProcedureError},
-        source_type::{RasterSource, SourceType},
+        source_type::{RasterSource, SourceType, UrlTemplateSource},
     },
     kernel::Kernel,
     raster::{
-        process_raster::{process_raster_tile, ProcessRasterContext, RasterTileRequest},
+        image_source::ImageSources,
+        process_raster::{
+            process_raster_tile, ProcessRasterContext, ProcessRasterError, RasterTileRequest,
+        },
+        resource::{HillshadeResources, RasterResources},
         transferables::{LayerRasterMissing, RasterTransferables},
         RasterLayersDataComponent,
     },
-    render::tile_view_pattern::DEFAULT_TILE_SIZE,
-    style::layer::LayerPaint,
-    tcs::system::System,
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        tile_view_pattern::DEFAULT_TILE_SIZE,
+        view_state::ViewState,
+    },
+    style::{
+        layer::LayerPaint,
+        mutation::StyleChange,
+        source::{ImageSource, Source, TileAddressingScheme},
+        Style,
+    },
+    tcs::{system::System, world::World},
 };
 
 pub struct RequestSystem<E: Environment, T: RasterTransferables> {
@@ -51,11 +66,18 @@ impl<E: Environment, T: RasterTransferables> System for RequestSystem<E, T> {
         let view_region =
             view_state.create_view_region(view_state.zoom().zoom_level(DEFAULT_TILE_SIZE));
 
-        if view_state.did_camera_change() || view_state.did_zoom_change() {
+        let reloaded_sources = world
+            .resources
+            .get_mut::<StyleChange>()
+            .map(|style_change| std::mem::take(&mut style_change.reloaded_sources))
+            .unwrap_or_default();
+        let reload = reload_sources(&reloaded_sources, style, view_state, world);
+
+        if reload || view_state.did_camera_change() || view_state.did_zoom_change() {
             if let Some(view_region) = &view_region {
                 // TODO: We also need to request tiles from layers above if we are over the maximum zoom level
 
-                for coords in view_region.iter() {
+                for coords in view_region.iter_wrapped() {
                     if coords.build_quad_key().is_none() {
                         continue;
                     }
@@ -73,34 +95,193 @@ impl<E: Environment, T: RasterTransferables> System for RequestSystem<E, T> {
                         .tiles
                         .spawn_mut(coords)
                         .unwrap()
-                        .insert(RasterLayersDataComponent::default());
+                        .insert(RasterLayersDataComponent {
+                            requested_layers: requested_layers(style),
+                            ..Default::default()
+                        });
 
                     tracing::event!(tracing::Level::ERROR, %coords, "tile request started: {coords}");
                     log::info!("tile request started: {coords}");
 
-                    self.kernel
-                        .apc()
-                        .call(
-                            Input::TileRequest {
-                                coords,
-                                style: style.clone(), // TODO: Avoid cloning whole style
-                            },
-                            fetch_raster_apc::<
+                    if let Err(error) = self.kernel.apc().call(
+                        Input::TileRequest {
+                            coords,
+                            style: style.clone(), // TODO: Avoid cloning whole style
+                        },
+                        fetch_raster_apc::<
+                            E::OffscreenKernelEnvironment,
+                            T,
+                            <E::AsyncProcedureCall as AsyncProcedureCall<
                                 E::OffscreenKernelEnvironment,
-                                T,
-                                <E::AsyncProcedureCall as AsyncProcedureCall<
-                                    E::OffscreenKernelEnvironment,
-                                >>::Context,
-                            >,
-                        )
-                        .unwrap(); // TODO: Remove unwrap
+                            >>::Context,
+                        >,
+                    ) {
+                        let message = format!("requesting tile {coords} failed: {error}");
+                        log::error!("{message}");
+                        events::emit(world, MapEvent::Error(message));
+                    }
                 }
             }
         }
 
+        self.request_images(style, world);
+
         view_state.update_references();
     }
 }
+
+/// Drops the tiles which have been fetched from the raster and raster-dem sources among
+/// `source_ids`. Returns whether the tiles have to be requested again.
+fn reload_sources(
+    source_ids: &[String],
+    style: &Style,
+    view_state: &mut ViewState,
+    world: &mut World,
+) -> bool {
+    let tiled_sources = source_ids
+        .iter()
+        .filter(|source_id| {
+            matches!(
+                style.sources.get(source_id.as_str()),
+                Some(Source::Raster(_) | Source::RasterDem(_))
+            )
+        })
+        .collect::<Vec<_>>();
+    if tiled_sources.is_empty() {
+        return false;
+    }
+
+    // The data of all raster and raster-dem sources is stored per tile
+    world.tiles.remove_components::<RasterLayersDataComponent>();
+    if let Some(Initialized(raster_resources)) =
+        world.resources.get_mut::<Eventually<RasterResources>>()
+    {
+        raster_resources.clear_bound_textures();
+    }
+    if let Some(Initialized(hillshade_resources)) =
+        world.resources.get_mut::<Eventually<HillshadeResources>>()
+    {
+        for source_id in &tiled_sources {
+            hillshade_resources.remove_source(source_id);
+        }
+    }
+    // The terrain is created again from the new tiles
+    if view_state
+        .elevation()
+        .is_some_and(|elevation| tiled_sources.iter().any(|id| *id == elevation.source()))
+    {
+        view_state.set_elevation(None);
+    }
+    true
+}
+
+impl<E: Environment, T: RasterTransferables> RequestSystem<E, T> {
+    /// Requests the images of image sources once.
+    fn request_images(&self, style: &Style, world: &mut World) {
+        let Some(image_sources) = world.resources.get_mut::<ImageSources>() else {
+            return;
+        };
+
+        image_sources.sync(style);
+
+        let mut errors = Vec::new();
+        for (source_id, source) in &style.sources {
+            let Source::Image(ImageSource { url: Some(url), .. }) = source else {
+                continue;
+            };
+
+            if image_sources.mark_requested(source_id, url) {
+                continue;
+            }
+
+            log::info!("image request started: {source_id}");
+
+            if let Err(error) =
+                self.kernel.apc().call(
+                    Input::ImageRequest {
+                        source_id: source_id.clone(),
+                        url: url.clone(),
+                    },
+                    fetch_image_apc::<
+                        E::OffscreenKernelEnvironment,
+                        T,
+                        <E::AsyncProcedureCall as AsyncProcedureCall<
+                            E::OffscreenKernelEnvironment,
+                        >>::Context,
+                    >,
+                )
+            {
+                let message = format!("requesting image {source_id} failed: {error}");
+                log::error!("{message}");
+                errors.push(message);
+            }
+        }
+
+        for message in errors {
+            events::emit(world, MapEvent::Error(message));
+        }
+    }
+}
+
+/// Fetches the image of an image source. The decoded image, or the missing layer if fetching
+/// failed, is sent back under the id of the source.
+pub fn fetch_image_apc<K: OffscreenKernel, T: RasterTransferables, C: Context + Clone + Send>(
+    input: Input,
+    context: C,
+    kernel: K,
+) -> AsyncProcedureFuture {
+    Box::pin(async move {
+        let Input::ImageRequest { source_id, url } = input else {
+            return Err(ProcedureError::IncompatibleInput);
+        };
+
+        // The url does not contain placeholders, so the coordinates are irrelevant
+        let coords = WorldTileCoords::default();
+        let source =
+            SourceType::UrlTemplate(UrlTemplateSource::new(&url, TileAddressingScheme::XYZ));
+
+        match kernel.source_client().fetch(&coords, &source).await {
+            Ok(data) => {
+                let mut process_context = ProcessRasterContext::<T, C>::new(context);
+
+                process_raster_tile(
+                    &data,
+                    RasterTileRequest {
+                        coords,
+                        layer_name: source_id,
+                    },
+                    &mut process_context,
+                )
+                .or_else(ignore_decoding_error)?;
+            }
+            Err(e) => {
+                log::error!("{e:?}");
+
+                // Lets the map request the image again
+                context
+                    .send_back(<T as RasterTransferables>::LayerRasterMissing::build_from(
+                        coords, source_id,
+                    ))
+                    .map_err(ProcedureError::Send)?;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Images which can not be decoded have been sent back as missing layers, so they do not fail
+/// the procedure.
+fn ignore_decoding_error(error: ProcessRasterError) -> Result<(), ProcedureError> {
+    match error {
+        ProcessRasterError::Decoding(e) => {
+            log::error!("{e:?}");
+            Ok(())
+        }
+        e => Err(ProcedureError::Execution(Box::new(e))),
+    }
+}
+
 pub fn fetch_raster_apc<K: OffscreenKernel, T: RasterTransferables, C: Context + Clone + Send>(
     input: Input,
     context: C,
@@ -111,23 +292,43 @@ pub fn fetch_raster_apc<K: OffscreenKernel, T: RasterTransferables, C: Context +
             return Err(ProcedureError::IncompatibleInput);
         };
 
-        let raster_layers: HashSet<String> = style
-            .layers
-            .iter()
-            .filter_map(|layer| {
-                if matches!(layer.paint, Some(LayerPaint::Raster(_))) {
-                    layer.source_layer.clone()
-                } else {
-                    None
+        let client = kernel.source_client();
+
+        for (source_id, source) in dem_sources(&style) {
+            let source = SourceType::UrlTemplate(source);
+
+            match client.fetch(&coords, &source).await {
+                Ok(data) => {
+                    let data = data.into_boxed_slice();
+
+                    let mut process_context = ProcessRasterContext::<T, C>::new(context.clone());
+
+                    process_raster_tile(
+                        &data,
+                        RasterTileRequest {
+                            coords,
+                            layer_name: source_id,
+                        },
+                        &mut process_context,
+                    )
+                    .or_else(ignore_decoding_error)?;
                 }
-            })
-            .collect();
+                Err(e) => {
+                    log::error!("{e:?}");
 
-        let client = kernel.source_client();
+                    context
+                        .send_back(<T as RasterTransferables>::LayerRasterMissing::build_from(
+                            coords, source_id,
+                        ))
+                        .map_err(ProcedureError::Send)?;
+                }
+            }
+        }
 
-        if !raster_layers.is_empty() {
+        if has_raster_layers(&style) {
             let context = context.clone();
-            let source = SourceType::Raster(RasterSource::default());
+            let source = raster_tile_source(&style)
+                .unwrap_or_else(|| SourceType::Raster(RasterSource::default()));
 
             match client.fetch(&coords, &source).await {
                 Ok(data) => {
@@ -135,8 +336,15 @@ pub fn fetch_raster_apc<K: OffscreenKernel, T: RasterTransferables, C: Context +
 
                     let mut process_context = ProcessRasterContext::<T, C>::new(context);
 
-                    process_raster_tile(&data, RasterTileRequest { coords }, &mut process_context)
-                        .map_err(|e| ProcedureError::Execution(Box::new(e)))?;
+                    process_raster_tile(
+                        &data,
+                        RasterTileRequest {
+                            coords,
+                            layer_name: "raster".to_string(),
+                        },
+                        &mut process_context,
+                    )
+                    .or_else(ignore_decoding_error)?;
                 }
                 Err(e) => {
                     log::error!("{e:?}");
@@ -144,6 +352,7 @@ pub fn fetch_raster_apc<K: OffscreenKernel, T: RasterTransferables, C: Context +
                     context
                         .send_back(<T as RasterTransferables>::LayerRasterMissing::build_from(
                             coords,
+                            "raster".to_string(),
                         ))
                         .map_err(ProcedureError::Send)?;
                 }
@@ -153,3 +362,55 @@ pub fn fetch_raster_apc<K: OffscreenKernel, T: RasterTransferables, C: Context +
         Ok(())
     })
 }
+
+/// Returns the raster-dem sources which are used by a hillshade layer or the terrain. Elevation is
+/// fetched once per source.
+fn dem_sources(style: &Style) -> HashMap<String, UrlTemplateSource> {
+    style
+        .layers
+        .iter()
+        .filter(|layer| matches!(layer.paint, Some(LayerPaint::Hillshade(_))))
+        .filter_map(|layer| layer.source.as_ref())
+        .chain(style.terrain.as_ref().map(|terrain| &terrain.source))
+        .filter_map(|source_id| match style.sources.get(source_id) {
+            Some(Source::RasterDem(source)) => match source.tiles.as_deref() {
+                Some(tiles) => Some((
+                    source_id.clone(),
+                    UrlTemplateSource::new(tiles, TileAddressingScheme::XYZ),
+                )),
+                None => {
+                    log::error!("raster-dem source {source_id} defines no tiles");
+                    None
+                }
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether the style contains raster layers whose tiles are fetched.
+fn has_raster_layers(style: &Style) -> bool {
+    style.layers.iter().any(|layer| {
+        matches!(layer.paint, Some(LayerPaint::Raster(_))) && layer.source_layer.is_some()
+    })
+}
+
+/// Returns the number of layers which [`fetch_raster_apc`] sends back for each tile, either as
+/// available or as missing layer.
+fn requested_layers(style: &Style) -> usize {
+    dem_sources(style).len() + usize::from(has_raster_layers(style))
+}
+
+/// Returns the tiles of the source of the first raster layer, if the style defines them.
+fn raster_tile_source(style: &Style) -> Option<SourceType> {
+    let source_id = style
+        .layers
+        .iter()
+        .find(|layer| matches!(layer.paint, Some(LayerPaint::Raster(_))))?
+        .source
+        .as_ref()?;
+    match style.sources.get(source_id) {
+        Some(Source::Raster(source)) => source.tile_source(),
+        _ => None,
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    coords::WorldTileCoords,
+    raster::dem::DemData,
+    render::{
+        resource::Texture,
+        settings::Msaa,
+        shaders::{ShaderHillshadeLayer, ShaderHillshadeTile},
+    },
+};
+
+/// Elevation data of a tile which has been uploaded to the GPU.
+pub struct DemTile {
+    pub dem: DemData,
+    pub texture: Texture,
+    bind_group: wgpu::BindGroup,
+    /// Bit mask of the neighbours which have already been copied into the border of `dem`.
+    pub backfilled: u8,
+}
+
+impl DemTile {
+    /// Returns the bit of a neighbour within [`DemTile::backfilled`].
+    pub fn neighbour_bit(dx: i32, dy: i32) -> u8 {
+        let index = (dy + 1) * 3 + (dx + 1);
+        // Skip the tile itself which would be at index 4
+        1 << if index > 4 { index - 1 } else { index }
+    }
+
+    pub fn is_backfilled(&self) -> bool {
+        self.backfilled == u8::MAX
+    }
+}
+
+struct HillshadeLayerBinding {
+    source: String,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Holds the resources necessary for rendering hillshade layers:
+/// * pipeline
+/// * elevation textures and bind groups per raster-dem source and tile
+/// * uniforms per hillshade layer
+pub struct HillshadeResources {
+    pipeline: wgpu::RenderPipeline,
+    dem_tiles: HashMap<(String, WorldTileCoords), DemTile>,
+    layers: HashMap<String, HillshadeLayerBinding>,
+}
+
+impl HillshadeResources {
+    pub fn new(pipeline: wgpu::RenderPipeline) -> Self {
+        Self {
+            pipeline,
+            dem_tiles: Default::default(),
+            layers: Default::default(),
+        }
+    }
+
+    pub fn pipeline(&self) -> &wgpu::RenderPipeline {
+        &self.pipeline
+    }
+
+    pub fn get_dem_tile(&self, source: &str, coords: WorldTileCoords) -> Option<&DemTile> {
+        self.dem_tiles.get(&(source.to_string(), coords.wrap()))
+    }
+
+    /// Removes the tile temporarily, so that it can be backfilled from its neighbours.
+    pub fn take_dem_tile(&mut self, source: &str, coords: WorldTileCoords) -> Option<DemTile> {
+        self.dem_tiles.remove(&(source.to_string(), coords))
+    }
+
+    pub fn put_dem_tile(&mut self, source: &str, coords: WorldTileCoords, tile: DemTile) {
+        self.dem_tiles.insert((source.to_string(), coords), tile);
+    }
+
+    /// Removes the elevation tiles which are not contained in `tiles`, e.g. because they are no
+    /// longer in view.
+    pub fn retain_dem_tiles(&mut self, tiles: &HashSet<WorldTileCoords>) {
+        self.dem_tiles
+            .retain(|(_, coords), _| tiles.contains(coords));
+    }
+
+    /// Removes the elevation tiles of a raster-dem source, such that they are uploaded again.
+    pub fn remove_source(&mut self, source: &str) {
+        self.dem_tiles
+            .retain(|(tile_source, _), _| tile_source != source);
+    }
+
+    /// Creates a texture for the elevation data and binds it together with the tile uniform.
+    pub fn create_dem_tile(
+        &self,
+        device: &wgpu::Device,
+        dem: DemData,
+        tile: ShaderHillshadeTile,
+    ) -> DemTile {
+        let stride = dem.stride();
+        let texture = Texture::new(
+            Some("dem texture"),
+            device,
+            wgpu::TextureFormat::Rgba8Unorm,
+            stride,
+            stride,
+            Msaa { samples: 1 },
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        );
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("hillshade tile uniform buffer"),
+            size: std::mem::size_of::<ShaderHillshadeTile>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: true,
+        });
+        buffer
+            .slice(..)
+            .get_mapped_range_mut()
+            .copy_from_slice(bytemuck::bytes_of(&tile));
+        buffer.unmap();
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+            label: None,
+        });
+
+        DemTile {
+            dem,
+            texture,
+            bind_group,
+            backfilled: 0,
+        }
+    }
+
+    /// Creates or updates the uniform of a hillshade layer.
+    pub fn update_layer(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layer_id: &str,
+        source: &str,
+        uniform: ShaderHillshadeLayer,
+    ) {
+        let binding = self.layers.entry(layer_id.to_string()).or_insert_with(|| {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("hillshade layer uniform buffer"),
+                size: std::mem::size_of::<ShaderHillshadeLayer>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.pipeline.get_bind_group_layout(1),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+                label: None,
+            });
+            HillshadeLayerBinding {
+                source: source.to_string(),
+                buffer,
+                bind_group,
+            }
+        });
+
+        binding.source = source.to_string();
+        queue.write_buffer(&binding.buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    /// Returns the bind groups of the tile and the layer.
+    pub fn get_bind_groups(
+        &self,
+        layer_id: &str,
+        coords: WorldTileCoords,
+    ) -> Option<(&wgpu::BindGroup, &wgpu::BindGroup)> {
+        let layer = self.layers.get(layer_id)?;
+        let tile = self.get_dem_tile(&layer.source, coords)?;
+        Some((&tile.bind_group, &layer.bind_group))
+    }
+}
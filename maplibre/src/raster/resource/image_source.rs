@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use crate::render::{
+    resource::Texture,
+    settings::Msaa,
+    shaders::{ShaderLayerMetadata, ShaderTileMetadata},
+};
+
+/// Frame of an image source which has been uploaded to the GPU.
+struct ImageTexture {
+    texture: Texture,
+    bind_group: wgpu::BindGroup,
+    /// Revision of the frame which is stored in the texture
+    revision: u64,
+    /// Places the image in the world
+    tile_metadata: wgpu::Buffer,
+    layer_metadata: wgpu::Buffer,
+}
+
+/// Holds the resources necessary for rendering image and canvas sources:
+/// * pipeline
+/// * sampler
+/// * textures and metadata per source
+pub struct ImageResources {
+    sampler: wgpu::Sampler,
+    pipeline: wgpu::RenderPipeline,
+    images: HashMap<String, ImageTexture>,
+    /// Source ids of the style layers which render images
+    layer_sources: HashMap<String, String>,
+}
+
+impl ImageResources {
+    pub fn new(device: &wgpu::Device, pipeline: wgpu::RenderPipeline) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        Self {
+            sampler,
+            pipeline,
+            images: Default::default(),
+            layer_sources: Default::default(),
+        }
+    }
+
+    pub fn pipeline(&self) -> &wgpu::RenderPipeline {
+        &self.pipeline
+    }
+
+    /// Returns the revision of the frame which has been uploaded for a source.
+    pub fn revision(&self, id: &str) -> Option<u64> {
+        self.images.get(id).map(|image| image.revision)
+    }
+
+    /// Uploads a frame of a source. The texture is only recreated if the size of the frame changed.
+    pub fn write_frame(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: &str,
+        frame: &image::RgbaImage,
+        revision: u64,
+    ) {
+        let (width, height) = frame.dimensions();
+        let is_compatible = self.images.get(id).is_some_and(|image| {
+            image.texture.size.width == width && image.texture.size.height == height
+        });
+
+        if !is_compatible {
+            let image = self.create_image(device, width, height);
+            self.images.insert(id.to_string(), image);
+        }
+
+        let image = self.images.get_mut(id).expect("image was created");
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &image.texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            frame,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            image.texture.size,
+        );
+        image.revision = revision;
+    }
+
+    fn create_image(&self, device: &wgpu::Device, width: u32, height: u32) -> ImageTexture {
+        let texture = Texture::new(
+            Some("image source texture"),
+            device,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            width,
+            height,
+            Msaa { samples: 1 },
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label: None,
+        });
+
+        let tile_metadata = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("image source tile metadata buffer"),
+            size: std::mem::size_of::<ShaderTileMetadata>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let layer_metadata = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("image source layer metadata buffer"),
+            size: std::mem::size_of::<ShaderLayerMetadata>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        ImageTexture {
+            texture,
+            bind_group,
+            revision: 0,
+            tile_metadata,
+            layer_metadata,
+        }
+    }
+
+    /// Places the image of a source in the world.
+    pub fn write_metadata(
+        &self,
+        queue: &wgpu::Queue,
+        id: &str,
+        tile_metadata: ShaderTileMetadata,
+        layer_metadata: ShaderLayerMetadata,
+    ) {
+        if let Some(image) = self.images.get(id) {
+            queue.write_buffer(&image.tile_metadata, 0, bytemuck::bytes_of(&tile_metadata));
+            queue.write_buffer(
+                &image.layer_metadata,
+                0,
+                bytemuck::bytes_of(&layer_metadata),
+            );
+        }
+    }
+
+    /// Sets the source of each style layer which renders an image.
+    pub fn set_layer_sources(&mut self, layer_sources: HashMap<String, String>) {
+        self.layer_sources = layer_sources;
+    }
+
+    /// Returns the bind group of the texture and the metadata buffers of the source which is
+    /// rendered by a style layer.
+    pub fn get_for_layer(
+        &self,
+        style_layer: &str,
+    ) -> Option<(&wgpu::BindGroup, &wgpu::Buffer, &wgpu::Buffer)> {
+        self.layer_sources
+            .get(style_layer)
+            .and_then(|id| self.images.get(id))
+            .map(|image| {
+                (
+                    &image.bind_group,
+                    &image.tile_metadata,
+                    &image.layer_metadata,
+                )
+            })
+    }
+
+    /// Removes the textures of sources for which `f` returns `false`.
+    pub fn retain(&mut self, mut f: impl FnMut(&str) -> bool) {
+        self.images.retain(|id, _| f(id));
+    }
+}
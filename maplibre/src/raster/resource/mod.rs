@@ -1,3 +1,7 @@
+pub use hillshade::*;
+pub use image_source::*;
 pub use raster::*;
 
+mod hillshade;
+mod image_source;
 mod raster;
@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 
+use wgpu::util::DeviceExt;
+
 use crate::{
     coords::WorldTileCoords,
-    render::{resource::Texture, settings::Msaa, tile_view_pattern::HasTile},
+    render::{
+        resource::Texture, settings::Msaa, shaders::ShaderLayerMetadata, tile_view_pattern::HasTile,
+    },
     tcs::world::World,
 };
 
@@ -11,11 +15,13 @@ use crate::{
 /// * texture
 /// * pipeline
 /// * bindgroups
+/// * layer metadata of the raster and hillshade layers
 pub struct RasterResources {
     sampler: wgpu::Sampler,
     msaa: Msaa,
     pipeline: wgpu::RenderPipeline,
     bound_textures: HashMap<WorldTileCoords, wgpu::BindGroup>,
+    layer_metadata: HashMap<String, wgpu::Buffer>,
 }
 
 impl RasterResources {
@@ -34,6 +40,7 @@ impl RasterResources {
             msaa,
             pipeline,
             bound_textures: Default::default(),
+            layer_metadata: Default::default(),
         }
     }
 
@@ -50,7 +57,7 @@ impl RasterResources {
     }
 
     pub fn get_bound_texture(&self, coords: &WorldTileCoords) -> Option<&wgpu::BindGroup> {
-        self.bound_textures.get(coords)
+        self.bound_textures.get(&coords.wrap())
     }
 
     /// Creates a bind group for each fetched raster tile and store it inside a hashmap.
@@ -79,13 +86,48 @@ impl RasterResources {
         );
     }
 
+    /// Removes the textures of all tiles, such that they are uploaded again.
+    pub fn clear_bound_textures(&mut self) {
+        self.bound_textures.clear();
+    }
+
     pub fn pipeline(&self) -> &wgpu::RenderPipeline {
         &self.pipeline
     }
+
+    /// Creates or updates the metadata of a style layer which is drawn per tile.
+    pub fn write_layer_metadata(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        style_layer: &str,
+        metadata: ShaderLayerMetadata,
+    ) {
+        match self.layer_metadata.get(style_layer) {
+            Some(buffer) => queue.write_buffer(buffer, 0, bytemuck::bytes_of(&metadata)),
+            None => {
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("raster layer metadata buffer"),
+                    contents: bytemuck::bytes_of(&metadata),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                });
+                self.layer_metadata.insert(style_layer.to_string(), buffer);
+            }
+        }
+    }
+
+    pub fn get_layer_metadata(&self, style_layer: &str) -> Option<&wgpu::Buffer> {
+        self.layer_metadata.get(style_layer)
+    }
+
+    /// Removes the metadata of style layers for which `f` returns false.
+    pub fn retain_layer_metadata<F: FnMut(&str) -> bool>(&mut self, mut f: F) {
+        self.layer_metadata.retain(|style_layer, _| f(style_layer));
+    }
 }
 
 impl HasTile for RasterResources {
     fn has_tile(&self, coords: WorldTileCoords, _world: &World) -> bool {
-        self.bound_textures.contains_key(&coords)
+        self.bound_textures.contains_key(&coords.wrap())
     }
 }
@@ -1,7 +1,7 @@
 //! Prepares GPU-owned resources by initializing them if they are uninitialized or out-of-date.
 use crate::{
     context::MapContext,
-    raster::resource::RasterResources,
+    raster::resource::{HillshadeResources, ImageResources, RasterResources},
     render::{
         eventually::Eventually,
         resource::{RenderPipeline, TilePipeline},
@@ -25,9 +25,12 @@ pub fn resource_system(
         ..
     }: &mut MapContext,
 ) {
-    let Some(raster_resources) = world
-        .resources
-        .query_mut::<&mut Eventually<RasterResources>>()
+    let Some((raster_resources, hillshade_resources, image_resources)) =
+        world.resources.query_mut::<(
+            &mut Eventually<RasterResources>,
+            &mut Eventually<HillshadeResources>,
+            &mut Eventually<ImageResources>,
+        )>()
     else {
         return;
     };
@@ -56,4 +59,98 @@ pub fn resource_system(
             .initialize(device),
         )
     });
+
+    hillshade_resources.initialize(|| {
+        let shader = shaders::HillshadeShader {
+            format: surface.surface_format(),
+        };
+
+        let mut descriptor = TilePipeline::new(
+            "hillshade_pipeline".into(),
+            *settings,
+            shader.describe_vertex(),
+            shader.describe_fragment(),
+            true,
+            false,
+            false,
+            false,
+            surface.is_multisampling_supported(settings.msaa),
+            true,
+        )
+        .describe_render_pipeline();
+
+        // Hillshade is drawn on top of the layers below it, so it should neither write nor test depth
+        if let Some(depth_stencil) = &mut descriptor.depth_stencil {
+            depth_stencil.depth_write_enabled = false;
+            depth_stencil.depth_compare = wgpu::CompareFunction::Always;
+        }
+
+        descriptor.layout = Some(vec![
+            vec![
+                // elevation
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                // tile uniform
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            vec![
+                // layer uniform
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        ]);
+
+        HillshadeResources::new(descriptor.initialize(device))
+    });
+    image_resources.initialize(|| {
+        let shader = shaders::RasterTileShader {
+            format: surface.surface_format(),
+        };
+
+        let mut descriptor = TilePipeline::new(
+            "image_pipeline".into(),
+            *settings,
+            shader.describe_vertex(),
+            shader.describe_fragment(),
+            true,
+            false,
+            false,
+            false,
+            surface.is_multisampling_supported(settings.msaa),
+            true,
+        )
+        .describe_render_pipeline();
+
+        // Images span across tiles, so they are not clipped by the tile masks
+        if let Some(depth_stencil) = &mut descriptor.depth_stencil {
+            depth_stencil.stencil = wgpu::StencilState::default();
+        }
+
+        ImageResources::new(device, descriptor.initialize(device))
+    });
 }
@@ -1,17 +1,28 @@
 //! Uploads data to the GPU which is needed for rendering.
+use std::{collections::HashSet, f64::consts::PI};
+
+use cint::{Alpha, EncodedSrgb};
+
 use crate::{
     context::MapContext,
-    coords::ViewRegion,
+    coords::{ViewRegion, WorldCoords, WorldTileCoords},
     raster::{
-        resource::RasterResources, AvailableRasterLayerData, RasterLayerData,
-        RasterLayersDataComponent,
+        dem::DemData,
+        image_source::{image_transform, ImageSources},
+        resource::{DemTile, HillshadeResources, ImageResources, RasterResources},
+        AvailableRasterLayerData, RasterLayerData, RasterLayersDataComponent,
     },
     render::{
         eventually::{Eventually, Eventually::Initialized},
+        shaders::{
+            ShaderHillshadeLayer, ShaderHillshadeTile, ShaderLayerMetadata, ShaderTileMetadata,
+            Vec4f32,
+        },
         tile_view_pattern::DEFAULT_TILE_SIZE,
+        view_state::ViewState,
         Renderer,
     },
-    style::Style,
+    style::{hillshade::HillshadeLayer, layer::LayerPaint, source::Source, Style},
     tcs::tiles::Tiles,
 };
 
@@ -24,12 +35,22 @@ pub fn upload_system(
         ..
     }: &mut MapContext,
 ) {
-    let Some(Initialized(raster_resources)) = world
-        .resources
-        .query_mut::<&mut Eventually<RasterResources>>()
+    let Some((
+        Initialized(raster_resources),
+        Initialized(hillshade_resources),
+        Initialized(image_resources),
+        image_sources,
+    )) = world.resources.query_mut::<(
+        &mut Eventually<RasterResources>,
+        &mut Eventually<HillshadeResources>,
+        &mut Eventually<ImageResources>,
+        &mut ImageSources,
+    )>()
     else {
         return;
     };
+    upload_layer_metadata(raster_resources, device, queue, style);
+
     let view_region =
         view_state.create_view_region(view_state.zoom().zoom_level(DEFAULT_TILE_SIZE));
 
@@ -42,6 +63,53 @@ pub fn upload_system(
             style,
             view_region,
         );
+        upload_hillshade_layer(
+            hillshade_resources,
+            device,
+            queue,
+            &world.tiles,
+            style,
+            view_region,
+        );
+    }
+
+    upload_image_sources(
+        image_resources,
+        image_sources,
+        device,
+        queue,
+        style,
+        view_state,
+    );
+}
+
+/// Writes the metadata of the style layers which are drawn per raster tile.
+fn upload_layer_metadata(
+    raster_resources: &mut RasterResources,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    style: &Style,
+) {
+    let tiled_layers = style
+        .layers
+        .iter()
+        .filter(|layer| {
+            matches!(
+                layer.paint,
+                Some(LayerPaint::Raster(_)) | Some(LayerPaint::Hillshade(_))
+            )
+        })
+        .collect::<Vec<_>>();
+
+    raster_resources.retain_layer_metadata(|id| tiled_layers.iter().any(|layer| layer.id == id));
+
+    for layer in tiled_layers {
+        raster_resources.write_layer_metadata(
+            device,
+            queue,
+            &layer.id,
+            ShaderLayerMetadata::new(layer.index as f32),
+        );
     }
 }
 
@@ -54,7 +122,7 @@ fn upload_raster_layer(
     style: &Style,
     view_region: &ViewRegion,
 ) {
-    for coords in view_region.iter() {
+    for coords in view_region.iter_wrapped() {
         if raster_resources.get_bound_texture(&coords).is_some() {
             continue;
         }
@@ -64,7 +132,13 @@ fn upload_raster_layer(
         };
 
         for style_layer in &style.layers {
-            let style_source_layer = style_layer.source_layer.as_ref().unwrap(); // FIXME: Remove unwrap
+            if !matches!(style_layer.paint, Some(LayerPaint::Raster(_))) {
+                continue;
+            }
+
+            let Some(style_source_layer) = style_layer.source_layer.as_ref() else {
+                continue;
+            };
 
             let Some(AvailableRasterLayerData { coords, image, .. }) = raster_layers
                 .layers
@@ -109,3 +183,249 @@ fn upload_raster_layer(
         }
     }
 }
+
+#[tracing::instrument(skip_all)]
+fn upload_hillshade_layer(
+    hillshade_resources: &mut HillshadeResources,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    tiles: &Tiles,
+    style: &Style,
+    view_region: &ViewRegion,
+) {
+    hillshade_resources.retain_dem_tiles(&view_region.wrapped_with_ancestors());
+
+    for style_layer in &style.layers {
+        let Some(LayerPaint::Hillshade(paint)) = &style_layer.paint else {
+            continue;
+        };
+        let Some(source_id) = &style_layer.source else {
+            continue;
+        };
+        let Some(Source::RasterDem(source)) = style.sources.get(source_id) else {
+            continue;
+        };
+
+        hillshade_resources.update_layer(
+            device,
+            queue,
+            &style_layer.id,
+            source_id,
+            hillshade_layer_uniform(paint),
+        );
+
+        let mut created = HashSet::new();
+
+        for coords in view_region.iter_wrapped() {
+            if hillshade_resources
+                .get_dem_tile(source_id, coords)
+                .is_some()
+            {
+                continue;
+            }
+
+            let Some(raster_layers) = tiles.query::<&RasterLayersDataComponent>(coords) else {
+                continue;
+            };
+
+            // The raster-dem data is keyed by the id of its source
+            let Some(AvailableRasterLayerData { image, .. }) = raster_layers
+                .layers
+                .iter()
+                .flat_map(|data| match data {
+                    RasterLayerData::Available(data) => Some(data),
+                    RasterLayerData::Missing(_) => None,
+                })
+                .find(|layer| source_id.as_str() == layer.source_layer)
+            else {
+                continue;
+            };
+
+            let dem = match DemData::from_image(image, source.encoding) {
+                Ok(dem) => dem,
+                Err(e) => {
+                    log::error!("failed to decode raster-dem tile {coords}: {e}");
+                    continue;
+                }
+            };
+
+            let tile_uniform = hillshade_tile_uniform(coords, dem.dim());
+            let tile = hillshade_resources.create_dem_tile(device, dem, tile_uniform);
+            hillshade_resources.put_dem_tile(source_id, coords, tile);
+            created.insert(coords);
+        }
+
+        // Backfill the borders of the tiles in view from their neighbours
+        for coords in view_region.iter_wrapped() {
+            let Some(mut tile) = hillshade_resources.take_dem_tile(source_id, coords) else {
+                continue;
+            };
+
+            let mut changed = created.contains(&coords);
+
+            if !tile.is_backfilled() {
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        if dx == 0 && dy == 0 {
+                            continue;
+                        }
+
+                        let bit = DemTile::neighbour_bit(dx, dy);
+                        if tile.backfilled & bit != 0 {
+                            continue;
+                        }
+
+                        let neighbour_coords = WorldTileCoords {
+                            x: coords.x + dx,
+                            y: coords.y + dy,
+                            z: coords.z,
+                        };
+
+                        if let Some(neighbour) =
+                            hillshade_resources.get_dem_tile(source_id, neighbour_coords)
+                        {
+                            tile.dem.backfill_border(&neighbour.dem, dx, dy);
+                            tile.backfilled |= bit;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+
+            if changed {
+                let image = tile.dem.to_image();
+                let stride = tile.dem.stride();
+
+                queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        aspect: wgpu::TextureAspect::All,
+                        texture: &tile.texture.texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                    },
+                    &image,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * stride),
+                        rows_per_image: Some(stride),
+                    },
+                    tile.texture.size,
+                );
+            }
+
+            hillshade_resources.put_dem_tile(source_id, coords, tile);
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+fn upload_image_sources(
+    image_resources: &mut ImageResources,
+    image_sources: &ImageSources,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    style: &Style,
+    view_state: &ViewState,
+) {
+    image_resources.retain(|id| image_sources.contains(id));
+    image_resources.set_layer_sources(
+        style
+            .layers
+            .iter()
+            .filter(|layer| matches!(layer.paint, Some(LayerPaint::Raster(_))))
+            .filter_map(|layer| Some((layer.id.clone(), layer.source.clone()?)))
+            .filter(|(_, source)| image_sources.contains(source))
+            .collect(),
+    );
+
+    let zoom = view_state.zoom();
+    let view_proj = view_state.view_projection();
+
+    for id in style.sources.keys() {
+        let Some(corners) = image_sources.corners(id, style) else {
+            continue;
+        };
+
+        if let Some((frame, revision)) = image_sources.frame(id) {
+            if image_resources.revision(id) != Some(revision) {
+                image_resources.write_frame(device, queue, id, frame, revision);
+            }
+        }
+
+        let transform =
+            image_transform(corners.map(|corner| WorldCoords::from_lat_lon(corner, zoom)));
+
+        // The lowest layer which renders the image determines its depth
+        let z_index = style
+            .layers
+            .iter()
+            .find(|layer| layer.source.as_ref() == Some(id))
+            .map_or(0, |layer| layer.index);
+
+        image_resources.write_metadata(
+            queue,
+            id,
+            ShaderTileMetadata::new(
+                view_proj
+                    .to_model_view_projection(transform)
+                    .downcast()
+                    .into(),
+                1.0,
+            ),
+            ShaderLayerMetadata::new(z_index as f32),
+        );
+    }
+}
+
+fn hillshade_tile_uniform(coords: WorldTileCoords, dim: u32) -> ShaderHillshadeTile {
+    let z: u8 = coords.z.into();
+    let tiles = (1u64 << z) as f64;
+    let latitude = |y: f64| (PI * (1.0 - 2.0 * y / tiles)).sinh().atan().to_degrees() as f32;
+
+    ShaderHillshadeTile {
+        latrange: [latitude(coords.y as f64), latitude(coords.y as f64 + 1.0)],
+        zoom: z as f32,
+        dim: dim as f32,
+    }
+}
+
+fn hillshade_layer_uniform(paint: &HillshadeLayer) -> ShaderHillshadeLayer {
+    let defaults = HillshadeLayer::default();
+
+    // Colors are premultiplied by their alpha
+    let color = |color: &Option<csscolorparser::Color>, default: &Option<csscolorparser::Color>| {
+        let color: Alpha<EncodedSrgb<f32>> = color
+            .as_ref()
+            .or(default.as_ref())
+            .cloned()
+            .unwrap_or_default()
+            .into();
+        let [r, g, b, a]: Vec4f32 = color.into();
+        [r * a, g * a, b * a, a]
+    };
+
+    ShaderHillshadeLayer {
+        shadow_color: color(
+            &paint.hillshade_shadow_color,
+            &defaults.hillshade_shadow_color,
+        ),
+        highlight_color: color(
+            &paint.hillshade_highlight_color,
+            &defaults.hillshade_highlight_color,
+        ),
+        accent_color: color(
+            &paint.hillshade_accent_color,
+            &defaults.hillshade_accent_color,
+        ),
+        exaggeration: paint
+            .hillshade_exaggeration
+            .or(defaults.hillshade_exaggeration)
+            .unwrap_or_default(),
+        illumination_direction: paint
+            .hillshade_illumination_direction
+            .or(defaults.hillshade_illumination_direction)
+            .unwrap_or_default()
+            .to_radians(),
+        _padding: [0.0; 2],
+    }
+}
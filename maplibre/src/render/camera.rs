@@ -63,8 +63,8 @@ impl ModelViewProjection {
     }
 }
 
-const MIN_PITCH: Deg<f64> = Deg(-30.0);
-const MAX_PITCH: Deg<f64> = Deg(30.0);
+pub const MIN_PITCH: Deg<f64> = Deg(-30.0);
+pub const MAX_PITCH: Deg<f64> = Deg(30.0);
 
 const MIN_YAW: Deg<f64> = Deg(-30.0);
 const MAX_YAW: Deg<f64> = Deg(30.0);
@@ -186,6 +186,19 @@ pub struct EdgeInsets {
     pub right: f64,
 }
 
+impl std::ops::Add for EdgeInsets {
+    type Output = EdgeInsets;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        EdgeInsets {
+            top: self.top + rhs.top,
+            bottom: self.bottom + rhs.bottom,
+            left: self.left + rhs.left,
+            right: self.right + rhs.right,
+        }
+    }
+}
+
 impl EdgeInsets {
     /**
      * Utility method that computes the new apprent center or vanishing point after applying insets.
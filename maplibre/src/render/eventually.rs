@@ -1,6 +1,10 @@
 use std::mem;
 
-use crate::{coords::WorldTileCoords, render::tile_view_pattern::HasTile, tcs::world::World};
+use crate::{
+    coords::WorldTileCoords,
+    render::tile_view_pattern::HasTile,
+    tcs::{resources::Resources, world::World},
+};
 
 /// Wrapper around a resource which can be initialized or uninitialized.
 /// Uninitialized resourced can be initialized by calling [`Eventually::initialize()`].
@@ -82,3 +86,70 @@ where
         }
     }
 }
+
+/// The [`Eventually`] resources of the world which hold GPU objects, e.g. buffers, textures or
+/// pipelines.
+///
+/// They are released while the map is suspended and initialized again by the resource systems
+/// once it is resumed. Data which has been uploaded must therefore be uploaded again from the
+/// world if it is missing in a fresh resource.
+#[derive(Default)]
+pub struct GpuResources {
+    releases: Vec<fn(&mut Resources)>,
+}
+
+impl GpuResources {
+    /// Registers the resource `Eventually<T>`.
+    pub fn add<T: 'static>(&mut self) -> &mut Self {
+        self.releases.push(release::<T>);
+        self
+    }
+}
+
+fn release<T: 'static>(resources: &mut Resources) {
+    if let Some(resource) = resources.get_mut::<Eventually<T>>() {
+        resource.take();
+    }
+}
+
+/// Releases all registered [`GpuResources`] of the world.
+pub fn release_gpu_resources(world: &mut World) {
+    let releases = world
+        .resources
+        .get::<GpuResources>()
+        .map(|gpu_resources| gpu_resources.releases.clone())
+        .unwrap_or_default();
+
+    for release in releases {
+        release(&mut world.resources);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_gpu_resources() {
+        let mut world = World::default();
+        world.resources.insert(Eventually::Initialized(1u32));
+        world.resources.insert(Eventually::Initialized(2u64));
+        world.resources.init::<GpuResources>();
+        world
+            .resources
+            .get_mut::<GpuResources>()
+            .unwrap()
+            .add::<u32>();
+
+        release_gpu_resources(&mut world);
+
+        assert!(matches!(
+            world.resources.get::<Eventually<u32>>(),
+            Some(Eventually::Uninitialized)
+        ));
+        assert!(matches!(
+            world.resources.get::<Eventually<u64>>(),
+            Some(Eventually::Initialized(2))
+        ));
+    }
+}
@@ -21,12 +21,17 @@
 use std::{ops::Deref, rc::Rc, sync::Arc};
 
 use crate::{
+    camera::{
+        animation::{camera_animation_system, CameraAnimator},
+        constraints::camera_constraints_system,
+    },
     environment::Environment,
+    events::{MapEventSystem, MapEvents},
     kernel::Kernel,
     plugin::Plugin,
     render::{
         error::RenderError,
-        eventually::Eventually,
+        eventually::{Eventually, GpuResources},
         graph::{EmptyNode, RenderGraph},
         main_pass::{MainPassDriverNode, MainPassNode},
         resource::{Head, Surface, Texture, TextureView},
@@ -37,6 +42,7 @@ use crate::{
             tile_view_pattern_system::tile_view_pattern_system,
         },
     },
+    repaint::Repaint,
     schedule::{Schedule, StageLabel},
     tcs::{
         system::{stage::SystemStage, SystemContainer},
@@ -59,7 +65,7 @@ pub mod builder;
 pub mod camera;
 pub mod error;
 pub mod eventually;
-//pub mod overlay;
+pub mod overlay;
 pub mod render_commands;
 pub mod render_phase;
 pub mod settings;
@@ -129,15 +135,28 @@ impl RenderResources {
         }
     }
 
+    /// Recreates the surface after it has been dropped by
+    /// [`RenderResources::drop_surface`]. The textures which depend on the surface are created
+    /// again before the next frame.
     pub fn recreate_surface<MW>(
         &mut self,
         window: &MW,
         instance: &wgpu::Instance,
+        device: &wgpu::Device,
     ) -> Result<(), RenderError>
     where
         MW: MapWindow + HeadedMapWindow,
     {
-        self.surface.recreate::<MW>(window, instance)
+        self.surface.recreate::<MW>(window, instance, device)
+    }
+
+    /// Drops the surface and the textures which are rendered to, e.g. because the app has been
+    /// moved to the background.
+    pub fn drop_surface(&mut self) {
+        self.surface.drop_surface();
+        self.render_target = Eventually::Uninitialized;
+        self.depth_texture = Eventually::Uninitialized;
+        self.multisampling_texture = Eventually::Uninitialized;
     }
 
     pub fn surface(&self) -> &Surface {
@@ -444,22 +463,9 @@ impl Renderer {
 mod tests {
     use crate::{
         tcs::world::World,
-        window::{MapWindow, MapWindowConfig, PhysicalSize, WindowCreateError},
+        window::{MapWindow, PhysicalSize},
     };
 
-    #[derive(Clone)]
-    pub struct HeadlessMapWindowConfig {
-        size: PhysicalSize,
-    }
-
-    impl MapWindowConfig for HeadlessMapWindowConfig {
-        type MapWindow = HeadlessMapWindow;
-
-        fn create(&self) -> Result<Self::MapWindow, WindowCreateError> {
-            Ok(Self::MapWindow { size: self.size })
-        }
-    }
-
     pub struct HeadlessMapWindow {
         size: PhysicalSize,
     }
@@ -534,6 +540,8 @@ pub mod main_graph {
     pub mod node {
         pub const MAIN_PASS_DEPENDENCIES: &str = "main_pass_dependencies";
         pub const MAIN_PASS_DRIVER: &str = "main_pass_driver";
+        pub const TERRAIN_DRAPE_PASS: &str = "terrain_drape_pass";
+        pub const HEATMAP_PASS: &str = "heatmap_pass";
     }
 }
 
@@ -545,6 +553,7 @@ mod draw_graph {
     // Labels for non-input nodes
     pub mod node {
         pub const MAIN_PASS: &str = "main_pass";
+        pub const OVERLAY_PASS: &str = "overlay_pass";
     }
 }
 
@@ -599,8 +608,23 @@ impl<E: Environment> Plugin<E> for RenderPlugin {
         resources.init::<ViewTileSources>();
         // masks
         resources.insert(Eventually::<MaskPipeline>::Uninitialized);
+        resources
+            .get_or_init_mut::<GpuResources>()
+            .add::<WgpuTileViewPattern>()
+            .add::<MaskPipeline>();
+
+        // camera
+        resources.init::<CameraAnimator>();
+        // events
+        resources.init::<MapEvents>();
+        resources.init::<Repaint>();
 
-        schedule.add_stage(RenderStageLabel::Extract, SystemStage::default());
+        schedule.add_stage(
+            RenderStageLabel::Extract,
+            SystemStage::default()
+                .with_system(camera_animation_system)
+                .with_system(camera_constraints_system),
+        );
         schedule.add_stage(
             RenderStageLabel::Prepare,
             SystemStage::default().with_system(SystemContainer::new(ResourceSystem)),
@@ -621,7 +645,9 @@ impl<E: Environment> Plugin<E> for RenderPlugin {
         );
         schedule.add_stage(
             RenderStageLabel::Cleanup,
-            SystemStage::default().with_system(cleanup_system),
+            SystemStage::default()
+                .with_system(cleanup_system)
+                .with_system(SystemContainer::new(MapEventSystem::default())),
         );
     }
 }
@@ -1,8 +1,26 @@
-//pub mod overlay_images;
-pub mod overlay_item;
-pub mod overlay_pass;
-pub mod overlay_phase;
-pub mod overlay_plugin;
-pub mod overlay_renderer;
-//pub mod overlay_shapes;
-pub mod overlay_text;
+//! Draws items in screen space on top of the map, e.g. markers, scale bars or measurements.
+//!
+//! The overlay is immediate-mode: items are added to the
+//! [`RenderPhase<OverlayItem>`](crate::render::render_phase::RenderPhase) for a single frame and
+//! removed after it has been rendered. Systems which run during the
+//! [`Queue`](crate::render::RenderStageLabel::Queue) stage can add items every frame, applications
+//! can add them before a frame through [`MapContext::overlay`](crate::context::MapContext::overlay).
+//!
+//! Positions and sizes are in logical pixels, such that items keep their size if the scale
+//! factor of the window changes. Consecutive items which sample the same texture are drawn with
+//! a single draw call. Text and shapes share the atlas of the built-in font.
+
+mod overlay_item;
+mod overlay_pass;
+mod overlay_phase;
+mod overlay_plugin;
+mod overlay_renderer;
+mod overlay_tessellation;
+mod overlay_text;
+mod overlay_texture;
+
+pub use overlay_item::{OverlayImage, OverlayText, Shape};
+pub use overlay_phase::{OverlayItem, OverlayKind};
+pub use overlay_plugin::OverlayPlugin;
+pub use overlay_text::OverlayFont;
+pub use overlay_texture::{OverlayTexture, OverlayTextureId, OverlayTextures};
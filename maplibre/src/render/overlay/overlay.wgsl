@@ -0,0 +1,36 @@
+struct Globals {
+    // Size of the surface in logical pixels
+    screen_size: vec2<f32>,
+    _padding: vec2<f32>,
+};
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(1) @binding(0) var overlay_texture: texture_2d<f32>;
+@group(1) @binding(1) var overlay_sampler: sampler;
+
+struct VSIn {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VSOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VSIn) -> VSOut {
+    var out: VSOut;
+    let ndc = in.position / globals.screen_size * 2.0 - 1.0;
+    out.position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.uv = in.uv;
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VSOut) -> @location(0) vec4<f32> {
+    return textureSample(overlay_texture, overlay_sampler, in.uv) * in.color;
+}
@@ -1,12 +1,20 @@
 use glam::{Vec2, Vec4};
 
+use crate::render::overlay::overlay_texture::OverlayTextureId;
+
+/// Text which is drawn with the built-in font. Lines are separated by `\n`.
+#[derive(Clone, Debug)]
 pub struct OverlayText {
     pub text: String,
+    /// The top-left corner of the first line in logical pixels
     pub position: Vec2,
     pub color: Vec4,
+    /// Height of a line in logical pixels
     pub size: f32,
 }
 
+/// A filled shape. Positions, sizes and widths are in logical pixels.
+#[derive(Clone, Debug)]
 pub enum Shape {
     Rect {
         pos: Vec2,
@@ -18,20 +26,20 @@ pub enum Shape {
         radius: f32,
         color: Vec4,
     },
-    Line {
-        a: Vec2,
-        b: Vec2,
+    /// Connected line segments with mitered joins. A closed polyline connects the last point
+    /// with the first one.
+    Polyline {
+        points: Vec<Vec2>,
         width: f32,
         color: Vec4,
+        closed: bool,
     },
 }
 
-pub struct OverlayShape {
-    pub shape: Shape,
-}
-
+/// An image which has been added to the [`OverlayTextures`](super::OverlayTextures).
+#[derive(Clone, Debug)]
 pub struct OverlayImage {
     pub pos: Vec2,
     pub size: Vec2,
-    pub texture_id: u32,
+    pub texture_id: OverlayTextureId,
 }
@@ -1,36 +1,74 @@
+use std::ops::Deref;
+
+use wgpu::StoreOp;
+
 use crate::{
     render::{
-        graph::{Node, NodeRunError, RenderContext, RenderGraphContext},
+        eventually::Eventually::Initialized,
+        graph::{Node, NodeRunError, RenderContext, RenderGraphContext, SlotInfo},
         overlay::overlay_phase::OverlayItem,
-        render_phase::RenderPhase,
+        render_phase::{PhaseItem, RenderPhase},
+        resource::TrackedRenderPass,
         RenderResources,
     },
     tcs::world::World,
 };
 
+/// Draws the [`OverlayItems`](OverlayItem) on top of the map.
 pub struct OverlayPassNode;
 
+impl OverlayPassNode {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
 impl Node for OverlayPassNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![]
+    }
+
     fn run(
         &self,
-        graph: &mut RenderGraphContext,
-        render_ctx: &mut RenderContext,
-        _state: &RenderResources,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        resources: &RenderResources,
         world: &World,
     ) -> Result<(), NodeRunError> {
-        let Some(output_view) = graph.get_input_texture_view(0) else {
+        let Initialized(render_target) = &resources.render_target else {
             return Ok(());
         };
-        let mut pass = render_ctx.begin_tracked_pass("overlay_pass", &output_view);
+        let Some(phase) = world.resources.get::<RenderPhase<OverlayItem>>() else {
+            return Ok(());
+        };
+        if phase.size() == 0 {
+            return Ok(());
+        }
+
+        let color_attachment = wgpu::RenderPassColorAttachment {
+            view: render_target.deref(),
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: StoreOp::Store,
+            },
+            resolve_target: None,
+        };
 
-        if let Some(mut phase) = world.resources.get_mut::<RenderPhase<OverlayItem>>() {
-            phase.sort();
+        let render_pass =
+            render_context
+                .command_encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("overlay_pass"),
+                    color_attachments: &[Some(color_attachment)],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
 
-            for item in phase.into_iter() {
-                item.draw_function().draw(&mut pass, world, item);
-            }
+        let mut tracked_pass = TrackedRenderPass::new(render_pass);
 
-            phase.clear();
+        for item in phase {
+            item.draw_function().draw(&mut tracked_pass, world, item);
         }
 
         Ok(())
@@ -1,24 +1,105 @@
-use crate::render::render_phase::{Draw, PhaseItem};
+use std::ops::Range;
 
+use crate::{
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        overlay::{
+            overlay_item::{OverlayImage, OverlayText, Shape},
+            overlay_renderer::OverlayResources,
+            overlay_texture::OverlayTextureId,
+        },
+        render_phase::{Draw, PhaseItem},
+        resource::TrackedRenderPass,
+    },
+    tcs::world::World,
+};
+
+#[derive(Clone, Debug)]
 pub enum OverlayKind {
-    Text,
-    //Shape,
-    //Image,
+    Text(OverlayText),
+    Shape(Shape),
+    Image(OverlayImage),
+}
+
+impl OverlayKind {
+    /// The texture which is sampled when drawing this kind of item.
+    pub fn texture_id(&self) -> OverlayTextureId {
+        match self {
+            OverlayKind::Text(_) | OverlayKind::Shape(_) => OverlayTextureId::ATLAS,
+            OverlayKind::Image(image) => image.texture_id,
+        }
+    }
 }
 
+impl From<OverlayText> for OverlayKind {
+    fn from(text: OverlayText) -> Self {
+        OverlayKind::Text(text)
+    }
+}
+
+impl From<Shape> for OverlayKind {
+    fn from(shape: Shape) -> Self {
+        OverlayKind::Shape(shape)
+    }
+}
+
+impl From<OverlayImage> for OverlayKind {
+    fn from(image: OverlayImage) -> Self {
+        OverlayKind::Image(image)
+    }
+}
+
+/// An item which is drawn in screen space on top of the map. Items with a higher `z` are drawn
+/// on top, items with the same `z` in the order in which they have been added.
 pub struct OverlayItem {
     pub kind: OverlayKind,
     pub z: u32,
+    /// The vertices of the batch which starts at this item. Consecutive items which sample the
+    /// same texture are drawn with a single draw call by the first of them.
+    pub(super) batch: Option<Range<u32>>,
+}
+
+impl OverlayItem {
+    pub fn new(kind: impl Into<OverlayKind>, z: u32) -> Self {
+        Self {
+            kind: kind.into(),
+            z,
+            batch: None,
+        }
+    }
 }
 
 impl PhaseItem for OverlayItem {
     type SortKey = u32;
 
     fn sort_key(&self) -> Self::SortKey {
-        self.sort
+        self.z
     }
 
     fn draw_function(&self) -> &dyn Draw<Self> {
-        self.draw_fn.as_ref()
+        &DrawOverlayBatch
+    }
+}
+
+struct DrawOverlayBatch;
+
+impl Draw<OverlayItem> for DrawOverlayBatch {
+    fn draw<'w>(&self, pass: &mut TrackedRenderPass<'w>, world: &'w World, item: &OverlayItem) {
+        let Some(vertices) = item.batch.clone() else {
+            return;
+        };
+        let Some(Initialized(resources)) = world.resources.get::<Eventually<OverlayResources>>()
+        else {
+            return;
+        };
+        let Some(texture_bind_group) = resources.texture_bind_group(item.kind.texture_id()) else {
+            return;
+        };
+
+        pass.set_render_pipeline(resources.pipeline());
+        pass.set_bind_group(0, resources.globals_bind_group(), &[]);
+        pass.set_bind_group(1, texture_bind_group, &[]);
+        pass.set_vertex_buffer(0, resources.vertices().slice(..));
+        pass.draw(vertices, 0..1);
     }
 }
@@ -1,18 +1,30 @@
 use std::rc::Rc;
 
-use super::{overlay_pass::OverlayPassNode, overlay_phase::OverlayItem};
 use crate::{
     environment::Environment,
     kernel::Kernel,
     plugin::Plugin,
-    render::{graph::RenderGraph, main_graph, render_phase::RenderPhase, RenderStageLabel},
-    schedule::Schedule,
-    tcs::{
-        system::{stage::SystemStage, SystemContainer},
-        world::World,
+    render::{
+        draw_graph,
+        eventually::{Eventually, GpuResources},
+        graph::RenderGraph,
+        overlay::{
+            overlay_pass::OverlayPassNode,
+            overlay_phase::OverlayItem,
+            overlay_renderer::{cleanup_system, resource_system, upload_system, OverlayResources},
+            overlay_text::OverlayFont,
+            overlay_texture::OverlayTextures,
+        },
+        render_phase::RenderPhase,
+        RenderStageLabel,
     },
+    schedule::Schedule,
+    tcs::world::World,
 };
 
+/// Draws the items of the [`RenderPhase<OverlayItem>`] on top of the map. Requires the
+/// [`RenderPlugin`](crate::render::RenderPlugin).
+#[derive(Default)]
 pub struct OverlayPlugin;
 
 impl<E: Environment> Plugin<E> for OverlayPlugin {
@@ -23,29 +35,27 @@ impl<E: Environment> Plugin<E> for OverlayPlugin {
         world: &mut World,
         graph: &mut RenderGraph,
     ) {
-        world.resources.init::<RenderPhase<OverlayItem>>();
-
-        let mut overlay_graph = RenderGraph::default();
-
-        overlay_graph.add_node("overlay_pass", OverlayPassNode::new());
-
-        let input = overlay_graph.set_input(vec![]);
-        overlay_graph.add_node_edge(input, "overlay_pass").unwrap();
-
-        graph.add_sub_graph("overlay_graph", overlay_graph);
-
-        graph.add_node("overlay_driver", super::overlay_renderer::OverlayDriverNode);
-        graph
-            .add_node_edge(main_graph::node::MAIN_PASS_DRIVER, "overlay_driver")
-            .unwrap();
-
-        schedule.add_stage(RenderStageLabel::Prepare, SystemStage::default());
-        schedule.add_stage(
-            RenderStageLabel::Queue,
-            SystemStage::default().with_system(SystemContainer::new(
-                super::overlay_renderer::queue_overlay_system,
-            )),
-        );
-        schedule.add_stage(RenderStageLabel::Render, SystemStage::default());
+        let resources = &mut world.resources;
+
+        resources.init::<RenderPhase<OverlayItem>>();
+        resources.init::<OverlayTextures>();
+        resources.init::<OverlayFont>();
+        resources.insert(Eventually::<OverlayResources>::Uninitialized);
+        resources
+            .get_or_init_mut::<GpuResources>()
+            .add::<OverlayResources>();
+
+        let draw_graph = graph
+            .get_sub_graph_mut(draw_graph::NAME)
+            .expect("draw graph does not exist");
+        draw_graph.add_node(draw_graph::node::OVERLAY_PASS, OverlayPassNode::new());
+        draw_graph
+            .add_node_edge(draw_graph::node::MAIN_PASS, draw_graph::node::OVERLAY_PASS)
+            .expect("main pass does not exist");
+
+        schedule.add_system_to_stage(RenderStageLabel::Prepare, resource_system);
+        // Items are queued by other systems during the queue stage
+        schedule.add_system_to_stage(RenderStageLabel::PhaseSort, upload_system);
+        schedule.add_system_to_stage(RenderStageLabel::Cleanup, cleanup_system);
     }
 }
@@ -1,58 +1,326 @@
-use super::{overlay_phase::OverlayItem, overlay_text};
+use std::collections::HashMap;
+
+use bytemuck_derive::{Pod, Zeroable};
+use glam::Vec2;
+
 use crate::{
-    render::{graph::RenderContext, RenderPhase},
-    tcs::world::World,
+    context::MapContext,
+    render::{
+        eventually::Eventually,
+        overlay::{
+            overlay_phase::OverlayItem,
+            overlay_tessellation::{tessellate, OverlayVertex},
+            overlay_text::OverlayFont,
+            overlay_texture::{OverlayTextureId, OverlayTextures},
+        },
+        render_phase::RenderPhase,
+        RenderResources, Renderer,
+    },
 };
 
-pub struct OverlayDriverNode;
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct OverlayGlobals {
+    screen_size: [f32; 2],
+    _padding: [f32; 2],
+}
+
+/// Holds the resources necessary for rendering the overlay:
+/// * a pipeline which draws textured and colored triangles in screen space
+/// * the vertices of the current frame
+/// * a bind group per texture of the [`OverlayTextures`]
+pub struct OverlayResources {
+    pipeline: wgpu::RenderPipeline,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+
+    globals: wgpu::Buffer,
+    globals_bind_group: wgpu::BindGroup,
+
+    vertices: wgpu::Buffer,
+    vertices_capacity: usize,
+
+    textures: HashMap<OverlayTextureId, wgpu::BindGroup>,
+}
+
+impl OverlayResources {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("overlay.wgsl"));
+
+        let globals_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("overlay globals layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("overlay texture layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("overlay pipeline layout"),
+            bind_group_layouts: &[&globals_layout, &texture_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("overlay pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<OverlayVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let globals = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("overlay globals buffer"),
+            size: std::mem::size_of::<OverlayGlobals>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let globals_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("overlay globals bind group"),
+            layout: &globals_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: globals.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            pipeline,
+            texture_layout,
+            sampler,
+            globals,
+            globals_bind_group,
+            vertices: Self::create_vertex_buffer(device, 0),
+            vertices_capacity: 0,
+            textures: HashMap::new(),
+        }
+    }
+
+    pub fn pipeline(&self) -> &wgpu::RenderPipeline {
+        &self.pipeline
+    }
+
+    pub fn globals_bind_group(&self) -> &wgpu::BindGroup {
+        &self.globals_bind_group
+    }
+
+    pub fn vertices(&self) -> &wgpu::Buffer {
+        &self.vertices
+    }
+
+    pub fn texture_bind_group(&self, id: OverlayTextureId) -> Option<&wgpu::BindGroup> {
+        self.textures.get(&id)
+    }
+
+    /// Uploads the textures which have been added since the last call and releases the ones
+    /// which have been removed.
+    pub fn sync_textures(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        textures: &OverlayTextures,
+    ) {
+        self.textures.retain(|id, _| textures.contains(*id));
+
+        for (id, texture) in textures.iter() {
+            if self.textures.contains_key(&id) {
+                continue;
+            }
+
+            let size = wgpu::Extent3d {
+                width: texture.width,
+                height: texture.height,
+                depth_or_array_layers: 1,
+            };
+            let gpu_texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("overlay texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            queue.write_texture(
+                gpu_texture.as_image_copy(),
+                &texture.data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * texture.width),
+                    rows_per_image: None,
+                },
+                size,
+            );
 
-impl RenderGraphNode for OverlayDriverNode {
-    fn run(&self, ctx: &mut RenderContext, _: &[NodeInput], _: &mut [NodeOutput]) {
-        let phase = ctx
-            .world
-            .resources
-            .get::<RenderPhase<OverlayItem>>()
-            .unwrap();
-        phase.sort();
+            let view = gpu_texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("overlay texture bind group"),
+                layout: &self.texture_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            });
+            self.textures.insert(id, bind_group);
+        }
+    }
+
+    /// Writes the vertices of the current frame for a surface of `screen_size` logical pixels.
+    pub fn write(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        screen_size: Vec2,
+        vertices: &[OverlayVertex],
+    ) {
+        let globals = OverlayGlobals {
+            screen_size: screen_size.to_array(),
+            _padding: [0.0; 2],
+        };
+        queue.write_buffer(&self.globals, 0, bytemuck::bytes_of(&globals));
+
+        if vertices.len() > self.vertices_capacity {
+            let capacity = vertices.len().next_power_of_two();
+            self.vertices = Self::create_vertex_buffer(device, capacity);
+            self.vertices_capacity = capacity;
+        }
+        if !vertices.is_empty() {
+            queue.write_buffer(&self.vertices, 0, bytemuck::cast_slice(vertices));
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("overlay vertex buffer"),
+            size: (capacity.max(1) * std::mem::size_of::<OverlayVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
     }
 }
 
-pub fn queue_overlay_system(world: &mut World) {
-    let phase = world
+/// Initializes the overlay resources and uploads new textures.
+pub fn resource_system(
+    MapContext {
+        world,
+        renderer:
+            Renderer {
+                device,
+                queue,
+                resources: RenderResources { surface, .. },
+                ..
+            },
+        ..
+    }: &mut MapContext,
+) {
+    let Some((overlay_resources, textures)) = world
         .resources
-        .get_mut::<RenderPhase<OverlayItem>>()
-        .unwrap();
+        .query_mut::<(&mut Eventually<OverlayResources>, &OverlayTextures)>()
+    else {
+        return;
+    };
 
-    overlay_text::extract_text(world, phase);
-    //overlay_shapes::extract_shapes(world, phase);
-    //overlay_images::extract_images(world, phase);
+    overlay_resources.initialize(|| OverlayResources::new(device, surface.surface_format()));
+
+    let Eventually::Initialized(overlay_resources) = overlay_resources else {
+        return;
+    };
+    overlay_resources.sync_textures(device, queue, textures);
 }
 
-pub fn render_overlay(ctx: &mut RenderContext) {
-    let phase = ctx
-        .world
-        .resources
-        .get::<RenderPhase<OverlayItem>>()
-        .unwrap();
-
-    let pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-        label: Some("overlay pass"),
-        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-            view: ctx.get_current_view(),
-            resolve_target: None,
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Load,
-                store: true,
-            },
-        })],
-        depth_stencil_attachment: None,
-    });
-
-    for item in phase.items.iter() {
-        match item.kind {
-            super::overlay_phase::OverlayKind::Text => overlay_text::draw(ctx, &item),
-            //super::overlay_phase::OverlayKind::Shape => overlay_shapes::draw(ctx, &item),
-            //super::overlay_phase::OverlayKind::Image => overlay_images::draw(ctx, &item),
-        }
+/// Sorts the items which have been queued for this frame and uploads their vertices.
+pub fn upload_system(
+    MapContext {
+        world,
+        view_state,
+        renderer: Renderer { device, queue, .. },
+        ..
+    }: &mut MapContext,
+) {
+    let Some((phase, Eventually::Initialized(overlay_resources), font)) =
+        world.resources.query_mut::<(
+            &mut RenderPhase<OverlayItem>,
+            &mut Eventually<OverlayResources>,
+            &OverlayFont,
+        )>()
+    else {
+        return;
+    };
+
+    phase.sort();
+    let vertices = tessellate(phase.iter_mut(), font);
+
+    let screen_size = Vec2::new(view_state.width() as f32, view_state.height() as f32);
+    overlay_resources.write(device, queue, screen_size, &vertices);
+}
+
+/// Removes the items of the previous frame.
+pub fn cleanup_system(MapContext { world, .. }: &mut MapContext) {
+    if let Some(phase) = world.resources.get_mut::<RenderPhase<OverlayItem>>() {
+        phase.clear();
     }
 }
@@ -0,0 +1,299 @@
+//! Turns overlay items into triangles in screen space.
+
+use std::f32::consts::TAU;
+
+use bytemuck_derive::{Pod, Zeroable};
+use glam::{Vec2, Vec4};
+
+use crate::render::overlay::{
+    overlay_item::Shape,
+    overlay_phase::{OverlayItem, OverlayKind},
+    overlay_text::OverlayFont,
+};
+
+/// Circles have a segment per this many logical pixels of their circumference.
+const CIRCLE_SEGMENT_LENGTH: f32 = 4.0;
+const MIN_CIRCLE_SEGMENTS: usize = 8;
+const MAX_CIRCLE_SEGMENTS: usize = 128;
+/// Miters of sharp corners are limited to this many times the half width of a polyline.
+const MITER_LIMIT: f32 = 4.0;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct OverlayVertex {
+    /// Position in logical pixels
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl OverlayVertex {
+    fn new(position: Vec2, uv: Vec2, color: Vec4) -> Self {
+        Self {
+            position: position.to_array(),
+            uv: uv.to_array(),
+            color: color.to_array(),
+        }
+    }
+}
+
+/// Appends two triangles for the quad `a`, `b`, `c`, `d` in winding order.
+fn push_quad(vertices: &mut Vec<OverlayVertex>, corners: [OverlayVertex; 4]) {
+    let [a, b, c, d] = corners;
+    vertices.extend_from_slice(&[a, b, c, a, c, d]);
+}
+
+fn push_rect(
+    vertices: &mut Vec<OverlayVertex>,
+    min: Vec2,
+    max: Vec2,
+    (uv_min, uv_max): (Vec2, Vec2),
+    color: Vec4,
+) {
+    push_quad(
+        vertices,
+        [
+            OverlayVertex::new(min, uv_min, color),
+            OverlayVertex::new(
+                Vec2::new(max.x, min.y),
+                Vec2::new(uv_max.x, uv_min.y),
+                color,
+            ),
+            OverlayVertex::new(max, uv_max, color),
+            OverlayVertex::new(
+                Vec2::new(min.x, max.y),
+                Vec2::new(uv_min.x, uv_max.y),
+                color,
+            ),
+        ],
+    );
+}
+
+fn push_circle(
+    vertices: &mut Vec<OverlayVertex>,
+    center: Vec2,
+    radius: f32,
+    uv: Vec2,
+    color: Vec4,
+) {
+    let segments = ((TAU * radius / CIRCLE_SEGMENT_LENGTH).ceil() as usize)
+        .clamp(MIN_CIRCLE_SEGMENTS, MAX_CIRCLE_SEGMENTS);
+    let point = |i: usize| center + Vec2::from_angle(TAU * i as f32 / segments as f32) * radius;
+
+    for i in 0..segments {
+        vertices.extend_from_slice(&[
+            OverlayVertex::new(center, uv, color),
+            OverlayVertex::new(point(i), uv, color),
+            OverlayVertex::new(point(i + 1), uv, color),
+        ]);
+    }
+}
+
+fn push_polyline(
+    vertices: &mut Vec<OverlayVertex>,
+    points: &[Vec2],
+    width: f32,
+    closed: bool,
+    uv: Vec2,
+    color: Vec4,
+) {
+    let mut points = points.to_vec();
+    points.dedup();
+    if closed && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    if points.len() < 2 {
+        return;
+    }
+    let closed = closed && points.len() > 2;
+
+    let half_width = width / 2.0;
+    let count = points.len();
+    let normal = |from: Vec2, to: Vec2| (to - from).normalize().perp();
+
+    // The offset of the left side of the line at each point
+    let offsets = (0..count)
+        .map(|i| {
+            let previous = (i > 0 || closed).then(|| points[(i + count - 1) % count]);
+            let next = (i + 1 < count || closed).then(|| points[(i + 1) % count]);
+
+            match (previous, next) {
+                (Some(previous), Some(next)) => {
+                    let incoming = normal(previous, points[i]);
+                    let outgoing = normal(points[i], next);
+                    let miter = (incoming + outgoing).try_normalize().unwrap_or(outgoing);
+                    let length = half_width / miter.dot(outgoing).max(1.0 / MITER_LIMIT);
+                    miter * length
+                }
+                (None, Some(next)) => normal(points[i], next) * half_width,
+                (Some(previous), None) => normal(previous, points[i]) * half_width,
+                (None, None) => Vec2::ZERO,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let segments = if closed { count } else { count - 1 };
+    for i in 0..segments {
+        let j = (i + 1) % count;
+        push_quad(
+            vertices,
+            [
+                OverlayVertex::new(points[i] + offsets[i], uv, color),
+                OverlayVertex::new(points[j] + offsets[j], uv, color),
+                OverlayVertex::new(points[j] - offsets[j], uv, color),
+                OverlayVertex::new(points[i] - offsets[i], uv, color),
+            ],
+        );
+    }
+}
+
+/// Appends the triangles of an item to `vertices`.
+fn tessellate_item(vertices: &mut Vec<OverlayVertex>, kind: &OverlayKind, font: &OverlayFont) {
+    let white = font.white_uv();
+
+    match kind {
+        OverlayKind::Text(text) => {
+            for glyph in font.layout(text) {
+                push_rect(
+                    vertices,
+                    glyph.min,
+                    glyph.max,
+                    (glyph.uv_min, glyph.uv_max),
+                    text.color,
+                );
+            }
+        }
+        OverlayKind::Shape(Shape::Rect { pos, size, color }) => {
+            push_rect(vertices, *pos, *pos + *size, (white, white), *color);
+        }
+        OverlayKind::Shape(Shape::Circle { pos, radius, color }) => {
+            push_circle(vertices, *pos, *radius, white, *color);
+        }
+        OverlayKind::Shape(Shape::Polyline {
+            points,
+            width,
+            color,
+            closed,
+        }) => {
+            push_polyline(vertices, points, *width, *closed, white, *color);
+        }
+        OverlayKind::Image(image) => {
+            push_rect(
+                vertices,
+                image.pos,
+                image.pos + image.size,
+                (Vec2::ZERO, Vec2::ONE),
+                Vec4::ONE,
+            );
+        }
+    }
+}
+
+/// Tessellates the items in their current order and assigns the vertices to batches. The first
+/// item of a run of items which sample the same texture draws the whole run.
+pub fn tessellate<'a>(
+    items: impl Iterator<Item = &'a mut OverlayItem>,
+    font: &OverlayFont,
+) -> Vec<OverlayVertex> {
+    let mut vertices = Vec::new();
+    let mut head: Option<&'a mut OverlayItem> = None;
+
+    for item in items {
+        let start = vertices.len() as u32;
+        tessellate_item(&mut vertices, &item.kind, font);
+        let end = vertices.len() as u32;
+
+        match &mut head {
+            Some(head) if head.kind.texture_id() == item.kind.texture_id() => {
+                item.batch = None;
+                if let Some(batch) = &mut head.batch {
+                    batch.end = end;
+                }
+            }
+            _ => {
+                item.batch = Some(start..end);
+                head = Some(item);
+            }
+        }
+    }
+
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{
+        overlay::{OverlayImage, OverlayText, OverlayTexture, OverlayTextures},
+        render_phase::RenderPhase,
+    };
+
+    #[test]
+    fn test_batches() {
+        let mut textures = OverlayTextures::default();
+        let image = textures.add(OverlayTexture {
+            width: 1,
+            height: 1,
+            data: vec![0; 4],
+        });
+        let rect = || Shape::Rect {
+            pos: Vec2::ZERO,
+            size: Vec2::ONE,
+            color: Vec4::ONE,
+        };
+
+        let mut phase = RenderPhase::default();
+        phase.add(OverlayItem::new(rect(), 0));
+        phase.add(OverlayItem::new(
+            OverlayImage {
+                pos: Vec2::ZERO,
+                size: Vec2::ONE,
+                texture_id: image,
+            },
+            1,
+        ));
+        phase.add(OverlayItem::new(
+            OverlayText {
+                text: "ab".to_string(),
+                position: Vec2::ZERO,
+                color: Vec4::ONE,
+                size: 16.0,
+            },
+            0,
+        ));
+        phase.sort();
+
+        let vertices = tessellate(phase.iter_mut(), &OverlayFont::default());
+        assert_eq!(vertices.len(), 24);
+
+        // The rect and the text share the atlas and are drawn by the rect, before the image on
+        // top
+        let batches = phase
+            .into_iter()
+            .map(|item| item.batch.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(batches, vec![Some(0..18), None, Some(18..24)]);
+    }
+
+    #[test]
+    fn test_polyline() {
+        let mut vertices = Vec::new();
+        let points = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 10.0),
+        ];
+        push_polyline(&mut vertices, &points, 2.0, false, Vec2::ZERO, Vec4::ONE);
+
+        // Duplicate points are skipped
+        assert_eq!(vertices.len(), 12);
+        // The outer corner is mitered
+        let corner = Vec2::from_array(vertices[1].position);
+        assert!(corner.abs_diff_eq(Vec2::new(9.0, 1.0), 1e-5));
+
+        vertices.clear();
+        push_polyline(&mut vertices, &points, 2.0, true, Vec2::ZERO, Vec4::ONE);
+        assert_eq!(vertices.len(), 18);
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use crate::render::overlay::overlay_text::font_atlas;
+
+/// Identifies a texture of the [`OverlayTextures`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OverlayTextureId(u32);
+
+impl OverlayTextureId {
+    /// The atlas of the built-in font, which also contains white texels for shapes. Text and
+    /// shapes are therefore drawn together.
+    pub const ATLAS: Self = Self(0);
+}
+
+/// An RGBA image with 8 bits per channel.
+#[derive(Clone, Debug)]
+pub struct OverlayTexture {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+/// The textures which overlay items can draw. They are kept in memory, such that they can be
+/// uploaded again after the GPU resources have been released.
+pub struct OverlayTextures {
+    textures: HashMap<OverlayTextureId, OverlayTexture>,
+    next_id: u32,
+}
+
+impl Default for OverlayTextures {
+    fn default() -> Self {
+        Self {
+            textures: HashMap::from([(OverlayTextureId::ATLAS, font_atlas())]),
+            next_id: 1,
+        }
+    }
+}
+
+impl OverlayTextures {
+    pub fn add(&mut self, texture: OverlayTexture) -> OverlayTextureId {
+        assert_eq!(
+            texture.data.len(),
+            (texture.width * texture.height * 4) as usize,
+            "texture data must be RGBA"
+        );
+
+        let id = OverlayTextureId(self.next_id);
+        self.next_id += 1;
+        self.textures.insert(id, texture);
+        id
+    }
+
+    pub fn remove(&mut self, id: OverlayTextureId) -> Option<OverlayTexture> {
+        if id == OverlayTextureId::ATLAS {
+            return None;
+        }
+        self.textures.remove(&id)
+    }
+
+    pub fn get(&self, id: OverlayTextureId) -> Option<&OverlayTexture> {
+        self.textures.get(&id)
+    }
+
+    pub fn contains(&self, id: OverlayTextureId) -> bool {
+        self.textures.contains_key(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (OverlayTextureId, &OverlayTexture)> {
+        self.textures.iter().map(|(id, texture)| (*id, texture))
+    }
+}
@@ -41,6 +41,17 @@ impl<I: PhaseItem> RenderPhase<I> {
         self.items.clear();
     }
 
+    /// Removes all [`PhaseItems`](PhaseItem) in their current order.
+    pub fn drain(&mut self) -> impl Iterator<Item = I> + '_ {
+        self.items.drain(..)
+    }
+
+    /// Returns the [`PhaseItems`](PhaseItem) in their current order for modification, e.g. in
+    /// order to attach prepared data to them.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut I> + '_ {
+        self.items.iter_mut()
+    }
+
     pub fn size(&self) -> usize {
         self.items.len()
     }
@@ -40,7 +40,8 @@ impl BufferDimensions {
 }
 
 pub struct WindowHead {
-    surface: wgpu::Surface<'static>,
+    /// The surface of the window, which is `None` while the map is suspended.
+    surface: Option<wgpu::Surface<'static>>,
     size: PhysicalSize,
 
     texture_format: wgpu::TextureFormat,
@@ -66,7 +67,9 @@ impl WindowHead {
             desired_maximum_frame_latency: 2,
         };
 
-        self.surface.configure(device, &surface_config);
+        if let Some(surface) = &self.surface {
+            surface.configure(device, &surface_config);
+        }
     }
 
     pub fn recreate_surface<MW>(
@@ -77,15 +80,21 @@ impl WindowHead {
     where
         MW: MapWindow + HeadedMapWindow,
     {
-        self.surface = unsafe {
+        self.surface = Some(unsafe {
             instance
                 .create_surface_unsafe(wgpu::SurfaceTargetUnsafe::from_window(&window.handle())?)?
-        };
+        });
         Ok(())
     }
 
-    pub fn surface(&self) -> &wgpu::Surface {
-        &self.surface
+    /// Drops the surface, e.g. because the native window is destroyed while the app is in the
+    /// background.
+    pub fn drop_surface(&mut self) {
+        self.surface = None;
+    }
+
+    pub fn surface(&self) -> Option<&wgpu::Surface> {
+        self.surface.as_ref()
     }
 }
 
@@ -199,7 +208,7 @@ impl Surface {
         Self {
             size,
             head: Head::Headed(WindowHead {
-                surface,
+                surface: Some(surface),
                 size,
                 texture_format,
                 texture_format_features,
@@ -272,7 +281,9 @@ impl Surface {
     pub fn create_view(&self, device: &wgpu::Device) -> TextureView {
         match &self.head {
             Head::Headed(window) => {
-                let WindowHead { surface, .. } = window;
+                let surface = window
+                    .surface()
+                    .expect("surface is not available while suspended");
                 let frame = match surface.get_current_texture() {
                     Ok(view) => view,
                     Err(wgpu::SurfaceError::Outdated) => {
@@ -312,25 +323,34 @@ impl Surface {
         }
     }
 
+    /// Recreates the surface of the window and configures it for the current size.
     pub fn recreate<MW>(
         &mut self,
         window: &MW,
         instance: &wgpu::Instance,
+        device: &wgpu::Device,
     ) -> Result<(), RenderError>
     where
         MW: MapWindow + HeadedMapWindow,
     {
         match &mut self.head {
             Head::Headed(window_head) => {
-                if window_head.has_changed(&(self.size.width(), self.size.height())) {
-                    window_head.recreate_surface(window, instance)?;
-                }
+                window_head.recreate_surface(window, instance)?;
+                window_head.resize_and_configure(self.size.width(), self.size.height(), device);
             }
             Head::Headless(_) => {}
         }
         Ok(())
     }
 
+    /// Drops the surface of the window. A headless surface is kept.
+    pub fn drop_surface(&mut self) {
+        match &mut self.head {
+            Head::Headed(window_head) => window_head.drop_surface(),
+            Head::Headless(_) => {}
+        }
+    }
+
     pub fn head(&self) -> &Head {
         &self.head
     }
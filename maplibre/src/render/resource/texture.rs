@@ -113,3 +113,60 @@ impl HasChanged for Texture {
         !size.eq(criteria)
     }
 }
+
+/// Texture which is rendered to by an offscreen pass and sampled by a later pass, e.g. the main
+/// pass.
+pub struct OffscreenTarget {
+    pub texture: Texture,
+}
+
+impl OffscreenTarget {
+    pub fn new(
+        label: wgpu::Label,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Self {
+            texture: Texture::new(
+                label,
+                device,
+                format,
+                width.max(1),
+                height.max(1),
+                Msaa { samples: 1 },
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            ),
+        }
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.texture.view
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.texture.size.width, self.texture.size.height)
+    }
+
+    /// Color attachment which clears the target before rendering.
+    pub fn color_attachment(&self, clear: wgpu::Color) -> wgpu::RenderPassColorAttachment<'_> {
+        wgpu::RenderPassColorAttachment {
+            view: self.view(),
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(clear),
+                store: wgpu::StoreOp::Store,
+            },
+            resolve_target: None,
+        }
+    }
+}
+
+impl HasChanged for OffscreenTarget {
+    type Criteria = (u32, u32);
+
+    fn has_changed(&self, criteria: &Self::Criteria) -> bool {
+        self.texture
+            .has_changed(&(criteria.0.max(1), criteria.1.max(1)))
+    }
+}
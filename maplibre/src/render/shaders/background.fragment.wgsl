@@ -0,0 +1,38 @@
+struct Background {
+    inverse_view_proj: mat4x4<f32>,
+    color: vec4<f32>,
+    pattern_size: vec2<f32>,
+    opacity: f32,
+    z_index: f32,
+};
+
+struct VertexOutput {
+    @location(0) ndc: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> background: Background;
+@group(0) @binding(1) var pattern_texture: texture_2d<f32>;
+@group(0) @binding(2) var pattern_sampler: sampler;
+
+@fragment
+fn main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Intersect the ray through the fragment with the ground, such that the pattern is anchored to
+    // the map
+    var near = background.inverse_view_proj * vec4<f32>(in.ndc, 0.0, 1.0);
+    var far = background.inverse_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    near = near / near.w;
+    far = far / far.w;
+    let t = -near.z / (far.z - near.z);
+    let position = mix(near.xy, far.xy, t);
+
+    let pattern = textureSample(
+        pattern_texture,
+        pattern_sampler,
+        position / max(background.pattern_size, vec2<f32>(1.0)),
+    );
+
+    // Colors are premultiplied
+    let pattern_color = vec4<f32>(pattern.rgb * pattern.a, pattern.a) * background.opacity;
+    return select(background.color, pattern_color, background.pattern_size.x > 0.0);
+}
@@ -0,0 +1,29 @@
+struct Background {
+    // Inverse of the view projection, relative to the origin of the pattern
+    inverse_view_proj: mat4x4<f32>,
+    color: vec4<f32>,
+    pattern_size: vec2<f32>,
+    opacity: f32,
+    z_index: f32,
+};
+
+struct VertexOutput {
+    @location(0) ndc: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> background: Background;
+
+// Amount of distinct depth values which are available for ordering the layers of a style
+var<private> LAYER_DEPTH_RANGE: f32 = 65536.0;
+
+// Covers the whole viewport with a single triangle
+@vertex
+fn main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    let ndc = uv * 2.0 - 1.0;
+    return VertexOutput(
+        ndc,
+        vec4<f32>(ndc, (background.z_index + 1.0) / LAYER_DEPTH_RANGE, 1.0),
+    );
+}
@@ -0,0 +1,23 @@
+struct HeatmapDensity {
+    extrude_scale: vec2<f32>,
+    intensity: f32,
+};
+
+struct VertexOutput {
+    @location(0) extrude: vec2<f32>,
+    @location(1) weight: f32,
+    @builtin(position) position: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> density: HeatmapDensity;
+
+// 1 / sqrt(2 * PI)
+var<private> GAUSS_COEF: f32 = 0.3989422804014327;
+
+@fragment
+fn main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The edge of the quad is at three standard deviations of the kernel
+    let d = dot(in.extrude, in.extrude) * 9.0;
+    let value = in.weight * density.intensity * GAUSS_COEF * exp(-0.5 * d);
+    return vec4<f32>(value, 0.0, 0.0, 1.0);
+}
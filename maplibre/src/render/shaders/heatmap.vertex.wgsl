@@ -0,0 +1,44 @@
+struct HeatmapDensity {
+    // Radius of the kernel in normalized device coordinates
+    extrude_scale: vec2<f32>,
+    intensity: f32,
+};
+
+struct VertexOutput {
+    @location(0) extrude: vec2<f32>,
+    @location(1) weight: f32,
+    @builtin(position) position: vec4<f32>,
+};
+
+struct HeatmapTile {
+    transform: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> density: HeatmapDensity;
+@group(1) @binding(0) var<uniform> tile: HeatmapTile;
+
+@vertex
+fn main(
+    @location(0) position: vec2<f32>,
+    @location(1) weight: f32,
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let extrude = corners[vertex_index];
+
+    // The radius of the kernel is constant on the screen. Points behind the camera have a negative
+    // w and are clipped.
+    let center = tile.transform * vec4<f32>(position, 0.0, 1.0);
+    return VertexOutput(
+        extrude,
+        weight,
+        vec4<f32>(center.xy + extrude * density.extrude_scale * center.w, 0.0, center.w),
+    );
+}
@@ -0,0 +1,20 @@
+struct HeatmapColor {
+    opacity: f32,
+};
+
+struct VertexOutput {
+    @location(0) tex_coords: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@group(0) @binding(0) var density_texture: texture_2d<f32>;
+@group(0) @binding(1) var color_ramp: texture_1d<f32>;
+@group(0) @binding(2) var texture_sampler: sampler;
+@group(0) @binding(3) var<uniform> color: HeatmapColor;
+
+@fragment
+fn main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let density = textureSample(density_texture, texture_sampler, in.tex_coords).r;
+    // The ramp is premultiplied
+    return textureSample(color_ramp, texture_sampler, clamp(density, 0.0, 1.0)) * color.opacity;
+}
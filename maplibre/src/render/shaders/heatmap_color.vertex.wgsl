@@ -0,0 +1,14 @@
+struct VertexOutput {
+    @location(0) tex_coords: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+// Covers the whole viewport with a single triangle
+@vertex
+fn main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return VertexOutput(
+        vec2<f32>(uv.x, 1.0 - uv.y),
+        vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0),
+    );
+}
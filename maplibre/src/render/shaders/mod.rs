@@ -388,3 +388,261 @@ impl Shader for RasterTileShader {
         }
     }
 }
+
+pub struct HillshadeShader {
+    pub format: wgpu::TextureFormat,
+}
+
+impl Shader for HillshadeShader {
+    fn describe_vertex(&self) -> VertexState {
+        // Hillshading is computed per fragment, the tile geometry is the same as for raster tiles
+        RasterTileShader {
+            format: self.format,
+        }
+        .describe_vertex()
+    }
+
+    fn describe_fragment(&self) -> FragmentState {
+        FragmentState {
+            source: include_str!("tile_hillshade.fragment.wgsl"),
+            entry_point: "main",
+            targets: vec![Some(wgpu::ColorTargetState {
+                format: self.format,
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }
+    }
+}
+
+/// Per-tile uniform of the hillshade shader.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct ShaderHillshadeTile {
+    /// Latitude of the top and bottom edge of the tile in degrees
+    pub latrange: Vec2f32,
+    pub zoom: f32,
+    /// Size of the elevation grid without its border
+    pub dim: f32,
+}
+
+/// Per-layer uniform of the hillshade shader.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct ShaderHillshadeLayer {
+    pub shadow_color: Vec4f32,
+    pub highlight_color: Vec4f32,
+    pub accent_color: Vec4f32,
+    pub exaggeration: f32,
+    /// Illumination direction in radians
+    pub illumination_direction: f32,
+    pub _padding: Vec2f32,
+}
+
+pub struct TerrainShader {
+    pub format: wgpu::TextureFormat,
+}
+
+impl Shader for TerrainShader {
+    fn describe_vertex(&self) -> VertexState {
+        VertexState {
+            source: include_str!("terrain.vertex.wgsl"),
+            entry_point: "main",
+            buffers: vec![VertexBufferLayout {
+                array_stride: std::mem::size_of::<Vec2f32>() as u64,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: vec![
+                    // tex_coords
+                    wgpu::VertexAttribute {
+                        offset: 0,
+                        format: wgpu::VertexFormat::Float32x2,
+                        shader_location: 0,
+                    },
+                ],
+            }],
+        }
+    }
+
+    fn describe_fragment(&self) -> FragmentState {
+        FragmentState {
+            source: include_str!("terrain.fragment.wgsl"),
+            entry_point: "main",
+            targets: vec![Some(wgpu::ColorTargetState {
+                format: self.format,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent::REPLACE,
+                    alpha: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }
+    }
+}
+
+/// Per-tile uniform of the terrain shader.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct ShaderTerrainTile {
+    pub transform: Mat4x4f32,
+    /// Offset of the tile within the elevation tile, the scale and the dimension of the elevation
+    /// tile
+    pub dem_transform: Vec4f32,
+    /// World units per meter, including the exaggeration
+    pub elevation_scale: f32,
+    pub _padding: Vec3f32,
+}
+
+/// Renders the density of the points of a heatmap into an offscreen texture.
+pub struct HeatmapShader;
+
+impl Shader for HeatmapShader {
+    fn describe_vertex(&self) -> VertexState {
+        VertexState {
+            source: include_str!("heatmap.vertex.wgsl"),
+            entry_point: "main",
+            buffers: vec![VertexBufferLayout {
+                array_stride: std::mem::size_of::<ShaderHeatmapPoint>() as u64,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: vec![
+                    // position
+                    wgpu::VertexAttribute {
+                        offset: 0,
+                        format: wgpu::VertexFormat::Float32x2,
+                        shader_location: 0,
+                    },
+                    // weight
+                    wgpu::VertexAttribute {
+                        offset: wgpu::VertexFormat::Float32x2.size(),
+                        format: wgpu::VertexFormat::Float32,
+                        shader_location: 1,
+                    },
+                ],
+            }],
+        }
+    }
+
+    fn describe_fragment(&self) -> FragmentState {
+        FragmentState {
+            source: include_str!("heatmap.fragment.wgsl"),
+            entry_point: "main",
+            targets: vec![Some(wgpu::ColorTargetState {
+                format: HEATMAP_DENSITY_FORMAT,
+                // The densities of overlapping points add up
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }
+    }
+}
+
+/// Format of the offscreen texture which holds the density of a heatmap.
+pub const HEATMAP_DENSITY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Colors the density of a heatmap and draws it onto the map.
+pub struct HeatmapColorShader {
+    pub format: wgpu::TextureFormat,
+}
+
+impl Shader for HeatmapColorShader {
+    fn describe_vertex(&self) -> VertexState {
+        VertexState {
+            source: include_str!("heatmap_color.vertex.wgsl"),
+            entry_point: "main",
+            buffers: vec![],
+        }
+    }
+
+    fn describe_fragment(&self) -> FragmentState {
+        FragmentState {
+            source: include_str!("heatmap_color.fragment.wgsl"),
+            entry_point: "main",
+            targets: vec![Some(wgpu::ColorTargetState {
+                format: self.format,
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }
+    }
+}
+
+/// Instance of the heatmap shader, i.e. a single point.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct ShaderHeatmapPoint {
+    /// Position of the point in tile coordinates
+    pub position: Vec2f32,
+    pub weight: f32,
+}
+
+/// Per-tile uniform of the heatmap shader.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct ShaderHeatmapTile {
+    pub transform: Mat4x4f32,
+}
+
+/// Per-layer uniform of the heatmap shader.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct ShaderHeatmapDensity {
+    /// Radius of the kernel in normalized device coordinates
+    pub extrude_scale: Vec2f32,
+    pub intensity: f32,
+    pub _padding: f32,
+}
+
+/// Per-layer uniform of the heatmap color shader.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct ShaderHeatmapColor {
+    pub opacity: f32,
+    pub _padding: Vec3f32,
+}
+
+/// Fills the whole viewport with the color or pattern of a background layer.
+pub struct BackgroundShader {
+    pub format: wgpu::TextureFormat,
+}
+
+impl Shader for BackgroundShader {
+    fn describe_vertex(&self) -> VertexState {
+        VertexState {
+            source: include_str!("background.vertex.wgsl"),
+            entry_point: "main",
+            buffers: vec![],
+        }
+    }
+
+    fn describe_fragment(&self) -> FragmentState {
+        FragmentState {
+            source: include_str!("background.fragment.wgsl"),
+            entry_point: "main",
+            targets: vec![Some(wgpu::ColorTargetState {
+                format: self.format,
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }
+    }
+}
+
+/// Per-layer uniform of the background shader.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct ShaderBackground {
+    /// Inverse of the view projection, relative to the origin of the pattern
+    pub inverse_view_proj: Mat4x4f32,
+    /// Premultiplied color, including the opacity
+    pub color: Vec4f32,
+    /// Size of the pattern in world coordinates, zero if there is no pattern
+    pub pattern_size: Vec2f32,
+    pub opacity: f32,
+    pub z_index: f32,
+}
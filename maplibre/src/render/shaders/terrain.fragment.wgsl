@@ -0,0 +1,14 @@
+struct VertexOutput {
+    @location(0) tex_coords: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@group(0) @binding(1)
+var t_drape: texture_2d<f32>;
+@group(0) @binding(2)
+var s_drape: sampler;
+
+@fragment
+fn main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_drape, s_drape, in.tex_coords);
+}
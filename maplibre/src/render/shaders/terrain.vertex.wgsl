@@ -0,0 +1,49 @@
+struct TerrainTile {
+    transform: mat4x4<f32>,
+    // xy: offset of the tile within the elevation tile, z: scale, w: dimension of the elevation tile
+    dem_transform: vec4<f32>,
+    elevation_scale: f32,
+};
+
+struct VertexOutput {
+    @location(0) tex_coords: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> tile: TerrainTile;
+@group(1) @binding(0) var dem_texture: texture_2d<f32>;
+
+var<private> EXTENT: f32 = 4096.0;
+
+// Elevations are packed using the Mapbox Terrain-RGB encoding
+fn load_elevation(position: vec2<i32>) -> f32 {
+    let rgb = textureLoad(dem_texture, position, 0).rgb * 255.0;
+    return (rgb.r * 65536.0 + rgb.g * 256.0 + rgb.b) * 0.1 - 10000.0;
+}
+
+fn elevation(tex_coords: vec2<f32>) -> f32 {
+    let dim = tile.dem_transform.w;
+    // Sample at pixel centers, the border of the texture covers the half pixel at the edges
+    let position = (tile.dem_transform.xy + tex_coords * tile.dem_transform.z) * dim - 0.5;
+    let base = clamp(floor(position), vec2<f32>(-1.0), vec2<f32>(dim - 1.0));
+    let f = clamp(position - base, vec2<f32>(0.0), vec2<f32>(1.0));
+
+    // Skip the border of the texture
+    let pixel = vec2<i32>(base) + vec2<i32>(1, 1);
+    let top = mix(load_elevation(pixel), load_elevation(pixel + vec2<i32>(1, 0)), f.x);
+    let bottom = mix(load_elevation(pixel + vec2<i32>(0, 1)), load_elevation(pixel + vec2<i32>(1, 1)), f.x);
+    return mix(top, bottom, f.y);
+}
+
+@vertex
+fn main(
+    @location(0) tex_coords: vec2<f32>,
+) -> VertexOutput {
+    let z = elevation(tex_coords) * tile.elevation_scale;
+
+    var position = tile.transform * vec4<f32>(tex_coords * EXTENT, z, 1.0);
+    // The depth test passes for greater values, so the terrain which is closer to the camera needs
+    // larger depth values.
+    position.z = position.w - position.z;
+    return VertexOutput(tex_coords, position);
+}
@@ -9,6 +9,9 @@ struct ShaderGlobals {
 
 @group(0) @binding(0) var<uniform> globals: ShaderGlobals;
 
+// Amount of distinct depth values which are available for ordering the layers of a style
+var<private> LAYER_DEPTH_RANGE: f32 = 65536.0;
+
 struct VertexOutput {
     @location(0)  v_color: vec4<f32>,
     @builtin(position) position: vec4<f32>,
@@ -27,7 +30,6 @@ fn main(
     @location(10) z_index: f32,
     @builtin(instance_index) instance_idx: u32 // instance_index is used when we have multiple instances of the same "object"
 ) -> VertexOutput {
-    let z = -z_index;
     let width = 3.0 * zoom_factor;
 
     // The following code moves all "invisible" vertices to (0, 0, 0)
@@ -35,7 +37,10 @@ fn main(
     //   return VertexOutput(color, vec4<f32>(0.0, 0.0, 0.0, 1.0));
     //}
 
-    var final_position = mat4x4<f32>(translate1, translate2, translate3, translate4) * vec4<f32>(position + normal * width, z, 1.0);
+    var final_position = mat4x4<f32>(translate1, translate2, translate3, translate4) * vec4<f32>(position + normal * width, 0.0, 1.0);
+    // The depth test passes for greater values, such that layers which come later in the style are
+    // drawn on top
+    final_position.z = (z_index + 1.0) / LAYER_DEPTH_RANGE * final_position.w;
 
     return VertexOutput(color, final_position);
 }
@@ -0,0 +1,90 @@
+struct VertexOutput {
+    @location(0) tex_coords: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+struct HillshadeTile {
+    latrange: vec2<f32>,
+    zoom: f32,
+    dim: f32,
+};
+
+struct HillshadeLayer {
+    shadow_color: vec4<f32>,
+    highlight_color: vec4<f32>,
+    accent_color: vec4<f32>,
+    exaggeration: f32,
+    illumination_direction: f32,
+};
+
+// Elevation encoded using Mapbox Terrain-RGB, including a border of one pixel
+@group(0) @binding(0)
+var t_dem: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> tile: HillshadeTile;
+
+@group(1) @binding(0)
+var<uniform> layer: HillshadeLayer;
+
+const PI: f32 = 3.141592653589793;
+
+fn elevation(position: vec2<i32>) -> f32 {
+    let pixel = textureLoad(t_dem, position, 0) * 255.0;
+    return (pixel.r * 65536.0 + pixel.g * 256.0 + pixel.b) * 0.1 - 10000.0;
+}
+
+// Adopted from the hillshade_prepare and hillshade shaders of maplibre-gl-js
+@fragment
+fn main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let max_position = vec2<f32>(tile.dim - 1.0);
+    // Skip the border
+    let position = vec2<i32>(clamp(floor(in.tex_coords * tile.dim), vec2<f32>(0.0), max_position)) + vec2<i32>(1, 1);
+
+    // Sobel filter over the neighbouring pixels
+    let a = elevation(position + vec2<i32>(-1, -1));
+    let b = elevation(position + vec2<i32>(0, -1));
+    let c = elevation(position + vec2<i32>(1, -1));
+    let d = elevation(position + vec2<i32>(-1, 0));
+    let f = elevation(position + vec2<i32>(1, 0));
+    let g = elevation(position + vec2<i32>(-1, 1));
+    let h = elevation(position + vec2<i32>(0, 1));
+    let i = elevation(position + vec2<i32>(1, 1));
+
+    // Lower zoom levels are exaggerated, otherwise the terrain looks flat
+    let exaggeration_factor = select(select(0.3, 0.35, tile.zoom < 4.5), 0.4, tile.zoom < 2.0);
+    let zoom_exaggeration = select(0.0, (tile.zoom - 15.0) * exaggeration_factor, tile.zoom < 15.0);
+
+    let deriv = clamp(
+        vec2<f32>((c + f + f + i) - (a + d + d + g), (g + h + h + i) - (a + b + b + c))
+            / pow(2.0, zoom_exaggeration + (19.2562 - tile.zoom)),
+        vec2<f32>(-1.0),
+        vec2<f32>(1.0)
+    );
+
+    // Pixels are larger close to the equator
+    let scale_factor = cos(radians((tile.latrange.x - tile.latrange.y) * (1.0 - in.tex_coords.y) + tile.latrange.y));
+    let slope = atan(1.25 * length(deriv) / scale_factor);
+    var aspect = PI / 2.0 * select(-1.0, 1.0, deriv.y > 0.0);
+    if (deriv.x != 0.0) {
+        aspect = atan2(deriv.y, -deriv.x);
+    }
+
+    let intensity = layer.exaggeration;
+    let azimuth = layer.illumination_direction + PI;
+
+    let base = 1.875 - intensity * 1.75;
+    let max_value = 0.5 * PI;
+    var scaled_slope = slope;
+    if (intensity != 0.5) {
+        scaled_slope = ((pow(base, slope) - 1.0) / (pow(base, max_value) - 1.0)) * max_value;
+    }
+
+    let accent = cos(scaled_slope);
+    let accent_color = (1.0 - accent) * layer.accent_color * clamp(intensity * 2.0, 0.0, 1.0);
+
+    let shade_position = (aspect + azimuth) / PI + 0.5;
+    let shade = abs(shade_position - 2.0 * floor(shade_position / 2.0) - 1.0);
+    let shade_color = mix(layer.shadow_color, layer.highlight_color, shade) * sin(scaled_slope) * clamp(intensity * 2.0, 0.0, 1.0);
+
+    return accent_color * (1.0 - shade_color.a) + shade_color;
+}
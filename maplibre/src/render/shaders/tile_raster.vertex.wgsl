@@ -4,6 +4,8 @@ struct VertexOutput {
 };
 
 var<private> EXTENT: f32 = 4096.0;
+// Amount of distinct depth values which are available for ordering the layers of a style
+var<private> LAYER_DEPTH_RANGE: f32 = 65536.0;
 
 @vertex
 fn main(
@@ -42,5 +44,6 @@ fn main(
     let tex_coords = TEX_COORDS[vertex_idx];
 
     var final_position = mat4x4<f32>(translate1, translate2, translate3, translate4) * vec4<f32>(vertex, 1.0);
+    final_position.z = (z_index + 1.0) / LAYER_DEPTH_RANGE * final_position.w;
     return VertexOutput(tex_coords, final_position);
 }
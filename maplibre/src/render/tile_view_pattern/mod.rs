@@ -82,7 +82,7 @@ pub struct TileShape {
 }
 
 impl TileShape {
-    fn new(coords: WorldTileCoords, zoom: Zoom) -> Self {
+    pub(crate) fn new(coords: WorldTileCoords, zoom: Zoom) -> Self {
         Self {
             coords,
             zoom_factor: zoom.scale_to_tile(&coords),
@@ -1,7 +1,9 @@
 use std::{collections::HashSet, marker::PhantomData};
 
+use cgmath::{Matrix4, SquareMatrix};
+
 use crate::{
-    coords::{ViewRegion, Zoom},
+    coords::{ViewRegion, WorldTileCoords, Zoom, EXTENT},
     render::{
         camera::ViewProjection,
         resource::{BackingBufferDescriptor, Queue},
@@ -127,33 +129,67 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
 
     #[tracing::instrument(skip_all)]
     pub fn upload_pattern(&mut self, queue: &Q, view_proj: &ViewProjection) {
+        self.upload(queue, |_target, shape| {
+            view_proj
+                .to_model_view_projection(shape.transform)
+                .downcast()
+        });
+    }
+
+    /// Uploads the pattern such that each source shape is rendered into a texture which covers
+    /// exactly its target tile. This is used to drape the layers over the terrain.
+    ///
+    /// The depth of the layers is set by their shaders from their z-index.
+    #[tracing::instrument(skip_all)]
+    pub fn upload_draped_pattern(&mut self, queue: &Q, zoom: Zoom) {
+        #[rustfmt::skip]
+        let tile_to_ndc: Matrix4<f64> = Matrix4::new(
+            2.0 / EXTENT, 0.0, 0.0, 0.0,
+            0.0, -2.0 / EXTENT, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            -1.0, 1.0, 0.0, 1.0,
+        );
+
+        self.upload(queue, |target, shape| {
+            let world_to_target = target
+                .transform_for_zoom(zoom)
+                .invert()
+                .expect("tile transform is not invertible");
+            (tile_to_ndc * world_to_target * shape.transform)
+                .cast::<f32>()
+                .expect("Unable to cast draped transform to f32")
+        });
+    }
+
+    fn upload<F>(&mut self, queue: &Q, transform: F)
+    where
+        F: Fn(WorldTileCoords, &TileShape) -> Matrix4<f32>,
+    {
         let mut buffer = Vec::with_capacity(self.view_tiles.len());
 
-        let mut add_to_buffer = |shape: &mut TileShape| {
+        let mut add_to_buffer = |target: WorldTileCoords, shape: &mut TileShape| {
             shape.set_buffer_range(buffer.len() as u64);
             // TODO: Name `ShaderTileMetadata` is unfortunate here, because for raster rendering it actually is a layer
             buffer.push(ShaderTileMetadata {
                 // We are casting here from 64bit to 32bit, because 32bit is more performant and is
                 // better supported.
-                transform: view_proj
-                    .to_model_view_projection(shape.transform)
-                    .downcast()
-                    .into(), // TODO: move this calculation to update() fn above
+                transform: transform(target, shape).into(), // TODO: move this calculation to update() fn above
                 zoom_factor: shape.zoom_factor as f32,
             });
         };
 
         for view_tile in &mut self.view_tiles {
+            let target = view_tile.target;
             match &mut view_tile.source {
                 SourceShapes::Parent(source_shape) => {
-                    add_to_buffer(source_shape);
+                    add_to_buffer(target, source_shape);
                 }
                 SourceShapes::Children(source_shapes) => {
                     for source_shape in source_shapes {
-                        add_to_buffer(source_shape);
+                        add_to_buffer(target, source_shape);
                     }
                 }
-                SourceShapes::SourceEqTarget(source_shape) => add_to_buffer(source_shape),
+                SourceShapes::SourceEqTarget(source_shape) => add_to_buffer(target, source_shape),
                 SourceShapes::None => {}
             }
         }
@@ -5,12 +5,17 @@ use std::{
 
 use cgmath::{prelude::*, *};
 
+use geo::BoundingRect;
+use geo_types::Geometry;
+
 use crate::{
-    coords::{ViewRegion, WorldCoords, Zoom, ZoomLevel},
+    camera::{constraints::CameraConstraints, CameraOptions},
+    coords::{LatLon, LatLonBounds, ScreenPoint, ViewRegion, WorldCoords, Zoom, ZoomLevel},
     render::camera::{
         Camera, EdgeInsets, InvertedViewProjection, Perspective, ViewProjection, FLIP_Y,
         OPENGL_TO_WGPU_MATRIX,
     },
+    terrain::Elevation,
     util::{
         math::{bounds_from_points, Aabb2, Aabb3, Plane},
         ChangeObserver,
@@ -20,6 +25,14 @@ use crate::{
 
 const VIEW_REGION_PADDING: i32 = 1;
 const MAX_N_TILES: usize = 512;
+/// Amount of steps which are used to find the intersection of a ray with the terrain
+const TERRAIN_RAY_MARCH_STEPS: usize = 64;
+/// Amount of bisections which are used to refine the intersection of a ray with the terrain
+const TERRAIN_RAY_REFINE_STEPS: usize = 10;
+/// Maximum amount of steps which are used to fit the camera to bounds
+const CAMERA_FIT_STEPS: usize = 16;
+/// The camera fits the bounds if the zoom changes less than this in a step
+const CAMERA_FIT_EPSILON: f64 = 1e-6;
 
 pub struct ViewState {
     zoom: ChangeObserver<Zoom>,
@@ -29,6 +42,10 @@ pub struct ViewState {
     width: f64,
     height: f64,
     edge_insets: EdgeInsets,
+
+    elevation: Option<Elevation>,
+
+    constraints: CameraConstraints,
 }
 
 impl ViewState {
@@ -55,6 +72,8 @@ impl ViewState {
                 left: 0.0,
                 right: 0.0,
             },
+            elevation: None,
+            constraints: CameraConstraints::default(),
         }
     }
     pub fn set_edge_insets(&mut self, edge_insets: EdgeInsets) {
@@ -65,6 +84,88 @@ impl ViewState {
         &self.edge_insets
     }
 
+    /// Sets the elevation of the terrain. If set, the camera is lifted above the terrain and
+    /// picking happens on the surface of the terrain instead of the `z=0` plane.
+    pub fn set_elevation(&mut self, elevation: Option<Elevation>) {
+        self.elevation = elevation;
+    }
+
+    pub fn constraints(&self) -> &CameraConstraints {
+        &self.constraints
+    }
+
+    /// Sets the limits of the camera and immediately applies them.
+    pub fn set_constraints(&mut self, constraints: CameraConstraints) {
+        self.constraints = constraints;
+        self.apply_constraints();
+    }
+
+    /// Enforces the [`CameraConstraints`] on the current zoom, pitch and center of the camera.
+    /// This happens once per frame in the
+    /// [`camera_constraints_system`](crate::camera::constraints::camera_constraints_system).
+    pub fn apply_constraints(&mut self) {
+        *self.zoom = self.constraints.constrain_zoom(*self.zoom);
+
+        let pitch = self.constraints.constrain_pitch(self.camera.get_pitch());
+        let center = self.constraints.constrain_center(
+            self.camera.position(),
+            *self.zoom,
+            self.width,
+            self.height,
+        );
+
+        let camera = self.camera.deref_mut();
+        camera.set_pitch(pitch);
+        camera.move_to(center);
+    }
+
+    pub fn elevation(&self) -> Option<&Elevation> {
+        self.elevation.as_ref()
+    }
+
+    pub fn elevation_mut(&mut self) -> Option<&mut Elevation> {
+        self.elevation.as_mut()
+    }
+
+    /// Returns the elevation of the terrain in world units at the specified world coordinates.
+    /// Returns `0.0` if there is no terrain or no elevation data available.
+    pub fn ground_elevation(&self, x: f64, y: f64) -> f64 {
+        self.elevation
+            .as_ref()
+            .and_then(|elevation| {
+                elevation.world_elevation_at(WorldCoords::at_ground(x, y), *self.zoom)
+            })
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the elevation of the terrain in world units below the center of the camera.
+    pub fn center_elevation(&self) -> f64 {
+        let position = self.camera.position();
+        self.ground_elevation(position.x, position.y)
+    }
+
+    /// Returns the range of elevations in world units which the terrain can have. The range
+    /// always contains `0.0`, because areas without elevation data are rendered at `z=0`.
+    fn elevation_range(&self) -> Option<(f64, f64)> {
+        let (min, max) = self.elevation.as_ref()?.elevation_range()?;
+        let world_y = self.camera.position().y;
+        let zoom = *self.zoom;
+        Some((
+            Elevation::meters_to_world(min, world_y, zoom).min(0.0),
+            Elevation::meters_to_world(max, world_y, zoom).max(0.0),
+        ))
+    }
+
+    /// Width of the view in logical pixels.
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    /// Height of the view in logical pixels.
+    pub fn height(&self) -> f64 {
+        self.height
+    }
+
     pub fn resize(&mut self, size: LogicalSize) {
         self.width = size.width() as f64;
         self.height = size.height() as f64;
@@ -80,9 +181,242 @@ impl ViewState {
                     *self.zoom,
                     visible_level,
                 )
+                .with_world_copies(self.constraints.render_world_copies)
             })
     }
 
+    /// Geographic coordinates of the center of the camera.
+    pub fn center(&self) -> LatLon {
+        let position = self.camera.position();
+        WorldCoords::at_ground(position.x, position.y).into_lat_lon(*self.zoom)
+    }
+
+    /// Rotation of the map in degrees counter-clockwise from north.
+    pub fn bearing(&self) -> Deg<f64> {
+        -Deg::from(self.camera.get_roll())
+    }
+
+    pub fn pitch(&self) -> Deg<f64> {
+        Deg::from(self.camera.get_pitch())
+    }
+
+    /// Returns the point on the screen at which `lat_lon` is shown. If copies of the world are
+    /// rendered, then the copy which is closest to the center is used.
+    ///
+    /// Returns `None` if the location is behind the camera.
+    pub fn project(&self, lat_lon: LatLon) -> Option<ScreenPoint> {
+        let mut world = WorldCoords::from_lat_lon(lat_lon, *self.zoom);
+        if self.constraints.render_world_copies {
+            let world_size = self.zoom.world_size();
+            let offset = world.x - self.camera.position().x;
+            world.x -= (offset / world_size).round() * world_size;
+        }
+
+        let z = self.ground_elevation(world.x, world.y);
+        let clip = self
+            .view_projection()
+            .project(Vector4::new(world.x, world.y, z, 1.0));
+        if clip.w <= 0.0 {
+            return None;
+        }
+
+        let window = self.clip_to_window(&clip);
+        Some(ScreenPoint::new(window.x, window.y))
+    }
+
+    /// Returns the location on the ground which is shown at `point`. The longitude exceeds
+    /// `-180..180` if the point is on a copy of the world.
+    ///
+    /// Returns `None` if the ground is not visible at `point`, e.g. above the horizon.
+    pub fn unproject(&self, point: ScreenPoint) -> Option<LatLon> {
+        let inverted_view_proj = self.view_projection().invert();
+        let world = self.window_to_world_at_ground(
+            &Vector2::new(point.x, point.y),
+            &inverted_view_proj,
+            true,
+        )?;
+        Some(WorldCoords::at_ground(world.x, world.y).into_lat_lon(*self.zoom))
+    }
+
+    /// Returns a box which contains the visible area of the map, including the areas which are
+    /// visible because of the pitch of the camera. If the ground is not visible, the whole world
+    /// is returned.
+    pub fn bounds(&self) -> LatLonBounds {
+        let world_size = self.zoom.world_size();
+        let (min, max) = self
+            .view_region_bounding_box(&self.view_projection().invert())
+            .map(|bounding_box| (bounding_box.min, bounding_box.max))
+            .unwrap_or((Point2::new(0.0, 0.0), Point2::new(world_size, world_size)));
+
+        let north_west =
+            WorldCoords::at_ground(min.x, min.y.clamp(0.0, world_size)).into_lat_lon(*self.zoom);
+        let south_east =
+            WorldCoords::at_ground(max.x, max.y.clamp(0.0, world_size)).into_lat_lon(*self.zoom);
+
+        LatLonBounds::new(
+            LatLon::new(south_east.latitude, north_west.longitude),
+            LatLon::new(north_west.latitude, south_east.longitude),
+        )
+    }
+
+    /// Returns a camera with the specified `bearing` and `pitch`, which shows the box between
+    /// `south_west` and `north_east` as large as possible. The box is placed within `padding`,
+    /// which is added to the [`EdgeInsets`] of the view. The elevation of the terrain is ignored.
+    ///
+    /// Returns `None` if the padding leaves no space for the box.
+    pub fn camera_for_bounds(
+        &self,
+        south_west: LatLon,
+        north_east: LatLon,
+        padding: EdgeInsets,
+        bearing: Deg<f64>,
+        pitch: Deg<f64>,
+    ) -> Option<CameraOptions> {
+        let padding = self.edge_insets + padding;
+        let available_width = self.width - padding.left - padding.right;
+        let available_height = self.height - padding.top - padding.bottom;
+        if available_width <= 0.0 || available_height <= 0.0 {
+            return None;
+        }
+
+        let (north_west, south_east) =
+            LatLonBounds::new(south_west, north_east).to_world(Zoom::default());
+        let corners = [
+            Point2::new(north_west.x, north_west.y),
+            Point2::new(south_east.x, north_west.y),
+            Point2::new(south_east.x, south_east.y),
+            Point2::new(north_west.x, south_east.y),
+        ];
+
+        // This is exact without bearing and pitch. Otherwise, the camera is refined by projecting
+        // the corners of the box.
+        let mut center = Point2::new(
+            (north_west.x + south_east.x) / 2.0,
+            (north_west.y + south_east.y) / 2.0,
+        );
+        let mut zoom = self.fit_zoom(
+            available_width,
+            available_height,
+            south_east.x - north_west.x,
+            south_east.y - north_west.y,
+        );
+
+        for _ in 0..CAMERA_FIT_STEPS {
+            let scale = 2.0_f64.powf(zoom);
+            let probe = self.probe(
+                Point2::new(center.x * scale, center.y * scale),
+                zoom,
+                bearing,
+                pitch,
+            );
+            let view_projection = probe.view_projection();
+
+            let mut min = Vector2::new(f64::INFINITY, f64::INFINITY);
+            let mut max = Vector2::new(f64::NEG_INFINITY, f64::NEG_INFINITY);
+            for corner in &corners {
+                let clip = view_projection.project(Vector4::new(
+                    corner.x * scale,
+                    corner.y * scale,
+                    0.0,
+                    1.0,
+                ));
+                if clip.w <= 0.0 {
+                    return None;
+                }
+                let window = probe.clip_to_window(&clip);
+                min = Vector2::new(min.x.min(window.x), min.y.min(window.y));
+                max = Vector2::new(max.x.max(window.x), max.y.max(window.y));
+            }
+
+            // Move the center of the box to the center of the padding
+            let inverted_view_projection = view_projection.invert();
+            let box_center = probe.window_to_world_at_ground(
+                &((min + max) / 2.0),
+                &inverted_view_projection,
+                false,
+            )?;
+            let padding_center = probe.window_to_world_at_ground(
+                &padding.center(self.width, self.height).to_vec(),
+                &inverted_view_projection,
+                false,
+            )?;
+            center += (box_center - padding_center) / scale;
+
+            let next_zoom = self.fit_zoom(
+                available_width,
+                available_height,
+                (max.x - min.x) / scale,
+                (max.y - min.y) / scale,
+            );
+            let converged = (next_zoom - zoom).abs() < CAMERA_FIT_EPSILON;
+            zoom = next_zoom;
+            if converged {
+                break;
+            }
+        }
+
+        Some(CameraOptions {
+            center: Some(WorldCoords::at_ground(center.x, center.y).into_lat_lon(Zoom::default())),
+            zoom: Some(Zoom::new(zoom)),
+            bearing: Some(bearing),
+            pitch: Some(pitch),
+            padding: None,
+        })
+    }
+
+    /// Returns a camera which shows the bounding box of `geometry` as large as possible. See
+    /// [`ViewState::camera_for_bounds`].
+    pub fn camera_for_geometry(
+        &self,
+        geometry: &Geometry<f64>,
+        padding: EdgeInsets,
+        bearing: Deg<f64>,
+        pitch: Deg<f64>,
+    ) -> Option<CameraOptions> {
+        let rect = geometry.bounding_rect()?;
+        self.camera_for_bounds(
+            LatLon::new(rect.min().y, rect.min().x),
+            LatLon::new(rect.max().y, rect.max().x),
+            padding,
+            bearing,
+            pitch,
+        )
+    }
+
+    /// Returns the zoom at which a box with a size of `width` and `height` at zoom `0` fills the
+    /// available space. The zoom is clamped to the [`CameraConstraints`].
+    fn fit_zoom(
+        &self,
+        available_width: f64,
+        available_height: f64,
+        width: f64,
+        height: f64,
+    ) -> f64 {
+        let scale = (available_width / width).min(available_height / height);
+        self.constraints
+            .constrain_zoom(Zoom::new(scale.log2()))
+            .into()
+    }
+
+    /// Creates a view with the same size, field of view and padding as this one, but with
+    /// another camera. The view has no terrain.
+    fn probe(&self, position: Point2<f64>, zoom: f64, bearing: Deg<f64>, pitch: Deg<f64>) -> Self {
+        let mut camera = Camera::new(position, Deg(0.0), Deg(0.0));
+        camera.set_roll(Deg(-bearing.0));
+        camera.set_pitch(pitch);
+
+        Self {
+            zoom: ChangeObserver::new(Zoom::new(zoom)),
+            camera: ChangeObserver::new(camera),
+            perspective: Perspective::new(self.perspective.fovy()),
+            width: self.width,
+            height: self.height,
+            edge_insets: self.edge_insets,
+            elevation: None,
+            constraints: self.constraints,
+        }
+    }
+
     pub fn get_intersection_time(
         ray_origin: Vector3<f64>,
         ray_direction: Vector3<f64>,
@@ -128,7 +462,13 @@ impl ViewState {
         ];
         let ray_origin = Vector3::new(-camera.x, -camera.y, -camera_height);
 
-        let plane_origin = Vector3::new(-camera.x, -camera.y, 0.0);
+        // The camera is lifted by the elevation at the center. The furthest visible point lies on
+        // the lowest possible terrain.
+        let depth_below_center = self
+            .elevation_range()
+            .map(|(min, _)| (self.center_elevation() - min).max(0.0))
+            .unwrap_or(0.0);
+        let plane_origin = Vector3::new(-camera.x, -camera.y, depth_below_center);
         let plane_normal = (rotation * Vector4::new(0.0, 0.0, 1.0, 1.0)).truncate();
 
         rays.iter()
@@ -160,7 +500,9 @@ impl ViewState {
 
         let camera_to_center_distance = self.camera_to_center_distance();
 
-        let camera_matrix = self.camera.calc_matrix(camera_to_center_distance);
+        // The camera keeps its distance to the terrain at the center
+        let camera_matrix = self.camera.calc_matrix(camera_to_center_distance)
+            * Matrix4::from_translation(Vector3::new(0.0, 0.0, -self.center_elevation()));
 
         // Add a bit extra to avoid precision problems when a fragment's distance is exactly `furthest_distance`
         let far_z = self.furthest_distance(camera_to_center_distance, center_offset) * 1.01;
@@ -211,7 +553,9 @@ impl ViewState {
         self.zoom.did_change(0.05)
     }
 
+    /// Sets the zoom, which is clamped to the [`CameraConstraints`].
     pub fn update_zoom(&mut self, new_zoom: Zoom) {
+        let new_zoom = self.constraints.constrain_zoom(new_zoom);
         *self.zoom = new_zoom;
         log::info!("zoom: {new_zoom}");
     }
@@ -348,7 +692,8 @@ impl ViewState {
         )
     }
 
-    /// Gets the world coordinates for the specified `window` coordinates on the `z=0` plane.
+    /// Gets the world coordinates for the specified `window` coordinates on the ground. The ground
+    /// is the surface of the terrain if there is elevation data, otherwise the `z=0` plane.
     pub fn window_to_world_at_ground(
         &self,
         window: &Vector2<f64>,
@@ -361,9 +706,11 @@ impl ViewState {
         let far_world =
             self.window_to_world(&Vector3::new(window.x, window.y, 1.0), inverted_view_proj);
 
-        // for z = 0 in world coordinates
-        // Idea comes from: https://dondi.lmu.build/share/cg/unproject-explained.pdf
-        let u = -near_world.z / (far_world.z - near_world.z);
+        let u = match self.elevation_range() {
+            Some((min, max)) => self.intersect_terrain(near_world, far_world, min, max),
+            None => Self::intersect_height(near_world, far_world, 0.0),
+        };
+
         if !bound || (0.0..=1.01).contains(&u) {
             let result = near_world + u * (far_world - near_world);
             Some(Vector2::new(result.x, result.y))
@@ -372,6 +719,65 @@ impl ViewState {
         }
     }
 
+    /// Returns `u` such that `near + u * (far - near)` lies on the plane with `z = height`.
+    ///
+    /// Idea comes from: https://dondi.lmu.build/share/cg/unproject-explained.pdf
+    fn intersect_height(near_world: Vector3<f64>, far_world: Vector3<f64>, height: f64) -> f64 {
+        (height - near_world.z) / (far_world.z - near_world.z)
+    }
+
+    /// Returns `u` such that `near + u * (far - near)` is the first intersection of the ray
+    /// with the terrain. The terrain lies between `min` and `max`.
+    ///
+    /// The ray is marched between the planes of the highest and lowest elevation. The first step
+    /// which ends below the terrain is then refined by bisection. If the ray does not hit the
+    /// terrain, then the intersection with the lowest plane is returned.
+    fn intersect_terrain(
+        &self,
+        near_world: Vector3<f64>,
+        far_world: Vector3<f64>,
+        min: f64,
+        max: f64,
+    ) -> f64 {
+        let u_max = Self::intersect_height(near_world, far_world, max);
+        let u_min = Self::intersect_height(near_world, far_world, min);
+
+        // The ray never reaches the terrain, e.g. if it points above the horizon
+        if !u_max.is_finite() || !u_min.is_finite() || u_min < 0.0 {
+            return u_min;
+        }
+
+        let below_ground = |u: f64| {
+            let point = near_world + u * (far_world - near_world);
+            point.z <= self.ground_elevation(point.x, point.y)
+        };
+
+        let start = u_max.max(0.0);
+        let step = (u_min - start) / TERRAIN_RAY_MARCH_STEPS as f64;
+
+        let mut previous = start;
+        for i in 0..=TERRAIN_RAY_MARCH_STEPS {
+            let current = start + step * i as f64;
+
+            if below_ground(current) {
+                let (mut above, mut below) = (previous, current);
+                for _ in 0..TERRAIN_RAY_REFINE_STEPS {
+                    let middle = (above + below) / 2.0;
+                    if below_ground(middle) {
+                        below = middle;
+                    } else {
+                        above = middle;
+                    }
+                }
+                return below;
+            }
+
+            previous = current;
+        }
+
+        u_min
+    }
+
     /// Calculates an [`Aabb2`] bounding box which contains at least the visible area on the `z=0`
     /// plane. One can think of it as being the bounding box of the geometry which forms the
     /// intersection between the viewing frustum and the `z=0` plane.
@@ -380,26 +786,41 @@ impl ViewState {
     /// window to calculate intersections points with the `z=0` plane. Then a bounding box is
     /// calculated.
     ///
+    /// If there is terrain, then the rays are intersected with the planes of the lowest and
+    /// highest elevation. The bounding box contains the intersections with both planes and
+    /// therefore all the terrain which is visible.
+    ///
     /// *Note:* It is possible that no such bounding box exists. This is the case if the `z=0` plane
     /// is not in view.
     pub fn view_region_bounding_box(
         &self,
         inverted_view_proj: &InvertedViewProjection,
     ) -> Option<Aabb2<f64>> {
+        let heights = match self.elevation_range() {
+            Some((min, max)) => vec![min, max],
+            None => vec![0.0],
+        };
+
         let screen_bounding_box = [
             Vector2::new(0.0, 0.0),
             Vector2::new(self.width, 0.0),
             Vector2::new(self.width, self.height),
             Vector2::new(0.0, self.height),
         ]
-        .map(|point| self.window_to_world_at_ground(&point, inverted_view_proj, false));
+        .into_iter()
+        .flat_map(|window| {
+            let near_world =
+                self.window_to_world(&Vector3::new(window.x, window.y, 0.0), inverted_view_proj);
+            let far_world =
+                self.window_to_world(&Vector3::new(window.x, window.y, 1.0), inverted_view_proj);
+
+            heights.iter().map(move |height| {
+                let u = Self::intersect_height(near_world, far_world, *height);
+                near_world + u * (far_world - near_world)
+            })
+        });
 
-        let (min, max) = bounds_from_points(
-            screen_bounding_box
-                .into_iter()
-                .flatten()
-                .map(|point| [point.x, point.y]),
-        )?;
+        let (min, max) = bounds_from_points(screen_bounding_box.map(|point| [point.x, point.y]))?;
 
         Some(Aabb2::new(Point2::from(min), Point2::from(max)))
     }
@@ -464,11 +885,16 @@ impl ViewState {
 
 #[cfg(test)]
 mod tests {
-    use cgmath::{Deg, Matrix4, Vector2, Vector4};
+    use cgmath::{Deg, Matrix4, Point2, Vector2, Vector4};
+    use image::RgbaImage;
 
     use crate::{
-        coords::{WorldCoords, Zoom},
-        render::view_state::ViewState,
+        camera::{animation::CameraAnimator, constraints::CameraConstraints},
+        coords::{LatLon, ScreenPoint, WorldCoords, WorldTileCoords, Zoom},
+        raster::dem::DemData,
+        render::{camera::EdgeInsets, view_state::ViewState},
+        style::source::DemEncoding,
+        terrain::Elevation,
         window::PhysicalSize,
     };
 
@@ -510,4 +936,220 @@ mod tests {
 
         // TODO: verify far distance plane calculation
     }
+
+    #[test]
+    fn apply_constraints() {
+        let mut state = ViewState::new(
+            PhysicalSize::new(800, 600).unwrap(),
+            WorldCoords::at_ground(-100.0, 100.0),
+            Zoom::new(2.0),
+            Deg(0.0),
+            Deg(60.0),
+        );
+        state.set_constraints(CameraConstraints {
+            max_zoom: Zoom::new(5.0),
+            max_pitch: Deg(10.0),
+            ..CameraConstraints::default()
+        });
+
+        // The world wraps around
+        assert_eq!(
+            state.camera().position(),
+            Point2::new(2048.0 - 100.0, 100.0)
+        );
+
+        state.update_zoom(Zoom::new(8.0));
+        assert_eq!(f64::from(state.zoom()), 5.0);
+
+        state.camera_mut().set_pitch(Deg(20.0));
+        state.apply_constraints();
+        assert!((Deg::from(state.camera().get_pitch()).0 - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn camera_for_bounds() {
+        let mut state = ViewState::new(
+            PhysicalSize::new(800, 600).unwrap(),
+            WorldCoords::at_ground(0.0, 0.0),
+            Zoom::new(2.0),
+            Deg(0.0),
+            Deg(60.0),
+        );
+        state.set_edge_insets(EdgeInsets {
+            top: 50.0,
+            ..EdgeInsets::default()
+        });
+
+        let south_west = LatLon::new(48.0, 2.0);
+        let north_east = LatLon::new(52.0, 9.0);
+        let padding = EdgeInsets {
+            top: 10.0,
+            bottom: 20.0,
+            left: 30.0,
+            right: 40.0,
+        };
+
+        for (bearing, pitch) in [(0.0, 0.0), (30.0, 0.0), (-45.0, 25.0)] {
+            let camera = state
+                .camera_for_bounds(south_west, north_east, padding, Deg(bearing), Deg(pitch))
+                .unwrap();
+            CameraAnimator::default().jump_to(&mut state, &camera);
+
+            let view_projection = state.view_projection();
+            let zoom = state.zoom();
+            let corners = [
+                LatLon::new(south_west.latitude, south_west.longitude),
+                LatLon::new(south_west.latitude, north_east.longitude),
+                LatLon::new(north_east.latitude, north_east.longitude),
+                LatLon::new(north_east.latitude, south_west.longitude),
+            ]
+            .map(|corner| {
+                let world = WorldCoords::from_lat_lon(corner, zoom);
+                state.clip_to_window(
+                    &view_projection.project(Vector4::new(world.x, world.y, 0.0, 1.0)),
+                )
+            });
+
+            let min_x = corners.iter().map(|c| c.x).fold(f64::INFINITY, f64::min);
+            let max_x = corners
+                .iter()
+                .map(|c| c.x)
+                .fold(f64::NEG_INFINITY, f64::max);
+            let min_y = corners.iter().map(|c| c.y).fold(f64::INFINITY, f64::min);
+            let max_y = corners
+                .iter()
+                .map(|c| c.y)
+                .fold(f64::NEG_INFINITY, f64::max);
+
+            // The box lies within the padding and touches it on two sides
+            let epsilon = 0.01;
+            assert!(min_x >= 30.0 - epsilon && max_x <= 760.0 + epsilon);
+            assert!(min_y >= 60.0 - epsilon && max_y <= 580.0 + epsilon);
+            assert!(
+                ((min_x - 30.0).abs() < epsilon && (max_x - 760.0).abs() < epsilon)
+                    || ((min_y - 60.0).abs() < epsilon && (max_y - 580.0).abs() < epsilon),
+                "{bearing} {pitch}: {min_x} {max_x} {min_y} {max_y}"
+            );
+        }
+
+        let too_much_padding = EdgeInsets {
+            left: 400.0,
+            right: 400.0,
+            ..EdgeInsets::default()
+        };
+        assert!(state
+            .camera_for_bounds(south_west, north_east, too_much_padding, Deg(0.0), Deg(0.0))
+            .is_none());
+    }
+
+    #[test]
+    fn project_round_trip() {
+        let center = LatLon::new(50.85045, 4.34878);
+        let zoom = Zoom::new(10.0);
+        let mut state = ViewState::new(
+            PhysicalSize::new(800, 600).unwrap(),
+            WorldCoords::from_lat_lon(center, zoom),
+            zoom,
+            Deg(20.0),
+            Deg(60.0),
+        );
+        state.camera_mut().set_roll(Deg(-30.0));
+
+        assert!((state.center().latitude - center.latitude).abs() < 1e-9);
+        assert!((state.center().longitude - center.longitude).abs() < 1e-9);
+        assert!((state.bearing().0 - 30.0).abs() < 1e-9);
+        assert!((state.pitch().0 - 20.0).abs() < 1e-9);
+
+        let projected = state.project(center).unwrap();
+        assert!((projected.x - 400.0).abs() < 1e-6, "{projected:?}");
+        assert!((projected.y - 300.0).abs() < 1e-6, "{projected:?}");
+
+        for point in [ScreenPoint::new(10.0, 20.0), ScreenPoint::new(700.0, 550.0)] {
+            let lat_lon = state.unproject(point).unwrap();
+            let projected = state.project(lat_lon).unwrap();
+            assert!((projected.x - point.x).abs() < 1e-6, "{projected:?}");
+            assert!((projected.y - point.y).abs() < 1e-6, "{projected:?}");
+        }
+
+        // Because of the pitch, more of the map is visible at the top than at the bottom
+        let bounds = state.bounds();
+        assert!(bounds.south_west.latitude < center.latitude);
+        assert!(bounds.north_east.latitude > center.latitude);
+        assert!(
+            bounds.north_east.latitude - center.latitude
+                > center.latitude - bounds.south_west.latitude
+        );
+    }
+
+    fn state_with_terrain(elevation: impl Fn(u32, u32) -> f32) -> ViewState {
+        let mut state = ViewState::new(
+            PhysicalSize::new(800, 600).unwrap(),
+            WorldCoords::at_ground(100.0, 100.0),
+            Zoom::new(2.0),
+            Deg(30.0),
+            Deg(60.0),
+        );
+
+        let image = RgbaImage::from_fn(16, 16, |x, y| {
+            let value = ((elevation(x, y) + 10000.0) * 10.0).round() as u32;
+            image::Rgba([(value >> 16) as u8, (value >> 8) as u8, value as u8, 255])
+        });
+        let mut terrain = Elevation::new("terrain", 1.0);
+        terrain.insert(
+            WorldTileCoords::from((0, 0, 0.into())),
+            DemData::from_image(&image, DemEncoding::Mapbox).unwrap(),
+        );
+        state.set_elevation(Some(terrain));
+        state
+    }
+
+    #[test]
+    fn pick_terrain_at_center() {
+        let state = state_with_terrain(|_, _| 1000000.0);
+        assert!(state.center_elevation() > 1.0);
+
+        let projection = state.view_projection().invert();
+        let center = state
+            .window_to_world_at_ground(
+                &Vector2::new(state.width / 2.0, state.height / 2.0),
+                &projection,
+                true,
+            )
+            .unwrap();
+
+        // The camera is lifted above the terrain, so the center is still below the camera
+        assert!((center.x - 100.0).abs() < 0.01, "{center:?}");
+        assert!((center.y - 100.0).abs() < 0.01, "{center:?}");
+    }
+
+    #[test]
+    fn pick_terrain_round_trip() {
+        let state = state_with_terrain(|x, y| (x + y) as f32 * 40000.0);
+
+        let view_projection = state.view_projection();
+        let projection = view_projection.invert();
+
+        for window in [
+            Vector2::new(400.0, 500.0),
+            Vector2::new(100.0, 550.0),
+            Vector2::new(700.0, 300.0),
+        ] {
+            let ground = state
+                .window_to_world_at_ground(&window, &projection, true)
+                .unwrap();
+            let z = state.ground_elevation(ground.x, ground.y);
+
+            // Projecting the point on the terrain back to the window yields the original position
+            let clip = view_projection.project(Vector4::new(ground.x, ground.y, z, 1.0));
+            let projected = state.clip_to_window(&clip);
+            assert!(
+                (projected.x - window.x).abs() < 0.5,
+                "{projected:?} {window:?}"
+            );
+            assert!(
+                (projected.y - window.y).abs() < 0.5,
+                "{projected:?} {window:?}"
+            );
+        }
+    }
 }
@@ -0,0 +1,110 @@
+//! Tracks whether the map needs to be rendered again, such that event loops can render on demand
+//! instead of continuously.
+//!
+//! Changes which are visible, e.g. style mutations or uploaded tiles, request a repaint. After
+//! each frame, the [`MapEventSystem`](crate::events::MapEventSystem) keeps the repaint requested
+//! while the camera moves, an animation runs or tiles in view are loading. A request while the map
+//! is idle wakes the event loop through the waker of the window.
+
+use crate::tcs::world::World;
+
+/// Whether the map needs to be rendered again.
+pub struct Repaint {
+    /// Set by changes since the last frame.
+    requested: bool,
+    /// Set if the map has not been idle after the last frame.
+    busy: bool,
+    /// Requests a redraw from the event loop, which stops rendering while the map is idle.
+    waker: Option<Box<dyn Fn()>>,
+}
+
+impl Default for Repaint {
+    fn default() -> Self {
+        // The first frame is always rendered
+        Self {
+            requested: true,
+            busy: false,
+            waker: None,
+        }
+    }
+}
+
+impl Repaint {
+    pub fn set_waker(&mut self, waker: Box<dyn Fn()>) {
+        self.waker = Some(waker);
+    }
+
+    /// Requests a repaint. The event loop is woken up if the map has been idle, otherwise it
+    /// renders the next frame anyway.
+    pub fn request(&mut self) {
+        if !self.is_needed() {
+            if let Some(waker) = &self.waker {
+                waker();
+            }
+        }
+        self.requested = true;
+    }
+
+    pub fn is_needed(&self) -> bool {
+        self.requested || self.busy
+    }
+
+    /// Resets the requests after a frame has been rendered. The repaint stays requested if the
+    /// map is `busy`.
+    pub fn finish_frame(&mut self, busy: bool) {
+        self.busy = busy || self.requested;
+        self.requested = false;
+    }
+}
+
+/// Requests a repaint, if the [`Repaint`] resource is available.
+pub fn request_repaint(world: &mut World) {
+    if let Some(repaint) = world.resources.get_mut::<Repaint>() {
+        repaint.request();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn test_finish_frame() {
+        let mut repaint = Repaint::default();
+        assert!(repaint.is_needed());
+
+        repaint.finish_frame(false);
+        // A request during the frame renders another frame
+        assert!(repaint.is_needed());
+        repaint.finish_frame(false);
+        assert!(!repaint.is_needed());
+
+        repaint.request();
+        assert!(repaint.is_needed());
+        repaint.finish_frame(true);
+        repaint.finish_frame(true);
+        assert!(repaint.is_needed());
+        repaint.finish_frame(false);
+        assert!(!repaint.is_needed());
+    }
+
+    #[test]
+    fn test_wake_idle_map() {
+        let wakes = Rc::new(Cell::new(0));
+        let mut repaint = Repaint::default();
+        let counter = wakes.clone();
+        repaint.set_waker(Box::new(move || counter.set(counter.get() + 1)));
+
+        // The event loop renders the first frame anyway
+        repaint.request();
+        assert_eq!(wakes.get(), 0);
+
+        repaint.finish_frame(false);
+        repaint.finish_frame(false);
+        repaint.request();
+        repaint.request();
+        assert_eq!(wakes.get(), 1);
+    }
+}
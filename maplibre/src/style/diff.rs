@@ -0,0 +1,479 @@
+//! Computes the operations which transform one [`Style`] into another.
+//!
+//! Applying the operations to a live style keeps the state which is derived from the style, e.g.
+//! tessellated tiles, whenever possible.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::style::{
+    layer::StyleLayer,
+    mutation::{StyleChange, StyleError},
+    source::Source,
+    terrain::Terrain,
+    Style,
+};
+
+/// A single mutation of a [`Style`].
+#[derive(Debug, Clone)]
+pub enum StyleOperation {
+    AddSource {
+        id: String,
+        source: Source,
+    },
+    RemoveSource {
+        id: String,
+    },
+    /// The source changed in place. Data which has been fetched from it is fetched again.
+    SetSource {
+        id: String,
+        source: Source,
+    },
+    /// Inserts a layer below the layer `before_id`, or on top if `before_id` is `None`
+    AddLayer {
+        layer: Box<StyleLayer>,
+        before_id: Option<String>,
+    },
+    RemoveLayer {
+        id: String,
+    },
+    MoveLayer {
+        id: String,
+        before_id: Option<String>,
+    },
+    SetPaintProperty {
+        layer_id: String,
+        name: String,
+        value: JsonValue,
+    },
+    SetLayoutProperty {
+        layer_id: String,
+        name: String,
+        value: JsonValue,
+    },
+    SetFilter {
+        layer_id: String,
+        filter: Option<JsonValue>,
+    },
+    SetLayerZoomRange {
+        layer_id: String,
+        minzoom: Option<u8>,
+        maxzoom: Option<u8>,
+    },
+    SetTerrain(Option<Terrain>),
+}
+
+impl StyleOperation {
+    pub fn apply(self, style: &mut Style) -> Result<StyleChange, StyleError> {
+        match self {
+            StyleOperation::AddSource { id, source } => style.add_source(&id, source),
+            StyleOperation::RemoveSource { id } => style.remove_source(&id),
+            StyleOperation::SetSource { id, source } => style.set_source(&id, source),
+            StyleOperation::AddLayer { layer, before_id } => {
+                style.add_layer(*layer, before_id.as_deref())
+            }
+            StyleOperation::RemoveLayer { id } => style.remove_layer(&id),
+            StyleOperation::MoveLayer { id, before_id } => {
+                style.move_layer(&id, before_id.as_deref())
+            }
+            StyleOperation::SetPaintProperty {
+                layer_id,
+                name,
+                value,
+            } => style.set_paint_property(&layer_id, &name, value),
+            StyleOperation::SetLayoutProperty {
+                layer_id,
+                name,
+                value,
+            } => style.set_layout_property(&layer_id, &name, value),
+            StyleOperation::SetFilter { layer_id, filter } => style.set_filter(&layer_id, filter),
+            StyleOperation::SetLayerZoomRange {
+                layer_id,
+                minzoom,
+                maxzoom,
+            } => style.set_layer_zoom_range(&layer_id, minzoom, maxzoom),
+            StyleOperation::SetTerrain(terrain) => {
+                style.terrain = terrain;
+                Ok(StyleChange::default())
+            }
+        }
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> JsonValue {
+    serde_json::to_value(value).unwrap_or(JsonValue::Null)
+}
+
+/// Returns the paint properties of a layer as JSON object.
+fn paint_properties(layer: &StyleLayer) -> JsonMap<String, JsonValue> {
+    match layer.paint.as_ref().map(to_json) {
+        Some(JsonValue::Object(mut paint)) => match paint.remove("paint") {
+            Some(JsonValue::Object(properties)) => properties,
+            _ => JsonMap::new(),
+        },
+        _ => JsonMap::new(),
+    }
+}
+
+/// Returns the type of a layer, e.g. `fill`.
+fn layer_type(layer: &StyleLayer) -> Option<JsonValue> {
+    layer.paint.as_ref().map(to_json)?.get("type").cloned()
+}
+
+/// Layers which differ in their type or source can not be updated in place.
+fn can_update_layer(before: &StyleLayer, after: &StyleLayer) -> bool {
+    layer_type(before) == layer_type(after)
+        && before.source == after.source
+        && before.source_layer == after.source_layer
+}
+
+/// Emits an operation for each property whose value differs. Removed properties are reset by
+/// setting them to `null`.
+fn diff_properties<F: FnMut(String, JsonValue)>(
+    before: &JsonMap<String, JsonValue>,
+    after: &JsonMap<String, JsonValue>,
+    mut emit: F,
+) {
+    let names = before.keys().chain(after.keys()).collect::<BTreeSet<_>>();
+    for name in names {
+        let value = after.get(name).cloned().unwrap_or(JsonValue::Null);
+        if before.get(name).cloned().unwrap_or(JsonValue::Null) != value {
+            emit(name.clone(), value);
+        }
+    }
+}
+
+fn diff_layer(before: &StyleLayer, after: &StyleLayer, operations: &mut Vec<StyleOperation>) {
+    let layer_id = &after.id;
+
+    diff_properties(
+        &paint_properties(before),
+        &paint_properties(after),
+        |name, value| {
+            operations.push(StyleOperation::SetPaintProperty {
+                layer_id: layer_id.clone(),
+                name,
+                value,
+            })
+        },
+    );
+
+    let layout = |layer: &StyleLayer| {
+        layer
+            .layout
+            .iter()
+            .flatten()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<JsonMap<_, _>>()
+    };
+    diff_properties(&layout(before), &layout(after), |name, value| {
+        operations.push(StyleOperation::SetLayoutProperty {
+            layer_id: layer_id.clone(),
+            name,
+            value,
+        })
+    });
+
+    if before.filter != after.filter {
+        operations.push(StyleOperation::SetFilter {
+            layer_id: layer_id.clone(),
+            filter: after.filter.clone(),
+        });
+    }
+
+    if before.minzoom != after.minzoom || before.maxzoom != after.maxzoom {
+        operations.push(StyleOperation::SetLayerZoomRange {
+            layer_id: layer_id.clone(),
+            minzoom: after.minzoom,
+            maxzoom: after.maxzoom,
+        });
+    }
+}
+
+fn diff_sources(before: &Style, after: &Style, operations: &mut Vec<StyleOperation>) {
+    // Sorted such that the operations are deterministic
+    let ids = before
+        .sources
+        .keys()
+        .chain(after.sources.keys())
+        .collect::<BTreeSet<_>>();
+
+    for id in ids {
+        match (before.sources.get(id), after.sources.get(id)) {
+            (None, Some(source)) => operations.push(StyleOperation::AddSource {
+                id: id.clone(),
+                source: source.clone(),
+            }),
+            (Some(before), Some(after)) if to_json(before) != to_json(after) => {
+                operations.push(StyleOperation::SetSource {
+                    id: id.clone(),
+                    source: after.clone(),
+                })
+            }
+            _ => {}
+        }
+    }
+}
+
+fn diff_layers(before: &Style, after: &Style, operations: &mut Vec<StyleOperation>) {
+    let before_layers = before
+        .layers
+        .iter()
+        .map(|layer| (layer.id.as_str(), layer))
+        .collect::<HashMap<_, _>>();
+    let after_ids = after
+        .layers
+        .iter()
+        .map(|layer| layer.id.as_str())
+        .collect::<BTreeSet<_>>();
+
+    // Order of the layers after applying the operations so far
+    let mut tracker = Vec::new();
+    for layer in &before.layers {
+        if after_ids.contains(layer.id.as_str()) {
+            tracker.push(layer.id.as_str());
+        } else {
+            operations.push(StyleOperation::RemoveLayer {
+                id: layer.id.clone(),
+            });
+        }
+    }
+
+    // Walk from the top, such that the layer `before_id` is always at its final position
+    for (i, layer) in after.layers.iter().enumerate().rev() {
+        let id = layer.id.as_str();
+        let before_id = after.layers.get(i + 1).map(|layer| layer.id.as_str());
+
+        let position = tracker.iter().position(|tracked| *tracked == id);
+        let target = before_id
+            .and_then(|before_id| tracker.iter().position(|tracked| *tracked == before_id))
+            .unwrap_or(tracker.len());
+
+        let existing = before_layers
+            .get(id)
+            .filter(|existing| can_update_layer(existing, layer));
+
+        match (position, existing) {
+            (Some(position), Some(existing)) => {
+                if position + 1 != target {
+                    tracker.remove(position);
+                    let target = before_id
+                        .and_then(|before_id| {
+                            tracker.iter().position(|tracked| *tracked == before_id)
+                        })
+                        .unwrap_or(tracker.len());
+                    tracker.insert(target, id);
+                    operations.push(StyleOperation::MoveLayer {
+                        id: id.to_string(),
+                        before_id: before_id.map(str::to_string),
+                    });
+                }
+                diff_layer(existing, layer, operations);
+            }
+            (position, _) => {
+                if let Some(position) = position {
+                    tracker.remove(position);
+                    operations.push(StyleOperation::RemoveLayer { id: id.to_string() });
+                }
+                let target = before_id
+                    .and_then(|before_id| tracker.iter().position(|tracked| *tracked == before_id))
+                    .unwrap_or(tracker.len());
+                tracker.insert(target, id);
+                operations.push(StyleOperation::AddLayer {
+                    layer: Box::new(layer.clone()),
+                    before_id: before_id.map(str::to_string),
+                });
+            }
+        }
+    }
+}
+
+/// Computes the operations which transform the style `before` into `after`.
+///
+/// Sources are added before and removed after the layers changed, such that no operation refers
+/// to a source which does not exist.
+pub fn diff_styles(before: &Style, after: &Style) -> Vec<StyleOperation> {
+    let mut operations = Vec::new();
+
+    diff_sources(before, after, &mut operations);
+    diff_layers(before, after, &mut operations);
+
+    for id in before.sources.keys().collect::<BTreeSet<_>>() {
+        if !after.sources.contains_key(id) {
+            operations.push(StyleOperation::RemoveSource { id: id.clone() });
+        }
+    }
+
+    if before.terrain != after.terrain {
+        operations.push(StyleOperation::SetTerrain(after.terrain.clone()));
+    }
+
+    operations
+}
+
+/// Computes how transforming the style `before` into `after` changes the derived state, e.g. the
+/// tessellated tiles. The operations are applied to a copy of `before`, so nothing is mutated if
+/// one of them fails.
+pub fn diff_change(before: &Style, after: &Style) -> Result<StyleChange, StyleError> {
+    let mut style = before.clone();
+    let mut change = StyleChange::default();
+    for operation in diff_styles(before, after) {
+        change.merge(operation.apply(&mut style)?);
+    }
+    Ok(change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // language=JSON
+    const DAY: &str = r##"
+    {
+      "version": 8,
+      "name": "Day",
+      "metadata": {},
+      "sources": {
+        "openmaptiles": {"type": "vector", "url": "https://example.com/tiles.json"}
+      },
+      "layers": [
+        {"id": "background", "type": "background", "paint": {"background-color": "#ffffff"}},
+        {"id": "water", "type": "fill", "source": "openmaptiles", "source-layer": "water",
+          "paint": {"fill-color": "#aad3df"}},
+        {"id": "roads", "type": "line", "source": "openmaptiles", "source-layer": "transportation",
+          "paint": {"line-color": "#ffffff"}},
+        {"id": "buildings", "type": "fill", "source": "openmaptiles", "source-layer": "building",
+          "paint": {"fill-color": "#d9d0c9"}}
+      ]
+    }
+    "##;
+
+    // language=JSON
+    const NIGHT: &str = r##"
+    {
+      "version": 8,
+      "name": "Night",
+      "metadata": {},
+      "sources": {
+        "openmaptiles": {"type": "vector", "url": "https://example.com/tiles.json"},
+        "terrain": {"type": "raster-dem", "tiles": "https://example.com/{z}/{x}/{y}.png"}
+      },
+      "layers": [
+        {"id": "background", "type": "background", "paint": {"background-color": "#000000"}},
+        {"id": "buildings", "type": "fill", "source": "openmaptiles", "source-layer": "building",
+          "paint": {"fill-color": "#333333"}, "filter": ["has", "height"]},
+        {"id": "water", "type": "fill", "source": "openmaptiles", "source-layer": "water",
+          "paint": {"fill-color": "#aad3df"}, "layout": {"visibility": "none"}},
+        {"id": "hillshade", "type": "hillshade", "source": "terrain"}
+      ]
+    }
+    "##;
+
+    fn describe(operation: &StyleOperation) -> String {
+        match operation {
+            StyleOperation::AddSource { id, .. } => format!("add source {id}"),
+            StyleOperation::RemoveSource { id } => format!("remove source {id}"),
+            StyleOperation::SetSource { id, .. } => format!("set source {id}"),
+            StyleOperation::AddLayer { layer, before_id } => {
+                format!("add {} before {before_id:?}", layer.id)
+            }
+            StyleOperation::RemoveLayer { id } => format!("remove {id}"),
+            StyleOperation::MoveLayer { id, before_id } => {
+                format!("move {id} before {before_id:?}")
+            }
+            StyleOperation::SetPaintProperty {
+                layer_id,
+                name,
+                value,
+            } => format!("paint {layer_id} {name}={value}"),
+            StyleOperation::SetLayoutProperty {
+                layer_id,
+                name,
+                value,
+            } => format!("layout {layer_id} {name}={value}"),
+            StyleOperation::SetFilter { layer_id, .. } => format!("filter {layer_id}"),
+            StyleOperation::SetLayerZoomRange { layer_id, .. } => format!("zoom {layer_id}"),
+            StyleOperation::SetTerrain(_) => "terrain".to_string(),
+        }
+    }
+
+    fn layer_ids(style: &Style) -> Vec<&str> {
+        style.layers.iter().map(|layer| layer.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_diff_day_night() {
+        let day: Style = serde_json::from_str(DAY).unwrap();
+        let night: Style = serde_json::from_str(NIGHT).unwrap();
+
+        let operations = diff_styles(&day, &night);
+        assert_eq!(
+            operations.iter().map(describe).collect::<Vec<_>>(),
+            vec![
+                "add source terrain",
+                "remove roads",
+                "add hillshade before None",
+                "move water before Some(\"hillshade\")",
+                "layout water visibility=\"none\"",
+                "paint buildings fill-color=\"#333333\"",
+                "filter buildings",
+                "paint background background-color=\"#000000\"",
+            ]
+        );
+
+        let mut style = day;
+        for operation in operations {
+            operation.apply(&mut style).unwrap();
+        }
+        assert_eq!(layer_ids(&style), layer_ids(&night));
+        assert_eq!(style.layers[1].index, 1);
+        assert!(diff_styles(&style, &night).is_empty());
+    }
+
+    #[test]
+    fn test_diff_incompatible_changes() {
+        let day: Style = serde_json::from_str(DAY).unwrap();
+        let mut changed = day.clone();
+        // Layers which change their type are added again
+        changed.layers[2] = serde_json::from_value(serde_json::json!({
+            "id": "roads", "type": "fill", "source": "openmaptiles", "source-layer": "transportation"
+        }))
+        .unwrap();
+        changed.sources.insert(
+            "openmaptiles".to_string(),
+            serde_json::from_value(
+                serde_json::json!({"type": "vector", "tiles": "https://example.com/{z}/{x}/{y}.pbf"}),
+            )
+            .unwrap(),
+        );
+
+        let operations = diff_styles(&day, &changed);
+        assert_eq!(
+            operations.iter().map(describe).collect::<Vec<_>>(),
+            vec![
+                "set source openmaptiles",
+                "remove roads",
+                "add roads before Some(\"buildings\")",
+            ]
+        );
+
+        // Vector sources are fetched again by retessellating
+        assert!(diff_change(&day, &changed).unwrap().retessellate);
+        let mut style = day;
+        let change = operations[0].clone().apply(&mut style).unwrap();
+        assert!(change.retessellate);
+
+        // Layers of unknown sources can not be added
+        changed.layers.push(
+            serde_json::from_value(serde_json::json!({
+                "id": "parks", "type": "fill", "source": "unknown", "source-layer": "park"
+            }))
+            .unwrap(),
+        );
+        assert!(matches!(
+            diff_change(&style, &changed),
+            Err(StyleError::SourceNotFound(source)) if source == "unknown"
+        ));
+    }
+}
@@ -0,0 +1,458 @@
+//! Expressions which compute style properties, e.g. depending on the zoom or feature properties.
+//!
+//! Only a subset of the expressions of the style specification is supported.
+
+use std::collections::HashMap;
+
+use csscolorparser::Color;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ExpressionError {
+    #[error("unsupported expression: {0}")]
+    Unsupported(String),
+    #[error("invalid arguments for expression {0}")]
+    InvalidArguments(String),
+}
+
+/// Interpolation between the stops of an `interpolate` expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    Exponential(f64),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "JsonValue", into = "JsonValue")]
+pub enum Expression {
+    Number(f64),
+    Color(Color),
+    String(String),
+    /// `["get", name]`: Property of the feature
+    Get(String),
+    /// `["zoom"]`: Current zoom of the map
+    Zoom,
+    /// `["heatmap-density"]`: Density of a heatmap at a pixel
+    HeatmapDensity,
+    /// `["feature-state", name]`: Value of the state of the feature, see
+    /// [`FeatureStates`](crate::feature_state::FeatureStates)
+    FeatureState(String),
+    /// `["coalesce", expression, ...]`: The first expression which evaluates to a value
+    Coalesce(Vec<Expression>),
+    /// `["interpolate", interpolation, input, stop, output, ...]`
+    Interpolate {
+        interpolation: Interpolation,
+        input: Box<Expression>,
+        stops: Vec<(f64, Expression)>,
+    },
+    /// `["step", input, default, stop, output, ...]`
+    Step {
+        input: Box<Expression>,
+        default: Box<Expression>,
+        stops: Vec<(f64, Expression)>,
+    },
+}
+
+/// Result of the evaluation of an [`Expression`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Color(Color),
+    String(String),
+}
+
+/// Inputs which are available during the evaluation of an [`Expression`].
+#[derive(Default, Clone, Copy)]
+pub struct EvaluationContext<'a> {
+    pub zoom: f64,
+    pub heatmap_density: f64,
+    pub properties: Option<&'a HashMap<String, String>>,
+    pub feature_state: Option<&'a HashMap<String, JsonValue>>,
+}
+
+impl Expression {
+    pub fn evaluate(&self, context: &EvaluationContext) -> Option<Value> {
+        match self {
+            Expression::Number(value) => Some(Value::Number(*value)),
+            Expression::Color(color) => Some(Value::Color(color.clone())),
+            Expression::String(value) => Some(Value::String(value.clone())),
+            Expression::Get(name) => {
+                let value = context.properties?.get(name)?;
+                Some(match value.parse::<f64>() {
+                    Ok(number) => Value::Number(number),
+                    Err(_) => Value::String(value.clone()),
+                })
+            }
+            Expression::Zoom => Some(Value::Number(context.zoom)),
+            Expression::HeatmapDensity => Some(Value::Number(context.heatmap_density)),
+            Expression::FeatureState(name) => match context.feature_state?.get(name)? {
+                JsonValue::Number(number) => number.as_f64().map(Value::Number),
+                // Booleans are numbers, such that states like `hover` can be used in a `step`
+                JsonValue::Bool(value) => Some(Value::Number(if *value { 1.0 } else { 0.0 })),
+                JsonValue::String(value) => Some(Value::String(value.clone())),
+                _ => None,
+            },
+            Expression::Coalesce(expressions) => expressions
+                .iter()
+                .find_map(|expression| expression.evaluate(context)),
+            Expression::Interpolate {
+                interpolation,
+                input,
+                stops,
+            } => {
+                let input = input.evaluate_number(context)?;
+
+                let upper = stops.iter().position(|(stop, _)| *stop > input);
+                let (lower, upper) = match upper {
+                    Some(0) => return stops.first()?.1.evaluate(context),
+                    Some(upper) => (&stops[upper - 1], &stops[upper]),
+                    None => return stops.last()?.1.evaluate(context),
+                };
+
+                let t = interpolation.factor(input, lower.0, upper.0);
+                interpolate(lower.1.evaluate(context)?, upper.1.evaluate(context)?, t)
+            }
+            Expression::Step {
+                input,
+                default,
+                stops,
+            } => {
+                let input = input.evaluate_number(context)?;
+                stops
+                    .iter()
+                    .rev()
+                    .find(|(stop, _)| *stop <= input)
+                    .map_or(default.as_ref(), |(_, output)| output)
+                    .evaluate(context)
+            }
+        }
+    }
+
+    pub fn evaluate_number(&self, context: &EvaluationContext) -> Option<f64> {
+        match self.evaluate(context)? {
+            Value::Number(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn evaluate_color(&self, context: &EvaluationContext) -> Option<Color> {
+        match self.evaluate(context)? {
+            Value::Color(color) => Some(color),
+            Value::String(value) => value.parse().ok(),
+            Value::Number(_) => None,
+        }
+    }
+
+    /// Whether the expression depends on the state of the feature.
+    pub fn uses_feature_state(&self) -> bool {
+        match self {
+            Expression::FeatureState(_) => true,
+            Expression::Coalesce(expressions) => {
+                expressions.iter().any(Expression::uses_feature_state)
+            }
+            Expression::Interpolate { input, stops, .. } => {
+                input.uses_feature_state()
+                    || stops.iter().any(|(_, output)| output.uses_feature_state())
+            }
+            Expression::Step {
+                input,
+                default,
+                stops,
+            } => {
+                input.uses_feature_state()
+                    || default.uses_feature_state()
+                    || stops.iter().any(|(_, output)| output.uses_feature_state())
+            }
+            Expression::Number(_)
+            | Expression::Color(_)
+            | Expression::String(_)
+            | Expression::Get(_)
+            | Expression::Zoom
+            | Expression::HeatmapDensity => false,
+        }
+    }
+}
+
+impl Interpolation {
+    fn factor(&self, input: f64, lower: f64, upper: f64) -> f64 {
+        let difference = upper - lower;
+        if difference == 0.0 {
+            return 0.0;
+        }
+        let progress = input - lower;
+
+        match self {
+            Interpolation::Linear => progress / difference,
+            Interpolation::Exponential(base) if *base == 1.0 => progress / difference,
+            Interpolation::Exponential(base) => {
+                (base.powf(progress) - 1.0) / (base.powf(difference) - 1.0)
+            }
+        }
+    }
+}
+
+fn interpolate(lower: Value, upper: Value, t: f64) -> Option<Value> {
+    match (lower, upper) {
+        (Value::Number(lower), Value::Number(upper)) => {
+            Some(Value::Number(lower + (upper - lower) * t))
+        }
+        (Value::Color(lower), Value::Color(upper)) => {
+            let mix = |lower: f64, upper: f64| lower + (upper - lower) * t;
+            Some(Value::Color(Color::new(
+                mix(lower.r, upper.r),
+                mix(lower.g, upper.g),
+                mix(lower.b, upper.b),
+                mix(lower.a, upper.a),
+            )))
+        }
+        _ => None,
+    }
+}
+
+fn parse_stops(
+    name: &str,
+    arguments: &[JsonValue],
+) -> Result<Vec<(f64, Expression)>, ExpressionError> {
+    if arguments.len() % 2 != 0 {
+        return Err(ExpressionError::InvalidArguments(name.to_string()));
+    }
+
+    arguments
+        .chunks(2)
+        .map(|stop| {
+            let input = stop[0]
+                .as_f64()
+                .ok_or_else(|| ExpressionError::InvalidArguments(name.to_string()))?;
+            Ok((input, Expression::try_from(stop[1].clone())?))
+        })
+        .collect()
+}
+
+/// Deserializes an optional color expression. Unsupported expressions are dropped like in
+/// [`deserialize_supported`], but values which can not be a color, e.g. numbers, are rejected.
+pub fn deserialize_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Expression>, D::Error> {
+    let value = Option::<JsonValue>::deserialize(deserializer)?;
+    match value.map(Expression::try_from) {
+        None => Ok(None),
+        Some(Ok(Expression::Number(_) | Expression::String(_))) => {
+            Err(serde::de::Error::custom("expected a color"))
+        }
+        Some(Ok(expression)) => Ok(Some(expression)),
+        Some(Err(error)) => {
+            log::warn!("ignoring expression: {error}");
+            Ok(None)
+        }
+    }
+}
+
+/// Deserializes an optional expression. Unsupported expressions are dropped instead of failing to
+/// load the whole style, which is used for properties which are not essential for rendering.
+pub fn deserialize_supported<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Expression>, D::Error> {
+    let value = Option::<JsonValue>::deserialize(deserializer)?;
+    Ok(value.and_then(|value| {
+        Expression::try_from(value)
+            .map_err(|error| log::warn!("ignoring expression: {error}"))
+            .ok()
+    }))
+}
+
+impl TryFrom<JsonValue> for Expression {
+    type Error = ExpressionError;
+
+    fn try_from(value: JsonValue) -> Result<Self, Self::Error> {
+        match value {
+            JsonValue::Number(number) => {
+                Ok(Expression::Number(number.as_f64().ok_or_else(|| {
+                    ExpressionError::Unsupported(number.to_string())
+                })?))
+            }
+            JsonValue::String(value) => Ok(match value.parse::<Color>() {
+                Ok(color) => Expression::Color(color),
+                Err(_) => Expression::String(value),
+            }),
+            JsonValue::Array(array) => {
+                let Some((JsonValue::String(name), arguments)) = array.split_first() else {
+                    return Err(ExpressionError::Unsupported(format!("{array:?}")));
+                };
+                let invalid = || ExpressionError::InvalidArguments(name.clone());
+
+                match (name.as_str(), arguments) {
+                    ("get", [JsonValue::String(property)]) => Ok(Expression::Get(property.clone())),
+                    ("zoom", []) => Ok(Expression::Zoom),
+                    ("heatmap-density", []) => Ok(Expression::HeatmapDensity),
+                    ("feature-state", [JsonValue::String(name)]) => {
+                        Ok(Expression::FeatureState(name.clone()))
+                    }
+                    ("coalesce", expressions) if !expressions.is_empty() => Ok(
+                        Expression::Coalesce(
+                            expressions
+                                .iter()
+                                .map(|expression| Expression::try_from(expression.clone()))
+                                .collect::<Result<_, _>>()?,
+                        ),
+                    ),
+                    ("interpolate", [JsonValue::Array(interpolation), input, stops @ ..]) => {
+                        let interpolation = match interpolation.as_slice() {
+                            [JsonValue::String(kind)] if kind == "linear" => Interpolation::Linear,
+                            [JsonValue::String(kind), base] if kind == "exponential" => {
+                                Interpolation::Exponential(base.as_f64().ok_or_else(invalid)?)
+                            }
+                            _ => return Err(invalid()),
+                        };
+                        Ok(Expression::Interpolate {
+                            interpolation,
+                            input: Box::new(Expression::try_from(input.clone())?),
+                            stops: parse_stops(name, stops)?,
+                        })
+                    }
+                    ("step", [input, default, stops @ ..]) => Ok(Expression::Step {
+                        input: Box::new(Expression::try_from(input.clone())?),
+                        default: Box::new(Expression::try_from(default.clone())?),
+                        stops: parse_stops(name, stops)?,
+                    }),
+                    (
+                        "get" | "zoom" | "heatmap-density" | "feature-state" | "coalesce"
+                        | "interpolate" | "step",
+                        _,
+                    ) => Err(invalid()),
+                    _ => Err(ExpressionError::Unsupported(name.clone())),
+                }
+            }
+            value => Err(ExpressionError::Unsupported(value.to_string())),
+        }
+    }
+}
+
+impl From<Expression> for JsonValue {
+    fn from(expression: Expression) -> Self {
+        let stops = |stops: Vec<(f64, Expression)>| {
+            stops
+                .into_iter()
+                .flat_map(|(stop, output)| [JsonValue::from(stop), output.into()])
+        };
+
+        match expression {
+            Expression::Number(value) => value.into(),
+            Expression::Color(color) => color.to_hex_string().into(),
+            Expression::String(value) => value.into(),
+            Expression::Get(property) => serde_json::json!(["get", property]),
+            Expression::Zoom => serde_json::json!(["zoom"]),
+            Expression::HeatmapDensity => serde_json::json!(["heatmap-density"]),
+            Expression::FeatureState(name) => serde_json::json!(["feature-state", name]),
+            Expression::Coalesce(expressions) => {
+                let mut array = vec!["coalesce".into()];
+                array.extend(expressions.into_iter().map(JsonValue::from));
+                JsonValue::Array(array)
+            }
+            Expression::Interpolate {
+                interpolation,
+                input,
+                stops: interpolate_stops,
+            } => {
+                let interpolation = match interpolation {
+                    Interpolation::Linear => serde_json::json!(["linear"]),
+                    Interpolation::Exponential(base) => serde_json::json!(["exponential", base]),
+                };
+                let mut array = vec!["interpolate".into(), interpolation, (*input).into()];
+                array.extend(stops(interpolate_stops));
+                JsonValue::Array(array)
+            }
+            Expression::Step {
+                input,
+                default,
+                stops: step_stops,
+            } => {
+                let mut array = vec!["step".into(), (*input).into(), (*default).into()];
+                array.extend(stops(step_stops));
+                JsonValue::Array(array)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate_color() {
+        let expression: Expression = serde_json::from_str(
+            r#"["interpolate", ["linear"], ["heatmap-density"], 0, "rgba(0,0,0,0)", 1, "red"]"#,
+        )
+        .unwrap();
+
+        let color = expression
+            .evaluate_color(&EvaluationContext {
+                heatmap_density: 0.5,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(color, Color::new(0.5, 0.0, 0.0, 0.5));
+
+        let json = serde_json::to_value(&expression).unwrap();
+        assert_eq!(Expression::try_from(json).unwrap(), expression);
+    }
+
+    #[test]
+    fn test_evaluate_property() {
+        let expression: Expression = serde_json::from_str(
+            r#"["step", ["get", "mag"], 0, 2, 1, 4, ["interpolate", ["exponential", 2], ["zoom"], 0, 1, 2, 4]]"#,
+        )
+        .unwrap();
+
+        let properties = HashMap::from([("mag".to_string(), "3".to_string())]);
+        let context = EvaluationContext {
+            zoom: 1.0,
+            properties: Some(&properties),
+            ..Default::default()
+        };
+        assert_eq!(expression.evaluate_number(&context), Some(1.0));
+
+        let properties = HashMap::from([("mag".to_string(), "5".to_string())]);
+        let context = EvaluationContext {
+            properties: Some(&properties),
+            ..context
+        };
+        // (2^1 - 1) / (2^2 - 1) = 1/3 of the way from 1 to 4
+        assert_eq!(expression.evaluate_number(&context), Some(2.0));
+
+        assert_eq!(
+            expression.evaluate_number(&EvaluationContext::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_feature_state() {
+        let expression: Expression = serde_json::from_str(
+            r#"["step", ["coalesce", ["feature-state", "hover"], 0], "blue", 1, "red"]"#,
+        )
+        .unwrap();
+        assert!(expression.uses_feature_state());
+
+        let blue = Color::new(0.0, 0.0, 1.0, 1.0);
+        assert_eq!(
+            expression.evaluate_color(&EvaluationContext::default()),
+            Some(blue.clone())
+        );
+
+        let state = HashMap::from([("hover".to_string(), JsonValue::from(true))]);
+        let context = EvaluationContext {
+            feature_state: Some(&state),
+            ..Default::default()
+        };
+        assert_eq!(
+            expression.evaluate_color(&context),
+            Some(Color::new(1.0, 0.0, 0.0, 1.0))
+        );
+
+        let json = serde_json::to_value(&expression).unwrap();
+        assert_eq!(Expression::try_from(json).unwrap(), expression);
+    }
+}
@@ -0,0 +1,189 @@
+//! Evaluates the `filter` of style layers, which selects features by their properties.
+//!
+//! The legacy syntax, e.g. `["==", "class", "lake"]`, and comparisons of the expression syntax,
+//! e.g. `["==", ["get", "class"], "lake"]`, are supported.
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use serde_json::Value as JsonValue;
+
+/// A feature which is matched against a filter.
+#[derive(Clone, Copy)]
+pub struct FilterFeature<'a> {
+    /// `"Point"`, `"LineString"` or `"Polygon"`
+    pub geometry_type: &'a str,
+    pub properties: &'a HashMap<String, JsonValue>,
+}
+
+/// Returns whether `feature` matches `filter`. Unsupported filters match all features, such that
+/// features are not dropped because of them.
+pub fn matches(filter: &JsonValue, feature: FilterFeature) -> bool {
+    evaluate(filter, feature).unwrap_or(true)
+}
+
+/// Returns `None` if the filter is not supported.
+fn evaluate(filter: &JsonValue, feature: FilterFeature) -> Option<bool> {
+    let JsonValue::Array(items) = filter else {
+        return filter.as_bool();
+    };
+    let (operator, arguments) = items.split_first()?;
+
+    match operator.as_str()? {
+        "all" => arguments.iter().try_fold(true, |all, argument| {
+            Some(evaluate(argument, feature)? && all)
+        }),
+        "any" => arguments.iter().try_fold(false, |any, argument| {
+            Some(evaluate(argument, feature)? || any)
+        }),
+        "none" => arguments.iter().try_fold(true, |none, argument| {
+            Some(!evaluate(argument, feature)? && none)
+        }),
+        "!" => Some(!evaluate(arguments.first()?, feature)?),
+        "has" => Some(has(arguments.first()?.as_str()?, feature)),
+        "!has" => Some(!has(arguments.first()?.as_str()?, feature)),
+        operator @ ("==" | "!=" | "<" | "<=" | ">" | ">=") => {
+            let [left, right] = arguments else {
+                return None;
+            };
+            let left = match left {
+                // Legacy syntax: the property name followed by a literal
+                JsonValue::String(key) => property(key, feature),
+                left => operand(left, feature)?,
+            };
+            Some(compare(operator, &left, &operand(right, feature)?))
+        }
+        operator @ ("in" | "!in") => {
+            let contained = match arguments.split_first()? {
+                // Legacy syntax: the property name followed by the literals
+                (JsonValue::String(key), values) => {
+                    let value = property(key, feature);
+                    values.iter().any(|candidate| equals(&value, candidate))
+                }
+                (needle, [haystack]) => {
+                    let needle = operand(needle, feature)?;
+                    match operand(haystack, feature)? {
+                        JsonValue::Array(values) => {
+                            values.iter().any(|candidate| equals(&needle, candidate))
+                        }
+                        JsonValue::String(haystack) => haystack.contains(needle.as_str()?),
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            };
+            Some(contained == (operator == "in"))
+        }
+        _ => None,
+    }
+}
+
+/// Evaluates an operand of the expression syntax. Returns `None` if it is not supported.
+fn operand(value: &JsonValue, feature: FilterFeature) -> Option<JsonValue> {
+    let JsonValue::Array(items) = value else {
+        return Some(value.clone());
+    };
+
+    match items.first()?.as_str()? {
+        "get" => Some(property(items.get(1)?.as_str()?, feature)),
+        "geometry-type" => Some(feature.geometry_type.into()),
+        "literal" => items.get(1).cloned(),
+        _ => None,
+    }
+}
+
+/// Returns a property of the feature, or `null` if it does not exist. The key `$type` refers to
+/// the type of the geometry.
+fn property(key: &str, feature: FilterFeature) -> JsonValue {
+    if key == "$type" {
+        return feature.geometry_type.into();
+    }
+    feature
+        .properties
+        .get(key)
+        .cloned()
+        .unwrap_or(JsonValue::Null)
+}
+
+fn has(key: &str, feature: FilterFeature) -> bool {
+    key == "$type" || feature.properties.contains_key(key)
+}
+
+fn compare(operator: &str, left: &JsonValue, right: &JsonValue) -> bool {
+    match operator {
+        "==" => equals(left, right),
+        "!=" => !equals(left, right),
+        operator => {
+            let ordering = match (left, right) {
+                (JsonValue::Number(left), JsonValue::Number(right)) => left
+                    .as_f64()
+                    .zip(right.as_f64())
+                    .and_then(|(left, right)| left.partial_cmp(&right)),
+                (JsonValue::String(left), JsonValue::String(right)) => Some(left.cmp(right)),
+                _ => None,
+            };
+            let Some(ordering) = ordering else {
+                return false;
+            };
+            match operator {
+                "<" => ordering == Ordering::Less,
+                "<=" => ordering != Ordering::Greater,
+                ">" => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            }
+        }
+    }
+}
+
+/// Compares numbers by their value, such that integers equal floats.
+fn equals(left: &JsonValue, right: &JsonValue) -> bool {
+    match (left, right) {
+        (JsonValue::Number(left), JsonValue::Number(right)) => left.as_f64() == right.as_f64(),
+        (left, right) => left == right,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let properties = HashMap::from([
+            ("class".to_string(), json!("lake")),
+            ("area".to_string(), json!(12)),
+        ]);
+        let feature = FilterFeature {
+            geometry_type: "Polygon",
+            properties: &properties,
+        };
+
+        assert!(matches(&json!(["==", "class", "lake"]), feature));
+        assert!(matches(&json!(["==", ["get", "area"], 12.0]), feature));
+        assert!(!matches(&json!(["!=", "class", "lake"]), feature));
+        assert!(matches(&json!(["in", "class", "river", "lake"]), feature));
+        assert!(matches(
+            &json!(["in", ["get", "class"], ["literal", ["river", "lake"]]]),
+            feature
+        ));
+        assert!(matches(&json!(["!in", "class", "river"]), feature));
+        assert!(matches(
+            &json!([
+                "all",
+                ["==", "$type", "Polygon"],
+                [">", "area", 10],
+                ["has", "class"]
+            ]),
+            feature
+        ));
+        assert!(!matches(
+            &json!(["any", ["<", "area", 10], ["!has", "class"]]),
+            feature
+        ));
+        assert!(matches(&json!(["none", ["==", "class", "river"]]), feature));
+        assert!(!matches(&json!(["==", "missing", "lake"]), feature));
+        // Unsupported filters match all features
+        assert!(matches(&json!(["within", {}]), feature));
+    }
+}
@@ -0,0 +1,97 @@
+//! Heatmap layer description
+
+use csscolorparser::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::style::expression::{EvaluationContext, Expression, Interpolation};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HeatmapLayer {
+    /// Color of each pixel depending on its density. Must be an expression which depends on
+    /// `["heatmap-density"]`.
+    #[serde(rename = "heatmap-color")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heatmap_color: Option<Expression>,
+    /// Multiplies the weight of all points, e.g. to increase the density at low zoom levels.
+    #[serde(rename = "heatmap-intensity")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heatmap_intensity: Option<Expression>,
+    #[serde(rename = "heatmap-opacity")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heatmap_opacity: Option<Expression>,
+    /// Radius of influence of each point in pixels.
+    #[serde(rename = "heatmap-radius")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heatmap_radius: Option<Expression>,
+    /// Contribution of each point to the density, e.g. depending on a property of the point.
+    #[serde(rename = "heatmap-weight")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heatmap_weight: Option<Expression>,
+}
+
+impl HeatmapLayer {
+    /// Default color ramp of the style specification.
+    pub fn default_color() -> Expression {
+        let color = |r: u8, g: u8, b: u8, a: u8| Expression::Color(Color::from_rgba8(r, g, b, a));
+
+        Expression::Interpolate {
+            interpolation: Interpolation::Linear,
+            input: Box::new(Expression::HeatmapDensity),
+            stops: vec![
+                (0.0, color(0, 0, 255, 0)),
+                (0.1, color(65, 105, 225, 255)),
+                (0.3, color(0, 255, 255, 255)),
+                (0.5, color(0, 255, 0, 255)),
+                (0.7, color(255, 255, 0, 255)),
+                (1.0, color(255, 0, 0, 255)),
+            ],
+        }
+    }
+
+    pub fn color(&self) -> Expression {
+        self.heatmap_color
+            .clone()
+            .unwrap_or_else(HeatmapLayer::default_color)
+    }
+
+    pub fn intensity(&self, context: &EvaluationContext) -> f64 {
+        evaluate_number(&self.heatmap_intensity, context, 1.0)
+    }
+
+    pub fn opacity(&self, context: &EvaluationContext) -> f64 {
+        evaluate_number(&self.heatmap_opacity, context, 1.0)
+    }
+
+    pub fn radius(&self, context: &EvaluationContext) -> f64 {
+        evaluate_number(&self.heatmap_radius, context, 30.0)
+    }
+
+    pub fn weight(&self, context: &EvaluationContext) -> f64 {
+        evaluate_number(&self.heatmap_weight, context, 1.0)
+    }
+}
+
+/// Evaluates an optional property. Falls back to the `default` if the property is not set or can
+/// not be evaluated.
+fn evaluate_number(
+    expression: &Option<Expression>,
+    context: &EvaluationContext,
+    default: f64,
+) -> f64 {
+    expression
+        .as_ref()
+        .and_then(|expression| expression.evaluate_number(context))
+        .unwrap_or(default)
+}
+
+impl Default for HeatmapLayer {
+    fn default() -> Self {
+        HeatmapLayer {
+            heatmap_color: Some(HeatmapLayer::default_color()),
+            heatmap_intensity: Some(Expression::Number(1.0)),
+            heatmap_opacity: Some(Expression::Number(1.0)),
+            heatmap_radius: Some(Expression::Number(30.0)),
+            heatmap_weight: Some(Expression::Number(1.0)),
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! Hillshade layer description
+
+use csscolorparser::Color;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HillshadeLayer {
+    #[serde(rename = "hillshade-accent-color")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hillshade_accent_color: Option<Color>,
+    #[serde(rename = "hillshade-exaggeration")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hillshade_exaggeration: Option<f32>,
+    #[serde(rename = "hillshade-highlight-color")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hillshade_highlight_color: Option<Color>,
+    /// The direction of the light source in degrees, measured clockwise from north.
+    #[serde(rename = "hillshade-illumination-direction")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hillshade_illumination_direction: Option<f32>,
+    #[serde(rename = "hillshade-shadow-color")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hillshade_shadow_color: Option<Color>,
+}
+
+impl Default for HillshadeLayer {
+    fn default() -> Self {
+        HillshadeLayer {
+            hillshade_accent_color: Some(Color::new(0.0, 0.0, 0.0, 1.0)),
+            hillshade_exaggeration: Some(0.5),
+            hillshade_highlight_color: Some(Color::new(1.0, 1.0, 1.0, 1.0)),
+            hillshade_illumination_direction: Some(335.0),
+            hillshade_shadow_color: Some(Color::new(0.0, 0.0, 0.0, 1.0)),
+        }
+    }
+}
@@ -5,33 +5,61 @@ use std::collections::HashMap;
 use cint::{Alpha, EncodedSrgb};
 use csscolorparser::Color;
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 
-use crate::style::raster::RasterLayer;
+use crate::style::{
+    expression::{deserialize_color, deserialize_supported, EvaluationContext, Expression},
+    heatmap::HeatmapLayer,
+    hillshade::HillshadeLayer,
+    raster::RasterLayer,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BackgroundPaint {
     #[serde(rename = "background-color")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub background_color: Option<Color>,
-    // TODO a lot
+    #[serde(rename = "background-opacity")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_opacity: Option<f32>,
+    /// Name of an image which is repeated instead of drawing the color
+    #[serde(rename = "background-pattern")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_pattern: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FillPaint {
     #[serde(rename = "fill-color")]
+    #[serde(default, deserialize_with = "deserialize_color")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub fill_color: Option<Color>,
+    pub fill_color: Option<Expression>,
     // TODO a lot
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LinePaint {
     #[serde(rename = "line-color")]
+    #[serde(default, deserialize_with = "deserialize_color")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub line_color: Option<Color>,
+    pub line_color: Option<Expression>,
+    /// Width of the lines in pixels, which is the tolerance when querying the rendered features.
+    #[serde(rename = "line-width")]
+    #[serde(default, deserialize_with = "deserialize_supported")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_width: Option<Expression>,
     // TODO a lot
 }
 
+impl LinePaint {
+    pub fn width(&self, context: &EvaluationContext) -> f64 {
+        self.line_width
+            .as_ref()
+            .and_then(|width| width.evaluate_number(context))
+            .unwrap_or(1.0)
+    }
+}
+
 /// The different types of paints.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type", content = "paint")]
@@ -44,30 +72,55 @@ pub enum LayerPaint {
     Fill(FillPaint),
     #[serde(rename = "raster")]
     Raster(RasterLayer),
+    #[serde(rename = "hillshade")]
+    Hillshade(HillshadeLayer),
+    #[serde(rename = "heatmap")]
+    Heatmap(HeatmapLayer),
 }
 
 impl LayerPaint {
+    /// Returns the color of the layer regardless of the zoom and the state of its features.
     pub fn get_color(&self) -> Option<Alpha<EncodedSrgb<f32>>> {
-        match self {
-            LayerPaint::Background(paint) => paint
-                .background_color
-                .as_ref()
-                .map(|color| color.clone().into()),
-            LayerPaint::Line(paint) => paint.line_color.as_ref().map(|color| color.clone().into()),
-            LayerPaint::Fill(paint) => paint.fill_color.as_ref().map(|color| color.clone().into()),
+        self.color(&EvaluationContext::default())
+    }
+
+    /// Returns the color of a feature of the layer.
+    pub fn color(&self, context: &EvaluationContext) -> Option<Alpha<EncodedSrgb<f32>>> {
+        let color = match self {
+            LayerPaint::Background(paint) => paint.background_color.clone(),
+            LayerPaint::Line(paint) => paint.line_color.as_ref()?.evaluate_color(context),
+            LayerPaint::Fill(paint) => paint.fill_color.as_ref()?.evaluate_color(context),
             LayerPaint::Raster(_) => None,
-        }
+            LayerPaint::Hillshade(_) => None,
+            LayerPaint::Heatmap(_) => None,
+        };
+        color.map(Into::into)
+    }
+
+    /// Whether the color of a feature depends on its state.
+    pub fn uses_feature_state(&self) -> bool {
+        let color = match self {
+            LayerPaint::Line(paint) => paint.line_color.as_ref(),
+            LayerPaint::Fill(paint) => paint.fill_color.as_ref(),
+            _ => None,
+        };
+        color.is_some_and(Expression::uses_feature_state)
     }
 }
 
 /// Stores all the styles for a specific layer.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StyleLayer {
+    /// Position of the layer in [`Style::layers`](crate::style::Style::layers), which is
+    /// assigned when the style is loaded
     #[serde(skip)]
-    pub index: u32, // FIXME: How is this initialized?
+    pub index: u32,
     pub id: String,
-    // TODO filter
-    // TODO layout
+    /// Expression which selects the features of the source layer which are drawn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<HashMap<String, JsonValue>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub maxzoom: Option<u8>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -79,15 +132,34 @@ pub struct StyleLayer {
     pub paint: Option<LayerPaint>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
+    #[serde(rename = "source-layer", alias = "source_layer")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source_layer: Option<String>,
 }
 
+impl StyleLayer {
+    /// Returns whether the layer is drawn at `zoom`. Hidden layers have the layout property
+    /// `"visibility": "none"`. The `minzoom` is inclusive and the `maxzoom` exclusive.
+    pub fn is_visible_at(&self, zoom: f64) -> bool {
+        let hidden = self
+            .layout
+            .as_ref()
+            .and_then(|layout| layout.get("visibility"))
+            .is_some_and(|visibility| visibility == "none");
+
+        !hidden
+            && self.minzoom.map_or(true, |minzoom| zoom >= minzoom as f64)
+            && self.maxzoom.map_or(true, |maxzoom| zoom < maxzoom as f64)
+    }
+}
+
 impl Default for StyleLayer {
     fn default() -> Self {
         Self {
             index: 0,
             id: "id".to_string(),
+            filter: None,
+            layout: None,
             maxzoom: None,
             minzoom: None,
             metadata: None,
@@ -0,0 +1,438 @@
+//! Loads styles from remote urls or files.
+//!
+//! Urls within a style, like the `sprite`, `glyphs` and the `tiles` of sources, may be relative
+//! to the url of the style. They are resolved while loading, such that the loaded [`Style`] only
+//! contains absolute urls. TileJSON resources which are referenced by sources are loaded as well.
+//! `mapbox://` urls are translated to urls of the Mapbox API, which requires an access token.
+
+use std::fs;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    io::source_client::{HttpClient, SourceClient, SourceFetchError},
+    style::{
+        source::{deserialize_tiles, Source, TileAddressingScheme, TileJSONUrl, TileUrl},
+        Style,
+    },
+};
+
+const MAPBOX_API_URL: &str = "https://api.mapbox.com";
+
+#[derive(Error, Debug)]
+pub enum StyleLoadError {
+    #[error("fetching {url} failed")]
+    Fetch {
+        url: String,
+        #[source]
+        error: SourceFetchError,
+    },
+    #[error("reading {url} failed")]
+    Read {
+        url: String,
+        #[source]
+        error: std::io::Error,
+    },
+    #[error("parsing {url} failed")]
+    Parse {
+        url: String,
+        #[source]
+        error: serde_json::Error,
+    },
+    #[error("{0} requires an access token")]
+    MissingAccessToken(String),
+}
+
+/// The properties of a TileJSON resource which are used by sources.
+#[derive(Deserialize, Debug)]
+struct TileJson {
+    #[serde(default, deserialize_with = "deserialize_tiles")]
+    tiles: Option<TileUrl>,
+    minzoom: Option<u8>,
+    maxzoom: Option<u8>,
+    bounds: Option<(f64, f64, f64, f64)>,
+    attribution: Option<String>,
+    scheme: Option<TileAddressingScheme>,
+}
+
+/// Loads styles and the resources they refer to through a [`SourceClient`].
+pub struct StyleLoader<HC: HttpClient> {
+    client: SourceClient<HC>,
+    access_token: Option<String>,
+}
+
+impl<HC: HttpClient> StyleLoader<HC> {
+    pub fn new(client: SourceClient<HC>) -> Self {
+        Self {
+            client,
+            access_token: None,
+        }
+    }
+
+    /// Sets the access token which is used for `mapbox://` urls.
+    pub fn with_access_token(mut self, access_token: impl Into<String>) -> Self {
+        self.access_token = Some(access_token.into());
+        self
+    }
+
+    /// Loads the style at `url`. Urls which do not start with `http://`, `https://` or
+    /// `mapbox://` are read from the file system.
+    pub async fn load(&self, url: &str) -> Result<Style, StyleLoadError> {
+        let url = normalize_mapbox_url(url, self.access_token.as_deref())?;
+        let style = self.fetch_json(&url).await?;
+        self.resolve(style, &url).await
+    }
+
+    /// Resolves the urls within `style` relative to `base_url` and completes sources with the
+    /// properties of the TileJSON resources they refer to.
+    pub async fn resolve(&self, mut style: Style, base_url: &str) -> Result<Style, StyleLoadError> {
+        if let Some(sprite) = &style.sprite {
+            style.sprite = Some(self.resolve_url(base_url, sprite)?);
+        }
+        if let Some(glyphs) = &style.glyphs {
+            style.glyphs = Some(self.resolve_url(base_url, glyphs)?);
+        }
+
+        for source in style.sources.values_mut() {
+            match source {
+                Source::Vector(source) | Source::Raster(source) => {
+                    let tile_json =
+                        self.resolve_tiles(&mut source.tiles, &mut source.url, base_url);
+                    if let Some(tile_json) = tile_json.await? {
+                        source.tiles = source.tiles.take().or(tile_json.tiles);
+                        source.minzoom = source.minzoom.or(tile_json.minzoom);
+                        source.maxzoom = source.maxzoom.or(tile_json.maxzoom);
+                        source.bounds = source.bounds.or(tile_json.bounds);
+                        source.attribution = source.attribution.take().or(tile_json.attribution);
+                        source.scheme = source.scheme.take().or(tile_json.scheme);
+                    }
+                }
+                Source::RasterDem(source) => {
+                    let tile_json =
+                        self.resolve_tiles(&mut source.tiles, &mut source.url, base_url);
+                    if let Some(tile_json) = tile_json.await? {
+                        source.tiles = source.tiles.take().or(tile_json.tiles);
+                        source.minzoom = source.minzoom.or(tile_json.minzoom);
+                        source.maxzoom = source.maxzoom.or(tile_json.maxzoom);
+                        source.bounds = source.bounds.or(tile_json.bounds);
+                        source.attribution = source.attribution.take().or(tile_json.attribution);
+                    }
+                }
+                Source::Image(source) => {
+                    if let Some(url) = &source.url {
+                        source.url = Some(self.resolve_url(base_url, url)?);
+                    }
+                }
+                Source::Canvas(_) => {}
+            }
+        }
+
+        Ok(style)
+    }
+
+    /// Resolves the `tiles` and `url` of a source. If the source refers to a TileJSON resource,
+    /// then it is loaded and its tiles are resolved relative to the TileJSON url.
+    async fn resolve_tiles(
+        &self,
+        tiles: &mut Option<TileUrl>,
+        url: &mut Option<TileJSONUrl>,
+        base_url: &str,
+    ) -> Result<Option<TileJson>, StyleLoadError> {
+        if let Some(source_tiles) = tiles {
+            *tiles = Some(self.resolve_url(base_url, source_tiles)?);
+        }
+
+        let Some(tile_json_url) = url else {
+            return Ok(None);
+        };
+        let tile_json_url = self.resolve_url(base_url, tile_json_url)?;
+
+        let mut tile_json: TileJson = self.fetch_json(&tile_json_url).await?;
+        if let Some(tile_json_tiles) = &tile_json.tiles {
+            tile_json.tiles = Some(self.resolve_url(&tile_json_url, tile_json_tiles)?);
+        }
+
+        *url = Some(tile_json_url);
+        Ok(Some(tile_json))
+    }
+
+    fn resolve_url(&self, base_url: &str, url: &str) -> Result<String, StyleLoadError> {
+        normalize_mapbox_url(&resolve_url(base_url, url), self.access_token.as_deref())
+    }
+
+    async fn fetch_json<T: for<'de> Deserialize<'de>>(
+        &self,
+        url: &str,
+    ) -> Result<T, StyleLoadError> {
+        let data = if url.starts_with("http://") || url.starts_with("https://") {
+            self.client
+                .fetch_url(url)
+                .await
+                .map_err(|error| StyleLoadError::Fetch {
+                    url: url.to_string(),
+                    error,
+                })?
+        } else {
+            fs::read(url.strip_prefix("file://").unwrap_or(url)).map_err(|error| {
+                StyleLoadError::Read {
+                    url: url.to_string(),
+                    error,
+                }
+            })?
+        };
+
+        serde_json::from_slice(&data).map_err(|error| StyleLoadError::Parse {
+            url: url.to_string(),
+            error,
+        })
+    }
+}
+
+/// Returns the length of the scheme of `url` including the `:`, if `url` has a scheme.
+fn scheme_len(url: &str) -> Option<usize> {
+    let end = url.find(':')?;
+    let scheme = &url[..end];
+    let is_scheme = scheme
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    // Single letters are drive letters of Windows paths
+    (is_scheme && scheme.len() > 1).then_some(end + 1)
+}
+
+/// Resolves `url` relative to `base_url`. Both urls may be paths of the file system as well.
+pub fn resolve_url(base_url: &str, url: &str) -> String {
+    if scheme_len(url).is_some() {
+        return url.to_string();
+    }
+
+    let (origin, base_path) = match scheme_len(base_url) {
+        Some(scheme_len) if base_url[scheme_len..].starts_with("//") => {
+            if url.starts_with("//") {
+                return format!("{}{url}", &base_url[..scheme_len]);
+            }
+            let authority = &base_url[scheme_len + 2..];
+            let origin_len = scheme_len + 2 + authority.find('/').unwrap_or(authority.len());
+            base_url.split_at(origin_len)
+        }
+        _ => ("", base_url),
+    };
+
+    if url.starts_with('/') {
+        return format!("{origin}{url}");
+    }
+
+    // The query and fragment of the base url are not part of the directory
+    let base_path = base_path.split(['?', '#']).next().unwrap_or_default();
+    let directory = &base_path[..base_path.rfind('/').map_or(0, |i| i + 1)];
+
+    let (path, query) = match url.find(['?', '#']) {
+        Some(i) => url.split_at(i),
+        None => (url, ""),
+    };
+
+    let joined = format!("{directory}{path}");
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "." => {}
+            ".." => match segments.last() {
+                // The parent of the root is the root
+                Some(&"") if segments.len() == 1 => {}
+                Some(&"..") | None => segments.push(".."),
+                Some(_) => {
+                    segments.pop();
+                }
+            },
+            segment => segments.push(segment),
+        }
+    }
+
+    format!("{origin}{}{query}", segments.join("/"))
+}
+
+/// Translates `mapbox://` urls to urls of the Mapbox API. Other urls are returned unchanged.
+pub fn normalize_mapbox_url(
+    url: &str,
+    access_token: Option<&str>,
+) -> Result<String, StyleLoadError> {
+    let Some(path) = url.strip_prefix("mapbox://") else {
+        return Ok(url.to_string());
+    };
+    let access_token =
+        access_token.ok_or_else(|| StyleLoadError::MissingAccessToken(url.to_string()))?;
+
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let mut normalized = if let Some(style) = path.strip_prefix("styles/") {
+        format!("{MAPBOX_API_URL}/styles/v1/{style}")
+    } else if let Some(sprite) = path.strip_prefix("sprites/") {
+        format!("{MAPBOX_API_URL}/styles/v1/{sprite}/sprite")
+    } else if let Some(fonts) = path.strip_prefix("fonts/") {
+        format!("{MAPBOX_API_URL}/fonts/v1/{fonts}")
+    } else if let Some(tiles) = path.strip_prefix("tiles/") {
+        format!("{MAPBOX_API_URL}/v4/{tiles}")
+    } else {
+        // Tileset ids refer to TileJSON resources
+        format!("{MAPBOX_API_URL}/v4/{path}.json?secure")
+    };
+
+    for parameter in [query, &format!("access_token={access_token}")] {
+        if !parameter.is_empty() {
+            normalized.push(if normalized.contains('?') { '&' } else { '?' });
+            normalized.push_str(parameter);
+        }
+    }
+
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::io::source_client::HttpSourceClient;
+
+    #[derive(Clone)]
+    struct StaticHttpClient(HashMap<&'static str, &'static str>);
+
+    #[cfg_attr(not(feature = "thread-safe-futures"), async_trait(?Send))]
+    #[cfg_attr(feature = "thread-safe-futures", async_trait)]
+    impl HttpClient for StaticHttpClient {
+        async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
+            self.0
+                .get(url)
+                .map(|data| data.as_bytes().to_vec())
+                .ok_or_else(|| SourceFetchError(format!("{url} not found").into()))
+        }
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        let base = "https://example.com/styles/bright/style.json?key=1";
+        assert_eq!(
+            resolve_url(base, "sprite"),
+            "https://example.com/styles/bright/sprite"
+        );
+        assert_eq!(
+            resolve_url(base, "../../tiles/{z}/{x}/{y}.pbf?key=2"),
+            "https://example.com/tiles/{z}/{x}/{y}.pbf?key=2"
+        );
+        assert_eq!(
+            resolve_url(base, "/fonts/{fontstack}/{range}.pbf"),
+            "https://example.com/fonts/{fontstack}/{range}.pbf"
+        );
+        assert_eq!(
+            resolve_url(base, "//cdn.example.com/tiles.json"),
+            "https://cdn.example.com/tiles.json"
+        );
+        assert_eq!(
+            resolve_url(base, "mapbox://mapbox.satellite"),
+            "mapbox://mapbox.satellite"
+        );
+        assert_eq!(
+            resolve_url("styles/style.json", "../sprites/sprite"),
+            "sprites/sprite"
+        );
+        assert_eq!(resolve_url("style.json", "../tiles.json"), "../tiles.json");
+        assert_eq!(resolve_url("/maps/style.json", "../../a.json"), "/a.json");
+    }
+
+    #[test]
+    fn test_normalize_mapbox_url() {
+        let token = Some("pk.token");
+        assert_eq!(
+            normalize_mapbox_url("mapbox://styles/mapbox/streets-v12", token).unwrap(),
+            "https://api.mapbox.com/styles/v1/mapbox/streets-v12?access_token=pk.token"
+        );
+        assert_eq!(
+            normalize_mapbox_url("mapbox://mapbox.mapbox-streets-v8,mapbox.terrain-v2", token)
+                .unwrap(),
+            "https://api.mapbox.com/v4/mapbox.mapbox-streets-v8,mapbox.terrain-v2.json?secure&access_token=pk.token"
+        );
+        assert_eq!(
+            normalize_mapbox_url("mapbox://fonts/mapbox/{fontstack}/{range}.pbf", token).unwrap(),
+            "https://api.mapbox.com/fonts/v1/mapbox/{fontstack}/{range}.pbf?access_token=pk.token"
+        );
+        assert_eq!(
+            normalize_mapbox_url("https://example.com/style.json", None).unwrap(),
+            "https://example.com/style.json"
+        );
+        assert!(matches!(
+            normalize_mapbox_url("mapbox://styles/mapbox/streets-v12", None),
+            Err(StyleLoadError::MissingAccessToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_load_style() {
+        // language=JSON
+        let style = r#"
+        {
+          "version": 8,
+          "sprite": "sprites/bright",
+          "glyphs": "/fonts/{fontstack}/{range}.pbf",
+          "sources": {
+            "openmaptiles": {"type": "vector", "url": "../tiles.json", "maxzoom": 12},
+            "satellite": {"type": "raster", "tiles": ["satellite/{z}/{x}/{y}.jpg"]}
+          },
+          "layers": []
+        }
+        "#;
+        // language=JSON
+        let tile_json = r#"
+        {
+          "tilejson": "2.2.0",
+          "tiles": ["data/{z}/{x}/{y}.pbf"],
+          "minzoom": 0,
+          "maxzoom": 14,
+          "attribution": "OpenMapTiles"
+        }
+        "#;
+
+        let client = StaticHttpClient(HashMap::from([
+            ("https://example.com/styles/bright.json", style),
+            ("https://example.com/tiles.json", tile_json),
+        ]));
+        let loader = StyleLoader::new(SourceClient::new(HttpSourceClient::new(client)));
+        let style = loader
+            .load("https://example.com/styles/bright.json")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            style.sprite.as_deref(),
+            Some("https://example.com/styles/sprites/bright")
+        );
+        assert_eq!(
+            style.glyphs.as_deref(),
+            Some("https://example.com/fonts/{fontstack}/{range}.pbf")
+        );
+
+        let Some(Source::Vector(source)) = style.sources.get("openmaptiles") else {
+            panic!("vector source is missing");
+        };
+        assert_eq!(
+            source.tiles.as_deref(),
+            Some("https://example.com/data/{z}/{x}/{y}.pbf")
+        );
+        assert_eq!(source.minzoom, Some(0));
+        // Properties of the style take precedence
+        assert_eq!(source.maxzoom, Some(12));
+        assert_eq!(source.attribution.as_deref(), Some("OpenMapTiles"));
+
+        let Some(Source::Raster(source)) = style.sources.get("satellite") else {
+            panic!("raster source is missing");
+        };
+        assert_eq!(
+            source.tiles.as_deref(),
+            Some("https://example.com/styles/satellite/{z}/{x}/{y}.jpg")
+        );
+    }
+}
@@ -3,7 +3,16 @@
 pub use cint::*;
 pub use style::*;
 
+pub mod diff;
+pub mod expression;
+pub mod filter;
+pub mod heatmap;
+pub mod hillshade;
 pub mod layer;
+pub mod loader;
+pub mod mutation;
 pub mod raster;
 pub mod source;
 mod style;
+pub mod terrain;
+pub mod validate;
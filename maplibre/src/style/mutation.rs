@@ -0,0 +1,490 @@
+//! Mutations of a live [`Style`].
+//!
+//! Each mutation returns a [`StyleChange`] which describes the state derived from the style that
+//! is outdated afterwards, e.g. uploaded or tessellated tiles.
+
+use std::collections::HashMap;
+
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+
+use crate::style::{
+    layer::{LayerPaint, StyleLayer},
+    source::Source,
+    Style,
+};
+
+#[derive(Error, Debug)]
+pub enum StyleError {
+    #[error("layer {0} does not exist")]
+    LayerNotFound(String),
+    #[error("layer {0} already exists")]
+    LayerExists(String),
+    #[error("source {0} does not exist")]
+    SourceNotFound(String),
+    #[error("source {0} already exists")]
+    SourceExists(String),
+    /// Sources can not be removed while a layer uses them
+    #[error("source {source_id} is used by layer {layer_id}")]
+    SourceInUse { source_id: String, layer_id: String },
+    #[error("invalid value for property {0}")]
+    InvalidProperty(String),
+}
+
+/// Describes which state derived from a [`Style`] is outdated after a mutation.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StyleChange {
+    /// Layers whose paint properties, position or zoom range changed. Their geometry is still
+    /// valid.
+    pub restyled_layers: Vec<String>,
+    pub removed_layers: Vec<String>,
+    /// Tiles have to be fetched and tessellated again, e.g. because a source layer is needed
+    pub retessellate: bool,
+    /// Raster, raster-dem, image and canvas sources whose data has to be fetched again. Vector
+    /// sources are fetched again by retessellating.
+    pub reloaded_sources: Vec<String>,
+}
+
+impl StyleChange {
+    fn restyle(layer_id: &str) -> Self {
+        Self {
+            restyled_layers: vec![layer_id.to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn retessellate() -> Self {
+        Self {
+            retessellate: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Combines two changes such that applying the result is equivalent to applying both.
+    pub fn merge(&mut self, other: StyleChange) {
+        for layer_id in other.restyled_layers {
+            if !self.restyled_layers.contains(&layer_id) {
+                self.restyled_layers.push(layer_id);
+            }
+        }
+        for layer_id in other.removed_layers {
+            if !self.removed_layers.contains(&layer_id) {
+                self.removed_layers.push(layer_id);
+            }
+        }
+        self.retessellate |= other.retessellate;
+        for source_id in other.reloaded_sources {
+            if !self.reloaded_sources.contains(&source_id) {
+                self.reloaded_sources.push(source_id);
+            }
+        }
+    }
+}
+
+/// Returns whether the tiles of the source layer of `layer` are fetched and tessellated.
+fn is_tessellated(layer: &StyleLayer) -> bool {
+    matches!(
+        layer.paint,
+        Some(LayerPaint::Fill(_)) | Some(LayerPaint::Line(_)) | Some(LayerPaint::Heatmap(_))
+    )
+}
+
+impl Style {
+    fn layer_position(&self, layer_id: &str) -> Result<usize, StyleError> {
+        self.layers
+            .iter()
+            .position(|layer| layer.id == layer_id)
+            .ok_or_else(|| StyleError::LayerNotFound(layer_id.to_string()))
+    }
+
+    fn layer_mut(&mut self, layer_id: &str) -> Result<&mut StyleLayer, StyleError> {
+        let position = self.layer_position(layer_id)?;
+        Ok(&mut self.layers[position])
+    }
+
+    /// Returns the position at which a layer is inserted such that it is drawn below
+    /// `before_id`. Layers without `before_id` are drawn on top of all other layers.
+    fn insert_position(&self, before_id: Option<&str>) -> Result<usize, StyleError> {
+        before_id.map_or(Ok(self.layers.len()), |before_id| {
+            self.layer_position(before_id)
+        })
+    }
+
+    /// Updates the layer indices and returns the layers whose index changed.
+    fn reindex_layers(&mut self) -> Vec<String> {
+        let previous: HashMap<String, u32> = self
+            .layers
+            .iter()
+            .map(|layer| (layer.id.clone(), layer.index))
+            .collect();
+
+        self.update_layer_indices();
+
+        self.layers
+            .iter()
+            .filter(|layer| previous.get(&layer.id) != Some(&layer.index))
+            .map(|layer| layer.id.clone())
+            .collect()
+    }
+
+    /// Inserts a layer below the layer `before_id`, or on top if `before_id` is `None`.
+    pub fn add_layer(
+        &mut self,
+        layer: StyleLayer,
+        before_id: Option<&str>,
+    ) -> Result<StyleChange, StyleError> {
+        if self.layers.iter().any(|existing| existing.id == layer.id) {
+            return Err(StyleError::LayerExists(layer.id));
+        }
+        if let Some(source) = &layer.source {
+            if !self.sources.contains_key(source) {
+                return Err(StyleError::SourceNotFound(source.clone()));
+            }
+        }
+        let position = self.insert_position(before_id)?;
+
+        // The source layer is only part of fetched tiles if another layer with the same filter
+        // needs it
+        let retessellate = is_tessellated(&layer)
+            && !self.layers.iter().any(|existing| {
+                is_tessellated(existing)
+                    && existing.source == layer.source
+                    && existing.source_layer == layer.source_layer
+                    && existing.filter == layer.filter
+            });
+
+        let layer_id = layer.id.clone();
+        self.layers.insert(position, layer);
+
+        let mut restyled_layers = self.reindex_layers();
+        restyled_layers.retain(|id| id != &layer_id);
+
+        Ok(StyleChange {
+            restyled_layers,
+            retessellate,
+            ..Default::default()
+        })
+    }
+
+    pub fn remove_layer(&mut self, layer_id: &str) -> Result<StyleChange, StyleError> {
+        let position = self.layer_position(layer_id)?;
+        self.layers.remove(position);
+
+        Ok(StyleChange {
+            restyled_layers: self.reindex_layers(),
+            removed_layers: vec![layer_id.to_string()],
+            ..Default::default()
+        })
+    }
+
+    /// Moves a layer below the layer `before_id`, or on top if `before_id` is `None`.
+    pub fn move_layer(
+        &mut self,
+        layer_id: &str,
+        before_id: Option<&str>,
+    ) -> Result<StyleChange, StyleError> {
+        let position = self.layer_position(layer_id)?;
+        if before_id == Some(layer_id) {
+            return Ok(StyleChange::default());
+        }
+        // Validate before removing such that the style stays untouched on errors
+        self.insert_position(before_id)?;
+
+        let layer = self.layers.remove(position);
+        let position = self.insert_position(before_id)?;
+        self.layers.insert(position, layer);
+
+        Ok(StyleChange {
+            restyled_layers: self.reindex_layers(),
+            ..Default::default()
+        })
+    }
+
+    /// Sets a paint property, e.g. `fill-color`. A `null` value resets the property to its
+    /// default.
+    pub fn set_paint_property(
+        &mut self,
+        layer_id: &str,
+        name: &str,
+        value: JsonValue,
+    ) -> Result<StyleChange, StyleError> {
+        let layer = self.layer_mut(layer_id)?;
+        let invalid = || StyleError::InvalidProperty(name.to_string());
+
+        let paint = layer.paint.as_ref().ok_or_else(invalid)?;
+        let mut json = serde_json::to_value(paint).map_err(|_| invalid())?;
+        let properties = json
+            .get_mut("paint")
+            .and_then(JsonValue::as_object_mut)
+            .ok_or_else(invalid)?;
+
+        let is_reset = value.is_null();
+        if is_reset {
+            properties.remove(name);
+        } else {
+            properties.insert(name.to_string(), value);
+        }
+
+        let updated: LayerPaint = serde_json::from_value(json).map_err(|_| invalid())?;
+        let updated_json = serde_json::to_value(&updated).map_err(|_| invalid())?;
+        // Unknown properties are dropped while deserializing
+        let is_known = updated_json
+            .get("paint")
+            .is_some_and(|properties| properties.get(name).is_some());
+        if !is_reset && !is_known {
+            return Err(invalid());
+        }
+
+        if serde_json::to_value(paint).ok() == Some(updated_json) {
+            return Ok(StyleChange::default());
+        }
+
+        layer.paint = Some(updated);
+        Ok(StyleChange::restyle(layer_id))
+    }
+
+    /// Sets a layout property. Only `visibility` is evaluated by this renderer, other layout
+    /// properties are rejected. A `null` value resets the property to its default.
+    pub fn set_layout_property(
+        &mut self,
+        layer_id: &str,
+        name: &str,
+        value: JsonValue,
+    ) -> Result<StyleChange, StyleError> {
+        if name != "visibility" {
+            return Err(StyleError::InvalidProperty(name.to_string()));
+        }
+
+        let layer = self.layer_mut(layer_id)?;
+        let layout = layer.layout.get_or_insert_with(Default::default);
+
+        let previous = if value.is_null() {
+            layout.remove(name)
+        } else {
+            layout.insert(name.to_string(), value.clone())
+        };
+        if layout.is_empty() {
+            layer.layout = None;
+        }
+
+        if previous.unwrap_or(JsonValue::Null) == value {
+            return Ok(StyleChange::default());
+        }
+
+        Ok(StyleChange::restyle(layer_id))
+    }
+
+    /// Sets the filter of a layer. The features of a layer are filtered while tessellating, so
+    /// changing the filter requires the tiles to be tessellated again.
+    pub fn set_filter(
+        &mut self,
+        layer_id: &str,
+        filter: Option<JsonValue>,
+    ) -> Result<StyleChange, StyleError> {
+        let layer = self.layer_mut(layer_id)?;
+        if layer.filter == filter {
+            return Ok(StyleChange::default());
+        }
+
+        layer.filter = filter;
+        Ok(StyleChange::retessellate())
+    }
+
+    /// Limits the zoom levels at which a layer is drawn. The `minzoom` is inclusive and the
+    /// `maxzoom` exclusive.
+    pub fn set_layer_zoom_range(
+        &mut self,
+        layer_id: &str,
+        minzoom: Option<u8>,
+        maxzoom: Option<u8>,
+    ) -> Result<StyleChange, StyleError> {
+        let layer = self.layer_mut(layer_id)?;
+        if layer.minzoom == minzoom && layer.maxzoom == maxzoom {
+            return Ok(StyleChange::default());
+        }
+
+        layer.minzoom = minzoom;
+        layer.maxzoom = maxzoom;
+        Ok(StyleChange::restyle(layer_id))
+    }
+
+    /// Adds a source. Nothing is fetched until a layer uses the source.
+    pub fn add_source(&mut self, id: &str, source: Source) -> Result<StyleChange, StyleError> {
+        if self.sources.contains_key(id) {
+            return Err(StyleError::SourceExists(id.to_string()));
+        }
+
+        self.sources.insert(id.to_string(), source);
+        Ok(StyleChange::default())
+    }
+
+    pub fn remove_source(&mut self, id: &str) -> Result<StyleChange, StyleError> {
+        if !self.sources.contains_key(id) {
+            return Err(StyleError::SourceNotFound(id.to_string()));
+        }
+        if let Some(layer) = self
+            .layers
+            .iter()
+            .find(|layer| layer.source.as_deref() == Some(id))
+        {
+            return Err(StyleError::SourceInUse {
+                source_id: id.to_string(),
+                layer_id: layer.id.clone(),
+            });
+        }
+
+        self.sources.remove(id);
+        Ok(StyleChange::default())
+    }
+
+    /// Replaces a source in place, e.g. because its tiles changed. The data which has been
+    /// fetched from the source is fetched again.
+    pub fn set_source(&mut self, id: &str, source: Source) -> Result<StyleChange, StyleError> {
+        if !self.sources.contains_key(id) {
+            return Err(StyleError::SourceNotFound(id.to_string()));
+        }
+
+        let is_vector = matches!(source, Source::Vector(_));
+        self.sources.insert(id.to_string(), source);
+        if is_vector {
+            Ok(StyleChange::retessellate())
+        } else {
+            Ok(StyleChange {
+                reloaded_sources: vec![id.to_string()],
+                ..Default::default()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn layer(id: &str, source_layer: &str) -> StyleLayer {
+        serde_json::from_value(json!({
+            "id": id,
+            "type": "fill",
+            "source-layer": source_layer,
+            "paint": {"fill-color": "#ffffff"}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_add_move_remove_layer() {
+        let mut style = Style {
+            layers: vec![],
+            ..Style::default()
+        };
+
+        let change = style.add_layer(layer("water", "water"), None).unwrap();
+        assert!(change.retessellate);
+
+        let change = style
+            .add_layer(layer("park", "park"), Some("water"))
+            .unwrap();
+        assert_eq!(change.restyled_layers, vec!["water".to_string()]);
+
+        // The source layer is already part of the fetched tiles
+        let change = style.add_layer(layer("lake", "water"), None).unwrap();
+        assert!(!change.retessellate);
+        assert!(matches!(
+            style.add_layer(layer("lake", "water"), None),
+            Err(StyleError::LayerExists(_))
+        ));
+
+        let change = style.move_layer("lake", Some("park")).unwrap();
+        assert_eq!(change.restyled_layers.len(), 3);
+        let ids: Vec<_> = style.layers.iter().map(|layer| layer.id.as_str()).collect();
+        assert_eq!(ids, vec!["lake", "park", "water"]);
+
+        let change = style.remove_layer("lake").unwrap();
+        assert_eq!(change.removed_layers, vec!["lake".to_string()]);
+        assert_eq!(style.layers[1].id, "water");
+        assert_eq!(style.layers[1].index, 1);
+        assert!(matches!(
+            style.remove_layer("lake"),
+            Err(StyleError::LayerNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_set_properties() {
+        let mut style = Style::default();
+
+        let change = style
+            .set_paint_property("water", "fill-color", json!("red"))
+            .unwrap();
+        assert_eq!(change, StyleChange::restyle("water"));
+        let color = style.layers[5].paint.as_ref().unwrap().get_color().unwrap();
+        assert_eq!((color.color.r, color.color.g), (1.0, 0.0));
+
+        // Unchanged values do not invalidate anything
+        let change = style
+            .set_paint_property("water", "fill-color", json!("#ff0000"))
+            .unwrap();
+        assert!(change.is_empty());
+
+        assert!(matches!(
+            style.set_paint_property("water", "fill-unknown", json!(1)),
+            Err(StyleError::InvalidProperty(_))
+        ));
+        assert!(matches!(
+            style.set_paint_property("water", "fill-color", json!(1)),
+            Err(StyleError::InvalidProperty(_))
+        ));
+
+        let change = style
+            .set_layout_property("water", "visibility", json!("none"))
+            .unwrap();
+        assert_eq!(change, StyleChange::restyle("water"));
+        assert!(!style.layers[5].is_visible_at(10.0));
+
+        assert!(matches!(
+            style.set_layout_property("water", "line-cap", json!("round")),
+            Err(StyleError::InvalidProperty(_))
+        ));
+
+        let filter = Some(json!(["==", "class", "lake"]));
+        let change = style.set_filter("water", filter.clone()).unwrap();
+        assert_eq!(change, StyleChange::retessellate());
+        assert!(style.set_filter("water", filter).unwrap().is_empty());
+
+        style
+            .set_layer_zoom_range("park", Some(5), Some(10))
+            .unwrap();
+        assert!(style.layers[0].is_visible_at(5.0));
+        assert!(!style.layers[0].is_visible_at(10.0));
+    }
+
+    #[test]
+    fn test_remove_used_source() {
+        let mut style = Style::default();
+        let source: Source =
+            serde_json::from_value(json!({"type": "vector", "url": "https://example.com"}))
+                .unwrap();
+        style.add_source("openmaptiles", source).unwrap();
+
+        let mut building = layer("building-3d", "building");
+        building.source = Some("openmaptiles".to_string());
+        style.add_layer(building, None).unwrap();
+
+        assert!(matches!(
+            style.remove_source("openmaptiles"),
+            Err(StyleError::SourceInUse { .. })
+        ));
+        style.remove_layer("building-3d").unwrap();
+        style.remove_source("openmaptiles").unwrap();
+        assert!(matches!(
+            style.remove_source("openmaptiles"),
+            Err(StyleError::SourceNotFound(_))
+        ));
+    }
+}
@@ -1,6 +1,11 @@
 //! Vector tile data utilities.
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    coords::LatLon,
+    io::source_type::{SourceType, UrlTemplateSource},
+};
 
 /// String url to a tile.
 pub type TileUrl = String;
@@ -23,6 +28,25 @@ impl Default for TileAddressingScheme {
     }
 }
 
+/// Deserializes the `tiles` of a source. The specification requires an array of urls, but only
+/// the first one is used. A single url is accepted as well.
+pub(crate) fn deserialize_tiles<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<TileUrl>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Tiles {
+        One(TileUrl),
+        Many(Vec<TileUrl>),
+    }
+
+    Ok(match Option::<Tiles>::deserialize(deserializer)? {
+        Some(Tiles::One(url)) => Some(url),
+        Some(Tiles::Many(urls)) => urls.into_iter().next(),
+        None => None,
+    })
+}
+
 /// Source properties for tiles or rasters.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VectorSource {
@@ -43,12 +67,109 @@ pub struct VectorSource {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scheme: Option<TileAddressingScheme>,
     /// Array of URLs which can contain place holders like {x}, {y}, {z}.
+    #[serde(default, deserialize_with = "deserialize_tiles")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tiles: Option<TileUrl>,
-    // url: Option<TileJSONUrl>,
+    /// Url to a TileJSON resource which provides the other properties of the source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<TileJSONUrl>,
     // TODO volatile
 }
 
+impl VectorSource {
+    /// Returns the source from which tiles are fetched, if the url of the tiles is known.
+    pub fn tile_source(&self) -> Option<SourceType> {
+        self.tiles.as_ref().map(|tiles| {
+            SourceType::UrlTemplate(UrlTemplateSource::new(
+                tiles,
+                self.scheme.clone().unwrap_or_default(),
+            ))
+        })
+    }
+}
+
+/// Encoding which is used to pack elevation values into the RGB channels of a raster-dem tile.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DemEncoding {
+    /// Mapbox Terrain-RGB: `-10000 + (R * 256 * 256 + G * 256 + B) * 0.1`
+    #[default]
+    #[serde(rename = "mapbox")]
+    Mapbox,
+    /// Terrarium: `(R * 256 + G + B / 256) - 32768`
+    #[serde(rename = "terrarium")]
+    Terrarium,
+}
+
+/// Source properties for raster tiles which encode elevation data.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RasterDemSource {
+    /// String which contains attribution information for the used tiles.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<String>,
+    /// The bounds in which tiles are available.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<(f64, f64, f64, f64)>,
+    /// Max zoom level at which tiles are available.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maxzoom: Option<u8>,
+    /// Min zoom level at which tiles are available.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minzoom: Option<u8>,
+    /// Url which can contain place holders like {x}, {y}, {z}.
+    #[serde(default, deserialize_with = "deserialize_tiles")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tiles: Option<TileUrl>,
+    /// Url to a TileJSON resource which provides the other properties of the source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<TileJSONUrl>,
+    /// The minimum visual size to display tiles for this layer.
+    #[serde(rename = "tileSize")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tile_size: Option<u32>,
+    /// The encoding used by this source.
+    #[serde(default)]
+    pub encoding: DemEncoding,
+}
+
+/// Corners of an image as `[longitude, latitude]` pairs. The corners are listed clockwise, starting
+/// with the top left corner of the image.
+pub type ImageCoordinates = [[f64; 2]; 4];
+
+/// Converts [`ImageCoordinates`] to the corners of an image.
+fn corners(coordinates: &ImageCoordinates) -> [LatLon; 4] {
+    coordinates.map(|[longitude, latitude]| LatLon::new(latitude, longitude))
+}
+
+/// Source properties for a single image which is anchored to four coordinates.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageSource {
+    /// Url of the image. If missing, then the image needs to be provided at runtime.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Corners of the image.
+    pub coordinates: ImageCoordinates,
+}
+
+impl ImageSource {
+    pub fn corners(&self) -> [LatLon; 4] {
+        corners(&self.coordinates)
+    }
+}
+
+/// Source properties for raw frames which are anchored to four coordinates. The frames are
+/// provided at runtime, e.g. for animations or video.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CanvasSource {
+    /// Corners of the frames.
+    pub coordinates: ImageCoordinates,
+}
+
+impl CanvasSource {
+    pub fn corners(&self) -> [LatLon; 4] {
+        corners(&self.coordinates)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum Source {
@@ -56,4 +177,30 @@ pub enum Source {
     Vector(VectorSource),
     #[serde(rename = "raster")]
     Raster(VectorSource), // FIXME: Does it make sense that a raster have a VectorSource?
+    #[serde(rename = "raster-dem")]
+    RasterDem(RasterDemSource),
+    #[serde(rename = "image")]
+    Image(ImageSource),
+    #[serde(rename = "canvas")]
+    Canvas(CanvasSource),
+}
+
+impl Source {
+    /// Returns the corners of image and canvas sources.
+    pub fn corners(&self) -> Option<[LatLon; 4]> {
+        match self {
+            Source::Image(source) => Some(source.corners()),
+            Source::Canvas(source) => Some(source.corners()),
+            _ => None,
+        }
+    }
+
+    /// Returns the attribution of tile sources, which has to be displayed while they are in use.
+    pub fn attribution(&self) -> Option<&str> {
+        match self {
+            Source::Vector(source) | Source::Raster(source) => source.attribution.as_deref(),
+            Source::RasterDem(source) => source.attribution.as_deref(),
+            _ => None,
+        }
+    }
 }
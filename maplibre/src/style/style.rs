@@ -3,25 +3,71 @@
 use std::{collections::HashMap, str::FromStr};
 
 use csscolorparser::Color;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
 
-use crate::style::{
-    layer::{FillPaint, LayerPaint, LinePaint, StyleLayer},
-    raster::RasterLayer,
-    source::Source,
+use crate::{
+    coords::LatLon,
+    style::{
+        expression::Expression,
+        layer::{FillPaint, LayerPaint, LinePaint, StyleLayer},
+        raster::RasterLayer,
+        source::Source,
+        terrain::Terrain,
+    },
 };
 
 /// Stores the style for a multi-layered map.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Style {
     pub version: u16,
+    #[serde(default)]
     pub name: String,
-    pub metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, JsonValue>,
     pub sources: HashMap<String, Source>,
+    #[serde(deserialize_with = "deserialize_layers")]
     pub layers: Vec<StyleLayer>,
+    /// Url of the sprite without the `.json` and `.png` extensions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sprite: Option<String>,
+    /// Url template of the glyphs which contains the `{fontstack}` and `{range}` place holders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glyphs: Option<String>,
+    /// Initial center as `[longitude, latitude]`.
     pub center: Option<[f64; 2]>, // TODO: Use LatLon type here
     pub zoom: Option<f64>,
     pub pitch: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub terrain: Option<Terrain>,
+}
+
+impl Style {
+    /// Assigns the index of each layer from its position in [`Style::layers`]. Layers with a
+    /// larger index are drawn on top of layers with a smaller index.
+    pub fn update_layer_indices(&mut self) {
+        assign_layer_indices(&mut self.layers);
+    }
+
+    /// Returns the initial center, which is `[0, 0]` if the style does not define one.
+    pub fn initial_center(&self) -> LatLon {
+        let [longitude, latitude] = self.center.unwrap_or_default();
+        LatLon::new(latitude, longitude)
+    }
+}
+
+fn assign_layer_indices(layers: &mut [StyleLayer]) {
+    for (index, layer) in layers.iter_mut().enumerate() {
+        layer.index = index as u32;
+    }
+}
+
+fn deserialize_layers<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<StyleLayer>, D::Error> {
+    let mut layers = Vec::<StyleLayer>::deserialize(deserializer)?;
+    assign_layer_indices(&mut layers);
+    Ok(layers)
 }
 
 impl Default for Style {
@@ -31,18 +77,23 @@ impl Default for Style {
             name: "Default Style".to_string(),
             metadata: Default::default(),
             sources: Default::default(),
-            center: Some([50.85045, 4.34878]),
+            sprite: None,
+            glyphs: None,
+            center: Some([4.34878, 50.85045]),
             pitch: Some(0.0),
             zoom: Some(13.0),
+            terrain: None,
             layers: vec![
                 StyleLayer {
                     index: 0,
                     id: "park".to_string(),
+                    filter: None,
+                    layout: None,
                     maxzoom: None,
                     minzoom: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
-                        fill_color: Some(Color::from_str("#c8facc").unwrap()),
+                        fill_color: Some(Expression::Color(Color::from_str("#c8facc").unwrap())),
                     })),
                     source: None,
                     source_layer: Some("park".to_string()),
@@ -50,11 +101,13 @@ impl Default for Style {
                 StyleLayer {
                     index: 1,
                     id: "landuse".to_string(),
+                    filter: None,
+                    layout: None,
                     maxzoom: None,
                     minzoom: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
-                        fill_color: Some(Color::from_str("#e0dfdf").unwrap()),
+                        fill_color: Some(Expression::Color(Color::from_str("#e0dfdf").unwrap())),
                     })),
                     source: None,
                     source_layer: Some("landuse".to_string()),
@@ -62,11 +115,13 @@ impl Default for Style {
                 StyleLayer {
                     index: 2,
                     id: "landcover".to_string(),
+                    filter: None,
+                    layout: None,
                     maxzoom: None,
                     minzoom: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
-                        fill_color: Some(Color::from_str("#aedfa3").unwrap()),
+                        fill_color: Some(Expression::Color(Color::from_str("#aedfa3").unwrap())),
                     })),
                     source: None,
                     source_layer: Some("landcover".to_string()),
@@ -74,11 +129,14 @@ impl Default for Style {
                 StyleLayer {
                     index: 3,
                     id: "transportation".to_string(),
+                    filter: None,
+                    layout: None,
                     maxzoom: None,
                     minzoom: None,
                     metadata: None,
                     paint: Some(LayerPaint::Line(LinePaint {
-                        line_color: Some(Color::from_str("#ffffff").unwrap()),
+                        line_color: Some(Expression::Color(Color::from_str("#ffffff").unwrap())),
+                        line_width: None,
                     })),
                     source: None,
                     source_layer: Some("transportation".to_string()),
@@ -86,23 +144,27 @@ impl Default for Style {
                 StyleLayer {
                     index: 4,
                     id: "building".to_string(),
+                    filter: None,
+                    layout: None,
                     maxzoom: None,
                     minzoom: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
-                        fill_color: Some(Color::from_str("#d9d0c9").unwrap()),
+                        fill_color: Some(Expression::Color(Color::from_str("#d9d0c9").unwrap())),
                     })),
                     source: None,
                     source_layer: Some("building".to_string()),
                 },
                 StyleLayer {
-                    index: 4,
+                    index: 5,
                     id: "water".to_string(),
+                    filter: None,
+                    layout: None,
                     maxzoom: None,
                     minzoom: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
-                        fill_color: Some(Color::from_str("#aad3df").unwrap()),
+                        fill_color: Some(Expression::Color(Color::from_str("#aad3df").unwrap())),
                     })),
                     source: None,
                     source_layer: Some("water".to_string()),
@@ -110,11 +172,13 @@ impl Default for Style {
                 StyleLayer {
                     index: 6,
                     id: "waterway".to_string(),
+                    filter: None,
+                    layout: None,
                     maxzoom: None,
                     minzoom: None,
                     metadata: None,
                     paint: Some(LayerPaint::Fill(FillPaint {
-                        fill_color: Some(Color::from_str("#aad3df").unwrap()),
+                        fill_color: Some(Expression::Color(Color::from_str("#aad3df").unwrap())),
                     })),
                     source: None,
                     source_layer: Some("waterway".to_string()),
@@ -122,11 +186,14 @@ impl Default for Style {
                 StyleLayer {
                     index: 7,
                     id: "boundary".to_string(),
+                    filter: None,
+                    layout: None,
                     maxzoom: None,
                     minzoom: None,
                     metadata: None,
                     paint: Some(LayerPaint::Line(LinePaint {
-                        line_color: Some(Color::from_str("black").unwrap()),
+                        line_color: Some(Expression::Color(Color::from_str("black").unwrap())),
+                        line_width: None,
                     })),
                     source: None,
                     source_layer: Some("boundary".to_string()),
@@ -134,6 +201,8 @@ impl Default for Style {
                 StyleLayer {
                     index: 8,
                     id: "raster".to_string(),
+                    filter: None,
+                    layout: None,
                     maxzoom: None,
                     minzoom: None,
                     metadata: None,
@@ -149,6 +218,7 @@ impl Default for Style {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::style::source::DemEncoding;
 
     #[test]
     fn test_reading() {
@@ -162,8 +232,17 @@ mod tests {
             "openmaptiles": {
               "type": "vector",
               "url": "https://maps.tuerantuer.org/europe_germany/tiles.json"
+            },
+            "terrain": {
+              "type": "raster-dem",
+              "tiles": "https://example.com/{z}/{x}/{y}.png",
+              "encoding": "terrarium"
             }
           },
+          "terrain": {
+            "source": "terrain",
+            "exaggeration": 1.5
+          },
           "layers": [
             {
               "id": "background",
@@ -198,11 +277,63 @@ mod tests {
               "paint": {
                 "line-color": "#3D3D3D"
               }
+            },
+            {
+              "id": "hillshade",
+              "type": "hillshade",
+              "source": "terrain",
+              "paint": {
+                "hillshade-exaggeration": 0.8,
+                "hillshade-shadow-color": "#473B24",
+                "hillshade-illumination-direction": 315
+              }
             }
           ]
         }
         "##;
 
-        let _style: Style = serde_json::from_str(style_json_str).unwrap();
+        let style: Style = serde_json::from_str(style_json_str).unwrap();
+
+        assert!(matches!(
+            style.sources.get("terrain"),
+            Some(Source::RasterDem(source)) if source.encoding == DemEncoding::Terrarium
+        ));
+        assert_eq!(
+            style.terrain,
+            Some(Terrain {
+                source: "terrain".to_string(),
+                exaggeration: Some(1.5)
+            })
+        );
+
+        let indices = style
+            .layers
+            .iter()
+            .map(|layer| (layer.id.as_str(), layer.index))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            indices,
+            vec![
+                ("background", 0),
+                ("transportation", 1),
+                ("boundary", 2),
+                ("building", 3),
+                ("hillshade", 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_initial_center() {
+        let style: Style = serde_json::from_str(
+            r#"{"version": 8, "sources": {}, "layers": [], "center": [11.5, 48.1]}"#,
+        )
+        .unwrap();
+        let center = style.initial_center();
+        assert_eq!((center.latitude, center.longitude), (48.1, 11.5));
+
+        // Brussels
+        let center = Style::default().initial_center();
+        assert_eq!((center.latitude, center.longitude), (50.85045, 4.34878));
     }
 }
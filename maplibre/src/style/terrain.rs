@@ -0,0 +1,19 @@
+//! Terrain description
+
+use serde::{Deserialize, Serialize};
+
+/// Renders the map in 3D by displacing the ground with the elevation of a raster-dem source.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Terrain {
+    /// Id of the raster-dem source which provides the elevation.
+    pub source: String,
+    /// Factor by which the elevation is multiplied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exaggeration: Option<f32>,
+}
+
+impl Terrain {
+    pub fn exaggeration(&self) -> f32 {
+        self.exaggeration.unwrap_or(1.0)
+    }
+}
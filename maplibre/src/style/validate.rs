@@ -0,0 +1,642 @@
+//! Validation of styles against the style specification.
+//!
+//! The validator reports every problem of a style instead of stopping at the first one. Errors
+//! are violations of the specification or prevent the style from loading. Warnings describe
+//! parts of a valid style which are not supported and ignored by this renderer.
+
+use std::{collections::HashSet, fmt, sync::OnceLock};
+
+use csscolorparser::Color;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::style::{
+    expression::{Expression, ExpressionError},
+    layer::StyleLayer,
+    source::Source,
+    Style,
+};
+
+const SPEC_JSON: &str = include_str!("../../style-spec-v8.json");
+
+/// Layer types which are rendered.
+const SUPPORTED_LAYER_TYPES: &[&str] = &[
+    "background",
+    "fill",
+    "line",
+    "raster",
+    "hillshade",
+    "heatmap",
+];
+
+/// Properties of the root which are read.
+const SUPPORTED_ROOT_PROPERTIES: &[&str] = &[
+    "version", "name", "metadata", "center", "zoom", "pitch", "terrain", "sprite", "glyphs",
+    "sources", "layers",
+];
+
+/// Layout properties which are evaluated.
+const SUPPORTED_LAYOUT_PROPERTIES: &[&str] = &["visibility"];
+
+/// Properties of canvas sources, which are not part of the specification.
+const CANVAS_SOURCE_PROPERTIES: &[&str] = &["type", "coordinates", "canvas", "animate"];
+
+fn spec() -> &'static JsonValue {
+    static SPEC: OnceLock<JsonValue> = OnceLock::new();
+    SPEC.get_or_init(|| serde_json::from_str(SPEC_JSON).expect("style specification is valid"))
+}
+
+fn spec_group(name: &str) -> Option<&'static JsonMap<String, JsonValue>> {
+    spec().get(name)?.as_object()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single problem of a style.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Path of the offending value, e.g. `layers[3].paint.line-color`
+    pub path: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        if self.path.is_empty() {
+            write!(f, "{severity}: {}", self.message)
+        } else {
+            write!(f, "{severity}: {}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Collects the diagnostics while walking the style.
+#[derive(Default)]
+struct Validator {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator {
+    fn error(&mut self, path: &str, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            path: path.to_string(),
+            severity: Severity::Error,
+            message: message.into(),
+        });
+    }
+
+    fn warning(&mut self, path: &str, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            path: path.to_string(),
+            severity: Severity::Warning,
+            message: message.into(),
+        });
+    }
+
+    fn error_count(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .count()
+    }
+
+    /// Returns the object at `path` or reports that the value is not an object.
+    fn object<'a>(
+        &mut self,
+        path: &str,
+        value: &'a JsonValue,
+    ) -> Option<&'a JsonMap<String, JsonValue>> {
+        let object = value.as_object();
+        if object.is_none() {
+            self.error(path, "expected an object");
+        }
+        object
+    }
+
+    fn validate_root(&mut self, root: &JsonMap<String, JsonValue>) {
+        let root_spec = spec_group("$root").expect("specification has a root");
+
+        for (name, value) in root {
+            if !root_spec.contains_key(name) {
+                self.error(name, "unknown property");
+            } else if !SUPPORTED_ROOT_PROPERTIES.contains(&name.as_str()) {
+                self.warning(name, "property is not supported and ignored");
+            } else if name == "version" && value.as_u64() != Some(8) {
+                self.error(name, "only version 8 is supported");
+            }
+        }
+
+        for required in ["version", "sources", "layers"] {
+            if !root.contains_key(required) {
+                self.error("", format!("missing required property {required}"));
+            }
+        }
+
+        let empty = JsonMap::new();
+        let sources = match root.get("sources") {
+            Some(sources) => self.object("sources", sources).unwrap_or(&empty),
+            None => &empty,
+        };
+        for (id, source) in sources {
+            self.validate_source(&format!("sources.{id}"), source);
+        }
+
+        if let Some(terrain) = root.get("terrain") {
+            self.validate_terrain(terrain, sources);
+        }
+
+        match root.get("layers") {
+            Some(JsonValue::Array(layers)) => {
+                let mut ids = HashSet::new();
+                for (i, layer) in layers.iter().enumerate() {
+                    self.validate_layer(&format!("layers[{i}]"), layer, sources, &mut ids);
+                }
+            }
+            Some(_) => self.error("layers", "expected an array"),
+            None => {}
+        }
+    }
+
+    fn validate_source(&mut self, path: &str, source: &JsonValue) {
+        let Some(object) = self.object(path, source) else {
+            return;
+        };
+        let Some(source_type) = object.get("type").and_then(JsonValue::as_str) else {
+            self.error(path, "missing source type");
+            return;
+        };
+
+        let known_properties: Vec<&str> = if source_type == "canvas" {
+            CANVAS_SOURCE_PROPERTIES.to_vec()
+        } else {
+            match spec_group(&format!("source_{}", source_type.replace('-', "_"))) {
+                Some(properties) => properties.keys().map(String::as_str).collect(),
+                None => {
+                    self.error(
+                        &format!("{path}.type"),
+                        format!("unknown source type {source_type}"),
+                    );
+                    return;
+                }
+            }
+        };
+        for name in object.keys() {
+            if !known_properties.contains(&name.as_str()) {
+                self.error(&format!("{path}.{name}"), "unknown property");
+            }
+        }
+
+        // Unlike vector tiles, there are no default tiles for elevation
+        if source_type == "raster-dem"
+            && !object.contains_key("tiles")
+            && !object.contains_key("url")
+        {
+            self.error(path, "raster-dem sources require the property tiles or url");
+        }
+
+        match serde_json::from_value::<Source>(source.clone()) {
+            Ok(parsed) => {
+                // Properties which are dropped while parsing are not supported
+                let parsed = serde_json::to_value(parsed).unwrap_or_default();
+                for name in object.keys() {
+                    if known_properties.contains(&name.as_str()) && parsed.get(name).is_none() {
+                        self.warning(
+                            &format!("{path}.{name}"),
+                            "property is not supported and ignored",
+                        );
+                    }
+                }
+            }
+            Err(_) if matches!(source_type, "geojson" | "video") => self.error(
+                &format!("{path}.type"),
+                format!("source type {source_type} is not supported"),
+            ),
+            Err(e) => self.error(path, format!("source can not be loaded: {e}")),
+        }
+    }
+
+    fn validate_terrain(&mut self, terrain: &JsonValue, sources: &JsonMap<String, JsonValue>) {
+        let Some(object) = self.object("terrain", terrain) else {
+            return;
+        };
+        let terrain_spec = spec_group("terrain").expect("specification has terrain");
+
+        for (name, value) in object {
+            let path = format!("terrain.{name}");
+            match terrain_spec.get(name) {
+                Some(property) => self.validate_value(&path, property, value),
+                None => self.error(&path, "unknown property"),
+            }
+        }
+
+        match object.get("source").and_then(JsonValue::as_str) {
+            Some(source) => {
+                let source_type = sources
+                    .get(source)
+                    .and_then(|source| source.get("type"))
+                    .and_then(JsonValue::as_str);
+                match source_type {
+                    None => self.error("terrain.source", format!("unknown source {source}")),
+                    Some("raster-dem") => {}
+                    Some(_) => self.error("terrain.source", "source must be a raster-dem source"),
+                }
+            }
+            None => self.error("terrain", "missing required property source"),
+        }
+    }
+
+    fn validate_layer(
+        &mut self,
+        path: &str,
+        layer: &JsonValue,
+        sources: &JsonMap<String, JsonValue>,
+        ids: &mut HashSet<String>,
+    ) {
+        let Some(object) = self.object(path, layer) else {
+            return;
+        };
+        let errors = self.error_count();
+        let layer_spec = spec_group("layer").expect("specification has layers");
+
+        for name in object.keys() {
+            if !layer_spec.contains_key(name) {
+                self.error(&format!("{path}.{name}"), "unknown property");
+            }
+        }
+
+        match object.get("id").and_then(JsonValue::as_str) {
+            Some(id) if !ids.insert(id.to_string()) => {
+                self.error(&format!("{path}.id"), format!("duplicate layer id {id}"))
+            }
+            Some(_) => {}
+            None => self.error(path, "missing required property id"),
+        }
+
+        let layer_type = object.get("type").and_then(JsonValue::as_str);
+        let Some(layer_type) = layer_type.filter(|layer_type| {
+            layer_spec["type"]["values"]
+                .as_object()
+                .is_some_and(|values| values.contains_key(*layer_type))
+        }) else {
+            self.error(&format!("{path}.type"), "missing or unknown layer type");
+            return;
+        };
+        let is_supported = SUPPORTED_LAYER_TYPES.contains(&layer_type);
+        if !is_supported {
+            self.warning(
+                &format!("{path}.type"),
+                format!("layer type {layer_type} is not supported and ignored"),
+            );
+        }
+
+        self.validate_layer_source(path, object, layer_type, sources);
+
+        for name in ["minzoom", "maxzoom"] {
+            if let Some(value) = object.get(name) {
+                self.validate_value(&format!("{path}.{name}"), &layer_spec[name], value);
+                if value.as_f64().is_some_and(|zoom| zoom.fract() != 0.0) {
+                    self.warning(
+                        &format!("{path}.{name}"),
+                        "fractional zoom levels are not supported",
+                    );
+                }
+            }
+        }
+        let zoom = |name| object.get(name).and_then(JsonValue::as_f64);
+        if let (Some(minzoom), Some(maxzoom)) = (zoom("minzoom"), zoom("maxzoom")) {
+            if minzoom > maxzoom {
+                self.error(path, "minzoom is larger than maxzoom");
+            }
+        }
+
+        if let Some(layout) = object.get("layout") {
+            self.validate_properties(
+                &format!("{path}.layout"),
+                &format!("layout_{layer_type}"),
+                layout,
+                |name, _| SUPPORTED_LAYOUT_PROPERTIES.contains(&name),
+            );
+        }
+
+        if let Some(paint) = object.get("paint") {
+            self.validate_properties(
+                &format!("{path}.paint"),
+                &format!("paint_{layer_type}"),
+                paint,
+                |name, value| !is_supported || is_paint_supported(layer_type, name, value),
+            );
+        }
+
+        // Report why the layer can not be loaded, unless the reason has already been reported
+        if is_supported && self.error_count() == errors {
+            if let Err(e) = serde_json::from_value::<StyleLayer>(layer.clone()) {
+                self.error(path, format!("layer can not be loaded: {e}"));
+            }
+        }
+    }
+
+    fn validate_layer_source(
+        &mut self,
+        path: &str,
+        layer: &JsonMap<String, JsonValue>,
+        layer_type: &str,
+        sources: &JsonMap<String, JsonValue>,
+    ) {
+        let source_id = layer.get("source").and_then(JsonValue::as_str);
+        if layer_type == "background" {
+            if source_id.is_some() {
+                self.warning(
+                    &format!("{path}.source"),
+                    "background layers do not use a source",
+                );
+            }
+            return;
+        }
+
+        let Some(source_id) = source_id else {
+            self.error(path, "missing required property source");
+            return;
+        };
+        let Some(source) = sources.get(source_id) else {
+            self.error(
+                &format!("{path}.source"),
+                format!("unknown source {source_id}"),
+            );
+            return;
+        };
+
+        let source_type = source.get("type").and_then(JsonValue::as_str);
+        if source_type == Some("vector") && !layer.contains_key("source-layer") {
+            self.error(
+                path,
+                "layers of vector sources require the property source-layer",
+            );
+        }
+    }
+
+    /// Validates the paint or layout properties of a layer. Properties for which `is_supported`
+    /// returns false are reported as warnings.
+    fn validate_properties<F: Fn(&str, &JsonValue) -> bool>(
+        &mut self,
+        path: &str,
+        group: &str,
+        properties: &JsonValue,
+        is_supported: F,
+    ) {
+        let Some(properties) = self.object(path, properties) else {
+            return;
+        };
+        let properties_spec = spec_group(group);
+
+        for (name, value) in properties {
+            let property_path = format!("{path}.{name}");
+            let Some(property) = properties_spec.and_then(|spec| spec.get(name)) else {
+                self.error(&property_path, "unknown property for this layer type");
+                continue;
+            };
+
+            let errors = self.error_count();
+            self.validate_value(&property_path, property, value);
+            if self.error_count() == errors && !is_supported(name, value) {
+                self.warning(&property_path, "property is not supported and ignored");
+            }
+        }
+    }
+
+    /// Checks that `value` has the type of the `property` in the specification.
+    fn validate_value(&mut self, path: &str, property: &JsonValue, value: &JsonValue) {
+        let property_type = property["type"].as_str().unwrap_or_default();
+
+        let is_expression = match value {
+            JsonValue::Array(array) => {
+                property_type != "array" || array.first().is_some_and(JsonValue::is_string)
+            }
+            _ => false,
+        };
+        if is_expression && property.get("expression").is_some() {
+            if let Err(e) = Expression::try_from(value.clone()) {
+                match e {
+                    ExpressionError::Unsupported(_) => self.warning(path, e.to_string()),
+                    ExpressionError::InvalidArguments(_) => self.error(path, e.to_string()),
+                }
+            }
+            return;
+        }
+        if value.is_object() && property.get("expression").is_some() {
+            self.warning(path, "functions are not supported, use expressions instead");
+            return;
+        }
+
+        let is_valid = match property_type {
+            "color" => value
+                .as_str()
+                .is_some_and(|color| color.parse::<Color>().is_ok()),
+            "number" => {
+                let Some(number) = value.as_f64() else {
+                    self.error(path, "expected a number");
+                    return;
+                };
+                self.validate_range(path, property, number);
+                true
+            }
+            "boolean" => value.is_boolean(),
+            "string" | "resolvedImage" | "formatted" => value.is_string(),
+            "enum" => value
+                .as_str()
+                .is_some_and(|value| match &property["values"] {
+                    JsonValue::Object(values) => values.contains_key(value),
+                    JsonValue::Array(values) => values.iter().any(|allowed| allowed == value),
+                    _ => false,
+                }),
+            "array" => {
+                let Some(array) = value.as_array() else {
+                    self.error(path, "expected an array");
+                    return;
+                };
+                let length = property["length"].as_u64();
+                if length.is_some_and(|length| length != array.len() as u64) {
+                    self.error(
+                        path,
+                        format!("expected an array of length {}", length.unwrap_or_default()),
+                    );
+                }
+                match property["value"].as_str() {
+                    Some("number") => {
+                        for number in array {
+                            match number.as_f64() {
+                                Some(number) => self.validate_range(path, property, number),
+                                None => self.error(path, "expected an array of numbers"),
+                            }
+                        }
+                    }
+                    Some("string") if !array.iter().all(JsonValue::is_string) => {
+                        self.error(path, "expected an array of strings")
+                    }
+                    _ => {}
+                }
+                true
+            }
+            // Types which are not validated further
+            _ => true,
+        };
+
+        if !is_valid {
+            self.error(path, format!("expected a value of type {property_type}"));
+        }
+    }
+
+    fn validate_range(&mut self, path: &str, property: &JsonValue, number: f64) {
+        if let Some(minimum) = property["minimum"].as_f64() {
+            if number < minimum {
+                self.error(
+                    path,
+                    format!("{number} is smaller than the minimum {minimum}"),
+                );
+            }
+        }
+        if let Some(maximum) = property["maximum"].as_f64() {
+            if number > maximum {
+                self.error(
+                    path,
+                    format!("{number} is larger than the maximum {maximum}"),
+                );
+            }
+        }
+    }
+}
+
+/// Returns whether a paint property is read by the renderer. Properties which are unknown to the
+/// paint of the layer type are dropped while parsing.
+fn is_paint_supported(layer_type: &str, name: &str, value: &JsonValue) -> bool {
+    let mut properties = JsonMap::new();
+    properties.insert(name.to_string(), value.clone());
+    let paint = serde_json::json!({"type": layer_type, "paint": properties});
+
+    serde_json::from_value::<crate::style::layer::LayerPaint>(paint)
+        .ok()
+        .and_then(|parsed| serde_json::to_value(parsed).ok())
+        .is_some_and(|parsed| parsed["paint"].get(name).is_some())
+}
+
+/// Validates a style in its JSON representation.
+pub fn validate_style(style: &JsonValue) -> Vec<Diagnostic> {
+    let mut validator = Validator::default();
+
+    if let Some(root) = validator.object("", style) {
+        validator.validate_root(root);
+
+        // Report why the style can not be loaded, unless the reason has already been reported
+        if validator.error_count() == 0 {
+            if let Err(e) = serde_json::from_value::<Style>(style.clone()) {
+                validator.error("", format!("style can not be loaded: {e}"));
+            }
+        }
+    }
+
+    validator.diagnostics
+}
+
+/// Parses and validates a style.
+pub fn validate_style_str(style: &str) -> Vec<Diagnostic> {
+    match serde_json::from_str::<JsonValue>(style) {
+        Ok(style) => validate_style(&style),
+        Err(e) => vec![Diagnostic {
+            path: String::new(),
+            severity: Severity::Error,
+            message: format!("invalid JSON: {e}"),
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(style: &str) -> Vec<String> {
+        validate_style_str(style)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_style() {
+        // language=JSON
+        let style = r##"
+        {
+          "version": 8,
+          "name": "Valid",
+          "metadata": {},
+          "sources": {
+            "openmaptiles": {"type": "vector", "tiles": "https://example.com/{z}/{x}/{y}.pbf"}
+          },
+          "layers": [
+            {"id": "background", "type": "background", "paint": {"background-color": "#ffffff"}},
+            {"id": "water", "type": "fill", "source": "openmaptiles", "source-layer": "water",
+              "minzoom": 2, "paint": {"fill-color": "#aad3df"}, "layout": {"visibility": "visible"}}
+          ]
+        }
+        "##;
+
+        assert_eq!(messages(style), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_reports_every_problem() {
+        // language=JSON
+        let style = r##"
+        {
+          "version": 8,
+          "name": "Broken",
+          "metadata": {},
+          "light": {"anchor": "viewport"},
+          "sources": {
+            "elevation": {"type": "raster-dem", "encoding": "terrarium"},
+            "openmaptiles": {"type": "vector", "url": "https://example.com/tiles.json",
+              "promoteId": "id"},
+            "points": {"type": "geojson", "data": {}}
+          },
+          "layers": [
+            {"id": "building", "type": "fill", "source": "openmaptiles",
+              "paint": {"line-color": "#3D3D3D", "fill-opacity": 2}},
+            {"id": "roads", "type": "line", "source": "unknown", "source-layer": "transportation",
+              "minzoom": 30, "paint": {"line-color": 5}},
+            {"id": "labels", "type": "symbol", "source": "openmaptiles", "source-layer": "place",
+              "filter": ["==", "class", "city"]}
+          ]
+        }
+        "##;
+
+        assert_eq!(
+            messages(style),
+            vec![
+                "warning: light: property is not supported and ignored",
+                "error: sources.elevation: raster-dem sources require the property tiles or url",
+                "warning: sources.openmaptiles.promoteId: property is not supported and ignored",
+                "error: sources.points.type: source type geojson is not supported",
+                "error: layers[0]: layers of vector sources require the property source-layer",
+                "error: layers[0].paint.fill-opacity: 2 is larger than the maximum 1",
+                "error: layers[0].paint.line-color: unknown property for this layer type",
+                "error: layers[1].source: unknown source unknown",
+                "error: layers[1].minzoom: 30 is larger than the maximum 24",
+                "error: layers[1].paint.line-color: expected a value of type color",
+                "warning: layers[2].type: layer type symbol is not supported and ignored",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let diagnostics = validate_style_str("{");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+}
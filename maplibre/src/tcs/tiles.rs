@@ -69,6 +69,13 @@ impl Tiles {
         }
     }
 
+    /// Removes the components of type `T` from all tiles.
+    pub fn remove_components<T: TileComponent>(&mut self) {
+        for components in self.components.values_mut() {
+            components.retain_mut(|component| !component.get_mut().is::<T>());
+        }
+    }
+
     pub fn clear(&mut self) {
         self.tiles.clear();
         self.components.clear();
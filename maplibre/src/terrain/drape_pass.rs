@@ -0,0 +1,96 @@
+//! Renders the layers of each tile in view into its drape texture.
+
+use wgpu::StoreOp;
+
+use crate::{
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        graph::{Node, NodeRunError, RenderContext, RenderGraphContext, SlotInfo},
+        resource::TrackedRenderPass,
+        RenderResources,
+    },
+    tcs::world::World,
+    terrain::{resource::TerrainResources, TerrainDrapePhase},
+};
+
+pub struct DrapePassNode {}
+
+impl DrapePassNode {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Node for DrapePassNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![]
+    }
+
+    fn update(&mut self, _state: &mut RenderResources) {}
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        _state: &RenderResources,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some((Initialized(terrain_resources), drape_phase)) = world
+            .resources
+            .query::<(&Eventually<TerrainResources>, &TerrainDrapePhase)>()
+        else {
+            return Ok(());
+        };
+
+        if !drape_phase.is_active() {
+            return Ok(());
+        }
+
+        for tile in drape_phase.iter() {
+            let Some((view, resolve_target)) = terrain_resources.slot_color_attachment(tile.slot)
+            else {
+                continue;
+            };
+
+            let render_pass =
+                render_context
+                    .command_encoder
+                    .begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("terrain_drape_pass"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                                store: StoreOp::Store,
+                            },
+                            resolve_target,
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                            view: terrain_resources.depth_texture(),
+                            depth_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(0.0),
+                                store: StoreOp::Store,
+                            }),
+                            stencil_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(0),
+                                store: StoreOp::Store,
+                            }),
+                        }),
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+
+            let mut tracked_pass = TrackedRenderPass::new(render_pass);
+
+            for item in &tile.masks {
+                item.draw_function.draw(&mut tracked_pass, world, item);
+            }
+
+            for item in &tile.layers {
+                item.draw_function.draw(&mut tracked_pass, world, item);
+            }
+        }
+
+        Ok(())
+    }
+}
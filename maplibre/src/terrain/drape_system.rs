@@ -0,0 +1,61 @@
+//! Moves the phase items of the main pass into the [`TerrainDrapePhase`].
+
+use crate::{
+    context::MapContext,
+    render::{
+        render_phase::{DrawState, LayerItem, RenderPhase, TileMaskItem},
+        tile_view_pattern::TileShape,
+    },
+    tcs::tiles::Tile,
+    terrain::{render_commands::DrawTerrainTiles, DrapedTile, TerrainDrapePhase},
+};
+
+pub fn drape_system(
+    MapContext {
+        world, view_state, ..
+    }: &mut MapContext,
+) {
+    let Some((drape_phase, layer_item_phase, tile_mask_phase)) = world.resources.query_mut::<(
+        &mut TerrainDrapePhase,
+        &mut RenderPhase<LayerItem>,
+        &mut RenderPhase<TileMaskItem>,
+    )>() else {
+        return;
+    };
+
+    if !drape_phase.is_active() {
+        return;
+    }
+
+    for item in tile_mask_phase.drain() {
+        if let Some(index) = find_tile(&drape_phase.tiles, &item.source_shape) {
+            drape_phase.tiles[index].masks.push(item);
+        }
+    }
+
+    // The layers are already sorted, so their order is kept within each tile
+    for item in layer_item_phase.drain() {
+        if let Some(index) = find_tile(&drape_phase.tiles, &item.source_shape) {
+            drape_phase.tiles[index].layers.push(item);
+        }
+    }
+
+    let zoom = view_state.zoom();
+    for tile in &drape_phase.tiles {
+        layer_item_phase.add(LayerItem {
+            draw_function: Box::new(DrawState::<LayerItem, DrawTerrainTiles>::new()),
+            index: 0,
+            style_layer: "terrain".to_string(),
+            tile: Tile {
+                coords: tile.coords,
+            },
+            source_shape: TileShape::new(tile.coords, zoom),
+        });
+    }
+}
+
+/// Each source shape belongs to exactly one tile in view, which is identified by its buffer range.
+fn find_tile(tiles: &[DrapedTile], shape: &TileShape) -> Option<usize> {
+    let start = shape.buffer_range()?.start;
+    tiles.iter().position(|tile| tile.shapes.contains(&start))
+}
@@ -0,0 +1,279 @@
+//! Elevation of the ground which is used to make the camera aware of the terrain.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    coords::{WorldCoords, WorldTileCoords, Zoom, ZoomLevel, TILE_SIZE},
+    raster::dem::DemData,
+};
+
+/// Decoded elevation tiles of the raster-dem source which is used as terrain.
+///
+/// Elevations returned by this type already include the exaggeration of the terrain.
+pub struct Elevation {
+    source: String,
+    exaggeration: f64,
+    tiles: HashMap<WorldTileCoords, DemData>,
+    max_zoom_level: ZoomLevel,
+    /// Lowest and highest elevation in meters of all tiles, without exaggeration
+    range: Option<(f32, f32)>,
+}
+
+impl Elevation {
+    pub fn new(source: &str, exaggeration: f64) -> Self {
+        Self {
+            source: source.to_string(),
+            exaggeration,
+            tiles: Default::default(),
+            max_zoom_level: ZoomLevel::default(),
+            range: None,
+        }
+    }
+
+    /// Id of the raster-dem source
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn exaggeration(&self) -> f64 {
+        self.exaggeration
+    }
+
+    pub fn set_exaggeration(&mut self, exaggeration: f64) {
+        self.exaggeration = exaggeration;
+    }
+
+    pub fn contains(&self, coords: WorldTileCoords) -> bool {
+        self.tiles.contains_key(&coords.wrap())
+    }
+
+    /// Inserts the elevation of a tile. The borders of the tile and its already available
+    /// neighbours are backfilled from each other, such that sampling is continuous across tiles.
+    ///
+    /// Returns the coordinates of all tiles whose data changed.
+    pub fn insert(&mut self, coords: WorldTileCoords, mut dem: DemData) -> Vec<WorldTileCoords> {
+        let mut changed = vec![coords];
+
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+
+                let neighbour_coords = WorldTileCoords {
+                    x: coords.x + dx,
+                    y: coords.y + dy,
+                    z: coords.z,
+                }
+                .wrap();
+
+                if neighbour_coords == coords {
+                    continue;
+                }
+
+                if let Some(neighbour) = self.tiles.get_mut(&neighbour_coords) {
+                    dem.backfill_border(neighbour, dx, dy);
+                    neighbour.backfill_border(&dem, -dx, -dy);
+                    if !changed.contains(&neighbour_coords) {
+                        changed.push(neighbour_coords);
+                    }
+                }
+            }
+        }
+
+        self.extend_bounds(coords, &dem);
+        self.tiles.insert(coords, dem);
+        changed
+    }
+
+    /// Removes the tiles which are not contained in `tiles`, e.g. because they are no longer in
+    /// view.
+    pub fn retain(&mut self, tiles: &HashSet<WorldTileCoords>) {
+        let len = self.tiles.len();
+        self.tiles.retain(|coords, _| tiles.contains(coords));
+        if self.tiles.len() == len {
+            return;
+        }
+
+        // The removed tiles might have contained the lowest or highest elevation
+        let remaining = std::mem::take(&mut self.tiles);
+        self.range = None;
+        self.max_zoom_level = ZoomLevel::default();
+        for (coords, dem) in &remaining {
+            self.extend_bounds(*coords, dem);
+        }
+        self.tiles = remaining;
+    }
+
+    /// Extends the elevation range and the maximum zoom level by a tile.
+    fn extend_bounds(&mut self, coords: WorldTileCoords, dem: &DemData) {
+        let dim = dem.dim() as i32;
+        for y in 0..dim {
+            for x in 0..dim {
+                let elevation = dem.get(x, y);
+                self.range = Some(match self.range {
+                    Some((min, max)) => (min.min(elevation), max.max(elevation)),
+                    None => (elevation, elevation),
+                });
+            }
+        }
+
+        self.max_zoom_level = self.max_zoom_level.max(coords.z);
+    }
+
+    pub fn get(&self, coords: WorldTileCoords) -> Option<&DemData> {
+        self.tiles.get(&coords.wrap())
+    }
+
+    /// Iterates over all decoded tiles.
+    pub fn iter(&self) -> impl Iterator<Item = (WorldTileCoords, &DemData)> + '_ {
+        self.tiles.iter().map(|(coords, dem)| (*coords, dem))
+    }
+
+    /// Finds the tile which covers `coords`. This is either the tile itself or the closest
+    /// ancestor which is available. The returned coordinates are wrapped into the bounds of their
+    /// zoom level.
+    pub fn find_tile(&self, coords: WorldTileCoords) -> Option<(WorldTileCoords, &DemData)> {
+        let mut current = coords.wrap();
+        loop {
+            if let Some(dem) = self.tiles.get(&current) {
+                return Some((current, dem));
+            }
+            current = current.get_parent()?;
+        }
+    }
+
+    /// Returns the exaggerated elevation in meters at the specified `world` coordinates.
+    pub fn elevation_at(&self, world: WorldCoords, zoom: Zoom) -> Option<f64> {
+        // Copies of the world share the elevation data
+        let world = WorldCoords::at_ground(world.x.rem_euclid(zoom.world_size()), world.y);
+        let (coords, dem) = self.find_tile(world.into_world_tile(self.max_zoom_level, zoom))?;
+
+        let tile_scale = zoom.scale_to_zoom_level(coords.z) / TILE_SIZE;
+        let dim = dem.dim() as f64;
+        // Sample at pixel centers, the border of the tile covers the half pixel at the edges
+        let x = (world.x * tile_scale - coords.x as f64) * dim - 0.5;
+        let y = (world.y * tile_scale - coords.y as f64) * dim - 0.5;
+
+        let x0 = x.floor().clamp(-1.0, dim - 1.0);
+        let y0 = y.floor().clamp(-1.0, dim - 1.0);
+        let fx = (x - x0).clamp(0.0, 1.0);
+        let fy = (y - y0).clamp(0.0, 1.0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let sample = |x: i32, y: i32| dem.get(x, y) as f64;
+        let top = sample(x0, y0) * (1.0 - fx) + sample(x0 + 1, y0) * fx;
+        let bottom = sample(x0, y0 + 1) * (1.0 - fx) + sample(x0 + 1, y0 + 1) * fx;
+
+        Some((top * (1.0 - fy) + bottom * fy) * self.exaggeration)
+    }
+
+    /// Returns the exaggerated elevation in world units at the specified `world` coordinates.
+    pub fn world_elevation_at(&self, world: WorldCoords, zoom: Zoom) -> Option<f64> {
+        self.elevation_at(world, zoom)
+            .map(|meters| Self::meters_to_world(meters, world.y, zoom))
+    }
+
+    /// Returns the lowest and highest exaggerated elevation in meters of all available tiles.
+    pub fn elevation_range(&self) -> Option<(f64, f64)> {
+        self.range.map(|(min, max)| {
+            (
+                min as f64 * self.exaggeration,
+                max as f64 * self.exaggeration,
+            )
+        })
+    }
+
+    /// Converts meters at the latitude of `world_y` into world units.
+    pub fn meters_to_world(meters: f64, world_y: f64, zoom: Zoom) -> f64 {
+        WorldCoords::at_ground(0.0, world_y)
+            .into_lat_lon(zoom)
+            .mercator_z_from_altitude(meters)
+            * zoom.world_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use std::collections::HashSet;
+
+    use super::Elevation;
+    use crate::{
+        coords::{WorldCoords, WorldTileCoords, Zoom},
+        raster::dem::DemData,
+        style::source::DemEncoding,
+    };
+
+    fn terrarium_tile(dim: u32, elevation: impl Fn(u32, u32) -> f32) -> DemData {
+        let image = RgbaImage::from_fn(dim, dim, |x, y| {
+            let value = (elevation(x, y) + 32768.0) * 256.0;
+            let value = value.round() as u32;
+            image::Rgba([(value >> 16) as u8, (value >> 8) as u8, value as u8, 255])
+        });
+        DemData::from_image(&image, DemEncoding::Terrarium).unwrap()
+    }
+
+    #[test]
+    fn test_elevation_at() {
+        let mut elevation = Elevation::new("terrain", 2.0);
+        elevation.insert(
+            WorldTileCoords::from((0, 0, 0.into())),
+            terrarium_tile(4, |x, _| x as f32 * 100.0),
+        );
+
+        let zoom = Zoom::new(0.0);
+        // Center of the second pixel
+        let at = |x: f64, y: f64| elevation.elevation_at(WorldCoords::at_ground(x, y), zoom);
+        assert_eq!(at(192.0, 64.0), Some(200.0));
+        // Between the second and the third pixel
+        assert_eq!(at(256.0, 300.0), Some(300.0));
+        // Clamped at the edge of the world
+        assert_eq!(at(0.0, 0.0), Some(0.0));
+        assert_eq!(elevation.elevation_range(), Some((0.0, 600.0)));
+
+        // Tiles which are not available fall back to their parent
+        elevation.insert(
+            WorldTileCoords::from((1, 1, 1.into())),
+            terrarium_tile(4, |_, _| 50.0),
+        );
+        let zoom = Zoom::new(1.0);
+        assert_eq!(
+            elevation.elevation_at(WorldCoords::at_ground(384.0, 128.0), zoom),
+            Some(200.0)
+        );
+        assert_eq!(
+            elevation.elevation_at(WorldCoords::at_ground(768.0, 768.0), zoom),
+            Some(100.0)
+        );
+    }
+
+    #[test]
+    fn test_backfill_neighbours() {
+        let mut elevation = Elevation::new("terrain", 1.0);
+        let left = WorldTileCoords::from((0, 0, 1.into()));
+        let right = WorldTileCoords::from((1, 0, 1.into()));
+        elevation.insert(left, terrarium_tile(2, |_, _| 10.0));
+        let changed = elevation.insert(right, terrarium_tile(2, |_, _| 30.0));
+        assert_eq!(changed, vec![right, left]);
+
+        // Sampling at the edge between both tiles interpolates across the border
+        let zoom = Zoom::new(1.0);
+        assert_eq!(
+            elevation
+                .elevation_at(WorldCoords::at_ground(512.0 - 0.001, 256.0), zoom)
+                .map(f64::round),
+            Some(20.0)
+        );
+        assert_eq!(
+            elevation.elevation_at(WorldCoords::at_ground(512.0, 256.0), zoom),
+            Some(20.0)
+        );
+
+        // The range only covers the remaining tiles
+        elevation.retain(&HashSet::from([left]));
+        assert!(!elevation.contains(right));
+        assert_eq!(elevation.elevation_range(), Some((10.0, 10.0)));
+    }
+}
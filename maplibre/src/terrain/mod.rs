@@ -0,0 +1,113 @@
+//! Renders the map in 3D by displacing the ground with the elevation of a raster-dem source.
+//!
+//! The layers of each tile in view are rendered into an offscreen texture first. Afterwards, a grid
+//! is displaced by the elevation of the tile and textured with the rendered layers. The elevation
+//! is also made available to the [`ViewState`](crate::render::view_state::ViewState), such that
+//! the camera and picking are aware of the terrain.
+
+use std::rc::Rc;
+
+pub use elevation::Elevation;
+
+use crate::{
+    coords::WorldTileCoords,
+    environment::Environment,
+    kernel::Kernel,
+    plugin::Plugin,
+    render::{
+        eventually::{Eventually, GpuResources},
+        graph::RenderGraph,
+        main_graph,
+        render_phase::{LayerItem, TileMaskItem},
+        RenderStageLabel,
+    },
+    schedule::Schedule,
+    tcs::world::World,
+    terrain::{
+        drape_pass::DrapePassNode, drape_system::drape_system, resource::TerrainResources,
+        resource_system::resource_system, upload_system::upload_system,
+    },
+};
+
+mod drape_pass;
+mod drape_system;
+mod elevation;
+mod render_commands;
+mod resource;
+mod resource_system;
+mod upload_system;
+
+/// A tile in view whose layers are rendered into a drape texture.
+pub struct DrapedTile {
+    /// The target tile which is covered by the drape texture
+    pub coords: WorldTileCoords,
+    /// The tile which provides the elevation, either the tile itself or an ancestor
+    pub dem: Option<WorldTileCoords>,
+    slot: usize,
+    /// Start of the buffer ranges of the source shapes which are rendered for this tile
+    shapes: Vec<wgpu::BufferAddress>,
+    masks: Vec<TileMaskItem>,
+    layers: Vec<LayerItem>,
+}
+
+/// Phase items which are rendered into the drape textures, grouped by the tiles in view.
+///
+/// Terrain is active as long as this phase is active. The items are taken from the
+/// [`RenderPhases`](crate::render::render_phase::RenderPhase) of the main pass.
+#[derive(Default)]
+pub struct TerrainDrapePhase {
+    active: bool,
+    tiles: Vec<DrapedTile>,
+}
+
+impl TerrainDrapePhase {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn get(&self, coords: WorldTileCoords) -> Option<&DrapedTile> {
+        self.tiles.iter().find(|tile| tile.coords == coords)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DrapedTile> + '_ {
+        self.tiles.iter()
+    }
+}
+
+/// Renders the terrain which is defined in the style. Requires the
+/// [`RasterPlugin`](crate::raster::RasterPlugin) for fetching the elevation.
+#[derive(Default)]
+pub struct TerrainPlugin;
+
+impl<E: Environment> Plugin<E> for TerrainPlugin {
+    fn build(
+        &self,
+        schedule: &mut Schedule,
+        _kernel: Rc<Kernel<E>>,
+        world: &mut World,
+        graph: &mut RenderGraph,
+    ) {
+        world
+            .resources
+            .insert(Eventually::<TerrainResources>::Uninitialized);
+        world.resources.init::<TerrainDrapePhase>();
+        world
+            .resources
+            .get_or_init_mut::<GpuResources>()
+            .add::<TerrainResources>();
+
+        graph.add_node(main_graph::node::TERRAIN_DRAPE_PASS, DrapePassNode::new());
+        graph
+            .add_node_edge(
+                main_graph::node::TERRAIN_DRAPE_PASS,
+                main_graph::node::MAIN_PASS_DEPENDENCIES,
+            )
+            .expect("main pass dependencies do not exist");
+
+        schedule.add_system_to_stage(RenderStageLabel::Prepare, resource_system);
+        // Must run after the tile view pattern has been uploaded
+        schedule.add_system_to_stage(RenderStageLabel::Queue, upload_system);
+        // Must run after the phases have been sorted
+        schedule.add_system_to_stage(RenderStageLabel::PhaseSort, drape_system);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        render_phase::{LayerItem, PhaseItem, RenderCommand, RenderCommandResult},
+        resource::TrackedRenderPass,
+        INDEX_FORMAT,
+    },
+    tcs::world::World,
+    terrain::{resource::TerrainResources, TerrainDrapePhase},
+};
+
+pub struct SetTerrainPipeline;
+impl<P: PhaseItem> RenderCommand<P> for SetTerrainPipeline {
+    fn render<'w>(
+        world: &'w World,
+        _item: &P,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(Initialized(terrain_resources)) =
+            world.resources.get::<Eventually<TerrainResources>>()
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_render_pipeline(terrain_resources.pipeline());
+        RenderCommandResult::Success
+    }
+}
+
+pub struct DrawTerrainTile;
+impl RenderCommand<LayerItem> for DrawTerrainTile {
+    fn render<'w>(
+        world: &'w World,
+        item: &LayerItem,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some((Initialized(terrain_resources), drape_phase)) = world
+            .resources
+            .query::<(&Eventually<TerrainResources>, &TerrainDrapePhase)>()
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        let Some(tile) = drape_phase.get(item.tile.coords) else {
+            return RenderCommandResult::Failure;
+        };
+
+        let Some(slot_bind_group) = terrain_resources.slot_bind_group(tile.slot) else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(0, slot_bind_group, &[]);
+        pass.set_bind_group(1, terrain_resources.dem_bind_group(tile.dem), &[]);
+        pass.set_vertex_buffer(0, terrain_resources.vertices().slice(..));
+        pass.set_index_buffer(terrain_resources.indices().slice(..), INDEX_FORMAT);
+        pass.draw_indexed(0..terrain_resources.index_count(), 0, 0..1);
+
+        RenderCommandResult::Success
+    }
+}
+
+pub type DrawTerrainTiles = (SetTerrainPipeline, DrawTerrainTile);
@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    coords::WorldTileCoords,
+    raster::dem::DemData,
+    render::{resource::Texture, settings::Msaa, shaders::ShaderTerrainTile},
+};
+
+/// Amount of quads along each side of the grid which is displaced by the elevation.
+pub const TERRAIN_GRID_SIZE: u32 = 64;
+/// Size of the textures into which the layers of a tile are rendered.
+pub const DRAPE_TEXTURE_SIZE: u32 = 512;
+
+/// Elevation of a tile which has been uploaded to the GPU.
+struct DemTexture {
+    texture: Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Offscreen texture into which the layers of a single tile are rendered, together with the
+/// uniform which places the tile in the world.
+struct DrapeSlot {
+    texture: Texture,
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Holds the resources necessary for rendering terrain:
+/// * pipeline
+/// * grid mesh which is shared by all tiles
+/// * elevation textures per tile
+/// * drape textures and uniforms per tile in view
+pub struct TerrainResources {
+    pipeline: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+    format: wgpu::TextureFormat,
+
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    index_count: u32,
+
+    /// Elevation which is used if no elevation is available for a tile
+    flat_dem: DemTexture,
+    dem_textures: HashMap<WorldTileCoords, DemTexture>,
+
+    slots: Vec<DrapeSlot>,
+    depth_texture: Texture,
+    multisampling_texture: Option<Texture>,
+}
+
+impl TerrainResources {
+    /// `format`, `depth_format` and `msaa` must match the pipelines of the layers which are
+    /// rendered into the drape textures.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pipeline: wgpu::RenderPipeline,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        msaa: Msaa,
+    ) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let (vertices, indices) = Self::create_grid(TERRAIN_GRID_SIZE);
+
+        let vertices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("terrain vertex buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_count = indices.len() as u32;
+        let indices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("terrain index buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let flat = DemData::flat(1);
+        let flat_dem = Self::create_dem_texture(device, &pipeline, flat.stride());
+        Self::write_dem(queue, &flat_dem, &flat);
+
+        let depth_texture = Texture::new(
+            Some("terrain drape depth texture"),
+            device,
+            depth_format,
+            DRAPE_TEXTURE_SIZE,
+            DRAPE_TEXTURE_SIZE,
+            msaa,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        );
+
+        let multisampling_texture = msaa.is_multisampling().then(|| {
+            Texture::new(
+                Some("terrain drape multisampling texture"),
+                device,
+                format,
+                DRAPE_TEXTURE_SIZE,
+                DRAPE_TEXTURE_SIZE,
+                msaa,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+        });
+
+        Self {
+            pipeline,
+            sampler,
+            format,
+            vertices,
+            indices,
+            index_count,
+            flat_dem,
+            dem_textures: Default::default(),
+            slots: Vec::new(),
+            depth_texture,
+            multisampling_texture,
+        }
+    }
+
+    /// Creates a grid over the unit square. The vertices are the texture coordinates of the tile.
+    fn create_grid(size: u32) -> (Vec<[f32; 2]>, Vec<u32>) {
+        let mut vertices = Vec::with_capacity(((size + 1) * (size + 1)) as usize);
+        for y in 0..=size {
+            for x in 0..=size {
+                vertices.push([x as f32 / size as f32, y as f32 / size as f32]);
+            }
+        }
+
+        let mut indices = Vec::with_capacity((size * size * 6) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let top_left = y * (size + 1) + x;
+                let bottom_left = top_left + size + 1;
+                indices.extend_from_slice(&[
+                    top_left,
+                    bottom_left,
+                    top_left + 1,
+                    top_left + 1,
+                    bottom_left,
+                    bottom_left + 1,
+                ]);
+            }
+        }
+
+        (vertices, indices)
+    }
+
+    pub fn pipeline(&self) -> &wgpu::RenderPipeline {
+        &self.pipeline
+    }
+
+    pub fn vertices(&self) -> &wgpu::Buffer {
+        &self.vertices
+    }
+
+    pub fn indices(&self) -> &wgpu::Buffer {
+        &self.indices
+    }
+
+    pub fn index_count(&self) -> u32 {
+        self.index_count
+    }
+
+    fn create_dem_texture(
+        device: &wgpu::Device,
+        pipeline: &wgpu::RenderPipeline,
+        stride: u32,
+    ) -> DemTexture {
+        let texture = Texture::new(
+            Some("terrain dem texture"),
+            device,
+            wgpu::TextureFormat::Rgba8Unorm,
+            stride,
+            stride,
+            Msaa { samples: 1 },
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &pipeline.get_bind_group_layout(1),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            }],
+            label: None,
+        });
+
+        DemTexture {
+            texture,
+            bind_group,
+        }
+    }
+
+    fn write_dem(queue: &wgpu::Queue, dem_texture: &DemTexture, dem: &DemData) {
+        let image = dem.to_image();
+        let stride = dem.stride();
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &dem_texture.texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &image,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * stride),
+                rows_per_image: Some(stride),
+            },
+            dem_texture.texture.size,
+        );
+    }
+
+    /// Creates or updates the elevation texture of a tile.
+    pub fn write_dem_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        coords: WorldTileCoords,
+        dem: &DemData,
+    ) {
+        let stride = dem.stride();
+        let is_compatible = self
+            .dem_textures
+            .get(&coords)
+            .is_some_and(|dem_texture| dem_texture.texture.size.width == stride);
+
+        if !is_compatible {
+            self.dem_textures.insert(
+                coords,
+                Self::create_dem_texture(device, &self.pipeline, stride),
+            );
+        }
+
+        Self::write_dem(queue, &self.dem_textures[&coords], dem);
+    }
+
+    /// Removes the elevation textures of the tiles for which `f` returns `false`.
+    pub fn retain_dem_textures<F: FnMut(WorldTileCoords) -> bool>(&mut self, mut f: F) {
+        self.dem_textures.retain(|coords, _| f(*coords));
+    }
+
+    /// Removes all elevation textures, e.g. because the source of the terrain changed.
+    pub fn clear_dem_textures(&mut self) {
+        self.dem_textures.clear();
+    }
+
+    /// Returns the bind group of the elevation of a tile. Falls back to a flat elevation if
+    /// `coords` is `None` or the texture is not available.
+    pub fn dem_bind_group(&self, coords: Option<WorldTileCoords>) -> &wgpu::BindGroup {
+        &coords
+            .and_then(|coords| self.dem_textures.get(&coords))
+            .unwrap_or(&self.flat_dem)
+            .bind_group
+    }
+
+    /// Makes sure that at least `count` drape textures are available.
+    pub fn ensure_slots(&mut self, device: &wgpu::Device, count: usize) {
+        while self.slots.len() < count {
+            let texture = Texture::new(
+                Some("terrain drape texture"),
+                device,
+                self.format,
+                DRAPE_TEXTURE_SIZE,
+                DRAPE_TEXTURE_SIZE,
+                Msaa { samples: 1 },
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            );
+
+            let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("terrain tile uniform buffer"),
+                size: std::mem::size_of::<ShaderTerrainTile>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+                label: None,
+            });
+
+            self.slots.push(DrapeSlot {
+                texture,
+                uniform,
+                bind_group,
+            });
+        }
+    }
+
+    pub fn write_slot_uniform(&self, queue: &wgpu::Queue, slot: usize, tile: ShaderTerrainTile) {
+        queue.write_buffer(&self.slots[slot].uniform, 0, bytemuck::bytes_of(&tile));
+    }
+
+    pub fn slot_bind_group(&self, slot: usize) -> Option<&wgpu::BindGroup> {
+        self.slots.get(slot).map(|slot| &slot.bind_group)
+    }
+
+    /// Returns the color attachment of a drape texture. If multisampling is enabled, then the
+    /// multisampled texture is resolved into the drape texture.
+    pub fn slot_color_attachment(
+        &self,
+        slot: usize,
+    ) -> Option<(&wgpu::TextureView, Option<&wgpu::TextureView>)> {
+        let slot = self.slots.get(slot)?;
+        Some(match &self.multisampling_texture {
+            Some(multisampling_texture) => (&multisampling_texture.view, Some(&slot.texture.view)),
+            None => (&slot.texture.view, None),
+        })
+    }
+
+    /// Depth and stencil texture which is shared by all drape textures.
+    pub fn depth_texture(&self) -> &wgpu::TextureView {
+        &self.depth_texture.view
+    }
+}
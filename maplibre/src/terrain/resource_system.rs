@@ -0,0 +1,137 @@
+//! Prepares GPU-owned resources by initializing them if they are uninitialized or out-of-date.
+use crate::{
+    context::MapContext,
+    render::{
+        eventually::Eventually,
+        resource::{RenderPipeline, TilePipeline},
+        settings::Msaa,
+        shaders,
+        shaders::Shader,
+        RenderResources, Renderer,
+    },
+    terrain::resource::TerrainResources,
+};
+
+pub fn resource_system(
+    MapContext {
+        world,
+        renderer:
+            Renderer {
+                device,
+                queue,
+                resources: RenderResources { surface, .. },
+                settings,
+                ..
+            },
+        view_state,
+        ..
+    }: &mut MapContext,
+) {
+    let Some(terrain_resources) = world
+        .resources
+        .query_mut::<&mut Eventually<TerrainResources>>()
+    else {
+        return;
+    };
+
+    let is_uninitialized = matches!(terrain_resources, Eventually::Uninitialized);
+
+    terrain_resources.initialize(|| {
+        let shader = shaders::TerrainShader {
+            format: surface.surface_format(),
+        };
+
+        let multisampling = surface.is_multisampling_supported(settings.msaa);
+
+        let mut descriptor = TilePipeline::new(
+            "terrain_pipeline".into(),
+            *settings,
+            shader.describe_vertex(),
+            shader.describe_fragment(),
+            true,
+            false,
+            false,
+            false,
+            multisampling,
+            false,
+        )
+        .describe_render_pipeline();
+
+        // Tiles do not overlap, so there is no need for masking them
+        if let Some(depth_stencil) = &mut descriptor.depth_stencil {
+            depth_stencil.stencil = wgpu::StencilState::default();
+        }
+
+        descriptor.layout = Some(vec![
+            vec![
+                // tile uniform
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // drape texture
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            vec![
+                // elevation
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+            ],
+        ]);
+
+        TerrainResources::new(
+            device,
+            queue,
+            descriptor.initialize(device),
+            surface.surface_format(),
+            settings.depth_texture_format,
+            if multisampling {
+                settings.msaa
+            } else {
+                Msaa { samples: 1 }
+            },
+        )
+    });
+    if !is_uninitialized {
+        return;
+    }
+
+    // The resources are new, e.g. because they have been released while the map was suspended.
+    // Therefore, the elevation which has already been decoded is uploaded again.
+    if let (Eventually::Initialized(terrain_resources), Some(elevation)) =
+        (terrain_resources, view_state.elevation())
+    {
+        for (coords, dem) in elevation.iter() {
+            terrain_resources.write_dem_texture(device, queue, coords, dem);
+        }
+    }
+}
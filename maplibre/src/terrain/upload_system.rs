@@ -0,0 +1,238 @@
+//! Uploads data to the GPU which is needed for rendering.
+use crate::{
+    context::MapContext,
+    coords::{WorldTileCoords, Zoom, TILE_SIZE},
+    raster::{dem::DemData, AvailableRasterLayerData, RasterLayerData, RasterLayersDataComponent},
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        shaders::ShaderTerrainTile,
+        tile_view_pattern::{WgpuTileViewPattern, DEFAULT_TILE_SIZE},
+        view_state::ViewState,
+        Renderer,
+    },
+    style::{source::Source, Style},
+    tcs::tiles::Tiles,
+    terrain::{resource::TerrainResources, DrapedTile, Elevation, TerrainDrapePhase},
+};
+
+pub fn upload_system(
+    MapContext {
+        world,
+        style,
+        view_state,
+        renderer: Renderer { device, queue, .. },
+        ..
+    }: &mut MapContext,
+) {
+    let Some((Initialized(terrain_resources), drape_phase, Initialized(tile_view_pattern))) =
+        world.resources.query_mut::<(
+            &mut Eventually<TerrainResources>,
+            &mut TerrainDrapePhase,
+            &mut Eventually<WgpuTileViewPattern>,
+        )>()
+    else {
+        return;
+    };
+
+    drape_phase.tiles.clear();
+
+    let Some(terrain) = &style.terrain else {
+        drape_phase.active = false;
+        if view_state.elevation().is_some() {
+            view_state.set_elevation(None);
+        }
+        return;
+    };
+
+    if view_state
+        .elevation()
+        .map_or(true, |elevation| elevation.source() != terrain.source)
+    {
+        view_state.set_elevation(Some(Elevation::new(
+            &terrain.source,
+            terrain.exaggeration() as f64,
+        )));
+        terrain_resources.clear_dem_textures();
+    }
+
+    if let Some(elevation) = view_state.elevation_mut() {
+        elevation.set_exaggeration(terrain.exaggeration() as f64);
+    }
+
+    upload_elevation(
+        terrain_resources,
+        device,
+        queue,
+        &world.tiles,
+        style,
+        view_state,
+    );
+
+    let zoom = view_state.zoom();
+    tile_view_pattern.upload_draped_pattern(queue, zoom);
+
+    let view_proj = view_state.view_projection();
+
+    for view_tile in tile_view_pattern.iter() {
+        let mut shapes = Vec::new();
+        view_tile.render(|source_shape| {
+            if let Some(buffer_range) = source_shape.buffer_range() {
+                shapes.push(buffer_range.start);
+            }
+        });
+
+        if shapes.is_empty() {
+            continue;
+        }
+
+        let coords = view_tile.coords();
+        let dem = view_state
+            .elevation()
+            .and_then(|elevation| elevation.find_tile(coords));
+
+        let slot = drape_phase.tiles.len();
+        terrain_resources.ensure_slots(device, slot + 1);
+        terrain_resources.write_slot_uniform(
+            queue,
+            slot,
+            ShaderTerrainTile {
+                transform: view_proj
+                    .to_model_view_projection(coords.transform_for_zoom(zoom))
+                    .downcast()
+                    .into(),
+                dem_transform: dem_transform(coords, dem),
+                elevation_scale: elevation_scale(coords, zoom, view_state) as f32,
+                _padding: [0.0; 3],
+            },
+        );
+
+        drape_phase.tiles.push(DrapedTile {
+            coords,
+            dem: dem.map(|(dem_coords, _)| dem_coords),
+            slot,
+            shapes,
+            masks: Vec::new(),
+            layers: Vec::new(),
+        });
+    }
+
+    drape_phase.active = true;
+}
+
+/// Decodes the elevation of the tiles in view and uploads it.
+#[tracing::instrument(skip_all)]
+fn upload_elevation(
+    terrain_resources: &mut TerrainResources,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    tiles: &Tiles,
+    style: &Style,
+    view_state: &mut ViewState,
+) {
+    let Some(view_region) =
+        view_state.create_view_region(view_state.zoom().zoom_level(DEFAULT_TILE_SIZE))
+    else {
+        return;
+    };
+
+    let Some(elevation) = view_state.elevation_mut() else {
+        return;
+    };
+
+    let Some(Source::RasterDem(source)) = style.sources.get(elevation.source()) else {
+        return;
+    };
+
+    // Only the tiles in view and their ancestors, which cover missing tiles, are kept
+    elevation.retain(&view_region.wrapped_with_ancestors());
+    terrain_resources.retain_dem_textures(|coords| elevation.contains(coords));
+
+    for coords in view_region.iter_wrapped() {
+        if elevation.contains(coords) {
+            continue;
+        }
+
+        let Some(raster_layers) = tiles.query::<&RasterLayersDataComponent>(coords) else {
+            continue;
+        };
+
+        // The raster-dem data is keyed by the id of its source
+        let Some(AvailableRasterLayerData { image, .. }) = raster_layers
+            .layers
+            .iter()
+            .flat_map(|data| match data {
+                RasterLayerData::Available(data) => Some(data),
+                RasterLayerData::Missing(_) => None,
+            })
+            .find(|layer| elevation.source() == layer.source_layer)
+        else {
+            continue;
+        };
+
+        let dem = match DemData::from_image(image, source.encoding) {
+            Ok(dem) => dem,
+            Err(e) => {
+                log::error!("failed to decode terrain tile {coords}: {e}");
+                continue;
+            }
+        };
+
+        for changed in elevation.insert(coords, dem) {
+            if let Some(dem) = elevation.get(changed) {
+                terrain_resources.write_dem_texture(device, queue, changed, dem);
+            }
+        }
+    }
+}
+
+/// Describes which part of the elevation tile covers the tile at `coords`.
+fn dem_transform(coords: WorldTileCoords, dem: Option<(WorldTileCoords, &DemData)>) -> [f32; 4] {
+    let Some((dem_coords, dem)) = dem else {
+        return [0.0, 0.0, 1.0, 1.0];
+    };
+
+    // The elevation tiles are shared by the copies of the world
+    let coords = coords.wrap();
+    let z_delta = u8::from(coords.z) - u8::from(dem_coords.z);
+    let scale = 1.0 / (1u32 << z_delta) as f32;
+
+    [
+        (coords.x - (dem_coords.x << z_delta)) as f32 * scale,
+        (coords.y - (dem_coords.y << z_delta)) as f32 * scale,
+        scale,
+        dem.dim() as f32,
+    ]
+}
+
+/// Returns the world units per meter at the center of the tile, including the exaggeration.
+fn elevation_scale(coords: WorldTileCoords, zoom: Zoom, view_state: &ViewState) -> f64 {
+    let exaggeration = view_state
+        .elevation()
+        .map_or(1.0, |elevation| elevation.exaggeration());
+    let center_y = (coords.y as f64 + 0.5) * TILE_SIZE / zoom.scale_to_zoom_level(coords.z);
+    Elevation::meters_to_world(exaggeration, center_y, zoom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coords::ZoomLevel;
+
+    #[test]
+    fn test_dem_transform() {
+        let dem = DemData::flat(256);
+        let dem_coords = WorldTileCoords::from((1, 1, ZoomLevel::from(1)));
+
+        // The last tile of the left copy of the world is covered by the right half of the
+        // elevation tile
+        let coords = WorldTileCoords::from((-1, 2, ZoomLevel::from(2)));
+        assert_eq!(
+            dem_transform(coords, Some((dem_coords, &dem))),
+            [0.5, 0.0, 0.5, 256.0]
+        );
+        assert_eq!(
+            dem_transform(coords.wrap(), Some((dem_coords, &dem))),
+            dem_transform(coords, Some((dem_coords, &dem)))
+        );
+    }
+}
@@ -122,11 +122,13 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> GeomProce
 
     fn multipoint_begin(&mut self, _size: usize, _idx: usize) -> GeoResult<()> {
         // log::info!("multipoint_begin");
+        self.is_point = true;
         Ok(())
     }
 
     fn multipoint_end(&mut self, _idx: usize) -> GeoResult<()> {
         // log::info!("multipoint_end");
+        self.is_point = false;
         Ok(())
     }
 
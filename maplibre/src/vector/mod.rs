@@ -1,17 +1,21 @@
-use std::{marker::PhantomData, ops::Deref, rc::Rc};
+use std::{collections::HashMap, marker::PhantomData, ops::Deref, rc::Rc};
+
+use serde_json::Value as JsonValue;
 
 use crate::{
     coords::WorldTileCoords,
     environment::Environment,
+    feature_state::FeatureStates,
     kernel::Kernel,
     plugin::Plugin,
     render::{
-        eventually::Eventually,
+        eventually::{Eventually, GpuResources},
         shaders::{ShaderFeatureStyle, ShaderLayerMetadata},
         tile_view_pattern::{HasTile, ViewTileSources},
         RenderStageLabel, ShaderVertex,
     },
     schedule::Schedule,
+    style::{layer::StyleLayer, mutation::StyleChange},
     tcs::{system::SystemContainer, tiles::TileComponent, world::World},
     tessellation::{IndexDataType, OverAlignedVertexBuffer},
     vector::{
@@ -21,6 +25,7 @@ use crate::{
     },
 };
 
+mod points;
 mod populate_world_system;
 mod process_vector;
 mod queue_system;
@@ -31,6 +36,7 @@ mod resource_system;
 mod transferables;
 mod upload_system;
 
+pub use points::PointFeature;
 pub use process_vector::*;
 pub use transferables::{
     DefaultVectorTransferables, LayerIndexed, LayerMissing, LayerTessellated, TileTessellated,
@@ -92,6 +98,14 @@ impl<E: Environment, T: VectorTransferables> Plugin<E> for VectorPlugin<T> {
 
         resources.insert(Eventually::<VectorBufferPool>::Uninitialized);
         resources.insert(Eventually::<VectorPipeline>::Uninitialized);
+        resources
+            .get_or_init_mut::<GpuResources>()
+            .add::<VectorBufferPool>()
+            .add::<VectorPipeline>();
+        // Changes of the style which have not been applied to the uploaded tiles yet
+        resources.get_or_init_mut::<StyleChange>();
+        // State of features which is used by the paint properties
+        resources.init::<FeatureStates>();
 
         resources
             .get_or_init_mut::<ViewTileSources>()
@@ -115,14 +129,26 @@ impl<E: Environment, T: VectorTransferables> Plugin<E> for VectorPlugin<T> {
 
 pub struct AvailableVectorLayerData {
     pub coords: WorldTileCoords,
+    /// The style source whose tile contains the layer
+    pub source: Option<String>,
     pub source_layer: String,
+    /// The filter which selected the features. Style layers with different filters render
+    /// different tessellations of the same source layer.
+    pub filter: Option<JsonValue>,
     pub buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
     /// Holds for each feature the count of indices.
     pub feature_indices: Vec<u32>,
+    /// Holds for each feature its id, which is `None` if the tile does not contain one.
+    pub feature_ids: Vec<Option<u64>>,
+    /// Holds for each feature its properties, which are used by data-driven paint properties.
+    pub feature_properties: Vec<HashMap<String, String>>,
+    /// Point features of the layer, which are not part of the tessellated buffer.
+    pub points: Vec<PointFeature>,
 }
 
 pub struct MissingVectorLayerData {
     pub coords: WorldTileCoords,
+    pub source: Option<String>,
     pub source_layer: String,
 }
 
@@ -137,4 +163,21 @@ pub struct VectorLayersDataComponent {
     pub layers: Vec<VectorLayerData>,
 }
 
+impl VectorLayersDataComponent {
+    /// Returns the data of the layer of the source which `style_layer` renders, tessellated with
+    /// the filter of `style_layer`. Layers of different sources can have the same name.
+    pub fn available_layer(&self, style_layer: &StyleLayer) -> Option<&AvailableVectorLayerData> {
+        self.layers.iter().find_map(|data| match data {
+            VectorLayerData::Available(data)
+                if data.source == style_layer.source
+                    && style_layer.source_layer.as_ref() == Some(&data.source_layer)
+                    && data.filter == style_layer.filter =>
+            {
+                Some(data)
+            }
+            _ => None,
+        })
+    }
+}
+
 impl TileComponent for VectorLayersDataComponent {}
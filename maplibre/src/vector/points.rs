@@ -0,0 +1,100 @@
+//! Collects the point features of a vector tile layer, e.g. for rendering heatmaps.
+
+use std::collections::HashMap;
+
+use geozero::{
+    mvt::{tile, tile::GeomType},
+    ColumnValue, FeatureProcessor, GeomProcessor, GeozeroDatasource, PropertyProcessor,
+};
+
+type GeoResult<T> = geozero::error::Result<T>;
+
+/// A point within a tile together with the properties of its feature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointFeature {
+    /// Position in tile coordinates, i.e. from `0` to [`EXTENT`](crate::coords::EXTENT)
+    pub position: [f32; 2],
+    pub properties: HashMap<String, String>,
+}
+
+/// Processor which collects the points of point and multi-point features.
+#[derive(Default)]
+pub struct PointCollector {
+    is_point: bool,
+    positions: Vec<[f32; 2]>,
+    properties: HashMap<String, String>,
+
+    pub points: Vec<PointFeature>,
+}
+
+impl GeomProcessor for PointCollector {
+    fn xy(&mut self, x: f64, y: f64, _idx: usize) -> GeoResult<()> {
+        if self.is_point {
+            self.positions.push([x as f32, y as f32]);
+        }
+        Ok(())
+    }
+
+    fn point_begin(&mut self, _idx: usize) -> GeoResult<()> {
+        self.is_point = true;
+        Ok(())
+    }
+
+    fn point_end(&mut self, _idx: usize) -> GeoResult<()> {
+        self.is_point = false;
+        Ok(())
+    }
+
+    fn multipoint_begin(&mut self, _size: usize, _idx: usize) -> GeoResult<()> {
+        self.is_point = true;
+        Ok(())
+    }
+
+    fn multipoint_end(&mut self, _idx: usize) -> GeoResult<()> {
+        self.is_point = false;
+        Ok(())
+    }
+}
+
+impl PropertyProcessor for PointCollector {
+    fn property(&mut self, _idx: usize, name: &str, value: &ColumnValue) -> GeoResult<bool> {
+        self.properties.insert(name.to_string(), value.to_string());
+        Ok(false)
+    }
+}
+
+impl FeatureProcessor for PointCollector {
+    fn feature_begin(&mut self, _idx: u64) -> GeoResult<()> {
+        self.positions.clear();
+        self.properties.clear();
+        Ok(())
+    }
+
+    fn feature_end(&mut self, _idx: u64) -> GeoResult<()> {
+        for position in self.positions.drain(..) {
+            self.points.push(PointFeature {
+                position,
+                properties: self.properties.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the points of a layer. Layers without point features are not processed.
+pub fn collect_points(layer: &mut tile::Layer) -> Vec<PointFeature> {
+    let has_points = layer
+        .features
+        .iter()
+        .any(|feature| feature.r#type == Some(GeomType::Point as i32));
+
+    if !has_points {
+        return Vec::new();
+    }
+
+    let mut collector = PointCollector::default();
+    if let Err(e) = layer.process(&mut collector) {
+        log::error!("collecting points of layer {} failed: {e:?}", layer.name);
+    }
+    collector.points
+}
@@ -3,8 +3,10 @@ use std::{borrow::Cow, marker::PhantomData, rc::Rc};
 use crate::{
     context::MapContext,
     environment::Environment,
+    events::{self, MapEvent, SourceDataEvent, TileStatus},
     io::apc::{AsyncProcedureCall, Message},
     kernel::Kernel,
+    repaint,
     tcs::system::System,
     vector::{transferables::*, VectorLayerData, VectorLayersDataComponent},
 };
@@ -36,16 +38,27 @@ impl<E: Environment, T: VectorTransferables> System for PopulateWorldSystem<E, T
                 || message.has_tag(T::LayerIndexed::message_tag())
         }) {
             let message: Message = message;
+            // The data of a tile changed, which becomes visible once it has been uploaded
+            repaint::request_repaint(world);
             if message.has_tag(T::TileTessellated::message_tag()) {
                 let message = message.into_transferable::<T::TileTessellated>();
+                let coords = message.coords();
                 let Some(component) = world
                     .tiles
-                    .query_mut::<&mut VectorLayersDataComponent>(message.coords())
+                    .query_mut::<&mut VectorLayersDataComponent>(coords)
                 else {
                     continue;
                 };
 
                 component.done = true;
+                events::emit(
+                    world,
+                    MapEvent::SourceData(SourceDataEvent {
+                        coords,
+                        source_layer: None,
+                        status: TileStatus::Loaded,
+                    }),
+                );
             } else if message.has_tag(T::LayerMissing::message_tag()) {
                 let message = message.into_transferable::<T::LayerMissing>();
                 let Some(component) = world
@@ -55,9 +68,14 @@ impl<E: Environment, T: VectorTransferables> System for PopulateWorldSystem<E, T
                     continue;
                 };
 
-                component
-                    .layers
-                    .push(VectorLayerData::Missing(message.to_layer()));
+                let layer = message.to_layer();
+                let event = SourceDataEvent {
+                    coords: layer.coords,
+                    source_layer: Some(layer.source_layer.clone()),
+                    status: TileStatus::Missing,
+                };
+                component.layers.push(VectorLayerData::Missing(layer));
+                events::emit(world, MapEvent::SourceData(event));
             } else if message.has_tag(T::LayerTessellated::message_tag()) {
                 let message = message.into_transferable::<T::LayerTessellated>();
                 // FIXME: Handle points!
@@ -1,9 +1,15 @@
-use std::{borrow::Cow, collections::HashSet, marker::PhantomData};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+};
 
 use geozero::{
-    mvt::{tile, Message},
+    mvt::{tile, tile::GeomType, Message, TileValue},
     GeozeroDatasource,
 };
+use rstar::RTree;
+use serde_json::Value as JsonValue;
 use thiserror::Error;
 
 use crate::{
@@ -13,9 +19,11 @@ use crate::{
         geometry_index::{IndexProcessor, IndexedGeometry, TileIndex},
     },
     render::ShaderVertex,
+    style::filter::{self, FilterFeature},
     tessellation::{zero_tessellator::ZeroTessellator, IndexDataType, OverAlignedVertexBuffer},
-    vector::transferables::{
-        LayerIndexed, LayerMissing, LayerTessellated, TileTessellated, VectorTransferables,
+    vector::{
+        points::{collect_points, PointFeature},
+        transferables::{LayerIndexed, LayerMissing, LayerTessellated, VectorTransferables},
     },
 };
 
@@ -32,7 +40,15 @@ pub enum ProcessVectorError {
 /// A request for a tile at the given coordinates and in the given layers.
 pub struct VectorTileRequest {
     pub coords: WorldTileCoords,
+    /// The style source whose tile is processed. The processed layers are identified by their
+    /// source and their name, because layers of different sources can have the same name.
+    pub source: Option<String>,
     pub layers: HashSet<String>,
+    /// The distinct filters of the style layers of each layer. A layer is tessellated once per
+    /// filter. Layers without filters are tessellated with all of their features.
+    pub filters: HashMap<String, Vec<Option<JsonValue>>>,
+    /// The layers whose points are collected, i.e. the source layers of heatmap layers.
+    pub point_layers: HashSet<String>,
 }
 
 pub fn process_vector_tile<T: VectorTransferables, C: Context>(
@@ -49,25 +65,41 @@ pub fn process_vector_tile<T: VectorTransferables, C: Context>(
 
     let coords = &tile_request.coords;
 
-    for layer in &mut tile.layers {
-        let cloned_layer = layer.clone();
-        let layer_name: &str = &cloned_layer.name;
+    let unfiltered = [None];
+    for layer in &tile.layers {
+        let layer_name: &str = &layer.name;
         if !tile_request.layers.contains(layer_name) {
             continue;
         }
 
-        let mut tessellator = ZeroTessellator::<IndexDataType>::default();
-        if let Err(e) = layer.process(&mut tessellator) {
-            context.layer_missing(coords, layer_name)?;
-
-            tracing::error!("layer {layer_name} at {coords} tesselation failed {e:?}");
-        } else {
-            context.layer_tesselation_finished(
-                coords,
-                tessellator.buffer.into(),
-                tessellator.feature_indices,
-                cloned_layer,
-            )?;
+        let filters = tile_request
+            .filters
+            .get(layer_name)
+            .map_or(&unfiltered[..], Vec::as_slice);
+        for filter in filters {
+            let mut filtered_layer = filter_layer(layer, filter.as_ref());
+
+            let mut tessellator = ZeroTessellator::<IndexDataType>::default();
+            if let Err(e) = filtered_layer.process(&mut tessellator) {
+                context.layer_missing(coords, &tile_request.source, layer_name)?;
+
+                tracing::error!("layer {layer_name} at {coords} tesselation failed {e:?}");
+            } else {
+                let points = if tile_request.point_layers.contains(layer_name) {
+                    collect_points(&mut filtered_layer)
+                } else {
+                    Vec::new()
+                };
+                context.layer_tesselation_finished(
+                    coords,
+                    &tile_request.source,
+                    filter.clone(),
+                    tessellator.buffer.into(),
+                    tessellator.feature_indices,
+                    filtered_layer,
+                    points,
+                )?;
+            }
         }
     }
 
@@ -82,7 +114,7 @@ pub fn process_vector_tile<T: VectorTransferables, C: Context>(
         .collect::<HashSet<_>>();
 
     for missing_layer in tile_request.layers.difference(&available_layers) {
-        context.layer_missing(coords, missing_layer)?;
+        context.layer_missing(coords, &tile_request.source, missing_layer)?;
         tracing::info!("requested layer {missing_layer} at {coords} not found in tile");
     }
 
@@ -91,19 +123,74 @@ pub fn process_vector_tile<T: VectorTransferables, C: Context>(
     let mut index = IndexProcessor::new();
 
     for layer in &mut tile.layers {
-        layer.process(&mut index).unwrap();
+        // Layers of a tile can be requested from several sources, which are indexed separately
+        if !tile_request.layers.contains(&layer.name) {
+            continue;
+        }
+        index.process_layer(layer).unwrap();
     }
 
-    context.layer_indexing_finished(&tile_request.coords, index.get_geometries())?;
+    let mut geometries = index.get_geometries();
+    for geometry in &mut geometries {
+        geometry.source = tile_request.source.clone();
+    }
+    context.layer_indexing_finished(&tile_request.coords, geometries)?;
 
     // End
 
     tracing::info!("tile tessellated at {coords} finished");
-    context.tile_finished(coords)?;
 
     Ok(())
 }
 
+/// Returns a copy of `layer` which only contains the features that match `filter`.
+fn filter_layer(layer: &tile::Layer, filter: Option<&JsonValue>) -> tile::Layer {
+    let mut filtered = layer.clone();
+    if let Some(filter) = filter {
+        filtered.features.retain(|feature| {
+            let properties = feature_properties(layer, feature);
+            filter::matches(
+                filter,
+                FilterFeature {
+                    geometry_type: geometry_type(feature),
+                    properties: &properties,
+                },
+            )
+        });
+    }
+    filtered
+}
+
+fn geometry_type(feature: &tile::Feature) -> &'static str {
+    match feature.r#type {
+        Some(r#type) if r#type == GeomType::Point as i32 => "Point",
+        Some(r#type) if r#type == GeomType::Linestring as i32 => "LineString",
+        Some(r#type) if r#type == GeomType::Polygon as i32 => "Polygon",
+        _ => "Unknown",
+    }
+}
+
+/// Returns the typed properties of a feature, which filters compare.
+fn feature_properties(layer: &tile::Layer, feature: &tile::Feature) -> HashMap<String, JsonValue> {
+    feature
+        .tags
+        .chunks_exact(2)
+        .filter_map(|tag| {
+            let key = layer.keys.get(tag[0] as usize)?;
+            let value =
+                match TileValue::try_from(layer.values.get(tag[1] as usize)?.clone()).ok()? {
+                    TileValue::Str(value) => value.into(),
+                    TileValue::Float(value) => value.into(),
+                    TileValue::Double(value) => value.into(),
+                    TileValue::Int(value) | TileValue::Sint(value) => value.into(),
+                    TileValue::Uint(value) => value.into(),
+                    TileValue::Bool(value) => value.into(),
+                };
+            Some((key.clone(), value))
+        })
+        .collect()
+}
+
 pub struct ProcessVectorContext<T: VectorTransferables, C: Context> {
     context: C,
     phantom_t: PhantomData<T>,
@@ -123,35 +210,41 @@ impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
         self.context
     }
 
-    fn tile_finished(&mut self, coords: &WorldTileCoords) -> Result<(), ProcessVectorError> {
-        self.context
-            .send_back(T::TileTessellated::build_from(*coords))
-            .map_err(|e| ProcessVectorError::SendError(e))
-    }
-
     fn layer_missing(
         &mut self,
         coords: &WorldTileCoords,
+        source: &Option<String>,
         layer_name: &str,
     ) -> Result<(), ProcessVectorError> {
         self.context
-            .send_back(T::LayerMissing::build_from(*coords, layer_name.to_owned()))
+            .send_back(T::LayerMissing::build_from(
+                *coords,
+                source.clone(),
+                layer_name.to_owned(),
+            ))
             .map_err(|e| ProcessVectorError::SendError(e))
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn layer_tesselation_finished(
         &mut self,
         coords: &WorldTileCoords,
+        source: &Option<String>,
+        filter: Option<JsonValue>,
         buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
         feature_indices: Vec<u32>,
         layer_data: tile::Layer,
+        points: Vec<PointFeature>,
     ) -> Result<(), ProcessVectorError> {
         self.context
             .send_back(T::LayerTessellated::build_from(
                 *coords,
+                source.clone(),
+                filter,
                 buffer,
                 feature_indices,
                 layer_data,
+                points,
             ))
             .map_err(|e| ProcessVectorError::SendError(e))
     }
@@ -164,7 +257,9 @@ impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
         self.context
             .send_back(T::LayerIndexed::build_from(
                 *coords,
-                TileIndex::Linear { list: geometries },
+                TileIndex::Spatial {
+                    tree: RTree::bulk_load(geometries),
+                },
             ))
             .map_err(|e| ProcessVectorError::SendError(e))
     }
@@ -172,7 +267,10 @@ impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
 
 #[cfg(test)]
 mod tests {
-    use super::ProcessVectorContext;
+    use geozero::mvt::tile;
+    use serde_json::json;
+
+    use super::{filter_layer, ProcessVectorContext};
     use crate::{
         coords::ZoomLevel,
         io::apc::tests::DummyContext,
@@ -189,9 +287,40 @@ mod tests {
             &[0],
             VectorTileRequest {
                 coords: (0, 0, ZoomLevel::default()).into(),
+                source: None,
                 layers: Default::default(),
+                filters: Default::default(),
+                point_layers: Default::default(),
             },
             &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(DummyContext),
         );
     }
+
+    #[test]
+    fn test_filter_layer() {
+        let feature = |class: u32| tile::Feature {
+            tags: vec![0, class],
+            r#type: Some(tile::GeomType::Polygon as i32),
+            ..Default::default()
+        };
+        let value = |value: &str| tile::Value {
+            string_value: Some(value.to_string()),
+            ..Default::default()
+        };
+        let layer = tile::Layer {
+            name: "water".to_string(),
+            features: vec![feature(0), feature(1), feature(0)],
+            keys: vec!["class".to_string()],
+            values: vec![value("lake"), value("river")],
+            ..Default::default()
+        };
+
+        assert_eq!(filter_layer(&layer, None).features.len(), 3);
+
+        let filter = json!(["==", "class", "lake"]);
+        assert_eq!(filter_layer(&layer, Some(&filter)).features.len(), 2);
+
+        let filter = json!(["==", "$type", "LineString"]);
+        assert!(filter_layer(&layer, Some(&filter)).features.is_empty());
+    }
 }
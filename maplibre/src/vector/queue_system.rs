@@ -1,4 +1,6 @@
 //! Queues [PhaseItems](crate::render::render_phase::PhaseItem) for rendering.
+use std::collections::HashMap;
+
 use crate::{
     context::MapContext,
     render::{
@@ -11,7 +13,14 @@ use crate::{
     vector::{render_commands::DrawVectorTiles, VectorBufferPool},
 };
 
-pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
+pub fn queue_system(
+    MapContext {
+        world,
+        style,
+        view_state,
+        ..
+    }: &mut MapContext,
+) {
     let Some((
         Initialized(tile_view_pattern),
         Initialized(buffer_pool),
@@ -29,6 +38,15 @@ pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
 
     let buffer_pool_index = buffer_pool.index();
 
+    // The style might have changed since the layers have been uploaded
+    let zoom = f64::from(view_state.zoom());
+    let visible_layers = style
+        .layers
+        .iter()
+        .filter(|style_layer| style_layer.is_visible_at(zoom))
+        .map(|style_layer| (style_layer.id.as_str(), style_layer))
+        .collect::<HashMap<_, _>>();
+
     for view_tile in tile_view_pattern.iter() {
         let coords = &view_tile.coords();
         tracing::trace!("Drawing tile at {coords}");
@@ -43,11 +61,16 @@ pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
 
             if let Some(layer_entries) = buffer_pool_index.get_layers(source_shape.coords()) {
                 for layer_entry in layer_entries {
+                    let Some(style_layer) = visible_layers.get(layer_entry.style_layer.id.as_str())
+                    else {
+                        continue;
+                    };
+
                     // Draw tile
                     layer_item_phase.add(LayerItem {
                         draw_function: Box::new(DrawState::<LayerItem, DrawVectorTiles>::new()),
-                        index: layer_entry.style_layer.index,
-                        style_layer: layer_entry.style_layer.id.clone(),
+                        index: style_layer.index,
+                        style_layer: style_layer.id.clone(),
                         tile: Tile {
                             coords: layer_entry.coords,
                         },
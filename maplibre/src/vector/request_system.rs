@@ -1,22 +1,33 @@
 //! Requests tiles which are currently in view
 
-use std::{borrow::Cow, collections::HashSet, marker::PhantomData, rc::Rc};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use serde_json::Value as JsonValue;
 
 use crate::{
     context::MapContext,
     environment::{Environment, OffscreenKernel},
+    events::{self, MapEvent},
     io::{
         apc::{AsyncProcedureCall, AsyncProcedureFuture, Context, Input, ProcedureError},
         source_type::{SourceType, TessellateSource},
     },
     kernel::Kernel,
-    render::tile_view_pattern::DEFAULT_TILE_SIZE,
-    style::layer::LayerPaint,
+    render::{
+        eventually::{Eventually, Eventually::Initialized},
+        tile_view_pattern::DEFAULT_TILE_SIZE,
+    },
+    style::{layer::LayerPaint, mutation::StyleChange, source::Source, Style},
     tcs::system::System,
     vector::{
         process_vector::{process_vector_tile, ProcessVectorContext, VectorTileRequest},
-        transferables::{LayerMissing, VectorTransferables},
-        VectorLayersDataComponent,
+        transferables::{LayerMissing, TileTessellated, VectorTransferables},
+        VectorBufferPool, VectorLayersDataComponent,
     },
 };
 
@@ -52,16 +63,30 @@ impl<E: Environment, T: VectorTransferables> System for RequestSystem<E, T> {
         let view_region =
             view_state.create_view_region(view_state.zoom().zoom_level(DEFAULT_TILE_SIZE));
 
-        if view_state.did_camera_change() || view_state.did_zoom_change() {
+        let retessellate = world
+            .resources
+            .get_mut::<StyleChange>()
+            .is_some_and(|style_change| std::mem::take(&mut style_change.retessellate));
+
+        if retessellate {
+            // The tessellation depends on the style, e.g. on the source layers which are used
+            world.tiles.remove_components::<VectorLayersDataComponent>();
+            if let Some(Initialized(buffer_pool)) =
+                world.resources.get_mut::<Eventually<VectorBufferPool>>()
+            {
+                buffer_pool.clear();
+            }
+        }
+
+        if retessellate || view_state.did_camera_change() || view_state.did_zoom_change() {
             if let Some(view_region) = &view_region {
                 // TODO: We also need to request tiles from layers above if we are over the maximum zoom level
 
-                for coords in view_region.iter() {
+                for coords in view_region.iter_wrapped() {
                     if coords.build_quad_key().is_none() {
                         continue;
                     }
 
-                    // TODO: Make tesselation depend on style? So maybe we need to request even if it exists
                     if world
                         .tiles
                         .query::<&VectorLayersDataComponent>(coords)
@@ -79,22 +104,23 @@ impl<E: Environment, T: VectorTransferables> System for RequestSystem<E, T> {
                     tracing::event!(tracing::Level::ERROR, %coords, "tile request started: {coords}");
                     log::info!("tile request started: {coords}");
 
-                    self.kernel
-                        .apc()
-                        .call(
-                            Input::TileRequest {
-                                coords,
-                                style: style.clone(), // TODO: Avoid cloning whole style
-                            },
-                            fetch_vector_apc::<
+                    if let Err(error) = self.kernel.apc().call(
+                        Input::TileRequest {
+                            coords,
+                            style: style.clone(), // TODO: Avoid cloning whole style
+                        },
+                        fetch_vector_apc::<
+                            E::OffscreenKernelEnvironment,
+                            T,
+                            <E::AsyncProcedureCall as AsyncProcedureCall<
                                 E::OffscreenKernelEnvironment,
-                                T,
-                                <E::AsyncProcedureCall as AsyncProcedureCall<
-                                    E::OffscreenKernelEnvironment,
-                                >>::Context,
-                            >,
-                        )
-                        .unwrap(); // TODO: Remove unwrap
+                            >>::Context,
+                        >,
+                    ) {
+                        let message = format!("requesting tile {coords} failed: {error}");
+                        log::error!("{message}");
+                        events::emit(world, MapEvent::Error(message));
+                    }
                 }
             }
         }
@@ -113,25 +139,49 @@ pub fn fetch_vector_apc<K: OffscreenKernel, T: VectorTransferables, C: Context +
             return Err(ProcedureError::IncompatibleInput);
         };
 
-        let fill_layers: HashSet<String> = style
-            .layers
-            .iter()
-            .filter_map(|layer| {
-                if matches!(layer.paint, Some(LayerPaint::Fill(_)))
-                    || matches!(layer.paint, Some(LayerPaint::Line(_)))
-                {
-                    layer.source_layer.clone()
-                } else {
-                    None
-                }
-            })
-            .collect();
+        // Layers are fetched from the tiles of their source. Layers of sources without tiles are
+        // fetched from the default tiles. The data of the layers is keyed by their source.
+        let mut requests: HashMap<Option<&str>, HashSet<String>> = HashMap::new();
+        let mut point_requests: HashMap<Option<&str>, HashSet<String>> = HashMap::new();
+        let mut filters: HashMap<Option<&str>, HashMap<String, Vec<Option<JsonValue>>>> =
+            HashMap::new();
+        for layer in &style.layers {
+            if !matches!(
+                layer.paint,
+                Some(LayerPaint::Fill(_) | LayerPaint::Line(_) | LayerPaint::Heatmap(_))
+            ) {
+                continue;
+            }
+            let Some(source_layer) = &layer.source_layer else {
+                continue;
+            };
+            requests
+                .entry(layer.source.as_deref())
+                .or_default()
+                .insert(source_layer.clone());
+            let layer_filters = filters
+                .entry(layer.source.as_deref())
+                .or_default()
+                .entry(source_layer.clone())
+                .or_default();
+            if !layer_filters.contains(&layer.filter) {
+                layer_filters.push(layer.filter.clone());
+            }
+            if matches!(layer.paint, Some(LayerPaint::Heatmap(_))) {
+                point_requests
+                    .entry(layer.source.as_deref())
+                    .or_default()
+                    .insert(source_layer.clone());
+            }
+        }
 
         let client = kernel.source_client();
 
-        if !fill_layers.is_empty() {
+        for (source_id, fill_layers) in requests {
             let context = context.clone();
-            let source = SourceType::Tessellate(TessellateSource::default());
+            let source = source_id
+                .and_then(|source_id| vector_tile_source(&style, source_id))
+                .unwrap_or_else(|| SourceType::Tessellate(TessellateSource::default()));
             match client.fetch(&coords, &source).await {
                 Ok(data) => {
                     let data = data.into_boxed_slice();
@@ -141,7 +191,10 @@ pub fn fetch_vector_apc<K: OffscreenKernel, T: VectorTransferables, C: Context +
                         &data,
                         VectorTileRequest {
                             coords,
+                            source: source_id.map(str::to_owned),
                             layers: fill_layers,
+                            filters: filters.remove(&source_id).unwrap_or_default(),
+                            point_layers: point_requests.remove(&source_id).unwrap_or_default(),
                         },
                         &mut pipeline_context,
                     )
@@ -153,6 +206,7 @@ pub fn fetch_vector_apc<K: OffscreenKernel, T: VectorTransferables, C: Context +
                         context
                             .send_back(<T as VectorTransferables>::LayerMissing::build_from(
                                 coords,
+                                source_id.map(str::to_owned),
                                 to_load.to_string(),
                             ))
                             .map_err(ProcedureError::Send)?;
@@ -161,6 +215,21 @@ pub fn fetch_vector_apc<K: OffscreenKernel, T: VectorTransferables, C: Context +
             }
         }
 
+        // The tile is done once the layers of all sources have been processed
+        context
+            .send_back(<T as VectorTransferables>::TileTessellated::build_from(
+                coords,
+            ))
+            .map_err(ProcedureError::Send)?;
+
         Ok(())
     })
 }
+
+/// Returns the tiles of the vector source `source_id`, if the style defines them.
+fn vector_tile_source(style: &Style, source_id: &str) -> Option<SourceType> {
+    match style.sources.get(source_id) {
+        Some(Source::Vector(source)) => source.tile_source(),
+        _ => None,
+    }
+}
@@ -1,7 +1,7 @@
 //! A ring-buffer like pool of [buffers](wgpu::Buffer).
 
 use std::{
-    collections::{btree_map, BTreeMap, HashSet, VecDeque},
+    collections::{btree_map, BTreeMap, BTreeSet, HashSet, VecDeque},
     fmt::Debug,
     marker::PhantomData,
     mem::size_of,
@@ -214,11 +214,11 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         (bytes, aligned_bytes)
     }
 
-    pub fn get_loaded_source_layers_at(&self, coords: WorldTileCoords) -> Option<HashSet<&str>> {
+    pub fn get_loaded_style_layers_at(&self, coords: WorldTileCoords) -> Option<HashSet<&str>> {
         self.index.get_layers(coords).map(|layers| {
             layers
                 .iter()
-                .map(|entry| entry.style_layer.source_layer.as_ref().unwrap().as_str()) // TODO: Remove unwrap
+                .map(|entry| entry.style_layer.id.as_str())
                 .collect()
         })
     }
@@ -342,9 +342,18 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         );
     }
 
+    /// Overwrites the feature metadata of an entry, starting at the element `offset`. This allows
+    /// to update only the metadata of some features, e.g. because their state changed.
     #[tracing::instrument(skip_all)]
-    pub fn update_feature_metadata(&self, queue: &Q, entry: &IndexEntry, feature_metadata: &[FM]) {
+    pub fn update_feature_metadata(
+        &self,
+        queue: &Q,
+        entry: &IndexEntry,
+        offset: wgpu::BufferAddress,
+        feature_metadata: &[FM],
+    ) {
         let feature_metadata_stride = size_of::<FM>() as wgpu::BufferAddress; // TODO: deduplicate
+        let offset_bytes = offset * feature_metadata_stride;
 
         let (feature_metadata_bytes, aligned_feature_metadata_bytes) = Self::align(
             feature_metadata_stride,
@@ -353,7 +362,7 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         );
 
         if entry.buffer_feature_metadata.end - entry.buffer_feature_metadata.start
-            != feature_metadata_bytes
+            < offset_bytes + feature_metadata_bytes
         {
             panic!("Updated feature metadata has wrong size!");
         }
@@ -367,11 +376,42 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
 
         queue.write_buffer(
             &self.feature_metadata.inner,
-            entry.buffer_feature_metadata.start,
+            entry.buffer_feature_metadata.start + offset_bytes,
             &bytemuck::cast_slice(feature_metadata)[0..aligned_feature_metadata_bytes as usize],
         );
     }
 
+    /// Replaces the style layer of the entries which have been allocated for `style_layer` and
+    /// rewrites their layer metadata. The geometry stays untouched. Returns the entries, such
+    /// that the metadata of their features can be written again.
+    pub fn restyle_layer(
+        &mut self,
+        queue: &Q,
+        style_layer: &StyleLayer,
+        layer_metadata: TM,
+    ) -> Vec<IndexEntry> {
+        let entries = self
+            .index
+            .iter_mut()
+            .filter(|entry| entry.style_layer.id == style_layer.id)
+            .map(|entry| {
+                entry.style_layer = style_layer.clone();
+                entry.clone()
+            })
+            .collect::<Vec<_>>();
+
+        for entry in &entries {
+            self.update_layer_metadata(queue, entry, layer_metadata);
+        }
+        entries
+    }
+
+    /// Removes the entries which have been allocated for the style layers `layer_ids`.
+    pub fn remove_layers(&mut self, layer_ids: &[String]) {
+        self.index
+            .retain(|entry| !layer_ids.contains(&entry.style_layer.id));
+    }
+
     pub fn index(&self) -> &RingIndex {
         &self.index
     }
@@ -469,6 +509,48 @@ impl RingIndex {
             .flat_map(|key| self.tree_index.get(key).map(|entry| entry.layers.iter()))
     }
 
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut IndexEntry> + '_ {
+        self.tree_index
+            .values_mut()
+            .flat_map(|entry| entry.layers.iter_mut())
+    }
+
+    /// Removes the entries for which `f` returns false. The memory of a removed entry is reused
+    /// once the entries which were allocated before it are evicted.
+    fn retain<F: FnMut(&IndexEntry) -> bool>(&mut self, mut f: F) {
+        let mut removed = BTreeSet::new();
+        for (key, entry) in &self.tree_index {
+            for (i, layer) in entry.layers.iter().enumerate() {
+                if !f(layer) {
+                    removed.insert((*key, i));
+                }
+            }
+        }
+
+        if removed.is_empty() {
+            return;
+        }
+
+        // The n-th occurrence of a key in the linear index belongs to the n-th entry of the key
+        let mut occurrences = BTreeMap::<Quadkey, usize>::new();
+        self.linear_index.retain(|key| {
+            let occurrence = occurrences.entry(*key).or_default();
+            let keep = !removed.contains(&(*key, *occurrence));
+            *occurrence += 1;
+            keep
+        });
+
+        for (key, entry) in &mut self.tree_index {
+            let mut i = 0;
+            entry.layers.retain(|_| {
+                let keep = !removed.contains(&(*key, i));
+                i += 1;
+                keep
+            });
+        }
+        self.tree_index.retain(|_, entry| !entry.layers.is_empty());
+    }
+
     fn pop_front(&mut self) -> Option<IndexEntry> {
         if let Some(entry) = self
             .linear_index
@@ -718,4 +800,51 @@ mod tests {
         println!("{:?}", pool.index);
         assert_eq!(0, pool.available_space(BackingBufferType::Vertices));
     }
+
+    #[test]
+    fn test_remove_layers() {
+        let mut pool: BufferPool<TestQueue, TestBuffer, TestVertex, u32, u32, u32> =
+            BufferPool::new(
+                BackingBufferDescriptor::new(TestBuffer { size: 128 }, 128),
+                BackingBufferDescriptor::new(TestBuffer { size: 128 }, 128),
+                BackingBufferDescriptor::new(TestBuffer { size: 128 }, 128),
+                BackingBufferDescriptor::new(TestBuffer { size: 128 }, 128),
+            );
+        let queue = TestQueue {};
+
+        let mut data24bytes = VertexBuffers::new();
+        data24bytes.vertices.append(&mut create_24byte());
+        data24bytes.indices.append(&mut vec![1, 2, 3, 4]);
+        let data24bytes_aligned = data24bytes.into();
+
+        for (x, id) in [(0, "water"), (0, "park"), (1, "water")] {
+            pool.allocate_layer_geometry(
+                &queue,
+                (x, 0, ZoomLevel::new(1)).into(),
+                StyleLayer {
+                    id: id.to_string(),
+                    ..StyleLayer::default()
+                },
+                &data24bytes_aligned,
+                2,
+                &[],
+            );
+        }
+
+        pool.remove_layers(&["water".to_string()]);
+
+        let loaded = pool
+            .get_loaded_style_layers_at((0, 0, ZoomLevel::new(1)).into())
+            .unwrap();
+        assert_eq!(loaded.into_iter().collect::<Vec<_>>(), vec!["park"]);
+        assert!(pool
+            .index()
+            .get_layers((1, 0, ZoomLevel::new(1)).into())
+            .is_none());
+        // The memory behind the remaining entry is reused first
+        assert_eq!(
+            128 - 2 * 24,
+            pool.available_space(BackingBufferType::Vertices)
+        );
+    }
 }
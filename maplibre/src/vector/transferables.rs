@@ -1,6 +1,10 @@
-use std::fmt::{Debug, Formatter};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter},
+};
 
-use geozero::mvt::tile::Layer;
+use geozero::mvt::{tile::Layer, TileValue};
+use serde_json::Value as JsonValue;
 
 use crate::{
     coords::WorldTileCoords,
@@ -10,7 +14,7 @@ use crate::{
     },
     render::ShaderVertex,
     tessellation::{IndexDataType, OverAlignedVertexBuffer},
-    vector::{AvailableVectorLayerData, MissingVectorLayerData},
+    vector::{points::PointFeature, AvailableVectorLayerData, MissingVectorLayerData},
 };
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
@@ -40,7 +44,7 @@ pub trait TileTessellated: IntoMessage + Debug + Send {
 pub trait LayerMissing: IntoMessage + Debug + Send {
     fn message_tag() -> &'static dyn MessageTag;
 
-    fn build_from(coords: WorldTileCoords, layer_name: String) -> Self
+    fn build_from(coords: WorldTileCoords, source: Option<String>, layer_name: String) -> Self
     where
         Self: Sized;
 
@@ -56,9 +60,12 @@ pub trait LayerTessellated: IntoMessage + Debug + Send {
 
     fn build_from(
         coords: WorldTileCoords,
+        source: Option<String>,
+        filter: Option<JsonValue>,
         buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
         feature_indices: Vec<u32>,
         layer_data: Layer,
+        points: Vec<PointFeature>,
     ) -> Self
     where
         Self: Sized;
@@ -114,6 +121,7 @@ impl TileTessellated for DefaultTileTessellated {
 
 pub struct DefaultLayerMissing {
     pub coords: WorldTileCoords,
+    pub source: Option<String>,
     pub layer_name: String,
 }
 
@@ -134,8 +142,12 @@ impl LayerMissing for DefaultLayerMissing {
         &VectorMessageTag::LayerMissing
     }
 
-    fn build_from(coords: WorldTileCoords, layer_name: String) -> Self {
-        Self { coords, layer_name }
+    fn build_from(coords: WorldTileCoords, source: Option<String>, layer_name: String) -> Self {
+        Self {
+            coords,
+            source,
+            layer_name,
+        }
     }
 
     fn coords(&self) -> WorldTileCoords {
@@ -149,6 +161,7 @@ impl LayerMissing for DefaultLayerMissing {
     fn to_layer(self) -> MissingVectorLayerData {
         MissingVectorLayerData {
             coords: self.coords,
+            source: self.source,
             source_layer: self.layer_name,
         }
     }
@@ -157,10 +170,14 @@ impl LayerMissing for DefaultLayerMissing {
 #[derive(Clone)]
 pub struct DefaultLayerTesselated {
     pub coords: WorldTileCoords,
+    pub source: Option<String>,
+    /// The filter which selected the features of the layer
+    pub filter: Option<JsonValue>,
     pub buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
     /// Holds for each feature the count of indices.
     pub feature_indices: Vec<u32>,
     pub layer_data: Layer, // FIXME (perf): Introduce a better structure for this
+    pub points: Vec<PointFeature>,
 }
 
 impl Debug for DefaultLayerTesselated {
@@ -182,15 +199,21 @@ impl LayerTessellated for DefaultLayerTesselated {
 
     fn build_from(
         coords: WorldTileCoords,
+        source: Option<String>,
+        filter: Option<JsonValue>,
         buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
         feature_indices: Vec<u32>,
         layer_data: Layer,
+        points: Vec<PointFeature>,
     ) -> Self {
         Self {
             coords,
+            source,
+            filter,
             buffer,
             feature_indices,
             layer_data,
+            points,
         }
     }
 
@@ -205,13 +228,53 @@ impl LayerTessellated for DefaultLayerTesselated {
     fn to_layer(self) -> AvailableVectorLayerData {
         AvailableVectorLayerData {
             coords: self.coords,
+            source: self.source,
+            filter: self.filter,
+            feature_ids: self
+                .layer_data
+                .features
+                .iter()
+                .map(|feature| feature.id)
+                .collect(),
+            feature_properties: feature_properties(&self.layer_data),
             source_layer: self.layer_data.name,
             buffer: self.buffer,
             feature_indices: self.feature_indices,
+            points: self.points,
         }
     }
 }
 
+/// Decodes the properties of each feature of a layer. Values are formatted as strings, like the
+/// properties of [`PointFeature`]s.
+fn feature_properties(layer: &Layer) -> Vec<HashMap<String, String>> {
+    layer
+        .features
+        .iter()
+        .map(|feature| {
+            feature
+                .tags
+                .chunks_exact(2)
+                .filter_map(|tag| {
+                    let key = layer.keys.get(tag[0] as usize)?;
+                    let value =
+                        match TileValue::try_from(layer.values.get(tag[1] as usize)?.clone())
+                            .ok()?
+                        {
+                            TileValue::Str(value) => value,
+                            TileValue::Float(value) => value.to_string(),
+                            TileValue::Double(value) => value.to_string(),
+                            TileValue::Int(value) | TileValue::Sint(value) => value.to_string(),
+                            TileValue::Uint(value) => value.to_string(),
+                            TileValue::Bool(value) => value.to_string(),
+                        };
+                    Some((key.clone(), value))
+                })
+                .collect()
+        })
+        .collect()
+}
+
 pub struct DefaultLayerIndexed {
     coords: WorldTileCoords,
     index: TileIndex,
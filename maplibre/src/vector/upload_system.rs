@@ -1,21 +1,30 @@
 //! Uploads data to the GPU which is needed for rendering.
 
-use std::iter;
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+};
+
+use serde_json::Value as JsonValue;
 
 use crate::{
     context::MapContext,
     coords::ViewRegion,
+    feature_state::{FeatureKey, FeatureStates},
     render::{
         eventually::{Eventually, Eventually::Initialized},
-        shaders::{ShaderFeatureStyle, ShaderLayerMetadata, Vec4f32},
+        shaders::{ShaderFeatureStyle, ShaderLayerMetadata},
         tile_view_pattern::DEFAULT_TILE_SIZE,
         Renderer,
     },
-    style::Style,
-    tcs::tiles::Tiles,
-    vector::{
-        AvailableVectorLayerData, VectorBufferPool, VectorLayerData, VectorLayersDataComponent,
+    style::{
+        expression::EvaluationContext,
+        layer::{LayerPaint, StyleLayer},
+        mutation::StyleChange,
+        Style,
     },
+    tcs::tiles::Tiles,
+    vector::{AvailableVectorLayerData, VectorBufferPool, VectorLayersDataComponent},
 };
 
 pub fn upload_system(
@@ -23,172 +32,288 @@ pub fn upload_system(
         world,
         style,
         view_state,
-        renderer: Renderer { device, queue, .. },
+        renderer: Renderer { queue, .. },
         ..
     }: &mut MapContext,
 ) {
-    let Some(Initialized(buffer_pool)) = world
-        .resources
-        .query_mut::<&mut Eventually<VectorBufferPool>>()
+    let Some((Initialized(buffer_pool), style_change, feature_states)) =
+        world.resources.query_mut::<(
+            &mut Eventually<VectorBufferPool>,
+            &mut StyleChange,
+            &mut FeatureStates,
+        )>()
     else {
         return;
     };
 
+    // Paint properties are evaluated at the zoom at which the features are uploaded
+    let zoom: f64 = view_state.zoom().into();
+
+    apply_style_change(
+        buffer_pool,
+        queue,
+        &world.tiles,
+        style,
+        style_change,
+        feature_states,
+        zoom,
+    );
+    update_feature_states(buffer_pool, queue, &world.tiles, feature_states, zoom);
+
     let view_region =
         view_state.create_view_region(view_state.zoom().zoom_level(DEFAULT_TILE_SIZE));
 
     if let Some(view_region) = &view_region {
         upload_tesselated_layer(
             buffer_pool,
-            device,
             queue,
             &mut world.tiles,
             style,
+            feature_states,
             view_region,
+            zoom,
         );
-        // self.update_metadata(state, tile_repository, queue);
     }
 }
 
-/* FIXME tcs fn update_metadata(
+/// Rewrites the metadata of the features whose state changed. Only the ranges of the affected
+/// features are written, their geometry stays untouched.
+fn update_feature_states(
     buffer_pool: &VectorBufferPool,
-    tiles: &Tiles,
     queue: &wgpu::Queue,
+    tiles: &Tiles,
+    feature_states: &mut FeatureStates,
+    zoom: f64,
 ) {
-    let animated_one = 0.5
-        * (1.0
-            + ((std::time::SystemTime::now()
-                .duration_since(std::time::SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_secs_f64())
-            .sin()));
-
-    for entries in buffer_pool.index().iter() {
-        for entry in entries {
-            let world_coords = entry.coords;
-
-            let source_layer = entry.style_layer.source_layer.as_ref().unwrap();
-
-            let Some(stored_layer) =
-                tile_repository
-                    .iter_layers_at(&world_coords)
-                    .and_then(|mut layers| {
-                        layers.find(|layer| source_layer.as_str() == layer.layer_name())
-                    })  else { continue; };
+    if !feature_states.has_changes() {
+        return;
+    }
+    let changed = feature_states.take_changed();
 
-            let color: Option<Vec4f32> = entry
+    let entries = buffer_pool
+        .index()
+        .iter()
+        .flatten()
+        .filter(|entry| {
+            entry
                 .style_layer
                 .paint
                 .as_ref()
-                .and_then(|paint| paint.get_color())
-                .map(|mut color| {
-                    color.color.b = animated_one as f32;
-                    color.into()
-                });
-
-            match stored_layer {
-                StoredLayer::UnavailableLayer { .. } => {}
-                StoredLayer::TessellatedLayer {
-                    feature_indices, ..
-                } => {
-                    /* let feature_metadata = layer_data
-                    .features()
-                    .iter()
-                    .enumerate()
-                    .flat_map(|(i, _feature)| {
-                        iter::repeat(ShaderFeatureStyle {
-                            color: color.unwrap(),
-                        })
-                        .take(feature_indices[i] as usize)
-                    })
-                    .collect::<Vec<_>>();*/
-
-                    let feature_metadata = (0..feature_indices.len())
-                        .flat_map(|i| {
-                            iter::repeat(ShaderFeatureStyle {
-                                color: color.unwrap(),
-                            })
-                            .take(feature_indices[i] as usize)
-                        })
-                        .collect::<Vec<_>>();
-
-                    buffer_pool.update_feature_metadata(queue, entry, &feature_metadata);
-                }
-
-                StoredLayer::RasterLayer { .. } => {}
+                .is_some_and(LayerPaint::uses_feature_state)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    for entry in entries {
+        let style_layer = &entry.style_layer;
+        let (Some(source), Some(source_layer)) = (&style_layer.source, &style_layer.source_layer)
+        else {
+            continue;
+        };
+
+        let ids = changed
+            .iter()
+            .filter(|key| key.source == *source && key.source_layer == *source_layer)
+            .map(|key| key.id)
+            .collect::<HashSet<_>>();
+        if ids.is_empty() {
+            continue;
+        }
+
+        let Some(layer) = tiles
+            .query::<&VectorLayersDataComponent>(entry.coords)
+            .and_then(|vector_layers| vector_layers.available_layer(style_layer))
+        else {
+            continue;
+        };
+
+        let mut offset = 0;
+        for (i, (feature_id, indices)) in layer
+            .feature_ids
+            .iter()
+            .zip(&layer.feature_indices)
+            .enumerate()
+        {
+            if let Some(id) = feature_id.filter(|id| ids.contains(id)) {
+                let state = feature_states.get(&FeatureKey::new(source, source_layer, id));
+                let style =
+                    feature_style(style_layer, layer.feature_properties.get(i), state, zoom);
+                buffer_pool.update_feature_metadata(
+                    queue,
+                    &entry,
+                    offset,
+                    &vec![style; *indices as usize],
+                );
             }
+            offset += *indices as wgpu::BufferAddress;
+        }
+    }
+}
+
+/// Returns the style of a feature, which depends on its properties and state.
+fn feature_style(
+    style_layer: &StyleLayer,
+    properties: Option<&HashMap<String, String>>,
+    state: Option<&HashMap<String, JsonValue>>,
+    zoom: f64,
+) -> ShaderFeatureStyle {
+    let paint = style_layer.paint.as_ref();
+    let color = paint
+        .and_then(|paint| {
+            paint.color(&EvaluationContext {
+                zoom,
+                properties,
+                feature_state: state,
+                ..EvaluationContext::default()
+            })
+        })
+        .or_else(|| paint.and_then(LayerPaint::get_color));
+
+    ShaderFeatureStyle {
+        // Colors which can not be evaluated, e.g. because a property is missing, fall back to
+        // black, the default of `fill-color` and `line-color`
+        color: color.map_or([0.0, 0.0, 0.0, 1.0], Into::into),
+    }
+}
+
+/// Returns the style of each feature of a tessellated layer, repeated for each of its indices.
+fn feature_metadata(
+    style_layer: &StyleLayer,
+    layer: &AvailableVectorLayerData,
+    feature_states: &FeatureStates,
+    zoom: f64,
+) -> Vec<ShaderFeatureStyle> {
+    let source = style_layer.source.as_deref().unwrap_or_default();
+    layer
+        .feature_indices
+        .iter()
+        .enumerate()
+        .flat_map(|(i, indices)| {
+            let state = layer.feature_ids.get(i).copied().flatten().and_then(|id| {
+                feature_states.get(&FeatureKey::new(source, &layer.source_layer, id))
+            });
+            let style = feature_style(style_layer, layer.feature_properties.get(i), state, zoom);
+            iter::repeat(style).take(*indices as usize)
+        })
+        .collect()
+}
+
+/// Removes the layers which no longer exist and restyles the layers whose paint properties or
+/// index changed. Tiles which need a new tessellation are requested by the request system.
+fn apply_style_change(
+    buffer_pool: &mut VectorBufferPool,
+    queue: &wgpu::Queue,
+    tiles: &Tiles,
+    style: &Style,
+    style_change: &mut StyleChange,
+    feature_states: &FeatureStates,
+    zoom: f64,
+) {
+    buffer_pool.remove_layers(&style_change.removed_layers);
+    style_change.removed_layers.clear();
+
+    for layer_id in style_change.restyled_layers.drain(..) {
+        let Some(style_layer) = style.layers.iter().find(|layer| layer.id == layer_id) else {
+            continue;
+        };
+
+        let entries = buffer_pool.restyle_layer(
+            queue,
+            style_layer,
+            ShaderLayerMetadata::new(style_layer.index as f32),
+        );
+
+        // The colors of the features depend on their properties and state
+        for entry in entries {
+            let Some(layer) = tiles
+                .query::<&VectorLayersDataComponent>(entry.coords)
+                .and_then(|vector_layers| vector_layers.available_layer(style_layer))
+            else {
+                continue;
+            };
+
+            buffer_pool.update_feature_metadata(
+                queue,
+                &entry,
+                0,
+                &feature_metadata(style_layer, layer, feature_states, zoom),
+            );
         }
     }
-}*/
+}
 
 fn upload_tesselated_layer(
     buffer_pool: &mut VectorBufferPool,
-    _device: &wgpu::Device,
     queue: &wgpu::Queue,
     tiles: &mut Tiles,
     style: &Style,
+    feature_states: &FeatureStates,
     view_region: &ViewRegion,
+    zoom: f64,
 ) {
     // Upload all tessellated layers which are in view
-    for coords in view_region.iter() {
+    for coords in view_region.iter_wrapped() {
         let Some(vector_layers) = tiles.query_mut::<&VectorLayersDataComponent>(coords) else {
             continue;
         };
 
         let loaded_layers = buffer_pool
-            .get_loaded_source_layers_at(coords)
+            .get_loaded_style_layers_at(coords)
             .unwrap_or_default();
 
-        let available_layers = vector_layers
+        let missing_layers = style
             .layers
             .iter()
-            .flat_map(|data| match data {
-                VectorLayerData::Available(data) => Some(data),
-                VectorLayerData::Missing(_) => None,
-            })
-            .filter(|data| !loaded_layers.contains(data.source_layer.as_str()))
+            .filter(|style_layer| !loaded_layers.contains(style_layer.id.as_str()))
             .collect::<Vec<_>>();
 
-        for style_layer in &style.layers {
-            let source_layer = style_layer.source_layer.as_ref().unwrap(); // TODO: Unwrap
-
-            let Some(AvailableVectorLayerData {
-                coords,
-                feature_indices,
-                buffer,
-                ..
-            }) = available_layers
-                .iter()
-                .find(|layer| source_layer.as_str() == layer.source_layer)
-            else {
+        for style_layer in missing_layers {
+            // Heatmaps render the points of a layer instead of its tessellation
+            if matches!(style_layer.paint, Some(LayerPaint::Heatmap(_))) {
+                continue;
+            }
+
+            let Some(layer) = vector_layers.available_layer(style_layer) else {
                 continue;
             };
 
-            let color: Option<Vec4f32> = style_layer
-                .paint
-                .as_ref()
-                .and_then(|paint| paint.get_color())
-                .map(|color| color.into());
-
-            let feature_metadata = (0..feature_indices.len()) // FIXME: Iterate over actual features
-                .enumerate()
-                .flat_map(|(i, _feature)| {
-                    iter::repeat(ShaderFeatureStyle {
-                        color: color.unwrap(),
-                    })
-                    .take(feature_indices[i] as usize)
-                })
-                .collect::<Vec<_>>();
-
-            log::debug!("Allocating geometry at {coords}");
+            let feature_metadata = feature_metadata(style_layer, layer, feature_states, zoom);
+
+            log::debug!("Allocating geometry at {}", layer.coords);
             buffer_pool.allocate_layer_geometry(
                 queue,
-                *coords,
+                layer.coords,
                 style_layer.clone(),
-                buffer,
+                &layer.buffer,
                 ShaderLayerMetadata::new(style_layer.index as f32),
                 &feature_metadata,
             );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_feature_style() {
+        let style_layer: StyleLayer = serde_json::from_value(json!({
+            "id": "buildings",
+            "type": "fill",
+            "source-layer": "building",
+            "paint": {"fill-color": ["get", "color"]}
+        }))
+        .unwrap();
+
+        let properties = HashMap::from([("color".to_string(), "#ff0000".to_string())]);
+        let style = feature_style(&style_layer, Some(&properties), None, 10.0);
+        assert_eq!(style.color, [1.0, 0.0, 0.0, 1.0]);
+
+        // Features without the property are drawn in the default color
+        let style = feature_style(&style_layer, None, None, 10.0);
+        assert_eq!(style.color, [0.0, 0.0, 0.0, 1.0]);
+    }
+}
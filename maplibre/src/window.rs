@@ -20,6 +20,10 @@ pub trait HeadedMapWindow: MapWindow {
     // TODO: Can we avoid this?
     fn request_redraw(&self);
 
+    /// Returns a function which requests a redraw of the window. The map calls it when it needs
+    /// to be rendered again while the event loop is idle.
+    fn redraw_waker(&self) -> Box<dyn Fn()>;
+
     fn scale_factor(&self) -> f64;
 
     fn id(&self) -> u64;
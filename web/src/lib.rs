@@ -130,8 +130,15 @@ pub async fn run_maplibre(new_worker: js_sys::Function) -> Result<(), JSError> {
         RendererBuilder::new(),
         vec![
             Box::<maplibre::render::RenderPlugin>::default(),
+            Box::<maplibre::background::BackgroundPlugin>::default(),
             Box::<maplibre::vector::VectorPlugin<platform::UsedVectorTransferables>>::default(),
-            // Box::new(RasterPlugin::<platform::UsedRasterTransferables>::default()),
+            Box::<maplibre::raster::RasterPlugin<platform::UsedRasterTransferables>>::default(),
+            Box::<maplibre::terrain::TerrainPlugin>::default(),
+            Box::<maplibre::heatmap::HeatmapPlugin>::default(),
+            Box::<maplibre::render::overlay::OverlayPlugin>::default(),
+            Box::<maplibre::annotation::AnnotationPlugin>::default(),
+            Box::<maplibre::control::ControlPlugin>::default(),
+            Box::<maplibre::draw::DrawPlugin>::default(),
             #[cfg(debug_assertions)]
             Box::<maplibre::debug::DebugPlugin>::default(),
         ],
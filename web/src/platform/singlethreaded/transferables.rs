@@ -18,9 +18,10 @@ use maplibre::{
     tile::Layer,
     vector::{
         AvailableVectorLayerData, LayerIndexed, LayerMissing, LayerTessellated,
-        MissingVectorLayerData, TileTessellated, VectorTransferables,
+        MissingVectorLayerData, PointFeature, TileTessellated, VectorTransferables,
     },
 };
+use serde_json::Value as JsonValue;
 
 use crate::platform::singlethreaded::{
     apc::WebMessageTag,
@@ -125,8 +126,9 @@ impl LayerMissing for FlatBufferTransferable {
         &WebMessageTag::LayerMissing
     }
 
-    fn build_from(coords: WorldTileCoords, layer_name: String) -> Self {
+    fn build_from(coords: WorldTileCoords, source: Option<String>, layer_name: String) -> Self {
         let mut inner_builder = FlatBufferBuilder::with_capacity(1024);
+        let source = source.map(|source| inner_builder.create_string(&source));
         let layer_name = inner_builder.create_string(&layer_name);
 
         let mut builder = FlatLayerMissingBuilder::new(&mut inner_builder);
@@ -135,6 +137,9 @@ impl LayerMissing for FlatBufferTransferable {
             coords.y,
             coords.z.into(),
         ));
+        if let Some(source) = source {
+            builder.add_source(source);
+        }
         builder.add_layer_name(layer_name);
         let root = builder.finish();
 
@@ -158,7 +163,9 @@ impl LayerMissing for FlatBufferTransferable {
     }
 
     fn to_layer(self) -> MissingVectorLayerData {
+        let data = root_as_flat_layer_missing(&self.data[self.start..]).unwrap();
         MissingVectorLayerData {
+            source: data.source().map(str::to_owned),
             source_layer: self.layer_name().to_owned(),
             coords: LayerMissing::coords(&self),
         }
@@ -184,9 +191,12 @@ impl LayerTessellated for FlatBufferTransferable {
 
     fn build_from(
         coords: WorldTileCoords,
+        source: Option<String>,
+        filter: Option<JsonValue>,
         buffer: OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
         feature_indices: Vec<u32>,
         layer_data: Layer,
+        _points: Vec<PointFeature>,
     ) -> Self {
         let mut inner_builder = FlatBufferBuilder::with_capacity(1024);
 
@@ -200,7 +210,9 @@ impl LayerTessellated for FlatBufferTransferable {
         );
         let indices = inner_builder.create_vector(&buffer.buffer.indices);
         let feature_indices = inner_builder.create_vector(&feature_indices);
+        let source = source.map(|source| inner_builder.create_string(&source));
         let layer_name = inner_builder.create_string(&layer_data.name);
+        let filter = filter.map(|filter| inner_builder.create_string(&filter.to_string()));
 
         let mut builder = FlatLayerTessellatedBuilder::new(&mut inner_builder);
 
@@ -209,6 +221,12 @@ impl LayerTessellated for FlatBufferTransferable {
             coords.y,
             coords.z.into(),
         ));
+        if let Some(source) = source {
+            builder.add_source(source);
+        }
+        if let Some(filter) = filter {
+            builder.add_filter(filter);
+        }
         builder.add_layer_name(layer_name);
         builder.add_vertices(vertices);
         builder.add_indices(indices);
@@ -248,9 +266,16 @@ impl LayerTessellated for FlatBufferTransferable {
         let usable_indices = data.usable_indices();
         AvailableVectorLayerData {
             coords: LayerTessellated::coords(&self),
+            source: data.source().map(str::to_owned),
             source_layer: data.layer_name().unwrap().to_owned(),
+            filter: data
+                .filter()
+                .map(|filter| serde_json::from_str(filter).expect("filter must be valid JSON")),
             buffer: OverAlignedVertexBuffer::from_iters(vertices, indices, usable_indices),
             feature_indices,
+            feature_ids: Vec::new(),        // TODO: Transfer feature ids
+            feature_properties: Vec::new(), // TODO: Transfer feature properties
+            points: Vec::new(),             // TODO: Transfer points
         }
     }
 }
@@ -348,7 +373,7 @@ impl LayerRasterMissing for FlatBufferTransferable {
         &WebMessageTag::LayerRasterMissing
     }
 
-    fn build_from(coords: WorldTileCoords) -> Self {
+    fn build_from(coords: WorldTileCoords, _layer_name: String) -> Self {
         let mut inner_builder = FlatBufferBuilder::with_capacity(1024);
         let mut builder = FlatLayerIndexedBuilder::new(&mut inner_builder);
 